@@ -0,0 +1,341 @@
+use crate::{
+    Flottant,
+    Valeur,
+    choix::{Enumeration, Histogramme},
+    course::{self, PolitiqueCourse},
+    enregistrement::{self, ErreurRejeu},
+    etat::EtatTour,
+    regles::Regles,
+    simulation::{self, Action, ResultatPartie},
+    stats::Stats,
+};
+
+use serde::{Deserialize, Serialize};
+
+
+// En-dessous de cet écart avec la meilleure action, une action jouée ne compte
+// pas comme une erreur: un point d'espérance de gain (comme pour
+// Stats::evaluer_options), ou un dixième de point de pourcentage de probabilité
+const ECART_NEGLIGEABLE_POINTS : Flottant = 1.;
+const ECART_NEGLIGEABLE_PROBA : Flottant = 1e-3;
+
+// A deux joueurs, quand les deux sont à moins de ce nombre de points du score
+// maximal, on juge les décisions à la probabilité de gagner la course plutôt
+// qu'à l'espérance de gain. Le calcul de la course coûte d'autant plus cher
+// que les scores sont bas, d'où ce seuil.
+const ECART_FIN_DE_PARTIE : Valeur = 1000;
+
+// Critère selon lequel on juge une décision
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Critere {
+    // Espérance de gain du tour, en points
+    Esperance,
+
+    // Probabilité de gagner la course à deux joueurs (voir PolitiqueCourse)
+    ProbaVictoire,
+
+    // Probabilité de dépasser le score à battre pendant le dernier tour
+    // d'égalisation (voir Stats::proba_depasser)
+    ProbaDepasser,
+}
+
+// Analyse d'une décision prise face à un jet de dés
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AnalyseCoup {
+    // Numéro du tour dans la partie et du lancer dans le tour (à partir de 0),
+    // et joueur qui a pris la décision
+    pub num_tour: usize,
+    pub num_lancer: usize,
+    pub joueur: usize,
+
+    // Etat du tour et dés obtenus
+    pub etat: EtatTour,
+    pub histo: Histogramme,
+
+    // Action jouée, et meilleure action selon le critère retenu
+    pub jouee: Action,
+    pub meilleure: Action,
+
+    // Critère retenu, et valeur de chacune des deux actions selon ce critère
+    pub critere: Critere,
+    pub valeur_jouee: Flottant,
+    pub valeur_meilleure: Flottant,
+
+    // Points d'espérance de gain laissés sur la table par ce joueur depuis le
+    // début de la partie, ce coup compris (on ne compte que les coups jugés à
+    // l'espérance de gain)
+    pub points_perdus_cumules: Flottant,
+}
+
+impl AnalyseCoup {
+    // Ce que l'action jouée fait perdre par rapport à la meilleure, en points
+    // ou en probabilité selon le critère
+    pub fn ecart(&self) -> Flottant {
+        self.valeur_meilleure - self.valeur_jouee
+    }
+
+    // Est-ce que l'action jouée est nettement moins bonne que la meilleure?
+    pub fn erreur(&self) -> bool {
+        let seuil = match self.critere {
+            Critere::Esperance => ECART_NEGLIGEABLE_POINTS,
+            Critere::ProbaVictoire | Critere::ProbaDepasser => ECART_NEGLIGEABLE_PROBA,
+        };
+        self.ecart() > seuil
+    }
+}
+
+// Analyse de toutes les décisions prises pendant une partie
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AnalysePartie {
+    // Nombre de joueurs ayant participé à la partie
+    pub nb_joueurs: usize,
+
+    // Décisions prises face à chaque jet de dés, dans l'ordre de la partie
+    // (les lancers perdus, où il n'y a rien à décider, ne sont pas comptés)
+    pub coups: Vec<AnalyseCoup>,
+
+    // Pour chaque joueur, points d'espérance de gain laissés sur la table, et
+    // probabilité de victoire (ou de dépasser le score à battre) perdue
+    pub points_perdus: Vec<Flottant>,
+    pub proba_perdue: Vec<Flottant>,
+}
+
+impl AnalysePartie {
+    // Nombre d'erreurs commises par le joueur i (voir AnalyseCoup::erreur)
+    pub fn nb_erreurs(&self, joueur: usize) -> usize {
+        self.coups.iter().filter(|coup| coup.joueur == joueur && coup.erreur()).count()
+    }
+}
+
+// Analyser chaque décision d'une partie jouée avec les règles de "stats", en
+// la comparant à la meilleure décision possible. On ne se fie pas aux actions
+// enregistrées comme possibles, qu'une partie saisie à la main peut omettre:
+// on énumère toutes les combinaisons légales (voir Enumeration::Complete), et
+// on évalue l'action jouée, même si elle n'a aucun intérêt. On vérifie
+// seulement que la partie est lisible avec ces règles (voir
+// enregistrement::verifier_partie), pas qu'elle respecte la règle, c'est le
+// rôle du rejeu (voir EnregistrementPartie::rejouer), et on n'analyse pas le
+// choix de reprendre ou non la mise du joueur précédent.
+//
+// Le critère dépend de la situation. Pendant le dernier tour d'égalisation,
+// seul compte le fait de dépasser le score à battre. A deux joueurs en fin de
+// partie, accumuler des points ne sert plus qu'à gagner la course, donc on
+// juge à la probabilité de victoire, quand la règle est celle que sait
+// étudier PolitiqueCourse. Sinon, on juge à l'espérance de gain du tour,
+// comme la stratégie Optimale.
+//
+pub fn analyser_partie(stats: &Stats,
+                       partie: &ResultatPartie) -> Result<AnalysePartie, ErreurRejeu> {
+    let regles = stats.regles();
+    enregistrement::verifier_partie(regles, partie)?;
+    let nb_joueurs = partie.nb_joueurs;
+    let mut analyse = AnalysePartie {
+        nb_joueurs,
+        coups: Vec::new(),
+        points_perdus: vec![0.; nb_joueurs],
+        proba_perdue: vec![0.; nb_joueurs],
+    };
+    for (num_tour, tour) in partie.tours.iter().enumerate() {
+        let joueur = num_tour % nb_joueurs;
+        let debut = match tour.lancers.first() {
+            Some(lancer) => lancer.etat,
+            None => continue,
+        };
+        let course = (debut.cible.is_none() && course_etudiable(regles, nb_joueurs, &debut))
+            .then(|| stats.politique_course(debut.score, debut.score_adverse));
+        for (num_lancer, lancer) in tour.lancers.iter().enumerate() {
+            let jouee = match lancer.action {
+                Some(action) => action,
+                None => continue,
+            };
+            let etat = lancer.etat;
+            let critere = match (etat.cible, &course) {
+                (Some(_), _) => Critere::ProbaDepasser,
+                (None, Some(_)) => Critere::ProbaVictoire,
+                (None, None) => Critere::Esperance,
+            };
+            let valeur = |action: &Action| -> Flottant {
+                evaluer(stats, &etat, critere, course.as_ref(), action)
+            };
+
+            // La meilleure action est la première de valeur maximale, comme
+            // pour la stratégie Optimale
+            let choix = lancer.histo.combinaisons_possibles(regles, Enumeration::Complete);
+            let actions = simulation::actions_possibles(regles, &etat, &choix);
+            let mut meilleure = match actions.first() {
+                Some(&action) => (action, valeur(&action)),
+                None => continue,
+            };
+            for action in &actions[1..] {
+                let valeur_action = valeur(action);
+                if valeur_action > meilleure.1 {
+                    meilleure = (*action, valeur_action);
+                }
+            }
+            let valeur_jouee = valeur(&jouee);
+            let ecart = (meilleure.1 - valeur_jouee).max(0.);
+            match critere {
+                Critere::Esperance => analyse.points_perdus[joueur] += ecart,
+                Critere::ProbaVictoire | Critere::ProbaDepasser => {
+                    analyse.proba_perdue[joueur] += ecart
+                },
+            }
+            analyse.coups.push(AnalyseCoup {
+                num_tour,
+                num_lancer,
+                joueur,
+                etat,
+                histo: lancer.histo.clone(),
+                jouee,
+                meilleure: meilleure.0,
+                critere,
+                valeur_jouee,
+                valeur_meilleure: meilleure.1,
+                points_perdus_cumules: analyse.points_perdus[joueur],
+            });
+        }
+    }
+    Ok(analyse)
+}
+
+// Est-ce qu'un tour qui commence dans cet état se juge à la probabilité de
+// gagner la course? PolitiqueCourse étudie une course à deux joueurs, avec
+// certaines règles seulement (voir course::regles_etudiables), et elle n'est
+// praticable qu'en fin de partie.
+fn course_etudiable(regles: &Regles, nb_joueurs: usize, debut: &EtatTour) -> bool {
+    let fin_de_partie = regles.score_max.saturating_sub(ECART_FIN_DE_PARTIE);
+    nb_joueurs == 2
+        && course::regles_etudiables(regles)
+        && debut.score >= fin_de_partie
+        && debut.score_adverse >= fin_de_partie
+}
+
+// Valeur d'une action face à un jet de dés, selon un certain critère
+fn evaluer(stats: &Stats,
+           etat: &EtatTour,
+           critere: Critere,
+           course: Option<&PolitiqueCourse>,
+           action: &Action) -> Flottant
+{
+    match (*action, critere, course) {
+        (Action::Encaisser { comb, mise }, Critere::ProbaDepasser, _) => {
+            let cible = etat.cible.expect("Pas de score à battre");
+            let gagne = stats.regles().fait_gagner(&comb) || etat.score + mise > cible;
+            if gagne { 1. } else { 0. }
+        },
+        (Action::Encaisser { comb, mise }, Critere::ProbaVictoire, Some(course)) => {
+            if stats.regles().fait_gagner(&comb) {
+                1.
+            } else {
+                course.proba_victoire_arret(mise)
+            }
+        },
+        (Action::Relancer { nb_des, mise, .. }, Critere::ProbaVictoire, Some(course)) => {
+            course.proba_victoire_relance(nb_des, mise)
+        },
+        (Action::Encaisser { mise, .. }, _, _) => {
+            stats.regles().gain_encaisse(etat.score, mise) as Flottant
+        },
+        (Action::Relancer { nb_des, mise, .. }, _, _) => {
+            let relance = EtatTour { nb_des, mise, ..*etat };
+            stats.proba_depasser_etat(&relance).unwrap_or_else(|| stats.esperance_etat(&relance))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        combinaison::Combinaison,
+        simulation::{Lancer, ResultatTour},
+    };
+
+    // Partie saisie à la main: le joueur 0 encaisse un 1 seul au lieu de
+    // relancer 5 dés, puis le joueur 1 ne sort rien
+    fn partie_a_la_main(regles: &Regles) -> ResultatPartie {
+        let lancer = |etat: EtatTour, faces: &str, action: Option<Action>| Lancer {
+            etat,
+            histo: Histogramme::parse(faces, regles).unwrap(),
+            actions: Vec::new(),
+            action,
+        };
+        let un = Combinaison::FacesSimples { nb_un: 1, nb_cinq: 0 };
+        let debut = |score, score_adverse| {
+            EtatTour { score_adverse, ..EtatTour::debut(regles, score) }
+        };
+        ResultatPartie {
+            nb_joueurs: 2,
+            gagnant: None,
+            tours: vec![
+                ResultatTour {
+                    mise: 100,
+                    lancers: vec![lancer(debut(0, 0), "1 2 3 4 6 6",
+                                         Some(Action::Encaisser { comb: un, mise: 100 }))],
+                },
+                ResultatTour {
+                    mise: 0,
+                    lancers: vec![lancer(debut(0, 100), "2 2 3 4 6 6", None)],
+                },
+            ],
+            premier_arrive: None,
+            scores: vec![100, 0],
+        }
+    }
+
+    #[test]
+    fn analyse_partie_a_la_main() {
+        let stats = Stats::with_score_max(2000);
+        let partie = partie_a_la_main(stats.regles());
+        let analyse = analyser_partie(&stats, &partie).unwrap();
+
+        // Seul le premier lancer demande une décision, et c'est une erreur
+        assert_eq!(analyse.coups.len(), 1);
+        let coup = &analyse.coups[0];
+        assert_eq!((coup.num_tour, coup.num_lancer, coup.joueur), (0, 0, 0));
+        assert_eq!(coup.critere, Critere::Esperance);
+        assert_eq!(coup.valeur_jouee, 100.);
+        assert!(matches!(coup.meilleure, Action::Relancer { nb_des: 5, mise: 100, .. }));
+        assert!(coup.erreur());
+        assert_eq!(analyse.nb_erreurs(0), 1);
+        assert_eq!(analyse.points_perdus, vec![coup.ecart(), 0.]);
+
+        // Une partie mal formée est rejetée au lieu de faire paniquer l'analyse
+        let sans_joueurs = ResultatPartie { nb_joueurs: 0, ..partie.clone() };
+        assert_eq!(analyser_partie(&stats, &sans_joueurs), Err(ErreurRejeu::Joueurs));
+        let mut trop_de_des = partie;
+        trop_de_des.tours[1].lancers[0].etat.nb_des = 7;
+        assert_eq!(analyser_partie(&stats, &trop_de_des),
+                   Err(ErreurRejeu::Etat { num_tour: 1, num_lancer: 0 }));
+    }
+
+    // Une prise partielle (un seul des deux 1), que l'enregistrement ne liste
+    // pas parmi les actions possibles, est tout de même évaluée
+    #[test]
+    fn analyse_prise_partielle() {
+        let stats = Stats::with_score_max(2000);
+        let regles = stats.regles();
+        let histo = Histogramme::parse("1 1 2 3 4 6", regles).unwrap();
+        let un = Combinaison::FacesSimples { nb_un: 1, nb_cinq: 0 };
+        assert!(histo.combinaisons_possibles(regles, Enumeration::Complete).contains(&un));
+        let debut = EtatTour::debut(regles, 0);
+        let jouee = Action::Relancer { comb: un, mise: 100, nb_des: 5 };
+        let partie = ResultatPartie {
+            nb_joueurs: 1,
+            gagnant: None,
+            tours: vec![ResultatTour {
+                mise: 0,
+                lancers: vec![Lancer { etat: debut, histo, actions: Vec::new(), action: Some(jouee) }],
+            }],
+            premier_arrive: None,
+            scores: vec![0],
+        };
+        let analyse = analyser_partie(&stats, &partie).unwrap();
+        assert_eq!(analyse.coups.len(), 1);
+        let coup = &analyse.coups[0];
+        assert_eq!(coup.jouee, jouee);
+        assert_eq!(coup.valeur_jouee,
+                   stats.esperance_etat(&EtatTour { nb_des: 5, mise: 100, ..debut }));
+        assert!(coup.valeur_meilleure >= coup.valeur_jouee);
+    }
+}
@@ -0,0 +1,141 @@
+use mitraillette::{
+    Decision,
+    EtatTour,
+    Stats,
+    Valeur,
+    choix::{Enumeration, Histogramme},
+};
+
+use std::io::{self, BufRead, Write};
+
+
+// Mode conseil interactif: on demande à l'utilisateur son score, sa mise et les
+// dés qu'il vient de tirer, et on lui indique ce qu'il a intérêt à faire
+pub fn conseil(stats: &Stats) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut lignes = stdin.lock().lines();
+    loop {
+        // On demande à l'utilisateur où il en est...
+        let score = match demander(&mut lignes, "Score acquis", lire_valeur)? {
+            Some(score) => score,
+            None => return Ok(()),
+        };
+        let mise = match demander(&mut lignes, "Mise en jeu", lire_valeur)? {
+            Some(mise) => mise,
+            None => return Ok(()),
+        };
+        let lire_histo = |texte: &str| lire_histogramme(stats, texte);
+        let histo = match demander(&mut lignes, "Dés tirés", lire_histo)? {
+            Some(histo) => histo,
+            None => return Ok(()),
+        };
+
+        // ...on vérifie que c'est possible, et on lui répond
+        let etat = EtatTour::new(score, histo.nb_des(), mise);
+        match etat.verifier(stats.regles()) {
+            Ok(()) => conseiller(stats, &etat, histo),
+            Err(erreur) => println!("Situation impossible: {}", erreur),
+        }
+        println!();
+    }
+}
+
+// Poser une question à l'utilisateur jusqu'à obtenir une réponse valide
+fn demander<T>(lignes: &mut impl Iterator<Item=io::Result<String>>,
+               question: &str,
+               interpreter: impl Fn(&str) -> Result<T, String>) -> io::Result<Option<T>>
+{
+    loop {
+        print!("{} ? ", question);
+        io::stdout().flush()?;
+        let ligne = match lignes.next() {
+            Some(ligne) => ligne?,
+            None => return Ok(None),
+        };
+        match interpreter(ligne.trim()) {
+            Ok(reponse) => return Ok(Some(reponse)),
+            Err(erreur) => println!("{}", erreur),
+        }
+    }
+}
+
+// Lecture d'un score ou d'une mise
+fn lire_valeur(texte: &str) -> Result<Valeur, String> {
+    texte.parse::<Valeur>()
+         .map_err(|_| format!("Valeur invalide: \"{}\"", texte))
+}
+
+// Lecture des dés tirés, sous la forme "3 3 3 1 5 2"
+fn lire_histogramme(stats: &Stats, texte: &str) -> Result<Histogramme, String> {
+    Histogramme::parse(texte, stats.regles()).map_err(|erreur| erreur.to_string())
+}
+
+// Analyse d'un jet de dés et recommandation
+fn conseiller(stats: &Stats, etat: &EtatTour, histo: Histogramme) {
+    let regles = stats.regles();
+    let (score, mise) = (etat.score, etat.mise);
+
+    // On énumère les combinaisons qu'on peut prendre
+    let choix = histo.combinaisons_possibles(regles, Enumeration::Elaguee);
+    if choix.is_empty() {
+        println!("Aucune combinaison, le tour est perdu!");
+        return;
+    }
+
+    // On passe en revue ce qu'on peut faire avec chacune, de la meilleure
+    // option à la moins bonne
+    let options = stats.evaluer_options(score, mise, &histo);
+    if options.is_empty() {
+        println!("Toutes les combinaisons dépassent le score maximal, \
+                  le tour est perdu!");
+        return;
+    }
+    println!("Options, de la meilleure à la moins bonne:");
+    for (rang, option) in options.iter().enumerate() {
+        let comparaison = if rang == 0 {
+            String::new()
+        } else if option.equivalente {
+            format!(", {:.1} de moins: ça revient au même", option.ecart)
+        } else {
+            format!(", {:.1} de moins", option.ecart)
+        };
+        println!("{}. Prendre {} et {} (espérance {:.1}{})",
+                 rang + 1, option.decision.combinaison(),
+                 decrire(stats, score, &option.decision),
+                 option.decision.esperance(), comparaison);
+    }
+
+    // On signale les combinaisons qu'on ne peut pas prendre
+    for comb in choix.iter() {
+        if options.iter().any(|option| option.decision.combinaison() == *comb) { continue; }
+        if score + mise + regles.bareme.valeur(comb) > regles.score_max {
+            println!("(Prendre {} dépasserait le score maximal)", comb);
+        } else {
+            println!("(Prendre {} est interdit, car une autre combinaison dépasse le \
+                      score maximal)", comb);
+        }
+    }
+
+    // ...et on recommande la meilleure option
+    let meilleure = &options[0];
+    println!("Meilleur choix: prendre {} et {} (espérance {:.1})",
+             meilleure.decision.combinaison(), decrire(stats, score, &meilleure.decision),
+             meilleure.decision.esperance());
+    if options[1..].iter().any(|option| option.equivalente) {
+        println!("(mais d'autres options font presque aussi bien, inutile d'hésiter)");
+    }
+}
+
+// Description textuelle d'une décision
+fn decrire(stats: &Stats, score: Valeur, decision: &Decision) -> String {
+    match *decision {
+        Decision::Encaisser { mise: gain, .. } => {
+            if score + gain == stats.regles().score_max {
+                format!("s'arrêter en encaissant {} points (partie gagnée!)", gain)
+            } else {
+                format!("s'arrêter en encaissant {} points", gain)
+            }
+        },
+        Decision::Relancer { nb_des, .. } => format!("relancer {} dés", nb_des),
+    }
+}
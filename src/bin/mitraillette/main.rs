@@ -0,0 +1,2156 @@
+mod conseil;
+
+use clap::{
+    CommandFactory,
+    Parser,
+    Subcommand,
+    ValueEnum,
+    error::ErrorKind,
+};
+
+use indicatif::ProgressBar;
+
+use log::{LevelFilter, warn};
+
+use num_traits::ToPrimitive;
+
+use rayon::prelude::*;
+
+use mitraillette::{
+    Enumeration,
+    Flottant,
+    NB_RELANCES_MAX,
+    Regles,
+    regles::NB_PERTES_PENALITE,
+    Stats,
+    Valeur,
+    analyse,
+    scenarios,
+    comparaison,
+    course,
+    duree,
+    enregistrement::EnregistrementPartie,
+    heuristiques,
+    ordre,
+    partie,
+    simulation::{
+        Aleatoire,
+        DerniereChance,
+        DesMinimum,
+        EtatTour,
+        Jeu,
+        MoyenneVariance,
+        NB_TOURS_MAX,
+        Optimale,
+        SeuilFixe,
+        SeuilSaufDes,
+        Strategie,
+        ToujoursRelancer,
+    },
+    sortie::{self, Tables},
+    stats,
+    stats_exact::StatsExactes,
+    table::{self, LigneStrategie},
+    tournoi,
+    tours_fixes,
+    verification,
+};
+
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{self, BufWriter, IsTerminal, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+
+/// Etude de la stratégie optimale à la mitraillette
+#[derive(Parser)]
+struct Options {
+    /// Fichier où garder les statistiques calculées d'une exécution à l'autre
+    #[arg(long, global = true)]
+    cache: Option<PathBuf>,
+
+    /// Nombre maximal de résultats intermédiaires que garde chaque cache de
+    /// calcul, pour borner la mémoire utilisée (pas de limite par défaut)
+    #[arg(long, global = true,
+          value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    capacite_caches: Option<usize>,
+
+    /// Indiquer sur la sortie d'erreur, à la fin de l'exécution, si les caches
+    /// de calcul ont été efficaces
+    #[arg(long, global = true, alias = "cache-stats")]
+    statistiques_caches: bool,
+
+    /// Format dans lequel afficher les tables par défaut
+    #[arg(long, global = true, value_enum, default_value_t = Format::Texte)]
+    format: Format,
+
+    /// Dossier où tracer les courbes des tables par défaut, en SVG
+    #[cfg(feature = "plots")]
+    #[arg(long, global = true)]
+    plot: Option<PathBuf>,
+
+    /// Ne pas afficher l'avancement des calculs, ni d'autre message que les
+    /// erreurs
+    #[arg(long, short, global = true)]
+    quiet: bool,
+
+    /// Afficher plus de détails sur les calculs (répéter pour en avoir plus)
+    #[arg(long, short, global = true, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Gagner en atteignant ou en dépassant le score maximal, sans devoir y
+    /// atterrir exactement
+    #[arg(long, global = true)]
+    depassement: bool,
+
+    /// Points qu'il faut encaisser d'un coup pour ouvrir (0 pour jouer sans
+    /// seuil d'ouverture)
+    #[arg(long, global = true, default_value_t = 0)]
+    seuil_ouverture: Valeur,
+
+    /// Mise qu'il faut avoir accumulée pour s'arrêter à chaque tour (0 pour
+    /// pouvoir s'arrêter avec n'importe quelle mise)
+    #[arg(long, global = true, default_value_t = 0)]
+    mise_minimale: Valeur,
+
+    /// Autoriser un joueur à reprendre les dés restants et la mise que son
+    /// adversaire vient d'encaisser
+    #[arg(long, global = true)]
+    reprise: bool,
+
+    /// Quand un joueur atteint le score maximal, laisser chacun des autres
+    /// jouer un dernier tour pour le dépasser (demande --depassement)
+    #[arg(long, global = true, requires = "depassement")]
+    dernier_tour: bool,
+
+    /// Rebondir sur le score maximal: ce qui le dépasse quand on encaisse en
+    /// est retranché
+    #[arg(long, global = true, conflicts_with = "depassement")]
+    rebond: bool,
+
+    /// Obliger à relancer tous les dés quand ils ont tous servi, au lieu de
+    /// pouvoir encaisser
+    #[arg(long, global = true)]
+    relance_plein: bool,
+
+    /// Points que fait perdre le fait de perdre trois tours d'affilée (0 pour
+    /// jouer sans pénalité)
+    #[arg(long, global = true, default_value_t = 0)]
+    penalite_pertes: Valeur,
+
+    /// Score acquis, pour n'étudier qu'une seule situation de jeu
+    #[arg(long, requires_all = ["des", "mise"])]
+    score: Option<Valeur>,
+
+    /// Nombre de dés à lancer dans cette situation
+    #[arg(long, requires_all = ["score", "mise"])]
+    des: Option<usize>,
+
+    /// Mise accumulée pendant le tour dans cette situation
+    #[arg(long, requires_all = ["score", "des"])]
+    mise: Option<Valeur>,
+
+    #[command(subcommand)]
+    commande: Option<Commande>,
+}
+
+/// Format de sortie des tables
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    /// Texte lisible par un humain
+    Texte,
+
+    /// JSON destiné à d'autres programmes
+    Json,
+
+    /// CSV destiné aux tableurs
+    Csv,
+
+    /// Markdown à coller dans un wiki ou un ticket
+    Markdown,
+}
+
+#[derive(Subcommand)]
+enum Commande {
+    /// Tabuler la stratégie optimale pour tous les états de jeu plausibles
+    Strategie {
+        /// Fichier dans lequel écrire la table
+        #[arg(long, short)]
+        sortie: PathBuf,
+    },
+
+    /// Tabuler le gain moyen pour tous les scores et toutes les mises
+    /// atteignables, avec un certain nombre de dés
+    Table {
+        /// Nombre de dés lancés
+        #[arg(long, default_value_t = 6,
+              value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        des: usize,
+
+        /// Ecart entre deux scores consécutifs de la table
+        #[arg(long, alias = "score-step", default_value_t = 500,
+              value_parser = clap::builder::RangedU64ValueParser::<Valeur>::new().range(1..))]
+        pas_score: Valeur,
+
+        /// Ecart entre deux mises consécutives de la table
+        #[arg(long, alias = "mise-step", default_value_t = 50,
+              value_parser = clap::builder::RangedU64ValueParser::<Valeur>::new().range(1..))]
+        pas_mise: Valeur,
+    },
+
+    /// Tabuler la probabilité de gagner ce tour-ci en fin de partie, pour tous
+    /// les nombres de dés et les mises atteignables
+    Atterrissages {
+        /// Ecart entre deux mises consécutives de la table
+        #[arg(long, default_value_t = 100,
+              value_parser = clap::builder::RangedU64ValueParser::<Valeur>::new().range(1..))]
+        pas_mise: Valeur,
+
+        /// Mise maximale étudiée
+        #[arg(long, default_value_t = 1000)]
+        mise_max: Valeur,
+    },
+
+    /// Conseiller interactivement le joueur sur ce qu'il doit faire d'un jet
+    Conseil,
+
+    /// Lancer des dés au hasard, et montrer les combinaisons qu'on peut prendre
+    Lancer {
+        /// Nombre de dés lancés
+        #[arg(default_value_t = 6,
+              value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        des: usize,
+
+        /// Graine du générateur de nombres aléatoires (tirée de l'horloge par
+        /// défaut)
+        #[arg(long)]
+        graine: Option<u64>,
+    },
+
+    /// Développer l'arbre des possibles à partir d'une situation de jeu, en
+    /// indiquant l'espérance de gain de chaque décision
+    Arbre {
+        /// Score acquis lors des tours précédents
+        #[arg(long, default_value_t = 9700)]
+        score: Valeur,
+
+        /// Nombre de dés lancés
+        #[arg(long, default_value_t = 2)]
+        des: usize,
+
+        /// Mise accumulée avant ce lancer
+        #[arg(long, default_value_t = 200)]
+        mise: Valeur,
+
+        /// Nombre de lancers successifs à développer
+        #[arg(long, default_value_t = 2)]
+        profondeur: usize,
+
+        /// Ecrire l'arbre au format DOT de Graphviz dans ce fichier, plutôt
+        /// que sous forme de texte
+        #[arg(long)]
+        dot: Option<PathBuf>,
+
+        /// Nombre de noeuds au-delà duquel on arrête de développer le graphe
+        #[arg(long, default_value_t = 200, requires = "dot")]
+        noeuds_max: usize,
+
+        /// Probabilité d'atteindre un choix en-dessous de laquelle on le
+        /// regroupe avec les autres choix improbables dans le graphe
+        #[arg(long, default_value_t = 0.01, requires = "dot")]
+        proba_min: Flottant,
+    },
+
+    /// Tabuler le nombre moyen de tours nécessaires pour finir la partie
+    Tours,
+
+    /// Projeter le score moyen au fil des tours, en maximisant l'espérance de
+    /// gain à chaque tour
+    Projection {
+        /// Score de départ
+        #[arg(long, default_value_t = 0)]
+        score: Valeur,
+
+        /// Nombre de tours à projeter
+        #[arg(long, default_value_t = 15)]
+        tours: usize,
+    },
+
+    /// Comparer la stratégie qui maximise l'espérance de gain à celle qui
+    /// maximise la probabilité de gagner une course contre un adversaire
+    Course {
+        /// Score acquis par le joueur
+        #[arg(long, default_value_t = 7000)]
+        mon_score: Valeur,
+
+        /// Score acquis par l'adversaire
+        #[arg(long, default_value_t = 9500)]
+        score_adverse: Valeur,
+    },
+
+    /// Mesurer à quel point on peut exploiter un adversaire qui encaisse dès
+    /// que sa mise atteint un certain seuil, en jouant au mieux contre lui
+    Exploitation {
+        /// Seuil à partir duquel l'adversaire encaisse
+        #[arg(long, default_value_t = 350)]
+        seuil: Valeur,
+
+        /// Etudier aussi le début de partie (0 contre 0), ce qui est très long
+        #[arg(long)]
+        depart: bool,
+    },
+
+    /// Tabuler la probabilité de victoire du joueur dont c'est le tour quand
+    /// les deux joueurs maximisent leur espérance de gain
+    Duel {
+        /// Ecart entre deux scores consécutifs de la table
+        #[arg(long, default_value_t = 1000,
+              value_parser = clap::builder::RangedU64ValueParser::<Valeur>::new().range(1..))]
+        pas: Valeur,
+    },
+
+    /// Vérifier quelques probabilités par un calcul exact en fractions
+    Exact,
+
+    /// Montrer comment l'espérance de gain converge quand on autorise plus de
+    /// relances, pour quelques situations de jeu
+    Convergence {
+        /// Nombre maximal de relances autorisées à étudier
+        #[arg(long, default_value_t = NB_RELANCES_MAX)]
+        relances: usize,
+    },
+
+    /// Tabuler la mise à partir de laquelle il vaut mieux encaisser que
+    /// relancer, selon le score et le nombre de dés à relancer
+    #[command(alias = "equilibres")]
+    Seuils {
+        /// Ecart entre deux scores consécutifs de la table
+        #[arg(long, default_value_t = 500,
+              value_parser = clap::builder::RangedU64ValueParser::<Valeur>::new().range(1..))]
+        pas: Valeur,
+    },
+
+    /// Comparer les seuils d'arrêt avant et après avoir perdu deux tours
+    /// d'affilée, quand la règle pénalise le troisième (voir --penalite-pertes)
+    Pertes {
+        /// Ecart entre deux scores consécutifs de la table
+        #[arg(long, default_value_t = 1000,
+              value_parser = clap::builder::RangedU64ValueParser::<Valeur>::new().range(1..))]
+        pas: Valeur,
+    },
+
+    /// Montrer comment les seuils d'arrêt baissent quand on a de plus en plus
+    /// peur de perdre sa mise (utilité exponentielle 1 - exp(-λ·mise))
+    Aversion {
+        /// Score acquis lors des tours précédents
+        #[arg(long, default_value_t = 0)]
+        score: Valeur,
+    },
+
+    /// Afficher les seuils d'arrêt d'une stratégie qui maximise l'espérance de
+    /// gain pénalisée par sa variance, selon la pénalité
+    Variance {
+        /// Score acquis lors des tours précédents
+        #[arg(long, default_value_t = 0)]
+        score: Valeur,
+    },
+
+    /// Comparer l'espérance de gain par tour de plusieurs stratégies
+    Strategies,
+
+    /// Comparer l'espérance de gain à score nul avec et sans mise minimale
+    /// pour s'arrêter
+    Minimum {
+        /// Mise minimale à comparer aux règles choisies
+        #[arg(long, default_value_t = 350)]
+        mise: Valeur,
+
+        /// Mise maximale étudiée
+        #[arg(long, default_value_t = 1000)]
+        mise_max: Valeur,
+    },
+
+    /// Mesurer ce que coûte l'obligation de relancer quand tous les dés ont
+    /// servi, notamment en début de tour et après une suite
+    Plein,
+
+    /// Mesurer comment l'espérance de gain change quand les dés sont pipés en
+    /// faveur du 1
+    Pipage {
+        /// Probabilité ajoutée à celle de sortir un 1, et prise aux autres
+        /// faces à parts égales
+        #[arg(long, default_value_t = 0.02)]
+        biais: Flottant,
+    },
+
+    /// Comparer la fin de partie quand il faut atterrir exactement sur le
+    /// score maximal et quand on rebondit dessus
+    Rebond {
+        /// Ecart au score maximal à partir duquel on compare
+        #[arg(long, default_value_t = 1000)]
+        depuis: Valeur,
+
+        /// Pas entre deux scores étudiés
+        #[arg(long, default_value_t = 50,
+              value_parser = clap::builder::RangedU64ValueParser::<Valeur>::new().range(1..))]
+        pas: Valeur,
+    },
+
+    /// Etudier les parties en un nombre fixe de tours, sans score à
+    /// atteindre, où le meilleur total gagne
+    ToursFixes {
+        /// Nombre de tours joués par chaque joueur
+        #[arg(long, default_value_t = 10,
+              value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        tours: usize,
+
+        /// Plus grand retard étudié au dernier tour
+        #[arg(long, default_value_t = 3000)]
+        retard_max: Valeur,
+
+        /// Pas entre deux retards étudiés
+        #[arg(long, default_value_t = 250,
+              value_parser = clap::builder::RangedU64ValueParser::<Valeur>::new().range(1..))]
+        pas: Valeur,
+
+        /// Nombre de parties à deux joueurs à simuler
+        #[arg(long, alias = "games", default_value_t = 20_000)]
+        parties: usize,
+
+        /// Graine du générateur de nombres aléatoires
+        #[arg(long, default_value_t = 0)]
+        graine: u64,
+    },
+
+    /// Vérifier les espérances de gain analytiques par simulation
+    Verification {
+        /// Nombre de tours à simuler pour chaque état étudié
+        #[arg(long, default_value_t = 100_000)]
+        tours: usize,
+
+        /// Graine du générateur de nombres aléatoires
+        #[arg(long, default_value_t = 0)]
+        graine: u64,
+    },
+
+    /// Faire s'affronter la stratégie optimale et une stratégie naïve
+    Combats {
+        /// Nombre de parties à jouer
+        #[arg(long, default_value_t = 1000)]
+        parties: usize,
+
+        /// Graine du générateur de nombres aléatoires
+        #[arg(long, default_value_t = 0)]
+        graine: u64,
+
+        /// Afficher le déroulement de chaque partie
+        #[arg(long)]
+        transcription: bool,
+    },
+
+    /// Faire s'affronter toutes les paires de stratégies prédéfinies
+    Tournoi {
+        /// Nombre de parties à jouer par confrontation
+        #[arg(long, alias = "games", default_value_t = 1000)]
+        parties: usize,
+
+        /// Graine du générateur de nombres aléatoires
+        #[arg(long, default_value_t = 0)]
+        graine: u64,
+
+        /// Ecrire la matrice des taux de victoire au format CSV
+        #[arg(long)]
+        csv: bool,
+
+        /// Transcrire en détail les N premières parties perdues par une
+        /// stratégie (sur la sortie d'erreur en CSV)
+        #[arg(long, alias = "trace-losses", value_name = "N", default_value_t = 0)]
+        defaites: usize,
+
+        /// Stratégie dont on transcrit les parties perdues
+        #[arg(long, default_value = "Optimale",
+              value_parser = clap::builder::PossibleValuesParser::new(NOMS_TOURNOI))]
+        perdant: String,
+    },
+
+    /// Comparer deux stratégies prédéfinies sur des paires de parties aux dés
+    /// appariés, avec un intervalle de confiance sur l'écart de leurs taux de
+    /// victoire (comparer une stratégie à elle-même vérifie l'appariement)
+    Comparer {
+        /// Première stratégie
+        #[arg(long, default_value = "Optimale",
+              value_parser = clap::builder::PossibleValuesParser::new(NOMS_TOURNOI))]
+        a: String,
+
+        /// Seconde stratégie
+        #[arg(long, default_value = "Dernière chance",
+              value_parser = clap::builder::PossibleValuesParser::new(NOMS_TOURNOI))]
+        b: String,
+
+        /// Nombre de paires de parties à jouer (chaque stratégie commence
+        /// l'une des deux parties de la paire)
+        #[arg(long, alias = "pairs", default_value_t = 10_000,
+              value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(2..))]
+        paires: usize,
+
+        /// Graine du générateur de nombres aléatoires
+        #[arg(long, default_value_t = 0)]
+        graine: u64,
+    },
+
+    /// Faire jouer des parties entre plusieurs stratégies prédéfinies, chacune
+    /// à sa place autour de la table (la première commence)
+    Partie {
+        /// Stratégie de chaque joueur, dans l'ordre où ils jouent (répéter
+        /// l'option pour chaque joueur)
+        #[arg(long = "joueur", value_name = "STRATEGIE",
+              default_values = ["Optimale", "Seuil à 300", "Seuil à 300", "Seuil à 300"],
+              value_parser = clap::builder::PossibleValuesParser::new(NOMS_TOURNOI))]
+        joueurs: Vec<String>,
+
+        /// Nombre de parties à jouer
+        #[arg(long, alias = "games", default_value_t = 20_000)]
+        parties: usize,
+
+        /// Graine du générateur de nombres aléatoires
+        #[arg(long, default_value_t = 0)]
+        graine: u64,
+    },
+
+    /// Mesurer l'avantage de chaque place autour de la table, à 2, 3 et 4
+    /// joueurs qui maximisent tous leur espérance de gain
+    Ordre {
+        /// Nombre de blocs de parties à jouer pour chaque nombre de joueurs
+        /// (un bloc compte une partie par joueur)
+        #[arg(long, default_value_t = 10_000,
+              value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(2..))]
+        blocs: usize,
+
+        /// Graine du générateur de nombres aléatoires
+        #[arg(long, default_value_t = 0)]
+        graine: u64,
+    },
+
+    /// Jouer une partie entre plusieurs stratégies prédéfinies, et
+    /// l'enregistrer en JSON avec les règles, les dés et chaque décision
+    Enregistrer {
+        /// Fichier dans lequel écrire l'enregistrement
+        #[arg(long, short)]
+        sortie: PathBuf,
+
+        /// Stratégie de chaque joueur, dans l'ordre où ils jouent (répéter
+        /// l'option pour chaque joueur)
+        #[arg(long = "joueur", value_name = "STRATEGIE",
+              default_values = ["Optimale", "Dernière chance"],
+              value_parser = clap::builder::PossibleValuesParser::new(NOMS_TOURNOI))]
+        joueurs: Vec<String>,
+
+        /// Graine du générateur de nombres aléatoires
+        #[arg(long, default_value_t = 0)]
+        graine: u64,
+    },
+
+    /// Rejouer une partie enregistrée à travers le moteur de jeu, avec les
+    /// règles de l'enregistrement, pour vérifier que chaque décision était
+    /// autorisée et que les scores finaux sont les bons
+    Rejouer {
+        /// Fichier contenant l'enregistrement
+        #[arg(long, short)]
+        entree: PathBuf,
+    },
+
+    /// Analyser chaque décision d'une partie enregistrée, avec les règles de
+    /// l'enregistrement, en la comparant à la meilleure décision possible
+    /// (--format json pour un rapport destiné à d'autres programmes)
+    Analyser {
+        /// Fichier contenant l'enregistrement
+        #[arg(long, short)]
+        entree: PathBuf,
+    },
+
+    /// Comparer les options contestées de quelques jets de dés prédéfinis
+    /// (un seul 5, un seul 1, deux 5, brelan et un 1, suite), selon le score
+    /// et la mise
+    Scenarios {
+        /// N'étudier que ce scénario
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(
+            scenarios::scenarios().into_iter().map(|scenario| scenario.nom)))]
+        nom: Option<String>,
+
+        /// Scores pour lesquels on étudie chaque scénario
+        #[arg(long, value_delimiter = ',', default_values_t = [0, 5000, 9000])]
+        scores: Vec<Valeur>,
+
+        /// Mises accumulées avant le jet pour lesquelles on étudie chaque
+        /// scénario
+        #[arg(long, value_delimiter = ',', default_values_t = [0, 300, 500, 1000, 2000])]
+        mises: Vec<Valeur>,
+    },
+
+    /// Mesurer ce que coûtent quelques règles de maison par rapport à la
+    /// stratégie optimale: espérance de gain par tour (calcul exact), taux de
+    /// victoire contre elle (simulation) et états où elles perdent le plus
+    Heuristiques {
+        /// Nombre d'états du tour les plus coûteux à afficher pour chaque
+        /// heuristique
+        #[arg(long, default_value_t = 5)]
+        etats: usize,
+
+        /// Nombre de paires de parties à jouer contre la stratégie optimale
+        /// pour chaque heuristique (voir la commande comparer)
+        #[arg(long, alias = "pairs", default_value_t = 5_000,
+              value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(2..))]
+        paires: usize,
+
+        /// Graine du générateur de nombres aléatoires
+        #[arg(long, default_value_t = 0)]
+        graine: u64,
+    },
+
+    /// Mesurer la durée des parties entre plusieurs joueurs qui maximisent
+    /// tous leur espérance de gain
+    Duree {
+        /// Nombre de joueurs autour de la table
+        #[arg(long, default_value_t = 3,
+              value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        joueurs: usize,
+
+        /// Nombre de parties à jouer
+        #[arg(long, alias = "games", default_value_t = 20_000)]
+        parties: usize,
+
+        /// Graine du générateur de nombres aléatoires
+        #[arg(long, default_value_t = 0)]
+        graine: u64,
+    },
+}
+
+
+fn main() -> io::Result<()> {
+    let options = Options::parse();
+
+    // Les messages de la bibliothèque vont sur la sortie d'erreur, pour ne pas
+    // se mélanger aux résultats. RUST_LOG a le dernier mot.
+    let niveau = match (options.quiet, options.verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Warn,
+        (false, 1) => LevelFilter::Info,
+        (false, 2) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    };
+    env_logger::Builder::new().filter_level(niveau).parse_default_env().init();
+
+    // Une partie enregistrée s'analyse avec ses propres règles, qu'on vérifie
+    // avant de préparer les statistiques, comme le reste de l'enregistrement
+    let enregistrement = match &options.commande {
+        Some(Commande::Analyser { entree }) => {
+            let enregistrement = EnregistrementPartie::lire(entree)?;
+            enregistrement.verifier()
+                          .map_err(|erreur| io::Error::new(io::ErrorKind::InvalidData, erreur))?;
+            Some(enregistrement)
+        },
+        _ => None,
+    };
+    let regles = Regles {
+        atterrissage_exact: !options.depassement,
+        rebond: options.rebond,
+        seuil_ouverture: options.seuil_ouverture,
+        mise_minimale_pour_stopper: options.mise_minimale,
+        reprise: options.reprise,
+        dernier_tour: options.dernier_tour,
+        relance_plein: options.relance_plein,
+        penalite_pertes: options.penalite_pertes,
+        ..Regles::default()
+    };
+    let regles = match &enregistrement {
+        Some(enregistrement) => enregistrement.regles.clone(),
+        None => regles,
+    };
+
+    // Si on ne s'intéresse qu'à une situation, on vérifie qu'elle a un sens
+    // avant de se lancer dans des calculs
+    let situation = match (options.score, options.des, options.mise) {
+        (Some(score), Some(nb_des), Some(mise)) => {
+            if options.commande.is_some() {
+                Options::command().error(ErrorKind::ArgumentConflict,
+                                         "--score, --des et --mise ne s'utilisent \
+                                          pas avec une sous-commande").exit();
+            }
+            if let Err(message) = EtatTour::new(score, nb_des, mise).verifier(&regles) {
+                Options::command().error(ErrorKind::ValueValidation, message).exit();
+            }
+            Some((score, nb_des, mise))
+        },
+        _ => None,
+    };
+    if let Some(Commande::Arbre { score, des, mise, .. }) = options.commande {
+        if let Err(message) = EtatTour::new(score, des, mise).verifier(&regles) {
+            Options::command().error(ErrorKind::ValueValidation, message).exit();
+        }
+    }
+    if matches!(options.commande, Some(Commande::Pertes { .. })) && regles.penalite_pertes == 0 {
+        Options::command().error(ErrorKind::MissingRequiredArgument,
+                                 "La commande pertes demande une pénalité (--penalite-pertes)")
+                          .exit();
+    }
+    if matches!(options.commande, Some(Commande::Duel { .. }))
+       && !course::regles_etudiables(&regles)
+    {
+        Options::command().error(ErrorKind::ArgumentConflict,
+                                 "La commande duel ne tient pas compte de --reprise, \
+                                  --dernier-tour, --rebond ni --penalite-pertes")
+                          .exit();
+    }
+    if let Some(Commande::Pipage { biais }) = options.commande {
+        if !regles_pipees(&regles, biais).poids_faces_valides() {
+            let message = format!("Biais impossible: {}", biais);
+            Options::command().error(ErrorKind::ValueValidation, message).exit();
+        }
+    }
+    if let Some(Commande::Table { des, .. } | Commande::Lancer { des, .. }) = options.commande {
+        if des > regles.nb_des_tot {
+            let message = format!("On ne peut pas lancer {} dés (maximum {})",
+                                  des, regles.nb_des_tot);
+            Options::command().error(ErrorKind::ValueValidation, message).exit();
+        }
+    }
+
+    // Tout d'abord, on explore les résultats de jets possibles, à moins qu'on
+    // ne l'ait déjà fait lors d'une exécution précédente...
+    let mut stats = match &options.cache {
+        Some(chemin) if chemin.exists() => {
+            Stats::charger(chemin, &regles).unwrap_or_else(|erreur| {
+                warn!("Cache {} ignoré: {}", chemin.display(), erreur);
+                Stats::with_regles(regles.clone())
+            })
+        },
+        _ => Stats::with_regles(regles.clone()),
+    };
+    stats.limiter_caches(options.capacite_caches);
+
+    // Les calculs pouvant être longs, on affiche leur avancement si quelqu'un
+    // est là pour le voir (mais pas en mode conseil, où ça gênerait la saisie)
+    let conseil = matches!(options.commande, Some(Commande::Conseil));
+    let barre = (!options.quiet && !conseil && io::stderr().is_terminal())
+        .then(|| suivre_progression(&mut stats));
+
+    // ...puis on fait ce qui nous a été demandé
+    match options.commande {
+        None => if let Some((score, nb_des, mise)) = situation {
+            afficher_situation(&stats, score, nb_des, mise)
+        } else {
+            let tables = Tables::new(&stats);
+            #[cfg(feature = "plots")]
+            if let Some(dossier) = &options.plot {
+                mitraillette::graphiques::tracer(&tables, dossier)?;
+            }
+            afficher_tables(&stats, &tables, options.format)?
+        },
+        Some(Commande::Strategie { sortie }) => ecrire_strategie(&stats, sortie)?,
+        Some(Commande::Table { des, pas_score, pas_mise }) => {
+            afficher_grille(&stats, des, pas_score, pas_mise, options.format)?
+        },
+        Some(Commande::Atterrissages { pas_mise, mise_max }) => {
+            afficher_atterrissages(&stats, pas_mise, mise_max, options.format)?
+        },
+        Some(Commande::Conseil) => conseil::conseil(&stats)?,
+        Some(Commande::Lancer { des, graine }) => afficher_lancer(&stats, des, graine),
+        Some(Commande::Arbre { score, des, mise, profondeur, dot, noeuds_max, proba_min }) => {
+            let arbre = stats.arbre(score, des, mise, profondeur);
+            match dot {
+                Some(chemin) => {
+                    let mut sortie = BufWriter::new(File::create(chemin)?);
+                    stats::ecrire_dot(&arbre, &mut sortie, noeuds_max, proba_min)?;
+                    sortie.flush()?;
+                },
+                None => print!("{}", arbre),
+            }
+        },
+        Some(Commande::Tours) => afficher_tours(&stats),
+        Some(Commande::Projection { score, tours }) => afficher_projection(&stats, score, tours),
+        Some(Commande::Course { mon_score, score_adverse }) => {
+            afficher_course(&stats, mon_score, score_adverse)
+        },
+        Some(Commande::Exploitation { seuil, depart }) => {
+            afficher_exploitation(&stats, seuil, depart)
+        },
+        Some(Commande::Duel { pas }) => afficher_duel(&stats, pas),
+        Some(Commande::Exact) => afficher_exact(&stats),
+        Some(Commande::Convergence { relances }) => afficher_convergence(&stats, relances),
+        Some(Commande::Seuils { pas }) => afficher_seuils(&stats, pas),
+        Some(Commande::Pertes { pas }) => afficher_pertes(&stats, pas),
+        Some(Commande::Aversion { score }) => afficher_aversion(&stats, score),
+        Some(Commande::Variance { score }) => afficher_variance(&stats, score),
+        Some(Commande::Strategies) => afficher_strategies(&stats),
+        Some(Commande::Minimum { mise, mise_max }) => afficher_minimum(&stats, mise, mise_max),
+        Some(Commande::Plein) => afficher_plein(&stats),
+        Some(Commande::Pipage { biais }) => afficher_pipage(&stats, biais),
+        Some(Commande::Rebond { depuis, pas }) => afficher_rebond(&stats, depuis, pas),
+        Some(Commande::ToursFixes { tours, retard_max, pas, parties, graine }) => {
+            afficher_tours_fixes(&stats, tours, retard_max, pas, parties, graine)
+        },
+        Some(Commande::Verification { tours, graine }) => {
+            afficher_verification(&stats, tours, graine)
+        },
+        Some(Commande::Combats { parties, graine, transcription }) => {
+            afficher_combats(&stats, parties, graine, transcription)
+        },
+        Some(Commande::Tournoi { parties, graine, csv, defaites, perdant }) => {
+            afficher_tournoi(&stats, parties, graine, csv, defaites, &perdant)
+        },
+        Some(Commande::Comparer { a, b, paires, graine }) => {
+            afficher_comparaison(&stats, &a, &b, paires, graine)
+        },
+        Some(Commande::Partie { joueurs, parties, graine }) => {
+            afficher_partie(&stats, &joueurs, parties, graine)
+        },
+        Some(Commande::Ordre { blocs, graine }) => afficher_ordre(&stats, blocs, graine),
+        Some(Commande::Enregistrer { sortie, joueurs, graine }) => {
+            enregistrer_partie(&stats, sortie, &joueurs, graine)?
+        },
+        Some(Commande::Rejouer { entree }) => rejouer_partie(entree)?,
+        Some(Commande::Scenarios { nom, scores, mises }) => {
+            afficher_scenarios(&stats, nom.as_deref(), &scores, &mises)
+        },
+        Some(Commande::Heuristiques { etats, paires, graine }) => {
+            afficher_heuristiques(&stats, etats, paires, graine)
+        },
+        Some(Commande::Analyser { .. }) => {
+            let enregistrement = enregistrement.expect("L'enregistrement a été lu plus haut");
+            afficher_analyse(&stats, &enregistrement, options.format)?
+        },
+        Some(Commande::Duree { joueurs, parties, graine }) => {
+            afficher_duree(&stats, joueurs, parties, graine)
+        },
+    }
+
+    // ...et on garde ce qu'on a calculé pour la prochaine fois
+    if let Some(barre) = barre { barre.finish_and_clear(); }
+    if options.statistiques_caches { afficher_statistiques_caches(&stats); }
+    if let Some(chemin) = &options.cache {
+        stats.sauvegarder(chemin)?;
+    }
+    Ok(())
+}
+
+// Bilan de l'utilisation des caches de calcul, sur la sortie d'erreur pour ne
+// pas se mélanger aux résultats
+fn afficher_statistiques_caches(stats: &Stats) {
+    eprintln!("\n=== UTILISATION DES CACHES ===\n");
+    for cache in stats.statistiques_cache() {
+        if cache.succes + cache.echecs + cache.insertions == 0 && cache.taille == 0 {
+            continue;
+        }
+        eprintln!("- {} dé{}, {}: {} succès, {} échecs ({:.1}% de succès), {} insertions, \
+                   {} résultats en cache",
+                  cache.nb_des, if cache.nb_des > 1 { "s" } else { "" }, cache.grandeur,
+                  cache.succes, cache.echecs, cache.taux_succes() * 100.,
+                  cache.insertions, cache.taille);
+    }
+    eprintln!();
+}
+
+// Mise en place d'une barre de progression sur la sortie d'erreur, qui indique
+// ce qu'on est en train de calculer
+fn suivre_progression(stats: &mut Stats) -> ProgressBar {
+    let barre = ProgressBar::new_spinner();
+    let barre_suivi = barre.clone();
+    stats.set_progression(move |progression| {
+        barre_suivi.set_message(format!("Score {}, {} dés, {} relances ({} résultats en cache)",
+                                        progression.score,
+                                        progression.nb_des,
+                                        progression.relances,
+                                        progression.taille_caches));
+        barre_suivi.tick();
+    });
+    barre
+}
+
+// Affichage de ce qu'il faut savoir sur une seule situation de jeu
+fn afficher_situation(stats: &Stats, score: Valeur, nb_des: usize, mise: Valeur) {
+    let gain = stats.statistiques_gain(score, nb_des, mise);
+    let bilan = stats.bilan(score, nb_des, mise);
+    println!("Score {}, {} dés, mise {}:", score, nb_des, mise);
+    println!("- Gain moyen en lançant: {:+} ± {}", gain.moyenne, gain.ecart_type);
+    println!("- Probabilité de perdre la mise: {:.1}%", bilan.proba_perte * 100.);
+    println!("- Gain moyen quand on ne perd pas: {:+}", bilan.gain_moyen_si_reussite);
+    match stats.relances_necessaires(score, nb_des, mise) {
+        Some(relances) => println!("- Relances étudiées: {}", relances),
+        None => println!("- Relances étudiées: {} (sans convergence!)", NB_RELANCES_MAX),
+    }
+    // On n'a le choix que si la règle nous laisse encaisser la mise, et on
+    // tranche alors comme la stratégie optimale (voir Stats::seuil_arret)
+    let etat = EtatTour::new(score, nb_des, mise);
+    if !stats.arret_possible(&etat) {
+        println!("- Action recommandée: lancer les dés (la règle interdit d'encaisser)");
+    } else if gain.moyenne > 0. {
+        println!("- Action recommandée: lancer les dés");
+    } else {
+        println!("- Action recommandée: encaisser la mise");
+    }
+}
+
+// Affichage des tables d'espérance de gain à score nul et de probabilité de
+// gagner lors de ce tour
+fn afficher_tables(stats: &Stats, tables: &Tables, format: Format) -> io::Result<()> {
+    let regles = stats.regles();
+
+    // Dans les formats structurés, on n'écrit que les tables sur la sortie
+    // standard, pour qu'un autre programme puisse les relire directement
+    let stdout = io::stdout();
+    match format {
+        Format::Json => {
+            sortie::ecrire_json(tables, stdout.lock())?;
+            println!();
+            return Ok(());
+        },
+        Format::Csv => return sortie::ecrire_csv(tables, stdout.lock()),
+        Format::Markdown => return sortie::ecrire_markdown(tables, stdout.lock()),
+        Format::Texte => {},
+    }
+
+    // Le chiffre qu'on cite le plus souvent: la probabilité de ne rien tirer
+    println!("\n=== PROBABILITE DE NE RIEN TIRER ===\n");
+    let probas_rien = (1..=regles.nb_des_tot).map(|nb_des| {
+        format!("{} dé{}: {:.1}%", nb_des, if nb_des > 1 { "s" } else { "" },
+                stats.proba_rien(nb_des) * 100.)
+    }).collect::<Vec<_>>();
+    println!("{}", probas_rien.join(", "));
+    println!();
+
+    // On tabule les espérances de gain à score nul
+    println!("\n=== ESPERANCES DE GAIN A SCORE NUL ===");
+    for nb_des in 1..=regles.nb_des_tot {
+        println!("\nEn lançant {} dés:", nb_des);
+        for ligne in tables.esperances.iter().filter(|l| l.nb_des == nb_des) {
+            println!("- Mise {}: {:+} ± {} (perte {:.1}%, sinon {:+})",
+                     ligne.mise, ligne.gain_moyen, ligne.ecart_type,
+                     ligne.proba_perte * 100., ligne.gain_moyen_si_reussite);
+        }
+    }
+    println!();
+
+    // Avec un seuil d'ouverture, on se demande combien de tours il faut pour
+    // ouvrir, et donc ce qu'il en coûte par rapport à un joueur déjà ouvert
+    if regles.seuil_ouverture > 0 {
+        println!("\n=== OUVERTURE A {} POINTS ===\n", regles.seuil_ouverture);
+        println!("Nombre moyen de tours pour ouvrir: {:.2}", stats.tours_pour_ouvrir());
+        println!();
+    }
+
+    // Ensuite, on s'intéresse aux "atterissages"
+    println!("\n=== PROBABILITE DE GAGNER CE TOUR-CI ===\n");
+    for ligne in tables.atterrissages.iter() {
+        let precision = if ligne.precision > 0. {
+            format!("±{:.0e}", ligne.precision)
+        } else {
+            "stable".to_owned()
+        };
+        println!("Score {}, {} dés sans mise: {} ({} après {} relances{})",
+                 ligne.score, ligne.nb_des, ligne.proba, precision, ligne.relances,
+                 if ligne.converge { "" } else { ", sous-estimée" });
+    }
+    println!();
+
+    // ...et à la probabilité de finir en quelques tours, qui donne une idée
+    // plus juste de l'intérêt des scores intermédiaires
+    println!("\n=== PROBABILITE DE GAGNER EN 1, 2 OU 3 TOURS ===\n");
+
+    let lignes = tables.atterrissages.par_iter().map(|ligne| {
+        let probas = (1..=3).map(|nb_tours| stats.proba_fin_en_tours(ligne.score, nb_tours))
+                            .collect::<Vec<_>>();
+        format!("Score {}: {} / {} / {}", ligne.score, probas[0], probas[1], probas[2])
+    }).collect::<Vec<_>>();
+    for ligne in lignes { println!("{}", ligne); }
+    println!();
+    Ok(())
+}
+
+// Affichage du nombre moyen de tours nécessaires pour finir la partie, en
+// fonction du score de départ
+fn afficher_tours(stats: &Stats) {
+    const TOURS_MAX : usize = 30;
+    println!("\n=== NOMBRE MOYEN DE TOURS POUR FINIR ===\n");
+    let scores = (0..stats.regles().score_max).step_by(500).collect::<Vec<_>>();
+    let lignes = scores.par_iter().map(|&score| {
+        format!("Score {}: {} tours", score, stats.esperance_tours(score))
+    }).collect::<Vec<_>>();
+    for ligne in lignes { println!("{}", ligne); }
+    println!();
+
+    // Au-delà de la moyenne, on regarde la distribution complète depuis le
+    // début de la partie, et ce qu'elle donne comme médiane et 9e décile
+    println!("\n=== NOMBRE DE TOURS POUR FINIR EN PARTANT DE 0 ===\n");
+    let distribution = stats.distribution_tours_victoire(0, TOURS_MAX);
+    let mut cumul = 0.;
+    let mut mediane = None;
+    let mut decile = None;
+    for (tour, &proba) in distribution.iter().enumerate() {
+        cumul += proba;
+        if mediane.is_none() && cumul >= 0.5 { mediane = Some(tour + 1); }
+        if decile.is_none() && cumul >= 0.9 { decile = Some(tour + 1); }
+        println!("En {} tour{}: {:.2}% (cumul {:.2}%)",
+                 tour + 1, if tour > 0 { "s" } else { "" }, proba * 100., cumul * 100.);
+    }
+    let decrire = |tours: Option<usize>| {
+        tours.map_or_else(|| format!("plus de {}", TOURS_MAX), |tours| tours.to_string())
+    };
+    println!("\nMédiane: {} tours, 9e décile: {} tours", decrire(mediane), decrire(decile));
+    println!();
+}
+
+// Affichage du score moyen après chaque tour, pour voir à quoi ressemble une
+// partie typique
+fn afficher_projection(stats: &Stats, score: Valeur, tours: usize) {
+    println!("\n=== SCORE MOYEN AU FIL DES TOURS (DEPART A {}) ===\n", score);
+    for (tour, score_moyen) in stats.projection(score, tours).into_iter().enumerate() {
+        println!("Après {} tour{}: {:.0} points",
+                 tour + 1, if tour > 0 { "s" } else { "" }, score_moyen);
+    }
+    println!();
+}
+
+// Affichage des états du tour où l'on ne joue pas de la même façon selon qu'on
+// maximise l'espérance de gain ou la probabilité de gagner la course
+fn afficher_course(stats: &Stats, mon_score: Valeur, score_adverse: Valeur) {
+    let regles = stats.regles();
+    let politique = stats.politique_course(mon_score, score_adverse);
+    println!("\n=== COURSE A {} CONTRE {} ===\n", mon_score, score_adverse);
+    println!("Probabilité de victoire: {:.1}%\n", politique.proba_victoire() * 100.);
+
+    let mises = stats.mises_atteignables();
+    for nb_des in 1..=regles.nb_des_tot {
+        for mise in mises.mises(nb_des) {
+            if mises.jet_impossible(mon_score, nb_des, mise) { continue; }
+            let relancer_esperance = stats.gain_moyen(mon_score, nb_des, mise) > 0.;
+            let proba_relance = politique.proba_victoire_relance(nb_des, mise);
+            let proba_arret = politique.proba_victoire_arret(mise);
+            let relancer_course = proba_relance > proba_arret;
+            if relancer_esperance == relancer_course { continue; }
+            let decrire = |relancer| if relancer { "relancer" } else { "encaisser" };
+            println!("- {} dés, mise {}: {} pour l'espérance, {} pour la course \
+                      (victoire {:.1}% en relançant, {:.1}% en encaissant)",
+                     nb_des, mise, decrire(relancer_esperance),
+                     decrire(relancer_course), proba_relance * 100.,
+                     proba_arret * 100.);
+        }
+    }
+    println!();
+}
+
+// Affichage de la probabilité de victoire de la meilleure réponse à un
+// adversaire qui encaisse à seuil fixe, comparée à celle qu'on a face à un
+// adversaire qui maximise son espérance de gain. Comme pour la course, le
+// calcul n'est praticable qu'en fin de partie, sauf à être très patient.
+fn afficher_exploitation(stats: &Stats, seuil: Valeur, depart: bool) {
+    let adversaire = SeuilFixe(seuil);
+    let mut etats = vec![(9500, 9500), (9000, 9000), (9000, 8000), (8000, 9000), (8000, 8000)];
+    if depart { etats.push((0, 0)); }
+    println!("\n=== MEILLEURE REPONSE A UN ADVERSAIRE QUI ENCAISSE A {} ===\n", seuil);
+    println!("Probabilité de victoire quand c'est à moi de jouer:\n");
+    for (mon_score, score_adverse) in etats {
+        let contre_seuil = stats.meilleure_reponse(&adversaire, mon_score, score_adverse)
+                                .proba_victoire();
+        let contre_optimale = stats.politique_course(mon_score, score_adverse)
+                                   .proba_victoire();
+        println!("- {} contre {}: {:.1}% (contre {:.1}% face à la stratégie optimale)",
+                 mon_score, score_adverse, contre_seuil * 100., contre_optimale * 100.);
+    }
+    println!();
+}
+
+// Affichage de la probabilité de victoire du joueur dont c'est le tour quand
+// les deux joueurs maximisent leur espérance de gain, selon les scores, et de
+// l'avantage qu'a le premier joueur en début de partie
+fn afficher_duel(stats: &Stats, pas: Valeur) {
+    let score_max = stats.regles().score_max;
+    let duel = stats.duel();
+    println!("\n=== DUEL ENTRE DEUX JOUEURS QUI MAXIMISENT LEUR ESPERANCE ===\n");
+    println!("Probabilité de victoire du joueur dont c'est le tour (lignes: son \
+              score, colonnes: celui de l'adversaire):\n");
+    let scores = (0..score_max).step_by(pas as usize).collect::<Vec<_>>();
+    print!("{:>8}", "Score");
+    for score_adverse in scores.iter() { print!("{:>8}", score_adverse); }
+    println!();
+    for &score_joueur in scores.iter() {
+        print!("{:>8}", score_joueur);
+        for &score_adverse in scores.iter() {
+            let proba = duel.proba_victoire(score_joueur, score_adverse);
+            print!("{:>8}", format!("{:.1}%", proba * 100.));
+        }
+        println!();
+    }
+    let proba_depart = duel.proba_victoire(0, 0);
+    println!("\nLe premier joueur gagne {:.2}% des parties (avantage de {:+.2} points)\n",
+             proba_depart * 100., (proba_depart - 0.5) * 100.);
+}
+
+// Affichage de l'espérance de gain en fonction du nombre de relances
+// autorisées, pour quelques situations de jeu représentatives
+fn afficher_convergence(stats: &Stats, max_relances: usize) {
+    const SITUATIONS : [(Valeur, usize, Valeur); 5] = [(0, 6, 0),
+                                                       (0, 3, 150),
+                                                       (0, 1, 250),
+                                                       (7000, 4, 100),
+                                                       (9500, 6, 0)];
+    println!("\n=== CONVERGENCE DE L'ESPERANCE DE GAIN ===");
+    for &(score, nb_des, mise) in SITUATIONS.iter() {
+        println!("\nScore {}, {} dés, mise {}:", score, nb_des, mise);
+        let courbe = stats.courbe_convergence(score, nb_des, mise, max_relances);
+        for (relances, esperance) in courbe.iter().enumerate() {
+            println!("- Jusqu'à {} relances: {}", relances, esperance);
+        }
+    }
+    println!();
+}
+
+// Comparaison de l'espérance de gain à score nul avec les règles choisies et
+// avec une mise minimale pour s'arrêter, qui compte surtout quand on n'a pas
+// encore beaucoup misé
+fn afficher_minimum(stats: &Stats, mise_minimale: Valeur, mise_max: Valeur) {
+    let regles = stats.regles();
+    let stats_minimum = Stats::with_regles(Regles {
+        mise_minimale_pour_stopper: mise_minimale,
+        ..regles.clone()
+    });
+    let mises = stats.mises_atteignables();
+    println!("\n=== ESPERANCE A SCORE NUL AVEC UNE MISE MINIMALE DE {} ===", mise_minimale);
+    for nb_des in 1..=regles.nb_des_tot {
+        println!("\nEn lançant {} dés:", nb_des);
+        for mise in mises.mises(nb_des).filter(|&mise| mise <= mise_max) {
+            let sans = stats.esperance(0, nb_des, mise);
+            let avec = stats_minimum.esperance(0, nb_des, mise);
+            println!("- Mise {}: {:.1} au lieu de {:.1} ({:+.1})", mise, avec, sans, avec - sans);
+        }
+    }
+    println!();
+}
+
+// Comparaison de l'espérance de gain avec et sans obligation de relancer quand
+// tous les dés ont servi, en début de tour et quand on vient de tirer une
+// suite (qui ne vaut plus ses points tout de suite, puisqu'il faut relancer)
+fn afficher_plein(stats: &Stats) {
+    let regles = stats.regles();
+    let regles_avec = Regles { relance_plein: true, ..regles.clone() };
+    let regles_sans = Regles { relance_plein: false, ..regles.clone() };
+    let stats_avec = Stats::with_regles(regles_avec);
+    let stats_sans = Stats::with_regles(regles_sans);
+    let nb_des = regles.nb_des_tot;
+    let valeur_suite = regles.bareme.suite;
+    let valeur_avec = |score| stats_avec.esperance(score, nb_des, valeur_suite);
+    let valeur_sans = |score| {
+        stats_sans.esperance(score, nb_des, valeur_suite).max(valeur_suite as Flottant)
+    };
+    println!("\n=== OBLIGATION DE RELANCER QUAND TOUS LES DES ONT SERVI ===\n");
+    for score in [0, regles.score_max / 2, regles.score_max * 9 / 10] {
+        let avec = stats_avec.esperance(score, nb_des, 0);
+        let sans = stats_sans.esperance(score, nb_des, 0);
+        println!("- Score {}, début de tour: espérance {:.1} au lieu de {:.1} ({:+.1})",
+                 score, avec, sans, avec - sans);
+        println!("- Score {}, suite tirée en début de tour: {:.1} au lieu de {:.1} ({:+.1})",
+                 score, valeur_avec(score), valeur_sans(score),
+                 valeur_avec(score) - valeur_sans(score));
+    }
+    println!();
+}
+
+// Comparaison côte à côte de la fin de partie avec atterrissage exact et avec
+// rebond sur le score maximal, en début de tour
+fn afficher_rebond(stats: &Stats, depuis: Valeur, pas: Valeur) {
+    let regles = stats.regles();
+    let stats_exact = Stats::with_regles(Regles { atterrissage_exact: true,
+                                                  rebond: false,
+                                                  ..regles.clone() });
+    let stats_rebond = Stats::with_regles(Regles { rebond: true, ..regles.clone() });
+    let nb_des = regles.nb_des_tot;
+    println!("\n=== FIN DE PARTIE: ATTERRISSAGE EXACT / REBOND ===\n");
+    println!("{:>8}{:>20}{:>20}{:>20}", "Score", "Espérance", "Proba de perte", "Tours restants");
+    let debut = regles.score_max.saturating_sub(depuis);
+    for score in (debut..regles.score_max).step_by(pas as usize) {
+        println!("{:>8}{:>20}{:>20}{:>20}",
+                 score,
+                 format!("{:.1} / {:.1}", stats_exact.esperance(score, nb_des, 0),
+                         stats_rebond.esperance(score, nb_des, 0)),
+                 format!("{:.1}% / {:.1}%", stats_exact.proba_perte(score, nb_des, 0) * 100.,
+                         stats_rebond.proba_perte(score, nb_des, 0) * 100.),
+                 format!("{:.1} / {:.1}", stats_exact.esperance_tours(score),
+                         stats_rebond.esperance_tours(score)));
+    }
+    println!("\n(avec rebond, un encaissement qui ferait reculer compte comme un tour perdu)\n");
+}
+
+// Etude des parties en un nombre fixe de tours: total qu'on peut espérer, sa
+// distribution, et ce que change le fait de jouer son dernier tour pour
+// dépasser l'adversaire plutôt que pour maximiser l'espérance
+fn afficher_tours_fixes(stats: &Stats,
+                        nb_tours: usize,
+                        retard_max: Valeur,
+                        pas: Valeur,
+                        nb_parties: usize,
+                        graine: u64) {
+    let stats_tours = Stats::with_regles(stats.regles().en_tours_fixes(nb_tours));
+    println!("\n=== PARTIES EN {} TOURS ===\n", nb_tours);
+    let distribution = tours_fixes::distribution_finale(&stats_tours, nb_tours);
+    println!("Total moyen après {} tours: {:.1}",
+             nb_tours, tours_fixes::esperance_finale(&stats_tours, nb_tours));
+    let mut proba_cumulee = 0.;
+    let cumul = distribution.iter().map(|&(score, proba)| {
+        proba_cumulee += proba;
+        (score, proba_cumulee)
+    }).collect::<Vec<_>>();
+    for proportion in [0.1, 0.25, 0.5, 0.75, 0.9] {
+        let score = cumul.iter()
+                         .find(|&&(_, cumulee)| cumulee >= proportion)
+                         .map_or(cumul.last().unwrap().0, |&(score, _)| score);
+        println!("- {:.0}% des joueurs finissent avec {} points au plus",
+                 proportion * 100., score);
+    }
+
+    println!("\nProbabilité de dépasser l'adversaire au dernier tour, en jouant pour le \
+              dépasser / en maximisant l'espérance:\n");
+    // Une fois qu'on a ouvert, seul le retard compte
+    let score = stats_tours.regles().seuil_ouverture;
+    for retard in (0..=retard_max).step_by(pas as usize) {
+        let (chasse, esperance) = tours_fixes::probas_dernier_tour(&stats_tours,
+                                                                   score,
+                                                                   score + retard);
+        println!("- Retard de {:>5}: {:>5.1}% / {:>5.1}%",
+                 retard, chasse * 100., esperance * 100.);
+    }
+
+    let resultats = duree::mesurer_duree(&stats_tours, 2, nb_parties, graine);
+    println!("\nSur {} parties à deux, le premier joueur gagne {:.1}% du temps \
+              (le second joue son dernier tour en connaissant le total à battre)\n",
+             nb_parties, resultats.taux_victoire(0) * 100.);
+}
+
+// Règles où les dés sont pipés pour sortir un 1 plus souvent, la probabilité
+// ajoutée étant prise aux autres faces à parts égales
+fn regles_pipees(regles: &Regles, biais: Flottant) -> Regles {
+    let nb_faces = regles.nb_faces as Flottant;
+    let mut poids = vec![1. / nb_faces - biais / (nb_faces - 1.); regles.nb_faces];
+    poids[0] = 1. / nb_faces + biais;
+    Regles { poids_faces: Some(poids), ..regles.clone() }
+}
+
+// Comparaison de l'espérance de gain avec des dés équilibrés et avec des dés
+// pipés en faveur du 1, pour quelques situations de début de tour
+fn afficher_pipage(stats: &Stats, biais: Flottant) {
+    let regles = stats.regles();
+    let stats_pipees = Stats::with_regles(regles_pipees(regles, biais));
+    let proba_un = stats_pipees.regles().poids_faces.as_ref().unwrap()[0];
+
+    println!("\n=== DES PIPES EN FAVEUR DU 1 ===\n");
+    println!("Probabilité de sortir un 1: {:.2}% au lieu de {:.2}%\n",
+             proba_un * 100., 100. / regles.nb_faces as Flottant);
+    for nb_des in 1..=regles.nb_des_tot {
+        println!("- Probabilité de ne rien tirer avec {} dés: {:.2}% au lieu de {:.2}%",
+                 nb_des, stats_pipees.proba_rien(nb_des) * 100., stats.proba_rien(nb_des) * 100.);
+    }
+    println!();
+    let nb_des = regles.nb_des_tot;
+    for score in [0, regles.score_max / 2, regles.score_max * 9 / 10] {
+        let equilibres = stats.esperance(score, nb_des, 0);
+        let pipes = stats_pipees.esperance(score, nb_des, 0);
+        println!("- Score {}, {} dés: espérance {:.1} au lieu de {:.1} ({:+.1}, {:+.1}%)",
+                 score, nb_des, pipes, equilibres, pipes - equilibres,
+                 (pipes / equilibres - 1.) * 100.);
+    }
+    println!();
+}
+
+// Comparaison de quelques probabilités calculées en flottants avec leur valeur
+// exacte, calculée en fractions
+fn afficher_exact(stats: &Stats) {
+    let regles = stats.regles();
+    let stats_exactes = StatsExactes::with_regles(regles.clone());
+
+    println!("\n=== PROBABILITE DE NE RIEN TIRER (CALCUL EXACT) ===\n");
+    for nb_des in 1..=regles.nb_des_tot {
+        let proba = stats_exactes.proba_rien(nb_des);
+        println!("{} dés: {} ≈ {}", nb_des, proba, proba.to_f64().unwrap());
+    }
+    println!();
+
+    println!("\n=== PROBABILITE DE GAGNER CE TOUR-CI AVEC 2 RELANCES ===\n");
+    let score_max = regles.score_max;
+    for score in (score_max-500..score_max).step_by(50).rev() {
+        let exacte = stats_exactes.proba_fin(score, regles.nb_des_tot, 0, 2);
+        let approchee = stats.proba_fin(score, regles.nb_des_tot, 0, 2);
+        println!("Score {}: {} ≈ {} (flottants: {})",
+                 score, exacte, exacte.to_f64().unwrap(), approchee);
+    }
+    println!();
+}
+
+// Affichage de la grille dense des gains moyens, rangée par rangée à mesure
+// que les calculs avancent
+fn afficher_grille(stats: &Stats,
+                   nb_des: usize,
+                   pas_score: Valeur,
+                   pas_mise: Valeur,
+                   format: Format) -> io::Result<()>
+{
+    let stdout = io::stdout();
+    let mut sortie = stdout.lock();
+    match format {
+        Format::Texte => writeln!(sortie, "\n=== GAIN MOYEN EN LANCANT {} DES ===", nb_des)?,
+        Format::Csv => writeln!(sortie, "score,nb_des,mise,gain_moyen")?,
+        Format::Markdown => {
+            writeln!(sortie, "| Score | Mise | Gain moyen |")?;
+            writeln!(sortie, "| ----: | ---: | ---------: |")?;
+        },
+        Format::Json => {},
+    }
+    for rangee in sortie::grille_gains(stats, nb_des, pas_score, pas_mise) {
+        if format == Format::Texte && !rangee.is_empty() {
+            writeln!(sortie, "\nScore {}:", rangee[0].score)?;
+        }
+        for ligne in rangee.iter() {
+            match format {
+                Format::Texte => writeln!(sortie, "- Mise {}: {:+.1}", ligne.mise, ligne.gain_moyen)?,
+                Format::Csv => writeln!(sortie, "{},{},{},{}",
+                                        ligne.score, ligne.nb_des, ligne.mise, ligne.gain_moyen)?,
+                Format::Markdown => writeln!(sortie, "| {} | {} | {:+.1} |",
+                                             ligne.score, ligne.mise, ligne.gain_moyen)?,
+                // En JSON, on écrit un objet par ligne pour pouvoir lire la
+                // grille au fil de l'eau
+                Format::Json => {
+                    serde_json::to_writer(&mut sortie, ligne).map_err(io::Error::from)?;
+                    writeln!(sortie)?;
+                },
+            }
+        }
+        sortie.flush()?;
+    }
+    Ok(())
+}
+
+// Affichage de la grille des probabilités de gagner ce tour-ci en fin de
+// partie, score par score à mesure que les calculs avancent. En texte, chaque
+// score donne un tableau avec une ligne par mise et une colonne par nombre de
+// dés.
+fn afficher_atterrissages(stats: &Stats,
+                          pas_mise: Valeur,
+                          mise_max: Valeur,
+                          format: Format) -> io::Result<()>
+{
+    let nb_des_tot = stats.regles().nb_des_tot;
+    let stdout = io::stdout();
+    let mut sortie = stdout.lock();
+    match format {
+        Format::Texte => writeln!(sortie, "\n=== PROBABILITE DE GAGNER CE TOUR-CI ===")?,
+        Format::Csv => writeln!(sortie, "score,nb_des,mise,proba,relances,precision,converge")?,
+        Format::Markdown => {
+            writeln!(sortie, "| Score | Dés | Mise | Probabilité |")?;
+            writeln!(sortie, "| ----: | --: | ---: | ----------: |")?;
+        },
+        Format::Json => {},
+    }
+    for rangee in sortie::grille_atterrissages(stats, pas_mise, mise_max) {
+        if rangee.is_empty() { continue; }
+        match format {
+            Format::Texte => {
+                writeln!(sortie, "\nScore {}:", rangee[0].score)?;
+                write!(sortie, "{:>8}", "Mise")?;
+                for nb_des in 1..=nb_des_tot {
+                    write!(sortie, "{:>8}",
+                           format!("{} dé{}", nb_des, if nb_des > 1 { "s" } else { "" }))?;
+                }
+                writeln!(sortie)?;
+                let mises = rangee.iter().map(|l| l.mise).collect::<BTreeSet<_>>();
+                for mise in mises {
+                    write!(sortie, "{:>8}", mise)?;
+                    for nb_des in 1..=nb_des_tot {
+                        let cellule = rangee.iter()
+                            .find(|l| l.nb_des == nb_des && l.mise == mise)
+                            .map_or_else(|| "-".to_owned(), |l| {
+                                format!("{:.1}%{}", l.proba * 100.,
+                                        if l.converge { "" } else { "?" })
+                            });
+                        write!(sortie, "{:>8}", cellule)?;
+                    }
+                    writeln!(sortie)?;
+                }
+            },
+            Format::Csv => for ligne in rangee.iter() {
+                writeln!(sortie, "{},{},{},{},{},{},{}",
+                         ligne.score, ligne.nb_des, ligne.mise, ligne.proba,
+                         ligne.relances, ligne.precision, ligne.converge)?;
+            },
+            Format::Markdown => for ligne in rangee.iter() {
+                writeln!(sortie, "| {} | {} | {} | {:.2}% |",
+                         ligne.score, ligne.nb_des, ligne.mise, ligne.proba * 100.)?;
+            },
+            // En JSON, on écrit un objet par ligne pour pouvoir lire la
+            // grille au fil de l'eau
+            Format::Json => for ligne in rangee.iter() {
+                serde_json::to_writer(&mut sortie, ligne).map_err(io::Error::from)?;
+                writeln!(sortie)?;
+            },
+        }
+        sortie.flush()?;
+    }
+    if format == Format::Texte {
+        writeln!(sortie, "\n(\"-\": situation impossible, \"?\": probabilité sous-estimée)\n")?;
+    }
+    Ok(())
+}
+
+// Affichage des seuils d'arrêt optimaux, l'antisèche qu'on garde à côté de la
+// piste de dés
+fn afficher_seuils(stats: &Stats, pas: Valeur) {
+    let regles = stats.regles();
+    let mises = stats.mises_atteignables();
+    println!("\n=== SEUILS D'ARRET OPTIMAUX ===\n");
+    println!("Encaisser dès que la mise atteint (selon les dés à relancer):\n");
+    print!("{:>8}", "Score");
+    for nb_des in 1..=regles.nb_des_tot {
+        print!("{:>8}", format!("{} dé{}", nb_des, if nb_des > 1 { "s" } else { "" }));
+    }
+    println!();
+    for score in (0..regles.score_max).step_by(pas as usize) {
+        print!("{:>8}", score);
+        for nb_des in 1..=regles.nb_des_tot {
+            // En fin de partie, toutes les combinaisons peuvent nous amener au
+            // score maximal, et il n'y a alors plus de relance à envisager
+            let relance_possible = mises.mises(nb_des).any(|mise| {
+                mise > 0 && score + mise < regles.score_max
+            });
+            let cellule = match stats.mise_equilibre(score, nb_des) {
+                Some(seuil) => seuil.to_string(),
+                None if relance_possible => "jamais".to_owned(),
+                None => "-".to_owned(),
+            };
+            print!("{:>8}", cellule);
+        }
+        println!();
+    }
+    println!("\n(\"-\": aucune mise ne permet de relancer sans atteindre {} points)",
+             regles.score_max);
+    if regles.atterrissage_exact {
+        println!("(en fin de partie, on ne peut s'arrêter que si aucune combinaison \
+                  tirée ne dépasse le score maximal)");
+    }
+    println!();
+}
+
+// Affichage des seuils d'arrêt sans série de pertes en cours, puis quand une
+// perte de plus coûterait la pénalité de la règle
+fn afficher_pertes(stats: &Stats, pas: Valeur) {
+    let regles = stats.regles();
+    let format_seuil = |seuil: Option<Valeur>| {
+        seuil.map_or_else(|| "jamais".to_owned(), |seuil| seuil.to_string())
+    };
+    println!("\n=== SEUILS D'ARRET SELON LA SERIE DE PERTES (PENALITE {}) ===\n",
+             regles.penalite_pertes);
+    println!("Encaisser dès que la mise atteint, sans perte récente / après {} pertes \
+              d'affilée (selon les dés à relancer):\n", NB_PERTES_PENALITE - 1);
+    print!("{:>8}", "Score");
+    for nb_des in 1..=regles.nb_des_tot {
+        print!("{:>14}", format!("{} dé{}", nb_des, if nb_des > 1 { "s" } else { "" }));
+    }
+    println!();
+    for score in (0..regles.score_max).step_by(pas as usize) {
+        print!("{:>8}", score);
+        for nb_des in 1..=regles.nb_des_tot {
+            let seuil = stats.seuil_arret(score, nb_des);
+            let seuil_serie = stats.seuil_arret_pertes(score, nb_des, NB_PERTES_PENALITE - 1);
+            print!("{:>14}", format!("{}/{}", format_seuil(seuil), format_seuil(seuil_serie)));
+        }
+        println!();
+    }
+    println!("\n(la pénalité ne s'applique pas à un score nul, qui ne peut pas baisser)\n");
+}
+
+// Affichage des seuils d'arrêt pour des aversions au risque croissantes, en
+// partant de la stratégie qui maximise l'espérance de gain (λ = 0)
+fn afficher_aversion(stats: &Stats, score: Valeur) {
+    const LAMBDAS : [Flottant; 5] = [1e-4, 3e-4, 1e-3, 2e-3, 3e-3];
+    let regles = stats.regles();
+    let format_seuil = |seuil: Option<Valeur>| {
+        seuil.map_or_else(|| "jamais".to_owned(), |seuil| seuil.to_string())
+    };
+    println!("\n=== SEUILS D'ARRET SELON L'AVERSION AU RISQUE (SCORE {}) ===\n", score);
+    print!("{:>8}", "λ");
+    for nb_des in 1..=regles.nb_des_tot {
+        print!("{:>8}", format!("{} dé{}", nb_des, if nb_des > 1 { "s" } else { "" }));
+    }
+    println!();
+    print!("{:>8}", 0);
+    for nb_des in 1..=regles.nb_des_tot {
+        print!("{:>8}", format_seuil(stats.seuil_arret(score, nb_des)));
+    }
+    println!();
+    for &lambda in LAMBDAS.iter() {
+        print!("{:>8}", lambda);
+        for nb_des in 1..=regles.nb_des_tot {
+            print!("{:>8}", format_seuil(stats.seuil_arret_utilite(score, nb_des, lambda)));
+        }
+        println!();
+    }
+    println!();
+}
+
+// Affichage des seuils d'arrêt d'une stratégie qui maximise E - k * V, pour
+// plusieurs valeurs de k. Avec k nul, on doit retrouver les seuils de la
+// stratégie qui maximise l'espérance, qu'on affiche en premier.
+fn afficher_variance(stats: &Stats, score: Valeur) {
+    const KS : [Flottant; 6] = [0., 1e-4, 3e-4, 1e-3, 3e-3, 1e-2];
+    let regles = stats.regles();
+    let format_seuil = |seuil: Option<Valeur>| {
+        seuil.map_or_else(|| "jamais".to_owned(), |seuil| seuil.to_string())
+    };
+    println!("\n=== SEUILS D'ARRET SELON LA PENALITE DE VARIANCE (SCORE {}) ===\n", score);
+    print!("{:>10}", "k");
+    for nb_des in 1..=regles.nb_des_tot {
+        print!("{:>8}", format!("{} dé{}", nb_des, if nb_des > 1 { "s" } else { "" }));
+    }
+    println!();
+    print!("{:>10}", "espérance");
+    for nb_des in 1..=regles.nb_des_tot {
+        print!("{:>8}", format_seuil(stats.seuil_arret(score, nb_des)));
+    }
+    println!();
+    for &k in KS.iter() {
+        print!("{:>10}", k);
+        for nb_des in 1..=regles.nb_des_tot {
+            print!("{:>8}", format_seuil(stats.seuil_arret_variance(score, nb_des, k)));
+        }
+        println!();
+    }
+    println!();
+}
+
+// Affichage de l'espérance de gain par tour de diverses stratégies, pour voir
+// combien perdent les heuristiques par rapport à la stratégie optimale
+fn afficher_strategies(stats: &Stats) {
+    let optimale = Optimale::new(stats);
+    let moyenne_variance = MoyenneVariance::new(stats, K_VARIANCE);
+    let strategies : [(&str, &dyn Strategie); 7] = [
+        ("Optimale", &optimale),
+        ("Moyenne-variance", &moyenne_variance),
+        ("Seuil à 300", &SeuilFixe(300)),
+        ("Seuil à 1000", &SeuilFixe(1000)),
+        ("Seuil à 2000", &SeuilFixe(2000)),
+        ("Toujours relancer", &ToujoursRelancer),
+        ("Aléatoire", &Aleatoire(42)),
+    ];
+    let scores = [0, 5000, 9000, 9500];
+
+    println!("\n=== ESPERANCE DE GAIN PAR TOUR SELON LA STRATEGIE ===\n");
+    print!("{:<20}", "Stratégie");
+    for score in scores.iter() {
+        print!("{:>12}", format!("Score {}", score));
+    }
+    println!();
+    for &(nom, strategie) in strategies.iter() {
+        print!("{:<20}", nom);
+        for &score in scores.iter() {
+            let etat = EtatTour::debut(stats.regles(), score);
+            print!("{:>12.1}", stats.esperance_strategie(strategie, &etat));
+        }
+        println!();
+    }
+    println!();
+
+    // Les joueurs humains encaissent souvent dès que la mise atteint un seuil,
+    // on regarde donc plus finement combien ça leur coûte en début de partie
+    let nb_des_tot = stats.regles().nb_des_tot;
+    let esperance_optimale = stats.esperance(0, nb_des_tot, 0);
+    println!("\n=== SEUILS FIXES A SCORE NUL ({} DES) ===\n", nb_des_tot);
+    println!("Stratégie optimale: {:+.1}", esperance_optimale);
+    for seuil in (250..=600).step_by(50) {
+        let esperance = stats.esperance_seuil(0, nb_des_tot, 0, seuil);
+        println!("- Seuil à {}: {:+.1} (perte {:.1} points par tour)",
+                 seuil, esperance, esperance_optimale - esperance);
+    }
+    println!();
+}
+
+// Affichage d'une comparaison entre gains moyens analytiques et simulés pour
+// quelques états du tour représentatifs
+// Affichage d'un jet de dés tiré au hasard et de ce qu'on peut en faire
+fn afficher_lancer(stats: &Stats, nb_des: usize, graine: Option<u64>) {
+    let graine = graine.unwrap_or_else(|| {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duree| duree.as_nanos() as u64)
+    });
+    let regles = stats.regles();
+    let histo = Jeu::with_regles(regles.clone(), graine).lancer(nb_des);
+    println!("Dés: {}", histo);
+    let choix = histo.combinaisons_possibles(regles, Enumeration::Elaguee);
+    if choix.is_empty() {
+        println!("Aucune combinaison, le tour est perdu!");
+    }
+    for comb in choix {
+        if regles.fait_gagner(&comb) {
+            println!("- {}, qui fait gagner la partie", comb);
+        } else {
+            println!("- {}", comb);
+        }
+    }
+}
+
+fn afficher_verification(stats: &Stats, nb_tours: usize, graine: u64) {
+    println!("\n=== VERIFICATION PAR SIMULATION ===\n");
+    let etats = [(0, 6, 0), (0, 3, 300), (0, 1, 500), (0, 5, 1000), (9000, 6, 0), (9800, 2, 100)];
+    for (num_etat, &(score, nb_des, mise)) in etats.iter().enumerate() {
+        let resultat = verification::verifier(stats, score, nb_des, mise, nb_tours,
+                                              graine + num_etat as u64);
+        println!("- {}", resultat);
+    }
+    println!();
+}
+
+// Affichage des résultats de parties entre la stratégie optimale et une
+// stratégie naïve, chacune commençant une partie sur deux
+fn afficher_combats(stats: &Stats, nb_parties: usize, graine: u64, transcription: bool) {
+    let optimale = Optimale::new(stats);
+    let naive = SeuilFixe(300);
+    let strategies : [(&str, &dyn Strategie); 2] = [
+        ("Optimale", &optimale),
+        ("Seuil à 300", &naive),
+    ];
+
+    let mut jeu = Jeu::with_regles(stats.regles().clone(), graine);
+    let mut victoires = [0; 2];
+    let mut nb_tours_total = 0;
+    for num_partie in 0..nb_parties {
+        // Les joueurs commencent chacun leur tour
+        let premier = num_partie % 2;
+        let joueurs = [strategies[premier].1, strategies[1-premier].1];
+        let resultat = jeu.jouer_partie(joueurs);
+        if transcription {
+            println!("\n=== PARTIE {} ({} contre {}) ===\n{}",
+                     num_partie+1, strategies[premier].0, strategies[1-premier].0,
+                     resultat);
+        }
+        if let Some(gagnant) = resultat.gagnant {
+            victoires[(gagnant + premier) % 2] += 1;
+        }
+        nb_tours_total += resultat.nb_tours();
+    }
+
+    println!("\n=== COMBATS DE ROBOTS ({} PARTIES) ===\n", nb_parties);
+    for (&(nom, _), &nb_victoires) in strategies.iter().zip(victoires.iter()) {
+        println!("- {}: {:.1}% de victoires",
+                 nom, nb_victoires as f64 / nb_parties as f64 * 100.);
+    }
+    println!("Nombre moyen de tours par partie: {}",
+             nb_tours_total as f64 / nb_parties as f64);
+    println!();
+}
+
+// Probabilité que l'adversaire finisse au prochain tour à partir de laquelle
+// la stratégie "dernière chance" ne cherche plus qu'à finir ce tour-ci
+const SEUIL_CRISE : Flottant = 0.25;
+
+// Pénalité de variance de la stratégie "moyenne-variance" des tournois
+const K_VARIANCE : Flottant = 1e-3;
+
+// Noms des stratégies qui s'affrontent lors des tournois...
+const NOMS_TOURNOI : [&str; 7] = [
+    "Optimale",
+    "Dernière chance",
+    "Moyenne-variance",
+    "Seuil à 300",
+    "Seuil à 1000",
+    "Toujours relancer",
+    "Aléatoire",
+];
+
+// ...et construction de ces stratégies, dans le même ordre
+fn strategies_tournoi(stats: &Stats) -> Vec<Box<dyn Strategie + '_>> {
+    vec![
+        Box::new(Optimale::new(stats)),
+        Box::new(DerniereChance::new(stats, SEUIL_CRISE)),
+        Box::new(MoyenneVariance::new(stats, K_VARIANCE)),
+        Box::new(SeuilFixe(300)),
+        Box::new(SeuilFixe(1000)),
+        Box::new(ToujoursRelancer),
+        Box::new(Aleatoire(42)),
+    ]
+}
+
+// Noms des règles de maison répandues dont on mesure le coût...
+const NOMS_HEURISTIQUES : [&str; 4] = [
+    "Seuil à 300",
+    "Seuil à 350 sauf 4 dés",
+    "Jamais moins de 3 dés",
+    "Seuil à 1000",
+];
+
+// ...et construction de ces stratégies, dans le même ordre
+fn heuristiques_maison() -> Vec<Box<dyn Strategie>> {
+    vec![
+        Box::new(SeuilFixe(300)),
+        Box::new(SeuilSaufDes { seuil: 350, nb_des_relance: 4 }),
+        Box::new(DesMinimum(3)),
+        Box::new(SeuilFixe(1000)),
+    ]
+}
+
+// Affichage des résultats d'un tournoi entre les stratégies prédéfinies, sous
+// forme de matrice des taux de victoire de chaque ligne contre chaque colonne
+fn afficher_tournoi(stats: &Stats,
+                    nb_parties: usize,
+                    graine: u64,
+                    csv: bool,
+                    nb_defaites: usize,
+                    perdant: &str)
+{
+    let strategie_tracee = NOMS_TOURNOI.iter()
+                                       .position(|&nom| nom == perdant)
+                                       .expect("clap a vérifié le nom de la stratégie");
+    let resultats = tournoi::tournoi_trace(stats, strategies_tournoi, nb_parties, graine,
+                                           strategie_tracee, nb_defaites);
+    let nb_strategies = NOMS_TOURNOI.len();
+
+    // Les parties perdues sont transcrites avant le bilan, sur la sortie
+    // d'erreur en CSV pour ne pas se mélanger à la matrice
+    for (num_defaite, defaite) in resultats.defaites.iter().enumerate() {
+        let transcription = format!(
+            "\n=== DEFAITE {} DE {} CONTRE {} (PARTIE {}, {} EST LE JOUEUR {}) ===\n{:#}",
+            num_defaite+1, perdant.to_uppercase(),
+            NOMS_TOURNOI[defaite.adversaire].to_uppercase(), defaite.num_partie+1,
+            perdant.to_uppercase(), defaite.joueur, defaite.partie
+        );
+        if csv { eprint!("{}", transcription); } else { print!("{}", transcription); }
+    }
+    if nb_defaites > resultats.defaites.len() {
+        warn!("{} n'a perdu que {} parties", perdant, resultats.defaites.len());
+    }
+
+    // En CSV, on se contente de la matrice des taux de victoire
+    if csv {
+        println!("strategie,{}", NOMS_TOURNOI.join(","));
+        for (i, nom) in NOMS_TOURNOI.iter().enumerate() {
+            let taux = (0..nb_strategies).map(|j| {
+                if i == j { String::new() } else { resultats.taux_victoire(i, j).to_string() }
+            }).collect::<Vec<_>>();
+            println!("{},{}", nom, taux.join(","));
+        }
+        return;
+    }
+
+    // Sinon, on affiche aussi la durée moyenne des parties
+    println!("\n=== TOURNOI ({} PARTIES PAR CONFRONTATION) ===", nb_parties);
+    let afficher_matrice = |titre: &str, cellule: &dyn Fn(usize, usize) -> String| {
+        println!("\n{}:\n", titre);
+        print!("{:<20}", "");
+        for nom in NOMS_TOURNOI.iter() { print!("{:>20}", nom); }
+        println!();
+        for (i, nom) in NOMS_TOURNOI.iter().enumerate() {
+            print!("{:<20}", nom);
+            for j in 0..nb_strategies {
+                let texte = if i == j { "-".to_owned() } else { cellule(i, j) };
+                print!("{:>20}", texte);
+            }
+            println!();
+        }
+    };
+    afficher_matrice("Taux de victoire de la ligne contre la colonne", &|i, j| {
+        format!("{:.1}%", resultats.taux_victoire(i, j) * 100.)
+    });
+    afficher_matrice("Nombre moyen de tours par partie", &|i, j| {
+        format!("{:.1}", resultats.nb_tours_moyen(i, j))
+    });
+
+    // Le classement Elo résume la matrice des taux de victoire
+    let elo = resultats.elo();
+    let mut rangs = (0..nb_strategies).collect::<Vec<_>>();
+    rangs.sort_by(|&i, &j| elo[j].classement.total_cmp(&elo[i].classement));
+    println!("\nClassement Elo (modèle de Bradley-Terry, moyenne nulle):\n");
+    for (rang, &i) in rangs.iter().enumerate() {
+        println!("{:>2}. {:<20}{:>+8.0} ± {:.0}",
+                 rang + 1, NOMS_TOURNOI[i], elo[i].classement, elo[i].erreur);
+    }
+    println!();
+}
+
+// Ecriture de la table de stratégie optimale dans un fichier, au fur et à
+// mesure du calcul puisque celui-ci prend un certain temps
+fn ecrire_strategie(stats: &Stats, chemin: PathBuf) -> io::Result<()> {
+    let mut sortie = BufWriter::new(File::create(chemin)?);
+    writeln!(sortie, "score\tnb_des\tmise\tdecision\tgain_moyen")?;
+    let mut resultat = Ok(());
+    table::table_strategie(stats, |ligne| {
+        if resultat.is_err() { return; }
+        let LigneStrategie { score, nb_des, mise, relancer, gain_moyen } = ligne;
+        let decision = if relancer { "relancer" } else { "encaisser" };
+        resultat = writeln!(sortie, "{}\t{}\t{}\t{}\t{}",
+                            score, nb_des, mise, decision, gain_moyen)
+                       .and_then(|()| sortie.flush());
+    });
+    resultat
+}
+
+// Affichage de la comparaison de deux stratégies du tournoi sur des paires de
+// parties, avec un verdict seulement si l'intervalle de confiance le permet
+fn afficher_comparaison(stats: &Stats, a: &str, b: &str, nb_paires: usize, graine: u64) {
+    let position = |nom: &str| {
+        NOMS_TOURNOI.iter()
+                    .position(|&autre| autre == nom)
+                    .expect("clap a vérifié le nom de la stratégie")
+    };
+    let (pos_a, pos_b) = (position(a), position(b));
+    let creer_strategies = |stats| {
+        let strategie = |position| strategies_tournoi(stats).swap_remove(position);
+        [strategie(pos_a), strategie(pos_b)]
+    };
+    let comparaison = comparaison::comparer(stats, creer_strategies, nb_paires, graine);
+
+    println!("\n=== COMPARAISON DE {} ET {} ({} PAIRES DE PARTIES) ===\n",
+             a.to_uppercase(), b.to_uppercase(), nb_paires);
+    let nb_parties = 2 * nb_paires;
+    for (nom, &victoires) in [a, b].iter().zip(comparaison.victoires.iter()) {
+        println!("- {} gagne {} parties ({:.2}%)",
+                 nom, victoires, victoires as f64 / nb_parties as f64 * 100.);
+    }
+    if comparaison.nb_interrompues > 0 {
+        println!("- {} parties interrompues au bout de {} tours",
+                 comparaison.nb_interrompues, NB_TOURS_MAX);
+    }
+    let [deux, une, aucune] = comparaison.paires_gagnees;
+    println!("- {} gagne les deux parties de {} paires, une seule de {}, aucune de {}",
+             a, deux, une, aucune);
+
+    // Avec des dés appariés, une stratégie opposée à elle-même rejoue deux
+    // fois la même partie, donc chacun gagne exactement une partie par paire
+    if a == b {
+        let nulles = une == nb_paires;
+        println!("\nAppariement des dés: {}",
+                 if nulles { "OK, toutes les paires sont nulles" }
+                 else { "ECHEC, certaines paires ne sont pas nulles!" });
+        println!();
+        return;
+    }
+
+    let (bas, haut) = comparaison.intervalle();
+    println!("\nEcart des taux de victoire ({} - {}): {:+.2}% (IC à 95%: [{:+.2}%, {:+.2}%])",
+             a, b, comparaison.difference() * 100., bas * 100., haut * 100.);
+    println!("p-valeur (test bilatéral, approximation normale): {:.2e}", comparaison.p_valeur());
+    println!("Taille d'échantillon effective: {:.0} parties indépendantes pour {} jouées \
+              (gain x{:.2})",
+             comparaison.taille_effective(), nb_parties,
+             comparaison.taille_effective() / nb_parties as f64);
+    match comparaison.gagnant() {
+        Some(gagnant) => println!("\n=> {} est meilleure", [a, b][gagnant]),
+        None => println!("\n=> Pas de différence significative, l'intervalle contient 0"),
+    }
+    println!();
+}
+
+// Affichage des résultats de parties entre plusieurs stratégies du tournoi:
+// taux de victoire et distribution du score final à chaque place
+fn afficher_partie(stats: &Stats, joueurs: &[String], nb_parties: usize, graine: u64) {
+    let positions = joueurs.iter().map(|nom| {
+        NOMS_TOURNOI.iter()
+                    .position(|autre| autre == nom)
+                    .expect("clap a vérifié le nom de la stratégie")
+    }).collect::<Vec<_>>();
+    let creer_strategies = |stats| {
+        positions.iter()
+                 .map(|&position| strategies_tournoi(stats).swap_remove(position))
+                 .collect::<Vec<_>>()
+    };
+    let resultats = partie::jouer_parties(stats, creer_strategies, nb_parties, graine);
+
+    println!("\n=== PARTIES A {} JOUEURS ({} PARTIES, GRAINE {}) ===\n",
+             joueurs.len(), nb_parties, graine);
+    if resultats.nb_interrompues > 0 {
+        println!("{} parties interrompues au bout de {} tours\n",
+                 resultats.nb_interrompues, NB_TOURS_MAX);
+    }
+    if resultats.nb_interrompues == resultats.nb_parties() {
+        println!("Aucune partie ne s'est terminée!");
+        println!();
+        return;
+    }
+    println!("Score final de chaque joueur dans les parties terminées:\n");
+    println!("{:>5}  {:<20}{:>10}{:>8}{:>8}{:>8}{:>8}",
+             "Place", "Stratégie", "Victoires", "Moyen", "10%", "Médian", "90%");
+    for (joueur, nom) in joueurs.iter().enumerate() {
+        println!("{:>5}  {:<20}{:>9.1}%{:>8.0}{:>8}{:>8}{:>8}",
+                 joueur + 1, nom, resultats.taux_victoire(joueur) * 100.,
+                 resultats.score_moyen(joueur), resultats.centile(joueur, 0.1),
+                 resultats.centile(joueur, 0.5), resultats.centile(joueur, 0.9));
+    }
+    println!();
+}
+
+// Jeu d'une partie entre stratégies du tournoi, affichée puis enregistrée
+fn enregistrer_partie(stats: &Stats,
+                      chemin: PathBuf,
+                      joueurs: &[String],
+                      graine: u64) -> io::Result<()> {
+    let strategies = joueurs.iter().map(|nom| {
+        let position = NOMS_TOURNOI.iter()
+                                   .position(|autre| autre == nom)
+                                   .expect("clap a vérifié le nom de la stratégie");
+        strategies_tournoi(stats).swap_remove(position)
+    }).collect::<Vec<_>>();
+    let strategies = strategies.iter().map(|strategie| &**strategie).collect::<Vec<_>>();
+    let mut jeu = Jeu::with_regles(stats.regles().clone(), graine);
+    let resultat = jeu.jouer_partie_a_plusieurs(&strategies);
+
+    println!("\n=== PARTIE ENREGISTREE (GRAINE {}) ===\n", graine);
+    for (joueur, nom) in joueurs.iter().enumerate() {
+        println!("Joueur {}: {}", joueur, nom);
+    }
+    println!("\n{}", resultat);
+    jeu.enregistrer(&resultat).ecrire(&chemin)?;
+    println!("Partie enregistrée dans {}\n", chemin.display());
+    Ok(())
+}
+
+// Rejeu d'une partie enregistrée, en affichant le déroulé rejoué ou la raison
+// pour laquelle il s'écarte de l'enregistrement
+fn rejouer_partie(chemin: PathBuf) -> io::Result<()> {
+    let enregistrement = EnregistrementPartie::lire(&chemin)?;
+    println!("\n=== REJEU DE {} (GRAINE {}, PARTIE {}) ===\n",
+             chemin.display(), enregistrement.graine, enregistrement.num_partie);
+    match enregistrement.rejouer() {
+        Ok(partie) => {
+            println!("{}", partie);
+            println!("Partie rejouée à l'identique, scores finaux: {:?}\n", partie.scores);
+        },
+        Err(erreur) => println!("Enregistrement invalide: {}\n", erreur),
+    }
+    Ok(())
+}
+
+// Affichage des scénarios prédéfinis: pour chaque score, l'espérance de gain
+// de chaque option contestée selon la mise, et l'écart avec la meilleure
+fn afficher_scenarios(stats: &Stats, nom: Option<&str>, scores: &[Valeur], mises: &[Valeur]) {
+    for scenario in scenarios::scenarios() {
+        if nom.is_some_and(|nom| nom != scenario.nom) { continue; }
+        let jet = scenario.faces.iter().map(|face| face.to_string()).collect::<Vec<_>>();
+        println!("\n=== SCENARIO {}: {} ===\n", scenario.nom, jet.join(" "));
+        println!("{}", scenario.question);
+        let attendue = &scenario.options[scenario.meilleure_attendue];
+        match scenarios::evaluer_scenario(stats, &scenario, 0, 0).and_then(|eval| eval.meilleure()) {
+            Some(meilleure) if meilleure == scenario.meilleure_attendue => {
+                println!("En début de partie, la meilleure option est bien \"{}\"", attendue.libelle)
+            },
+            Some(meilleure) => {
+                println!("Attention, en début de partie, la meilleure option est \"{}\" \
+                          et non \"{}\" comme attendu avec les règles par défaut",
+                         scenario.options[meilleure].libelle, attendue.libelle)
+            },
+            None => println!("Ce jet ne peut pas se produire en début de partie avec ces règles"),
+        }
+        for &score in scores {
+            let evaluations = mises.iter()
+                .filter_map(|&mise| scenarios::evaluer_scenario(stats, &scenario, score, mise))
+                .collect::<Vec<_>>();
+            if evaluations.is_empty() { continue; }
+            print!("\nScore {:<6}", score);
+            for option in scenario.options.iter() {
+                print!("{:>26}", option.libelle);
+            }
+            println!();
+            for evaluation in evaluations {
+                print!("- mise {:<5}", evaluation.mise);
+                for num_option in 0..scenario.options.len() {
+                    match (evaluation.esperances[num_option], evaluation.ecart(num_option)) {
+                        (Some(esperance), Some(ecart)) if ecart > 0. => {
+                            print!("{:>26}", format!("{:.1} ({:+.1})", esperance, -ecart))
+                        },
+                        (Some(esperance), _) => print!("{:>26}", format!("{:.1} (*)", esperance)),
+                        (None, _) => print!("{:>26}", "interdit"),
+                    }
+                }
+                println!();
+            }
+        }
+    }
+    println!("\n(*) meilleure option, les autres sont suivies de leur écart avec elle\n");
+}
+
+// Affichage du coût de quelques règles de maison répandues, comparées à la
+// stratégie optimale, d'abord en résumé puis en détail pour chacune
+fn afficher_heuristiques(stats: &Stats, nb_etats: usize, nb_paires: usize, graine: u64) {
+    let evaluations = NOMS_HEURISTIQUES.iter().enumerate().map(|(position, &nom)| {
+        let creer_heuristique = || heuristiques_maison().swap_remove(position);
+        (nom, heuristiques::evaluer_heuristique(stats, creer_heuristique, nb_etats,
+                                                nb_paires, graine))
+    }).collect::<Vec<_>>();
+
+    println!("\n=== HEURISTIQUES FACE A LA STRATEGIE OPTIMALE ({} PAIRES DE PARTIES, GRAINE {}) ===\n",
+             nb_paires, graine);
+    println!("Espérance de gain par tour à score nul (calcul exact), et écart de taux de \
+              victoire\nen parties entières contre la stratégie optimale (simulation, \
+              intervalle à 95%):\n");
+    println!("{:<24}{:>10}{:>10}{:>12}{:>22}",
+             "Stratégie", "Espérance", "Perte", "Victoires", "Ecart de victoires");
+    let esperance_optimale = stats.esperance_etat(&EtatTour::debut(stats.regles(), 0));
+    println!("{:<24}{:>10.1}{:>10.1}{:>12}{:>22}", "Optimale", esperance_optimale, 0., "", "");
+    for (nom, evaluation) in evaluations.iter() {
+        let comparaison = &evaluation.comparaison;
+        let taux_victoire = comparaison.victoires[1] as f64 / (2 * comparaison.nb_paires) as f64;
+        let (bas, haut) = comparaison.intervalle();
+        println!("{:<24}{:>10.1}{:>10.1}{:>11.1}%{:>22}",
+                 nom, evaluation.esperance_tour, evaluation.perte_tour(), taux_victoire * 100.,
+                 format!("[{:+.1}%, {:+.1}%]", -haut * 100., -bas * 100.));
+    }
+
+    if nb_etats == 0 { println!(); return; }
+    println!("\nEtats du tour à score nul où chaque heuristique perd le plus d'espérance de \
+              gain,\npar rapport à la meilleure décision face aux mêmes dés (en points \
+              par tour, puis\nquand elle passe par cet état):");
+    for (nom, evaluation) in evaluations.iter() {
+        println!("\n{}:", nom);
+        for perte in evaluation.pires_etats.iter() {
+            println!("- {} dés avec une mise de {}: {:.1} points par tour ({:.1}% des tours, \
+                      {:.1} points à chaque fois)",
+                     perte.etat.nb_des, perte.etat.mise, perte.contribution(),
+                     perte.proba * 100., perte.perte);
+        }
+        println!("(perte sur tous les états: {:.1} points par tour, pour {:.1} calculés \
+                  directement)", evaluation.perte_etats, evaluation.perte_tour());
+    }
+    println!();
+}
+
+// Rapport d'analyse d'une partie enregistrée, coup par coup puis par joueur.
+// Le JSON reprend toute l'analyse (voir analyse::AnalysePartie), les autres
+// formats structurés n'ayant pas de sens ici, on les affiche comme du texte.
+fn afficher_analyse(stats: &Stats,
+                    enregistrement: &EnregistrementPartie,
+                    format: Format) -> io::Result<()> {
+    let analyse = analyse::analyser_partie(stats, &enregistrement.partie)
+        .map_err(|erreur| io::Error::new(io::ErrorKind::InvalidData, erreur))?;
+    if format == Format::Json {
+        serde_json::to_writer_pretty(io::stdout().lock(), &analyse).map_err(io::Error::from)?;
+        println!();
+        return Ok(());
+    }
+
+    println!("\n=== ANALYSE DE LA PARTIE (GRAINE {}, PARTIE {}) ===\n",
+             enregistrement.graine, enregistrement.num_partie);
+    println!("Chaque décision est comparée à la meilleure possible, selon \
+              l'espérance de gain du tour,\nou en fin de partie selon la \
+              probabilité de victoire (les erreurs sont marquées d'un \"?\")");
+    let mut tour_precedent = None;
+    for coup in analyse.coups.iter() {
+        if tour_precedent != Some(coup.num_tour) {
+            println!("\nTour {}, joueur {} (score {}, adversaire {}):",
+                     coup.num_tour+1, coup.joueur, coup.etat.score, coup.etat.score_adverse);
+            tour_precedent = Some(coup.num_tour);
+        }
+        let marque = if coup.erreur() { '?' } else { ' ' };
+        println!("  {} {} -> {}", marque, coup.histo, coup.jouee);
+        let evaluation = match coup.critere {
+            analyse::Critere::Esperance => {
+                format!("espérance {:.1} contre {:.1} (cumul des points perdus: {:.1})",
+                        coup.valeur_jouee, coup.valeur_meilleure, coup.points_perdus_cumules)
+            },
+            analyse::Critere::ProbaVictoire => {
+                format!("victoire {:.1}% contre {:.1}%",
+                        coup.valeur_jouee * 100., coup.valeur_meilleure * 100.)
+            },
+            analyse::Critere::ProbaDepasser => {
+                format!("dépassement {:.1}% contre {:.1}%",
+                        coup.valeur_jouee * 100., coup.valeur_meilleure * 100.)
+            },
+        };
+        if coup.erreur() {
+            println!("      mieux: {}, {}", coup.meilleure, evaluation);
+        } else {
+            println!("      {}", evaluation);
+        }
+    }
+
+    println!("\n{:>6}{:>10}{:>16}{:>16}", "Joueur", "Erreurs", "Points perdus", "Proba perdue");
+    for joueur in 0..analyse.nb_joueurs {
+        println!("{:>6}{:>10}{:>16.1}{:>15.1}%",
+                 joueur, analyse.nb_erreurs(joueur),
+                 analyse.points_perdus[joueur], analyse.proba_perdue[joueur] * 100.);
+    }
+    println!();
+    Ok(())
+}
+
+// Affichage de l'avantage que donne chaque place autour de la table: calcul
+// exact à deux joueurs, puis simulation à 2, 3 et 4 joueurs, avec intervalles
+// de confiance à 95%
+fn afficher_ordre(stats: &Stats, nb_blocs: usize, graine: u64) {
+    println!("\n=== AVANTAGE DE CHAQUE PLACE AUTOUR DE LA TABLE ===\n");
+    // Le calcul exact ne sait pas tenir compte de toutes les règles (voir
+    // course::regles_etudiables), seule la simulation fait alors foi
+    if course::regles_etudiables(stats.regles()) {
+        let proba_premier = stats.duel().proba_victoire(0, 0);
+        println!("A deux joueurs, le premier gagne exactement {:.2}% des parties \
+                  (avantage de {:+.2} points)",
+                 proba_premier * 100., (proba_premier - 0.5) * 100.);
+    } else {
+        println!("Pas de calcul exact de l'avantage du premier joueur avec ces règles");
+    }
+
+    for nb_joueurs in 2..=4 {
+        let resultats = ordre::avantage_places(stats, nb_joueurs, nb_blocs, graine);
+        println!("\nSimulation de {} parties à {} joueurs, avec rotation des dés:\n",
+                 resultats.nb_parties(), nb_joueurs);
+        if resultats.nb_interrompues > 0 {
+            println!("{} parties interrompues au bout de {} tours\n",
+                     resultats.nb_interrompues, NB_TOURS_MAX);
+        }
+        println!("{:>5}{:>20}{:>30}", "Place", "Victoires", "Parties indépendantes égales");
+        for joueur in 0..nb_joueurs {
+            let (taux, largeur) = resultats.taux_victoire(joueur);
+            let equivalent = match resultats.gain_rotation(joueur) {
+                Some(gain) => format!("{:.0} (x{:.1})", gain * resultats.nb_parties() as f64, gain),
+                None => "-".to_owned(),
+            };
+            println!("{:>5}{:>20}{:>30}",
+                     joueur + 1, format!("{:.2}% ± {:.2}", taux * 100., largeur * 100.),
+                     equivalent);
+        }
+    }
+    println!();
+}
+
+// Affichage de la durée des parties à plusieurs, et de l'avantage que donne
+// chaque place autour de la table
+fn afficher_duree(stats: &Stats, nb_joueurs: usize, nb_parties: usize, graine: u64) {
+    let resultats = duree::mesurer_duree(stats, nb_joueurs, nb_parties, graine);
+    println!("\n=== DUREE DES PARTIES A {} JOUEURS ({} PARTIES) ===\n",
+             nb_joueurs, nb_parties);
+    let nb_tours_moyen = match resultats.nb_tours_moyen() {
+        Some(nb_tours_moyen) => nb_tours_moyen,
+        None => {
+            println!("Aucune partie ne s'est terminée!");
+            println!();
+            return;
+        },
+    };
+
+    // On compte les tours de chaque joueur, mais un tour de table est plus
+    // parlant pour savoir combien de temps on va passer à jouer
+    println!("Nombre moyen de tours: {:.1} ({:.1} tours de table)",
+             nb_tours_moyen, nb_tours_moyen / nb_joueurs as f64);
+    for &proportion in [0.1, 0.25, 0.5, 0.75, 0.9, 0.99].iter() {
+        println!("- {:.0}% des parties finissent en {} tours au plus",
+                 proportion * 100., resultats.centile(proportion).unwrap());
+    }
+    println!("- La plus longue partie a duré {} tours", resultats.nb_tours.last().unwrap());
+    if resultats.nb_interrompues > 0 {
+        println!("- {} parties interrompues au bout de {} tours",
+                 resultats.nb_interrompues, NB_TOURS_MAX);
+    }
+
+    println!("\nTaux de victoire selon la place autour de la table:");
+    for joueur in 0..nb_joueurs {
+        println!("- Joueur {}: {:.1}%", joueur + 1, resultats.taux_victoire(joueur) * 100.);
+    }
+    if let Some(taux_remontee) = resultats.taux_remontee() {
+        println!("\nLe premier à atteindre {} points perd quand même {} parties sur {} ({:.1}%)",
+                 stats.regles().score_max, resultats.nb_remontees, resultats.nb_egalisations,
+                 taux_remontee * 100.);
+    }
+    println!();
+}
@@ -2,61 +2,378 @@ use crate::{
     Flottant,
     NB_FACES,
     combinaison::Combinaison,
+    regles::Regles,
 };
 
-use std::collections::HashMap;
+use log::debug;
+
+use rand::{Rng, RngExt};
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display},
+    ops::Deref,
+};
 
 
 // Enumérer les choix auxquels on peut faire face en lançant N dés, et leurs
-// probas. Le choix [] correspond à une absence de combinaisons (perdu!)
-pub fn enumerer_choix(nb_des: usize) -> HashMap<Vec<Combinaison>, Flottant> {
-    // Le nombre de combinaisons est connu (et suffisamment faible)
-    let nb_comb = NB_FACES.pow(nb_des as u32);
-
-    // On énumère tous les lancers possibles pour ce nombre de dés
-    let mut comptage_choix = HashMap::new();
-    for num_comb in 0..nb_comb {
-        // On énumère les faces en traitant la combinaison comme un nombre
-        // en base NB_FACES (note: la face 1 est numérotée 0), et on calcule
-        // l'histogramme du nombre de dés étant tombé sur chaque face.
-        let mut reste = num_comb;
-        let mut histo = [0; NB_FACES];
+// probas. Le choix [] correspond à une absence de combinaisons (perdu!). Les
+// choix sont triés, pour que les calculs qui en découlent fassent leurs sommes
+// dans le même ordre d'une exécution à l'autre.
+pub fn enumerer_choix(nb_des: usize,
+                      regles: &Regles) -> BTreeMap<Vec<Combinaison>, Flottant> {
+    // Avec des dés équilibrés, on transforme les nombres de lancers en
+    // probabilités...
+    let poids = match &regles.poids_faces {
+        None => {
+            let nb_comb = regles.nb_faces.pow(nb_des as u32);
+            let norme = 1. / (nb_comb as Flottant);
+            return compter_choix(nb_des, regles).into_iter()
+                .map(|(choix, compte)| (choix, compte as Flottant * norme))
+                .collect();
+        },
+        Some(poids) => poids,
+    };
+
+    // ...mais avec des dés pipés, les lancers ordonnés qui mènent à un même
+    // histogramme n'ont plus la probabilité 1/nb_faces^nb_des: chacun a la
+    // probabilité d'obtenir chaque face autant de fois que l'histogramme le dit
+    let mut probas_choix = BTreeMap::new();
+    enumerer_histogrammes(nb_des, regles.nb_faces, &mut |histo| {
+        let proba_lancer = histo.iter()
+                                .zip(poids)
+                                .map(|(&nb, &proba_face)| proba_face.powi(nb as i32))
+                                .product::<Flottant>();
+        *probas_choix.entry(choix_canonique(histo, regles)).or_insert(0.) +=
+            nb_lancers(histo) as Flottant * proba_lancer;
+    });
+    probas_choix
+}
+
+// Probabilité qu'un lancer de N dés ne donne aucune combinaison, et fasse
+// donc perdre le tour
+pub fn proba_rien(nb_des: usize, regles: &Regles) -> Flottant {
+    enumerer_choix(nb_des, regles).remove(&[][..]).unwrap_or(0.)
+}
+
+// Probabilité qu'un lancer de N dés permette de prendre au moins une
+// combinaison d'un certain type, par exemple une suite ou un brelan
+pub fn proba_combinaison(nb_des: usize,
+                         regles: &Regles,
+                         predicat: impl Fn(&Combinaison) -> bool) -> Flottant {
+    enumerer_choix(nb_des, regles).into_iter()
+        .filter(|(choix, _)| choix.iter().any(&predicat))
+        .map(|(_, proba)| proba)
+        .sum()
+}
+
+// Même chose que enumerer_choix, mais en comptant le nombre de lancers (parmi nb_faces^nb_des)
+// qui mènent à chaque choix, ce qui permet de calculer des probabilités exactes
+// (ces nombres ne disent rien des probabilités si les dés sont pipés)
+pub fn compter_choix(nb_des: usize,
+                     regles: &Regles) -> BTreeMap<Vec<Combinaison>, u64> {
+    // Plutôt que d'énumérer les nb_faces^nb_des lancers ordonnés, on énumère
+    // directement les histogrammes du nombre de dés tombés sur chaque face, qui
+    // sont beaucoup moins nombreux, et on pondère chacun par le nombre de
+    // lancers ordonnés qui y mènent.
+    let mut comptage_choix = BTreeMap::new();
+    let mut nb_histogrammes = 0;
+    enumerer_histogrammes(nb_des, regles.nb_faces, &mut |histo| {
+        // On déduit de cet histogramme les combinaisons entre lesquelles on
+        // peut raisonnablement choisir, et on en compte les occurences, dont
+        // on déduira la probabilité
+        *comptage_choix.entry(choix_canonique(histo, regles)).or_insert(0) += nb_lancers(histo);
+        nb_histogrammes += 1;
+    });
+
+    // Chaque lancer ordonné doit avoir été compté une et une seule fois
+    let nb_comb = (regles.nb_faces as u64).pow(nb_des as u32);
+    assert_eq!(comptage_choix.values().sum::<u64>(), nb_comb,
+               "Des lancers à {} dés ont été perdus ou comptés deux fois", nb_des);
+    debug!("Nombre de lancers possibles à {} dés: {} ({} histogrammes, {} choix distincts)",
+           nb_des, nb_comb, nb_histogrammes, comptage_choix.len());
+    comptage_choix
+}
+
+// Combinaisons entre lesquelles on peut raisonnablement choisir face à un
+// histogramme. L'ordre d'énumération n'a pas de sens et la récursion sur les
+// brelans peut produire des doublons, on met donc le choix sous forme
+// canonique pour que deux énumérations d'un même choix tombent sur la même clé.
+fn choix_canonique(histo: &[usize], regles: &Regles) -> Vec<Combinaison> {
+    let mut choix = combinaisons(histo, regles);
+    choix.sort_unstable();
+    choix.dedup();
+    choix
+}
+
+// Enumération de tous les histogrammes qu'on peut obtenir en lançant N dés
+fn enumerer_histogrammes(nb_des: usize,
+                         nb_faces: usize,
+                         traiter: &mut impl FnMut(&[usize])) {
+    // On choisit combien de dés tombent sur chaque face tour à tour, et la
+    // dernière face reçoit les dés restants
+    fn remplir(histo: &mut [usize],
+               idx_face: usize,
+               des_restants: usize,
+               traiter: &mut impl FnMut(&[usize])) {
+        if idx_face == histo.len() - 1 {
+            histo[idx_face] = des_restants;
+            traiter(histo);
+            return;
+        }
+        for nb_des_face in 0..=des_restants {
+            histo[idx_face] = nb_des_face;
+            remplir(histo, idx_face + 1, des_restants - nb_des_face, traiter);
+        }
+    }
+    remplir(&mut vec![0; nb_faces], 0, nb_des, traiter)
+}
+
+// Nombre de lancers ordonnés menant à un certain histogramme, donné par le
+// coefficient multinomial N! / (n1! n2! ... nk!)
+fn nb_lancers(histo: &[usize]) -> u64 {
+    let factorielle = |n: usize| (1..=n as u64).product::<u64>();
+    let nb_des = histo.iter().sum::<usize>();
+    histo.iter().fold(factorielle(nb_des), |nb, &bin| nb / factorielle(bin))
+}
+
+// Histogramme d'un jet de dés par face (nombre de dés tombés sur chaque face,
+// la face 1 ayant l'indice 0). On ne peut en construire que des valides pour
+// les règles considérées, avec une case par face et entre 1 et nb_des_tot dés
+// en tout, et on le lit ensuite comme une tranche. Un histogramme relu depuis
+// un fichier ne connaît pas les règles: on le valide pour les règles les plus
+// larges que l'on sait étudier, il reste à le vérifier pour les règles de la
+// partie (voir Histogramme::verifier).
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(try_from = "Vec<usize>", into = "Vec<usize>")]
+pub struct Histogramme(Vec<usize>);
+
+impl Histogramme {
+    // Lecture d'un jet de dés écrit sous la forme "1 3 3 5 6 6", "1,3,3,5,6,6"
+    // ou "133566" (cette dernière forme supposant des faces à un seul chiffre)
+    pub fn parse(texte: &str, regles: &Regles) -> Result<Self, ErreurLancer> {
+        // On découpe le texte en faces
+        let texte = texte.trim();
+        let separateur = |c: char| c.is_whitespace() || c == ',';
+        let faces : Vec<&str> = if texte.contains(separateur) {
+            texte.split(separateur).filter(|mot| !mot.is_empty()).collect()
+        } else {
+            texte.matches(|_| true).collect()
+        };
+
+        // On lit chaque face, puis on construit l'histogramme
+        let faces = faces.into_iter().map(|face| {
+            face.parse::<usize>().map_err(|_| ErreurLancer::FaceInvalide(face.to_owned()))
+        }).collect::<Result<Vec<_>, _>>()?;
+        Self::depuis_faces(&faces, regles)
+    }
+
+    // Construction à partir d'un jet de dés donné face par face (numérotées à
+    // partir de 1)
+    pub fn depuis_faces(faces: &[usize], regles: &Regles) -> Result<Self, ErreurLancer> {
+        let mut comptes = vec![0; regles.nb_faces];
+        for &num_face in faces {
+            if !(1..=regles.nb_faces).contains(&num_face) {
+                return Err(ErreurLancer::FaceHorsLimites(num_face));
+            }
+            comptes[num_face-1] += 1;
+        }
+        Self::depuis_comptes(comptes, regles)
+    }
+
+    // Construction à partir du nombre de dés tombés sur chaque face
+    pub fn depuis_comptes(comptes: Vec<usize>, regles: &Regles) -> Result<Self, ErreurLancer> {
+        if comptes.len() != regles.nb_faces {
+            return Err(ErreurLancer::NombreFaces(comptes.len()));
+        }
+        let nb_des = comptes.iter().sum::<usize>();
+        if !(1..=regles.nb_des_tot).contains(&nb_des) {
+            return Err(ErreurLancer::NombreDes(nb_des));
+        }
+        Ok(Self(comptes))
+    }
+
+    // Vérifier qu'un histogramme, par exemple relu depuis un fichier, est
+    // valide pour certaines règles
+    pub fn verifier(&self, regles: &Regles) -> Result<(), ErreurLancer> {
+        Self::depuis_comptes(self.0.clone(), regles).map(|_| ())
+    }
+
+    // Jet aléatoire de nb_des dés, chaque face sortant avec la même
+    // probabilité (ou celle que donnent les règles, si les dés sont pipés).
+    // Tous les lancers de dés passent par ici, pour que le simulateur et les
+    // vérifications les tirent tous de la même façon.
+    pub fn aleatoire<R: Rng + ?Sized>(nb_des: usize,
+                                      regles: &Regles,
+                                      rng: &mut R) -> Result<Self, ErreurLancer> {
+        let mut comptes = vec![0; regles.nb_faces];
         for _ in 0..nb_des {
-            let idx_face = reste % NB_FACES;
-            histo[idx_face] += 1;
-            reste /= NB_FACES;
+            let idx_face = match &regles.poids_faces {
+                None => rng.random_range(0..regles.nb_faces),
+                Some(poids) => {
+                    let mut tirage = rng.random::<Flottant>();
+                    poids.iter()
+                         .position(|&proba_face| {
+                             tirage -= proba_face;
+                             tirage < 0.
+                         })
+                         .unwrap_or(regles.nb_faces - 1)
+                },
+            };
+            comptes[idx_face] += 1;
+        }
+        Self::depuis_comptes(comptes, regles)
+    }
+
+    // Nombre de dés lancés
+    pub fn nb_des(&self) -> usize {
+        self.0.iter().sum()
+    }
+
+    // Nombre de dés tombés sur une face (numérotée à partir de 1)
+    pub fn compte(&self, face: usize) -> usize {
+        face.checked_sub(1).and_then(|idx_face| self.0.get(idx_face)).copied().unwrap_or(0)
+    }
+
+    // Combinaisons qu'on a le droit de prendre face à ce jet de dés, triées et
+    // sans doublons. Un résultat vide signifie qu'on n'a aucune combinaison et
+    // que le tour est perdu. En mode élagué, on ne garde que les combinaisons
+    // entre lesquelles on peut raisonnablement hésiter (celles qu'utilise
+    // l'analyse statistique), en mode complet on garde toutes les prises
+    // légales, y compris partielles.
+    pub fn combinaisons_possibles(&self,
+                                  regles: &Regles,
+                                  mode: Enumeration) -> Vec<Combinaison> {
+        match mode {
+            Enumeration::Elaguee => choix_canonique(self, regles),
+            Enumeration::Complete => enumerer_combinaisons_completes(self, regles),
+        }
+    }
+}
+
+// Manière d'énumérer les combinaisons qu'on peut prendre face à un jet de dés,
+// voir Histogramme::combinaisons_possibles
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Enumeration {
+    // Seulement les combinaisons qu'on peut raisonnablement choisir
+    Elaguee,
+
+    // Toutes les combinaisons légales
+    Complete,
+}
+
+// On relit un histogramme avec le même constructeur que les autres, pour des
+// dés d'autant de faces qu'il a de cases, et au plus NB_DES_TOT dés
+impl TryFrom<Vec<usize>> for Histogramme {
+    type Error = ErreurLancer;
+
+    fn try_from(comptes: Vec<usize>) -> Result<Self, ErreurLancer> {
+        if comptes.len() < NB_FACES {
+            return Err(ErreurLancer::NombreFaces(comptes.len()));
+        }
+        let regles = Regles { nb_faces: comptes.len(), ..Regles::default() };
+        Self::depuis_comptes(comptes, &regles)
+    }
+}
+
+impl From<Histogramme> for Vec<usize> {
+    fn from(histo: Histogramme) -> Self {
+        histo.0
+    }
+}
+
+// On affiche les faces dans l'ordre, sous la forme "1 3 3 5 6 6" qu'accepte
+// Histogramme::parse
+impl Display for Histogramme {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let mut separateur = "";
+        for (idx_face, &nb) in self.0.iter().enumerate() {
+            for _ in 0..nb {
+                write!(formatter, "{}{}", separateur, idx_face+1)?;
+                separateur = " ";
+            }
         }
+        Ok(())
+    }
+}
 
-        // On déduit de cet histogramme les combinaisons entre lesquelles
-        // on peut raisonnablement choisir...
-        let choix = enumerer_combinaisons(histo);
+impl Deref for Histogramme {
+    type Target = [usize];
 
-        // ...et on en compte les occurences, dont on déduira la probabilité
-        let compte = comptage_choix.entry(choix.clone()).or_insert(0);
-        *compte += 1;
+    fn deref(&self) -> &[usize] {
+        &self.0
     }
+}
 
-    // Nous déduisons une table des choix auxquels on peut faire face, et du
-    // nombre de tirages associés. On transforme les comptes en probabilités.
-    let norme = 1. / (nb_comb as Flottant);
-    comptage_choix.into_iter()
-        .map(|(choix, compte)| (choix, compte as Flottant * norme))
-        .collect()
+// Erreurs qui peuvent survenir lors de la lecture d'un jet de dés
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ErreurLancer {
+    // Ce qu'on a lu n'est pas un nombre
+    FaceInvalide(String),
+
+    // Le nombre lu ne correspond pas à une face du dé
+    FaceHorsLimites(usize),
+
+    // On n'a pas lu un nombre de dés qu'on peut lancer
+    NombreDes(usize),
+
+    // L'histogramme n'a pas autant de cases que les dés ont de faces
+    NombreFaces(usize),
 }
 
-// Histogramme d'un jet de dé par face (nb de dés tombé sur chaque face)
-type HistogrammeFaces = [usize; NB_FACES];
+impl Display for ErreurLancer {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            ErreurLancer::FaceInvalide(face) =>
+                write!(formatter, "Face invalide: \"{}\"", face),
+            ErreurLancer::FaceHorsLimites(face) =>
+                write!(formatter, "Face hors limites: {}", face),
+            ErreurLancer::NombreDes(nb_des) =>
+                write!(formatter, "Nombre de dés invalide: {}", nb_des),
+            ErreurLancer::NombreFaces(nb_faces) =>
+                write!(formatter, "Nombre de faces invalide: {}", nb_faces),
+        }
+    }
+}
+
+impl Error for ErreurLancer {}
 
-// Combinaisons qu'on peut raisonnablement choisir pour un histogramme donné
-fn enumerer_combinaisons(histo: HistogrammeFaces) -> Vec<Combinaison> {
+// Combinaisons qu'on peut raisonnablement choisir pour un histogramme
+// quelconque, éventuellement vide, ce qui sert pendant la récursion sur les
+// brelans et l'énumération des histogrammes. L'ordre n'a pas de sens et il peut
+// y avoir des doublons: hors de ce module, on passe par
+// Histogramme::combinaisons_possibles, qui met le résultat sous forme canonique.
+fn combinaisons(histo: &[usize], regles: &Regles) -> Vec<Combinaison> {
     // Préparation du stockage
     let mut choix = Vec::new();
 
-    // Traitement des suites
+    // Traitement des suites (chaque face sort une fois, ce qui suppose qu'on
+    // lance autant de dés que les dés ont de faces)
     if histo.iter().all(|&bin| bin == 1) {
         choix.push(Combinaison::Suite);
     }
 
+    // Traitement des petites suites (cinq faces consécutives), si la règle les
+    // autorise. Le dé restant éventuel peut être pris s'il s'agit d'un 1 ou 5.
+    if regles.petite_suite {
+        for idx_debut in 0..histo.len().saturating_sub(4) {
+            if histo[idx_debut..idx_debut+5].contains(&0) {
+                continue;
+            }
+            let mut histo_restant = histo.to_vec();
+            for bin in &mut histo_restant[idx_debut..idx_debut+5] { *bin -= 1; }
+            for nb_un in 0..=histo_restant[0] {
+                for nb_cinq in 0..=histo_restant[4] {
+                    choix.push(Combinaison::PetiteSuite { idx_debut, nb_un, nb_cinq });
+                }
+            }
+        }
+    }
+
     // Traitement des triple paires
     let num_paires : usize = histo.iter().map(|&bin| bin/2).sum();
     if num_paires == 3 {
@@ -69,9 +386,9 @@ fn enumerer_combinaisons(histo: HistogrammeFaces) -> Vec<Combinaison> {
 
         choix.push(Combinaison::BrelanSimple { idx_face, nb_un: 0, nb_cinq: 0 });
         
-        let mut histo_sans_brelans = histo.clone();
+        let mut histo_sans_brelans = histo.to_vec();
         histo_sans_brelans[idx_face] -= 3;
-        let choix_internes = enumerer_combinaisons(histo_sans_brelans);
+        let choix_internes = combinaisons(&histo_sans_brelans, regles);
 
         for combi in choix_internes {
             match combi {
@@ -87,7 +404,43 @@ fn enumerer_combinaisons(histo: HistogrammeFaces) -> Vec<Combinaison> {
         }
     }
 
-    // Traitement des faces simples
+    // Traitement des carrés, quintes et sextes, si la règle les autorise
+    if regles.carres_et_plus {
+        for (idx_face, &bin) in histo.iter().enumerate() {
+            if bin == 6 { choix.push(Combinaison::Sexte { idx_face }); }
+            for multiplicite in 4..=bin.min(5) {
+                // On peut compléter avec des 1 et des 5 parmi les dés restants
+                let mut histo_restant = histo.to_vec();
+                histo_restant[idx_face] -= multiplicite;
+                for nb_un in 0..=histo_restant[0] {
+                    for nb_cinq in 0..=histo_restant[4] {
+                        choix.push(if multiplicite == 4 {
+                            Combinaison::Carre { idx_face, nb_un, nb_cinq }
+                        } else {
+                            Combinaison::Quinte { idx_face, nb_un, nb_cinq }
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    // Traitement des fulls (brelan + paire), si la règle les autorise
+    if regles.full {
+        for (idx_brelan, &bin_brelan) in histo.iter().enumerate() {
+            if bin_brelan < 3 { continue; }
+            for (idx_paire, &bin_paire) in histo.iter().enumerate() {
+                if idx_paire == idx_brelan || bin_paire < 2 { continue; }
+                choix.push(Combinaison::Full { idx_brelan, idx_paire });
+            }
+        }
+    }
+
+    // Traitement des faces simples. On énumère toutes les façons de prendre des
+    // 1 et des 5, y compris celles qui semblent irrationnelles du point de vue
+    // de l'espérance (prendre un 5 en laissant un 1, ou un seul 1 alors qu'on
+    // en a trois qui pourraient former un brelan), car en fin de partie il peut
+    // falloir exactement 50 ou 100 points pour atterrir sur le score maximal.
     for nb_un in 0..=histo[0] {
         for nb_cinq in 0..=histo[4] {
             if nb_un == 0 && nb_cinq == 0 { continue; }
@@ -97,4 +450,324 @@ fn enumerer_combinaisons(histo: HistogrammeFaces) -> Vec<Combinaison> {
 
     // ...et on a tout traité
     choix
-}
\ No newline at end of file
+}
+
+// Toutes les combinaisons qu'on a le droit de prendre pour un histogramme
+// donné, y compris celles qui n'ont aucun intérêt, triées et sans doublons (un
+// résultat vide signifie que le tour est perdu). Ca ne sert pas à calculer des
+// espérances mais à vérifier qu'une prise proposée par un joueur est légale, ou
+// que l'énumération élaguée n'oublie rien: on énumère donc bêtement toutes les
+// combinaisons permises par les règles, et on garde celles pour lesquelles on a
+// les dés. Pour l'instant, l'énumération élaguée trouve exactement les mêmes
+// combinaisons (à l'ordre près) avec toutes les variantes des règles, mais rien
+// ne garantit que ça restera le cas si on y ajoute des élagages.
+pub fn enumerer_combinaisons_completes(histo: &Histogramme,
+                                       regles: &Regles) -> Vec<Combinaison> {
+    let nb_faces = histo.len();
+    let faces = 0..nb_faces;
+    let faces_simples = || {
+        (0..=histo[0]).flat_map(|nb_un| (0..=histo[4]).map(move |nb_cinq| (nb_un, nb_cinq)))
+    };
+    let mut candidates = vec![Combinaison::Suite, Combinaison::TriplePaire];
+    for idx_face in faces.clone() {
+        for idx_face_2 in idx_face..nb_faces {
+            candidates.push(Combinaison::BrelanDouble { idx_faces: [idx_face, idx_face_2] });
+        }
+        for (nb_un, nb_cinq) in faces_simples() {
+            candidates.push(Combinaison::BrelanSimple { idx_face, nb_un, nb_cinq });
+            if regles.carres_et_plus {
+                candidates.push(Combinaison::Carre { idx_face, nb_un, nb_cinq });
+                candidates.push(Combinaison::Quinte { idx_face, nb_un, nb_cinq });
+            }
+        }
+        if regles.carres_et_plus {
+            candidates.push(Combinaison::Sexte { idx_face });
+        }
+        if regles.full {
+            for idx_paire in faces.clone().filter(|&idx_paire| idx_paire != idx_face) {
+                candidates.push(Combinaison::Full { idx_brelan: idx_face, idx_paire });
+            }
+        }
+    }
+    if regles.petite_suite {
+        for idx_debut in 0..nb_faces.saturating_sub(4) {
+            for (nb_un, nb_cinq) in faces_simples() {
+                candidates.push(Combinaison::PetiteSuite { idx_debut, nb_un, nb_cinq });
+            }
+        }
+    }
+    for (nb_un, nb_cinq) in faces_simples().filter(|&faces| faces != (0, 0)) {
+        candidates.push(Combinaison::FacesSimples { nb_un, nb_cinq });
+    }
+
+    let mut choix = candidates.into_iter()
+                              .filter(|comb| comb.est_realisable(histo))
+                              .collect::<Vec<_>>();
+    choix.sort_unstable();
+    choix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Le dénombrement par histogrammes, pondérés par le nombre de lancers
+    // ordonnés qui y mènent, retrouve le dénombrement bête des nb_faces^nb_des
+    // lancers ordonnés
+    #[test]
+    fn comptage_multinomial() {
+        let regles = Regles::default();
+        for nb_des in 1..=regles.nb_des_tot {
+            let nb_lancers = regles.nb_faces.pow(nb_des as u32);
+            let mut comptes = BTreeMap::new();
+            for num_lancer in 0..nb_lancers {
+                let mut histo = vec![0; regles.nb_faces];
+                let mut reste = num_lancer;
+                for _ in 0..nb_des {
+                    histo[reste % regles.nb_faces] += 1;
+                    reste /= regles.nb_faces;
+                }
+                let mut choix = combinaisons(&histo, &regles);
+                choix.sort_unstable();
+                choix.dedup();
+                *comptes.entry(choix).or_insert(0) += 1;
+            }
+            assert_eq!(compter_choix(nb_des, &regles), comptes, "{} dés", nb_des);
+        }
+    }
+
+    // Lecture de jets de dés écrits par un joueur, sous toutes les formes
+    // acceptées, et rejet des jets qu'on ne peut pas lancer
+    #[test]
+    fn lecture_lancer() {
+        let regles = Regles::default();
+        let attendu = Histogramme(vec![1, 0, 2, 0, 1, 2]);
+        for texte in ["1 3 3 5 6 6", "133566", "1,3,3,5,6,6", "  6 3\t1, 5,,3 6\n",
+                      " 663351\n"] {
+            assert_eq!(Histogramme::parse(texte, &regles), Ok(attendu.clone()), "{:?}", texte);
+        }
+        let cinqs = Histogramme(vec![0, 0, 0, 0, 6, 0]);
+        assert_eq!(Histogramme::parse("555555", &regles), Ok(cinqs));
+        assert_eq!(Histogramme::parse("4", &regles), Ok(Histogramme(vec![0, 0, 0, 1, 0, 0])));
+
+        for (texte, erreur) in [
+            ("", ErreurLancer::NombreDes(0)),
+            (" , ", ErreurLancer::NombreDes(0)),
+            ("1111111", ErreurLancer::NombreDes(7)),
+            ("1 2 3 4 5 6 1", ErreurLancer::NombreDes(7)),
+            ("1 2 7", ErreurLancer::FaceHorsLimites(7)),
+            ("103", ErreurLancer::FaceHorsLimites(0)),
+            ("12 3", ErreurLancer::FaceHorsLimites(12)),
+            ("1 -2", ErreurLancer::FaceInvalide("-2".to_owned())),
+            ("1a3", ErreurLancer::FaceInvalide("a".to_owned())),
+        ] {
+            assert_eq!(Histogramme::parse(texte, &regles), Err(erreur), "{:?}", texte);
+        }
+    }
+
+    // L'énumération complète retrouve, à l'ordre et aux doublons près, les
+    // combinaisons de l'énumération élaguée, pour tous les jets possibles et
+    // toutes les variantes des règles
+    #[test]
+    fn enumeration_complete() {
+        for nb_faces in NB_FACES..=NB_FACES+2 {
+            for variante in 0..8 {
+                let regles = Regles {
+                    nb_faces,
+                    carres_et_plus: variante & 1 != 0,
+                    full: variante & 2 != 0,
+                    petite_suite: variante & 4 != 0,
+                    ..Regles::default()
+                };
+                for nb_des in 1..=regles.nb_des_tot {
+                    enumerer_histogrammes(nb_des, nb_faces, &mut |histo| {
+                        let choix = choix_canonique(histo, &regles);
+                        let histo = Histogramme(histo.to_vec());
+                        assert_eq!(enumerer_combinaisons_completes(&histo, &regles), choix,
+                                   "{:?}, variante {}", histo, variante);
+                    });
+                }
+            }
+        }
+    }
+
+    // Nombre de prises légales pour quelques jets de dés, avec les règles par
+    // défaut puis avec carrés, fulls et petites suites
+    #[test]
+    fn nombre_de_prises() {
+        let defaut = Regles::default();
+        let toutes = Regles {
+            carres_et_plus: true,
+            full: true,
+            petite_suite: true,
+            ..defaut.clone()
+        };
+        for &(faces, nb_defaut, nb_toutes) in &[
+            ("2 3 3 4 6 6", 0, 0),
+            ("5", 1, 1),
+            ("1 5", 3, 3),
+            ("2 2 3 3 4 4", 1, 1),
+            ("1 2 3 4 5 6", 4, 7),
+            ("2 2 2 3 3 3", 3, 5),
+            ("2 2 2 6 6 1", 3, 4),
+            ("1 2 3 4 5 5", 5, 7),
+            ("1 1 5 3 3 3", 11, 12),
+            ("3 3 3 3 1 5", 7, 11),
+            ("1 1 1 5 5 2", 14, 15),
+            ("4 4 4 4 4 4", 3, 6),
+            ("1 1 1 1 1 1", 12, 18),
+        ] {
+            for (regles, nb_prises) in [(&defaut, nb_defaut), (&toutes, nb_toutes)] {
+                let histo = Histogramme::parse(faces, regles).unwrap();
+                assert_eq!(enumerer_combinaisons_completes(&histo, regles).len(), nb_prises,
+                           "{}", faces);
+            }
+        }
+    }
+
+    // Un histogramme relu passe par les mêmes vérifications qu'un histogramme
+    // construit à la main
+    #[test]
+    fn relecture_histogramme() {
+        let histo = Histogramme::parse("1 3 3 6 6 6", &Regles::default()).unwrap();
+        let json = serde_json::to_string(&histo).unwrap();
+        assert_eq!(json, "[1,0,2,0,0,3]");
+        assert_eq!(serde_json::from_str::<Histogramme>(&json).unwrap(), histo);
+
+        for invalide in ["[6]", "[0,0,0,0,0,0]", "[7,0,0,0,0,0]", "[1,2,3,4,5,6,7]"] {
+            assert!(serde_json::from_str::<Histogramme>(invalide).is_err(), "{}", invalide);
+        }
+
+        // Des dés à 8 faces sont acceptés à la relecture, mais pas pour les
+        // règles par défaut
+        let histo = serde_json::from_str::<Histogramme>("[0,0,0,0,0,0,1,1]").unwrap();
+        assert_eq!(histo.verifier(&Regles::default()), Err(ErreurLancer::NombreFaces(8)));
+        assert_eq!(histo.verifier(&Regles { nb_faces: 8, ..Regles::default() }), Ok(()));
+    }
+
+    // Combinaisons proposées pour quelques jets de dés typiques (suites,
+    // triples paires, doubles brelans, jets mélangés): elles sont triées, sans
+    // doublons, et aucune combinaison signifie un tour perdu. Pour l'instant,
+    // les deux modes d'énumération trouvent les mêmes.
+    #[test]
+    fn combinaisons_possibles() {
+        let defaut = Regles::default();
+        let toutes = Regles {
+            carres_et_plus: true,
+            full: true,
+            petite_suite: true,
+            ..defaut.clone()
+        };
+        for &(faces, regles, attendu) in &[
+            // Rien à prendre
+            ("2 3 3 4 6 6", &defaut, "[]"),
+
+            // Suites, qui demandent un dé de chaque face
+            ("1 2 3 4 5 6", &defaut, "[Suite, 1x5, 1x1, 1x1+1x5]"),
+            ("6 5 4 3 2 1", &defaut, "[Suite, 1x5, 1x1, 1x1+1x5]"),
+            ("1 2 3 4 5", &defaut, "[1x5, 1x1, 1x1+1x5]"),
+
+            // Triples paires, y compris avec quatre dés identiques
+            ("2 2 3 3 4 4", &defaut, "[3Paires]"),
+            ("3 3 4 4 6 6", &defaut, "[3Paires]"),
+            ("1 1 5 5 6 6", &defaut, "[3Paires, 1x5, 2x5, 1x1, 1x1+1x5, 1x1+2x5, 2x1, \
+                                       2x1+1x5, 2x1+2x5]"),
+            ("1 1 1 1 5 5", &defaut, "[3Paires, Brelan1, Brelan1+1x5, Brelan1+2x5, Brelan1+1x1, \
+                                       Brelan1+1x1+1x5, Brelan1+1x1+2x5, 1x5, 2x5, 1x1, \
+                                       1x1+1x5, 1x1+2x5, 2x1, 2x1+1x5, 2x1+2x5, 3x1, \
+                                       3x1+1x5, 3x1+2x5, 4x1, 4x1+1x5, 4x1+2x5]"),
+
+            // Doubles brelans, y compris de la même face
+            ("2 2 2 3 3 3", &defaut, "[Brelan2+Brelan3, Brelan2, Brelan3]"),
+            ("4 4 4 4 4 4", &defaut, "[3Paires, Brelan4+Brelan4, Brelan4]"),
+            ("1 1 1 5 5 5", &defaut, "[Brelan1+Brelan5, Brelan1, Brelan1+1x5, Brelan1+2x5, \
+                                       Brelan1+3x5, Brelan5, Brelan5+1x1, Brelan5+2x1, \
+                                       Brelan5+3x1, 1x5, 2x5, 3x5, 1x1, 1x1+1x5, 1x1+2x5, \
+                                       1x1+3x5, 2x1, 2x1+1x5, 2x1+2x5, 2x1+3x5, 3x1, 3x1+1x5, \
+                                       3x1+2x5, 3x1+3x5]"),
+
+            // Jets mélangés, et variantes des règles
+            ("1 1 5 3 3 3", &defaut, "[Brelan3, Brelan3+1x5, Brelan3+1x1, Brelan3+1x1+1x5, \
+                                       Brelan3+2x1, Brelan3+2x1+1x5, 1x5, 1x1, 1x1+1x5, 2x1, \
+                                       2x1+1x5]"),
+            ("1 1 5 3 3 3", &toutes, "[Brelan3, Brelan3+1x5, Brelan3+1x1, Brelan3+1x1+1x5, \
+                                       Brelan3+2x1, Brelan3+2x1+1x5, 1x5, 1x1, 1x1+1x5, 2x1, \
+                                       2x1+1x5, Full3+1]"),
+            ("3 3 3 3 2 5", &toutes, "[Brelan3, Brelan3+1x5, 1x5, Carre3, Carre3+1x5]"),
+            ("2 2 2 6 6 1", &toutes, "[Brelan2, Brelan2+1x1, 1x1, Full2+6]"),
+            ("2 3 4 5 6 6", &toutes, "[1x5, PetiteSuite2]"),
+            ("1 2 3 4 5 6", &toutes, "[Suite, 1x5, 1x1, 1x1+1x5, PetiteSuite1, PetiteSuite2, \
+                                       PetiteSuite2+1x1]"),
+        ] {
+            let histo = Histogramme::parse(faces, regles).unwrap();
+            let attendu = attendu.split_whitespace().collect::<Vec<_>>().join(" ");
+            for mode in [Enumeration::Elaguee, Enumeration::Complete] {
+                let choix = histo.combinaisons_possibles(regles, mode);
+                assert!(choix.windows(2).all(|paire| paire[0] < paire[1]), "{}", faces);
+                assert_eq!(format!("{:?}", choix), attendu, "{}, {:?}", faces, mode);
+            }
+        }
+    }
+
+    // Face à six dés, les 1 et les 5 qui forment un brelan ou l'accompagnent
+    // peuvent aussi se prendre un par un, pour atterrir exactement sur le
+    // score maximal (voir Stats::proba_fin)
+    #[test]
+    fn faces_simples_a_cote_d_un_brelan() {
+        use Combinaison::*;
+        let regles = Regles::default();
+        for &(faces, attendues) in &[
+            ("1 1 1 2 3 4", &[BrelanSimple { idx_face: 0, nb_un: 0, nb_cinq: 0 },
+                              FacesSimples { nb_un: 1, nb_cinq: 0 },
+                              FacesSimples { nb_un: 2, nb_cinq: 0 },
+                              FacesSimples { nb_un: 3, nb_cinq: 0 }][..]),
+            ("5 5 5 2 3 4", &[BrelanSimple { idx_face: 4, nb_un: 0, nb_cinq: 0 },
+                              FacesSimples { nb_un: 0, nb_cinq: 1 },
+                              FacesSimples { nb_un: 0, nb_cinq: 2 }][..]),
+            ("2 2 2 1 5 3", &[BrelanSimple { idx_face: 1, nb_un: 1, nb_cinq: 1 },
+                              BrelanSimple { idx_face: 1, nb_un: 0, nb_cinq: 0 },
+                              FacesSimples { nb_un: 1, nb_cinq: 0 },
+                              FacesSimples { nb_un: 0, nb_cinq: 1 },
+                              FacesSimples { nb_un: 1, nb_cinq: 1 }][..]),
+        ] {
+            let histo = Histogramme::parse(faces, &regles).unwrap();
+            assert_eq!(histo.nb_des(), 6);
+            for mode in [Enumeration::Elaguee, Enumeration::Complete] {
+                let choix = histo.combinaisons_possibles(&regles, mode);
+                for comb in attendues {
+                    assert!(choix.contains(comb), "{}, {:?}: {:?} absent", faces, mode, comb);
+                }
+            }
+        }
+    }
+
+    // Les faces des jets aléatoires sortent avec la fréquence attendue, que les
+    // dés soient équilibrés ou pipés, à en juger par un test du khi-deux avec 5
+    // degrés de liberté (on le rate avec une probabilité de 0.1% pour des dés
+    // qui suivent bien la loi attendue)
+    #[test]
+    fn jets_aleatoires() {
+        use rand::{SeedableRng, rngs::Xoshiro256PlusPlus};
+        const NB_JETS : usize = 50_000;
+        let equilibres = Regles::default();
+        let pipes = Regles { poids_faces: Some(vec![0.5, 0.1, 0.1, 0.1, 0.1, 0.1]),
+                             ..Regles::default() };
+        for regles in [equilibres, pipes] {
+            let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+            let mut comptes = [0; NB_FACES];
+            for _ in 0..NB_JETS {
+                let histo = Histogramme::aleatoire(6, &regles, &mut rng).unwrap();
+                assert_eq!(histo.nb_des(), 6);
+                for (compte, &nb) in comptes.iter_mut().zip(histo.iter()) { *compte += nb; }
+            }
+            let nb_tirages = (6 * NB_JETS) as Flottant;
+            let khi2 : Flottant = comptes.iter().enumerate().map(|(idx_face, &compte)| {
+                let proba = regles.poids_faces.as_ref()
+                                  .map_or(1. / 6., |poids| poids[idx_face]);
+                let attendu = proba * nb_tirages;
+                (compte as Flottant - attendu).powi(2) / attendu
+            }).sum();
+            assert!(khi2 < 20.5, "{:?}: {:?} (khi2 = {})", regles.poids_faces, comptes, khi2);
+        }
+    }
+}
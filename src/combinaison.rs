@@ -1,13 +1,20 @@
-use crate::{NB_FACES, Valeur};
-use std::fmt::{self, Debug};
+use crate::{
+    Valeur,
+    choix::{self, Histogramme},
+    regles::Regles,
+};
 
+use serde::{Deserialize, Serialize};
 
-// Valeur minimale d'un dé dans une combinaison
+use std::fmt::{self, Debug, Display};
+
+
+// Valeur minimale d'un dé dans une combinaison, avec le barème classique
 pub const VALEUR_MIN_DE : Valeur = 50;
 
 // Combinaison gagnante définie par la règle de la mitraillette, que l'on peut
 // choisir d'encaisser ou de mettre de côté en relançant le reste des dés.
-#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum Combinaison {
     // 1 2 3 4 5 6
     Suite,
@@ -23,6 +30,22 @@ pub enum Combinaison {
 
     // Des 1, des 5, et rien d'autre
     FacesSimples { nb_un: usize, nb_cinq: usize },
+
+    // aaaa xy (où x, y peut contenir 1 et 5), si les règles l'autorisent
+    Carre { idx_face: usize, nb_un: usize, nb_cinq: usize },
+
+    // aaaaa x (où x peut être un 1 ou un 5), si les règles l'autorisent
+    Quinte { idx_face: usize, nb_un: usize, nb_cinq: usize },
+
+    // aaaaaa, qui fait gagner la partie immédiatement si les règles l'autorisent
+    Sexte { idx_face: usize },
+
+    // aaa bb, si les règles l'autorisent
+    Full { idx_brelan: usize, idx_paire: usize },
+
+    // 1 2 3 4 5 ou 2 3 4 5 6 (+ éventuellement un 1 ou un 5), si les règles
+    // l'autorisent
+    PetiteSuite { idx_debut: usize, nb_un: usize, nb_cinq: usize },
 }
 
 impl Debug for Combinaison {
@@ -41,6 +64,28 @@ impl Debug for Combinaison {
                 if nb_cinq > 0 { write!(formatter, "+{}x5", nb_cinq)?; }
                 Ok(())
             },
+            Carre { idx_face, nb_un, nb_cinq } => {
+                write!(formatter, "Carre{}", idx_face+1)?;
+                if nb_un > 0 { write!(formatter, "+{}x1", nb_un)?; }
+                if nb_cinq > 0 { write!(formatter, "+{}x5", nb_cinq)?; }
+                Ok(())
+            },
+            Quinte { idx_face, nb_un, nb_cinq } => {
+                write!(formatter, "Quinte{}", idx_face+1)?;
+                if nb_un > 0 { write!(formatter, "+{}x1", nb_un)?; }
+                if nb_cinq > 0 { write!(formatter, "+{}x5", nb_cinq)?; }
+                Ok(())
+            },
+            Sexte { idx_face } => write!(formatter, "Sexte{}", idx_face+1),
+            Full { idx_brelan, idx_paire } => {
+                write!(formatter, "Full{}+{}", idx_brelan+1, idx_paire+1)
+            },
+            PetiteSuite { idx_debut, nb_un, nb_cinq } => {
+                write!(formatter, "PetiteSuite{}", idx_debut+1)?;
+                if nb_un > 0 { write!(formatter, "+{}x1", nb_un)?; }
+                if nb_cinq > 0 { write!(formatter, "+{}x5", nb_cinq)?; }
+                Ok(())
+            },
             FacesSimples { nb_un, nb_cinq } => {
                 if nb_un > 0 {
                     write!(formatter, "{}x1", nb_un)?;
@@ -53,32 +98,402 @@ impl Debug for Combinaison {
     }
 }
 
+impl Display for Combinaison {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        use Combinaison::*;
+        match *self {
+            Suite => write!(formatter, "Suite")?,
+            TriplePaire => write!(formatter, "Triple paire")?,
+            BrelanDouble { idx_faces } => {
+                write!(formatter, "Brelan de {} + brelan de {}",
+                       idx_faces[0]+1, idx_faces[1]+1)?;
+            },
+            BrelanSimple { idx_face, nb_un, nb_cinq } => {
+                write!(formatter, "Brelan de {}", idx_face+1)?;
+                ecrire_faces_simples(formatter, nb_un, nb_cinq, true)?;
+            },
+            FacesSimples { nb_un, nb_cinq } => {
+                ecrire_faces_simples(formatter, nb_un, nb_cinq, false)?;
+            },
+            Carre { idx_face, nb_un, nb_cinq } => {
+                write!(formatter, "Carré de {}", idx_face+1)?;
+                ecrire_faces_simples(formatter, nb_un, nb_cinq, true)?;
+            },
+            Quinte { idx_face, nb_un, nb_cinq } => {
+                write!(formatter, "Quinte de {}", idx_face+1)?;
+                ecrire_faces_simples(formatter, nb_un, nb_cinq, true)?;
+            },
+            // Selon la règle, la sexte fait gagner ou rapporte ce que lui donne
+            // le barème (voir Regles::fait_gagner): c'est à l'appelant qui
+            // connaît la règle de dire si elle fait gagner
+            Sexte { idx_face } => write!(formatter, "Sexte de {}", idx_face+1)?,
+            Full { idx_brelan, idx_paire } => {
+                write!(formatter, "Full (brelan de {} + paire de {})",
+                       idx_brelan+1, idx_paire+1)?;
+            },
+            PetiteSuite { idx_debut, nb_un, nb_cinq } => {
+                write!(formatter, "Petite suite de {} à {}", idx_debut+1, idx_debut+5)?;
+                ecrire_faces_simples(formatter, nb_un, nb_cinq, true)?;
+            },
+        }
+        let nb_des = self.nb_des();
+        write!(formatter, " ({} points, {} dé{})",
+               self.valeur(), nb_des, if nb_des > 1 { "s" } else { "" })
+    }
+}
+
+// Ecriture en toutes lettres d'un ensemble de 1 et de 5 ("deux 1 + un 5"),
+// éventuellement à la suite d'une autre combinaison. Au-delà de six dés, ce
+// que la règle ne permet pas mais qu'on peut construire à la main, on écrit
+// le nombre en chiffres.
+fn ecrire_faces_simples(formatter: &mut fmt::Formatter,
+                        nb_un: usize,
+                        nb_cinq: usize,
+                        a_la_suite: bool) -> Result<(), fmt::Error> {
+    const NOMBRES : [&str; 7] = ["zéro", "un", "deux", "trois", "quatre", "cinq", "six"];
+    let mut separateur = if a_la_suite { " + " } else { "" };
+    for &(nb, face) in [(nb_un, 1), (nb_cinq, 5)].iter() {
+        if nb == 0 { continue; }
+        match NOMBRES.get(nb) {
+            Some(nombre) => write!(formatter, "{}{} {}", separateur, nombre, face)?,
+            None => write!(formatter, "{}{} {}", separateur, nb, face)?,
+        }
+        separateur = " + ";
+    }
+    Ok(())
+}
+
 impl Combinaison {
-    // Valeur de la combinaison en points
+    // Valeur de la combinaison en points, selon le barème classique
     pub fn valeur(&self) -> Valeur {
+        Bareme::default().valeur(self)
+    }
+
+    // Est-ce que cette combinaison fait gagner la partie immédiatement, quand
+    // on joue jusqu'au score maximal (voir Regles::fait_gagner)?
+    pub fn fait_gagner(&self) -> bool {
+        matches!(self, Combinaison::Sexte { .. })
+    }
+
+    // Nombre de dés consommé si on encaisse la combinaison
+    pub fn nb_des(&self) -> usize {
         use Combinaison::*;
-        const VAL_BRELANS : [Valeur; NB_FACES] = [1000, 200, 300, 400, 500, 600];
         match self {
-            Suite | TriplePaire => 500,
+            Suite | TriplePaire | BrelanDouble { .. } | Sexte { .. } => 6,
+            BrelanSimple { idx_face: _, nb_un, nb_cinq } => 3 + nb_un + nb_cinq,
+            Carre { idx_face: _, nb_un, nb_cinq } => 4 + nb_un + nb_cinq,
+            Quinte { idx_face: _, nb_un, nb_cinq } => 5 + nb_un + nb_cinq,
+            Full { .. } => 5,
+            PetiteSuite { idx_debut: _, nb_un, nb_cinq } => 5 + nb_un + nb_cinq,
+            FacesSimples { nb_un, nb_cinq } => nb_un + nb_cinq,
+        }
+    }
+
+    // Combinaisons les plus complètes que la règle autorise face à un jet de
+    // dés (donné sous forme d'histogramme), c'est à dire celles dont on ne
+    // peut pas garder plus de dés en prenant une autre combinaison. Les prises
+    // partielles restent légales (voir choix::enumerer_combinaisons_completes),
+    // mais ce sont ces combinaisons-là qu'on propose à un joueur humain.
+    pub fn depuis_histogramme(histo: &Histogramme, regles: &Regles) -> Vec<Combinaison> {
+        let possibles = choix::enumerer_combinaisons_completes(histo, regles);
+        let pris = possibles.iter()
+                            .map(|comb| comb.des_pris(histo)
+                                            .expect("Combinaison énumérée invalide"))
+                            .collect::<Vec<_>>();
+        let contenu_dans = |a: &[usize], b: &[usize]| {
+            a != b && a.iter().zip(b).all(|(&nb_a, &nb_b)| nb_a <= nb_b)
+        };
+        possibles.iter()
+                 .zip(pris.iter())
+                 .filter(|(_, des)| !pris.iter().any(|autres| contenu_dans(des, autres)))
+                 .map(|(&comb, _)| comb)
+                 .collect()
+    }
+
+    // Est-ce que cette combinaison est bien formée (faces existantes, nombres
+    // de dés cohérents), et est-ce qu'on a tiré les dés nécessaires pour la
+    // prendre? On ne vérifie pas que la règle l'autorise (carrés, fulls...),
+    // seulement qu'elle correspond aux dés.
+    pub fn est_realisable(&self, histo: &[usize]) -> bool {
+        match self.des_pris(histo) {
+            Some(pris) => pris.iter().zip(histo).all(|(&nb_pris, &nb_tires)| nb_pris <= nb_tires),
+            None => false,
+        }
+    }
+
+    // Histogramme des dés que cette combinaison prend face à un certain jet,
+    // ou None si elle est mal formée pour des dés à autant de faces
+    fn des_pris(&self, histo: &[usize]) -> Option<Vec<usize>> {
+        use Combinaison::*;
+        let nb_faces = histo.len();
+        let face_valide = |idx_face: usize| idx_face < nb_faces;
+
+        // Ces deux-là prennent tous les dés, qui doivent avoir la bonne forme
+        match *self {
+            Suite => return Some(vec![1; nb_faces]),
+            TriplePaire => {
+                if histo.iter().map(|&bin| bin/2).sum::<usize>() != 3 { return None; }
+                return Some(histo.iter().map(|&bin| bin - bin % 2).collect());
+            },
+            _ => {},
+        }
+
+        // Pour les autres, on construit l'histogramme des dés pris, en
+        // vérifiant au passage que les faces existent
+        let mut pris = vec![0; nb_faces];
+        let prendre = |pris: &mut Vec<usize>, idx_face: usize, nb: usize| {
+            if nb == 0 { return Some(()); }
+            *pris.get_mut(idx_face)? += nb;
+            Some(())
+        };
+        match *self {
+            Suite | TriplePaire => unreachable!(),
+            BrelanDouble { idx_faces: [idx_face_1, idx_face_2] } => {
+                if idx_face_1 > idx_face_2 || !face_valide(idx_face_2) { return None; }
+                pris[idx_face_1] += 3;
+                pris[idx_face_2] += 3;
+            },
+            BrelanSimple { idx_face, nb_un, nb_cinq }
+            | Carre { idx_face, nb_un, nb_cinq }
+            | Quinte { idx_face, nb_un, nb_cinq } => {
+                if !face_valide(idx_face) { return None; }
+                let multiplicite = match *self {
+                    BrelanSimple { .. } => 3,
+                    Carre { .. } => 4,
+                    _ => 5,
+                };
+                pris[idx_face] += multiplicite;
+                prendre(&mut pris, 0, nb_un)?;
+                prendre(&mut pris, 4, nb_cinq)?;
+            },
+            Sexte { idx_face } => {
+                if !face_valide(idx_face) { return None; }
+                pris[idx_face] += 6;
+            },
+            Full { idx_brelan, idx_paire } => {
+                if idx_brelan == idx_paire || !face_valide(idx_brelan) || !face_valide(idx_paire) {
+                    return None;
+                }
+                pris[idx_brelan] += 3;
+                pris[idx_paire] += 2;
+            },
+            PetiteSuite { idx_debut, nb_un, nb_cinq } => {
+                if idx_debut + 5 > nb_faces { return None; }
+                for bin in &mut pris[idx_debut..idx_debut+5] { *bin += 1; }
+                prendre(&mut pris, 0, nb_un)?;
+                prendre(&mut pris, 4, nb_cinq)?;
+            },
+            FacesSimples { nb_un, nb_cinq } => {
+                if nb_un == 0 && nb_cinq == 0 { return None; }
+                prendre(&mut pris, 0, nb_un)?;
+                prendre(&mut pris, 4, nb_cinq)?;
+            },
+        }
+        Some(pris)
+    }
+}
+
+// Barème indiquant combien de points rapporte chaque combinaison
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Bareme {
+    // Valeur d'une suite 1 2 3 4 5 6
+    pub suite: Valeur,
+
+    // Valeur d'une petite suite (cinq faces consécutives)
+    pub petite_suite: Valeur,
+
+    // Valeur d'une triple paire
+    pub triple_paire: Valeur,
+
+    // Valeur d'un brelan de 1
+    pub brelan_de_un: Valeur,
+
+    // Les autres brelans valent ce nombre de points multiplié par la face
+    pub brelan_par_face: Valeur,
+
+    // Facteur multiplicatif appliqué à la valeur du brelan pour un carré
+    pub multiplicateur_carre: Valeur,
+
+    // Facteur multiplicatif appliqué à la valeur du brelan pour une quinte
+    pub multiplicateur_quinte: Valeur,
+
+    // Même chose pour une sexte, qui ne compte que quand on joue un nombre fixe
+    // de tours: sinon, elle fait gagner la partie (voir Regles::fait_gagner)
+    pub multiplicateur_sexte: Valeur,
+
+    // Bonus ajouté à la valeur du brelan pour un full
+    pub bonus_full: Valeur,
+
+    // Valeur d'un 1 isolé
+    pub un: Valeur,
+
+    // Valeur d'un 5 isolé
+    pub cinq: Valeur,
+}
+
+impl Default for Bareme {
+    fn default() -> Self {
+        Self {
+            suite: 500,
+            petite_suite: 350,
+            triple_paire: 500,
+            brelan_de_un: 1000,
+            brelan_par_face: 100,
+            multiplicateur_carre: 2,
+            multiplicateur_quinte: 4,
+            multiplicateur_sexte: 8,
+            bonus_full: 250,
+            un: 100,
+            cinq: VALEUR_MIN_DE,
+        }
+    }
+}
+
+impl Bareme {
+    // Valeur d'une combinaison en points
+    pub fn valeur(&self, comb: &Combinaison) -> Valeur {
+        use Combinaison::*;
+        match *comb {
+            Suite => self.suite,
+            TriplePaire => self.triple_paire,
             BrelanDouble { idx_faces: [idx_face_1, idx_face_2] } =>
-                VAL_BRELANS[*idx_face_1] + VAL_BRELANS[*idx_face_2],
+                self.brelan(idx_face_1) + self.brelan(idx_face_2),
             BrelanSimple { idx_face, nb_un, nb_cinq } =>
-                VAL_BRELANS[*idx_face]
-                    + (*nb_un as Valeur) * 100
-                    + (*nb_cinq as Valeur) * 50,
+                self.brelan(idx_face) + self.faces_simples(nb_un, nb_cinq),
+            Carre { idx_face, nb_un, nb_cinq } =>
+                self.brelan(idx_face) * self.multiplicateur_carre
+                    + self.faces_simples(nb_un, nb_cinq),
+            Quinte { idx_face, nb_un, nb_cinq } =>
+                self.brelan(idx_face) * self.multiplicateur_quinte
+                    + self.faces_simples(nb_un, nb_cinq),
+            PetiteSuite { idx_debut: _, nb_un, nb_cinq } =>
+                self.petite_suite + self.faces_simples(nb_un, nb_cinq),
+            Full { idx_brelan, idx_paire: _ } =>
+                self.brelan(idx_brelan) + self.bonus_full,
+            Sexte { idx_face } =>
+                self.brelan(idx_face) * self.multiplicateur_sexte,
             FacesSimples { nb_un, nb_cinq } =>
-                (*nb_un as Valeur) * 100
-                    + (*nb_cinq as Valeur) * 50,
+                self.faces_simples(nb_un, nb_cinq),
         }
     }
 
-    // Nombre de dés consommé si on encaisse la combinaison
-    pub fn nb_des(&self) -> usize {
+    // Plus petite valeur que peut rapporter un dé mis de côté
+    pub fn valeur_min_de(&self) -> Valeur {
+        self.un.min(self.cinq)
+    }
+
+    // Valeur d'un brelan en fonction de la face (numérotée à partir de 0)
+    fn brelan(&self, idx_face: usize) -> Valeur {
+        if idx_face == 0 {
+            self.brelan_de_un
+        } else {
+            (idx_face as Valeur + 1) * self.brelan_par_face
+        }
+    }
+
+    // Valeur d'un ensemble de 1 et de 5 isolés
+    fn faces_simples(&self, nb_un: usize, nb_cinq: usize) -> Valeur {
+        (nb_un as Valeur) * self.un + (nb_cinq as Valeur) * self.cinq
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Affichage de chaque sorte de combinaison, avec sa valeur et ses dés
+    #[test]
+    fn affichage() {
         use Combinaison::*;
-        match self {
-            Suite | TriplePaire | BrelanDouble { .. } => 6,
-            BrelanSimple { idx_face: _, nb_un, nb_cinq } => 3 + nb_un + nb_cinq,
-            FacesSimples { nb_un, nb_cinq } => nb_un + nb_cinq,
+        for &(comb, attendu) in &[
+            (Suite, "Suite (500 points, 6 dés)"),
+            (TriplePaire, "Triple paire (500 points, 6 dés)"),
+            (BrelanDouble { idx_faces: [0, 2] },
+             "Brelan de 1 + brelan de 3 (1300 points, 6 dés)"),
+            (BrelanSimple { idx_face: 0, nb_un: 0, nb_cinq: 2 },
+             "Brelan de 1 + deux 5 (1100 points, 5 dés)"),
+            (BrelanSimple { idx_face: 3, nb_un: 1, nb_cinq: 1 },
+             "Brelan de 4 + un 1 + un 5 (550 points, 5 dés)"),
+            (FacesSimples { nb_un: 2, nb_cinq: 1 }, "deux 1 + un 5 (250 points, 3 dés)"),
+            (FacesSimples { nb_un: 0, nb_cinq: 1 }, "un 5 (50 points, 1 dé)"),
+            (Carre { idx_face: 1, nb_un: 0, nb_cinq: 1 },
+             "Carré de 2 + un 5 (450 points, 5 dés)"),
+            (Quinte { idx_face: 5, nb_un: 1, nb_cinq: 0 },
+             "Quinte de 6 + un 1 (2500 points, 6 dés)"),
+            (Sexte { idx_face: 2 }, "Sexte de 3 (2400 points, 6 dés)"),
+            (Full { idx_brelan: 1, idx_paire: 5 },
+             "Full (brelan de 2 + paire de 6) (450 points, 5 dés)"),
+            (PetiteSuite { idx_debut: 1, nb_un: 1, nb_cinq: 0 },
+             "Petite suite de 2 à 6 + un 1 (450 points, 6 dés)"),
+            (FacesSimples { nb_un: 7, nb_cinq: 0 }, "7 1 (700 points, 7 dés)"),
+        ] {
+            assert_eq!(comb.to_string(), attendu);
+        }
+    }
+
+    // Combinaisons réalisables face à un jet (donné sous forme d'histogramme),
+    // et formes invalides de chaque sorte de combinaison: faces inexistantes,
+    // nombres de dés incohérents, ou dés qu'on n'a pas tirés
+    #[test]
+    fn realisabilite() {
+        use Combinaison::*;
+        for &(comb, histo, attendu) in &[
+            (Suite, [1, 1, 1, 1, 1, 1], true),
+            (Suite, [1, 1, 1, 1, 2, 0], false),
+            (TriplePaire, [2, 0, 4, 0, 0, 0], true),
+            (TriplePaire, [2, 2, 1, 1, 0, 0], false),
+            (BrelanDouble { idx_faces: [1, 3] }, [0, 3, 0, 3, 0, 0], true),
+            (BrelanDouble { idx_faces: [3, 1] }, [0, 3, 0, 3, 0, 0], false),
+            (BrelanDouble { idx_faces: [1, 6] }, [0, 3, 0, 3, 0, 0], false),
+            (BrelanDouble { idx_faces: [1, 2] }, [0, 3, 0, 3, 0, 0], false),
+            (BrelanSimple { idx_face: 1, nb_un: 1, nb_cinq: 1 }, [1, 3, 0, 0, 1, 1], true),
+            (BrelanSimple { idx_face: 9, nb_un: 0, nb_cinq: 0 }, [1, 3, 0, 0, 1, 1], false),
+            (BrelanSimple { idx_face: 1, nb_un: 2, nb_cinq: 0 }, [1, 3, 0, 0, 1, 1], false),
+            (BrelanSimple { idx_face: 5, nb_un: 0, nb_cinq: 0 }, [1, 3, 0, 0, 1, 1], false),
+            (FacesSimples { nb_un: 2, nb_cinq: 1 }, [2, 1, 1, 0, 1, 1], true),
+            (FacesSimples { nb_un: 0, nb_cinq: 0 }, [2, 1, 1, 0, 1, 1], false),
+            (FacesSimples { nb_un: 7, nb_cinq: 0 }, [2, 1, 1, 0, 1, 1], false),
+            (FacesSimples { nb_un: 0, nb_cinq: 2 }, [2, 1, 1, 0, 1, 1], false),
+            (Carre { idx_face: 2, nb_un: 0, nb_cinq: 1 }, [0, 0, 4, 0, 1, 1], true),
+            (Carre { idx_face: 6, nb_un: 0, nb_cinq: 0 }, [0, 0, 4, 0, 1, 1], false),
+            (Carre { idx_face: 2, nb_un: 1, nb_cinq: 0 }, [0, 0, 4, 0, 1, 1], false),
+            (Carre { idx_face: 4, nb_un: 0, nb_cinq: 0 }, [0, 0, 4, 0, 1, 1], false),
+            (Quinte { idx_face: 3, nb_un: 1, nb_cinq: 0 }, [1, 0, 0, 5, 0, 0], true),
+            (Quinte { idx_face: 8, nb_un: 0, nb_cinq: 0 }, [1, 0, 0, 5, 0, 0], false),
+            (Quinte { idx_face: 3, nb_un: 0, nb_cinq: 1 }, [1, 0, 0, 5, 0, 0], false),
+            (Sexte { idx_face: 2 }, [0, 0, 6, 0, 0, 0], true),
+            (Sexte { idx_face: 6 }, [0, 0, 6, 0, 0, 0], false),
+            (Sexte { idx_face: 2 }, [0, 0, 5, 0, 0, 1], false),
+            (Full { idx_brelan: 1, idx_paire: 5 }, [0, 3, 0, 0, 1, 2], true),
+            (Full { idx_brelan: 5, idx_paire: 5 }, [0, 3, 0, 0, 1, 2], false),
+            (Full { idx_brelan: 1, idx_paire: 7 }, [0, 3, 0, 0, 1, 2], false),
+            (Full { idx_brelan: 5, idx_paire: 1 }, [0, 3, 0, 0, 1, 2], false),
+            (PetiteSuite { idx_debut: 1, nb_un: 1, nb_cinq: 0 }, [1, 1, 1, 1, 1, 1], true),
+            (PetiteSuite { idx_debut: 2, nb_un: 0, nb_cinq: 0 }, [1, 1, 1, 1, 1, 1], false),
+            (PetiteSuite { idx_debut: 0, nb_un: 0, nb_cinq: 1 }, [1, 1, 1, 1, 1, 1], false),
+            (PetiteSuite { idx_debut: 0, nb_un: 0, nb_cinq: 0 }, [1, 1, 0, 1, 1, 2], false),
+        ] {
+            assert_eq!(comb.est_realisable(&histo), attendu, "{:?} {:?}", comb, histo);
         }
     }
-}
\ No newline at end of file
+
+    // Seules les prises les plus complètes d'un jet sont proposées
+    #[test]
+    fn prises_maximales() {
+        use Combinaison::*;
+        let regles = Regles::default();
+        for (faces, attendu) in [
+            (&[1, 2, 2, 2, 3, 5][..], vec![BrelanSimple { idx_face: 1, nb_un: 1, nb_cinq: 1 }]),
+            (&[1, 2, 3, 4, 5, 6][..], vec![Suite]),
+            (&[5, 5, 3, 3, 2, 2][..], vec![TriplePaire]),
+            (&[1, 1, 5, 4][..], vec![FacesSimples { nb_un: 2, nb_cinq: 1 }]),
+            (&[2, 3, 4, 6, 6, 3][..], vec![]),
+        ] {
+            let histo = Histogramme::depuis_faces(faces, &regles).unwrap();
+            let prises = Combinaison::depuis_histogramme(&histo, &regles);
+            assert_eq!(prises, attendu, "{:?}", faces);
+            assert!(prises.iter().all(|comb| comb.est_realisable(&histo)));
+        }
+    }
+}
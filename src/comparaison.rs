@@ -0,0 +1,183 @@
+use crate::{
+    simulation::{Jeu, QUANTILE_95, SourceDes, Strategie, TAILLE_LOT},
+    stats::Stats,
+};
+
+use rayon::prelude::*;
+
+
+// Comparaison statistique de deux stratégies A et B, qui jouent des paires de
+// parties: dans chaque paire, A commence la première partie et B la seconde,
+// avec les mêmes dés à chaque place (voir SourceDes::Appariee). Les dés qui ont
+// favorisé l'un des joueurs dans la première partie favorisent donc l'autre
+// dans la seconde, ce qui réduit la part de la chance dans l'écart des taux de
+// victoire. Deux stratégies identiques font ainsi match nul sur chaque paire.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparaison {
+    // Nombre de paires de parties jouées
+    pub nb_paires: usize,
+
+    // victoires[0] et victoires[1] sont les parties gagnées par A et B
+    pub victoires: [usize; 2],
+
+    // Nombre de parties qui n'ont pas fini en NB_TOURS_MAX tours
+    pub nb_interrompues: usize,
+
+    // Nombre de paires dont A a gagné les deux parties, une seule, ou aucune
+    // (sans compter les paires où une partie a été interrompue)
+    pub paires_gagnees: [usize; 3],
+
+    // Variance de l'écart (victoires de A - victoires de B) / 2 au sein d'une
+    // paire, et variance de l'écart (1 si A gagne, -1 s'il perd) sur une
+    // partie isolée
+    variance_paire: f64,
+    variance_partie: f64,
+}
+
+impl Comparaison {
+    // Ecart entre les taux de victoire de A et de B
+    pub fn difference(&self) -> f64 {
+        (self.victoires[0] as f64 - self.victoires[1] as f64) / (2 * self.nb_paires) as f64
+    }
+
+    // Erreur standard sur cet écart, calculée à partir des paires
+    pub fn erreur_standard(&self) -> f64 {
+        (self.variance_paire / self.nb_paires as f64).sqrt()
+    }
+
+    // Intervalle de confiance à 95% sur l'écart, selon l'approximation normale
+    pub fn intervalle(&self) -> (f64, f64) {
+        let marge = QUANTILE_95 * self.erreur_standard();
+        (self.difference() - marge, self.difference() + marge)
+    }
+
+    // Probabilité d'observer un écart au moins aussi grand si les deux
+    // stratégies se valaient (test bilatéral, approximation normale)
+    pub fn p_valeur(&self) -> f64 {
+        let erreur = self.erreur_standard();
+        if erreur == 0. {
+            return if self.difference() == 0. { 1. } else { 0. };
+        }
+        erfc(self.difference().abs() / erreur / 2f64.sqrt())
+    }
+
+    // Meilleure des deux stratégies (0 pour A, 1 pour B), ou None si
+    // l'intervalle de confiance ne permet pas de trancher
+    pub fn gagnant(&self) -> Option<usize> {
+        let (bas, haut) = self.intervalle();
+        if bas > 0. {
+            Some(0)
+        } else if haut < 0. {
+            Some(1)
+        } else {
+            None
+        }
+    }
+
+    // Nombre de parties indépendantes (sans appariement des dés) qu'il
+    // faudrait jouer pour atteindre la même précision
+    pub fn taille_effective(&self) -> f64 {
+        if self.variance_paire == 0. { return f64::INFINITY; }
+        self.variance_partie * self.nb_paires as f64 / self.variance_paire
+    }
+}
+
+// Fonction d'erreur complémentaire, selon l'approximation 7.1.26 d'Abramowitz
+// et Stegun (erreur absolue inférieure à 1.5e-7), pour x positif
+fn erfc(x: f64) -> f64 {
+    let t = 1. / (1. + 0.3275911 * x);
+    let polynome = t * (0.254829592
+                        + t * (-0.284496736
+                               + t * (1.421413741
+                                      + t * (-1.453152027
+                                             + t * 1.061405429))));
+    polynome * (-x * x).exp()
+}
+
+// Faire jouer un certain nombre de paires de parties à deux stratégies,
+// construites par "creer_strategies" dans l'ordre [A, B] pour que chaque
+// tâche parallèle ait les siennes (voir tournoi::tournoi)
+pub fn comparer<'stats>(stats: &'stats Stats,
+                        creer_strategies: impl Fn(&'stats Stats) -> [Box<dyn Strategie + 'stats>; 2] + Sync,
+                        nb_paires: usize,
+                        graine: u64) -> Comparaison
+{
+    assert!(nb_paires > 1, "Il faut au moins deux paires de parties");
+
+    // On joue les paires par lots, en parallèle. Les deux parties d'une paire
+    // sont jouées avec des dés appariés, tirés d'une graine propre à la paire.
+    let lots = (0..nb_paires).step_by(TAILLE_LOT).collect::<Vec<_>>();
+    let resultats_lots = lots.par_iter().map(|&debut| {
+        let strategies = creer_strategies(stats);
+        let (a, b) = (&*strategies[0], &*strategies[1]);
+        let fin = (debut + TAILLE_LOT).min(nb_paires);
+        (debut..fin).map(|num_paire| {
+            let graine_paire = graine.wrapping_add(num_paire as u64);
+            let nouveau_jeu = || {
+                Jeu::with_source(stats.regles().clone(), graine_paire, SourceDes::Appariee)
+            };
+            let premiere = nouveau_jeu().jouer_partie([a, b]).gagnant.map(|gagnant| gagnant == 0);
+            let seconde = nouveau_jeu().jouer_partie([b, a]).gagnant.map(|gagnant| gagnant == 1);
+            [premiere, seconde]
+        }).collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+
+    // ...et on rassemble les résultats. Chaque partie vaut 1 si A la gagne,
+    // -1 s'il la perd, et 0 si elle est interrompue.
+    let mut comparaison = Comparaison {
+        nb_paires,
+        victoires: [0; 2],
+        nb_interrompues: 0,
+        paires_gagnees: [0; 3],
+        variance_paire: 0.,
+        variance_partie: 0.,
+    };
+    let mut ecarts_paires = Vec::with_capacity(nb_paires);
+    let mut ecarts_parties = Vec::with_capacity(2 * nb_paires);
+    for paire in resultats_lots.into_iter().flatten() {
+        let mut ecart_paire = 0.;
+        for partie in paire {
+            let ecart = match partie {
+                Some(true) => { comparaison.victoires[0] += 1; 1. },
+                Some(false) => { comparaison.victoires[1] += 1; -1. },
+                None => { comparaison.nb_interrompues += 1; 0. },
+            };
+            ecart_paire += ecart / 2.;
+            ecarts_parties.push(ecart);
+        }
+        if let [Some(premiere), Some(seconde)] = paire {
+            comparaison.paires_gagnees[2 - premiere as usize - seconde as usize] += 1;
+        }
+        ecarts_paires.push(ecart_paire);
+    }
+    comparaison.variance_paire = variance(&ecarts_paires);
+    comparaison.variance_partie = variance(&ecarts_parties);
+    comparaison
+}
+
+// Variance empirique (non biaisée) d'un échantillon
+fn variance(echantillon: &[f64]) -> f64 {
+    let n = echantillon.len() as f64;
+    let moyenne = echantillon.iter().sum::<f64>() / n;
+    echantillon.iter().map(|x| (x - moyenne) * (x - moyenne)).sum::<f64>() / (n - 1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::SeuilFixe;
+
+    // Aux dés appariés, deux stratégies identiques gagnent chacune une partie
+    // de chaque paire
+    #[test]
+    fn strategies_identiques() {
+        let stats = Stats::with_score_max(1000);
+        let comparaison = comparer(&stats, |_| {
+            [Box::new(SeuilFixe(300)) as Box<dyn Strategie>, Box::new(SeuilFixe(300))]
+        }, 50, 42);
+        assert_eq!(comparaison.victoires, [50, 50]);
+        assert_eq!(comparaison.paires_gagnees, [0, 50, 0]);
+        assert_eq!(comparaison.difference(), 0.);
+        assert_eq!(comparaison.gagnant(), None);
+    }
+}
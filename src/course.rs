@@ -0,0 +1,452 @@
+use crate::{
+    Flottant,
+    Valeur,
+    combinaison::Combinaison,
+    regles::Regles,
+    simulation::{EtatTour, Strategie},
+    stats::{Decision, OptimiseurUtilite, Stats},
+};
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+};
+
+
+// Précision avec laquelle on calcule les probabilités de victoire
+const PRECISION : Flottant = 1e-6;
+
+// Est-ce que Course, PolitiqueCourse et Duel savent étudier une partie jouée
+// avec ces règles? Ils modélisent une course où le premier à atteindre le
+// score maximal gagne, sans nombre fixe de tours ni dernier tour
+// d'égalisation, et où chaque tour ne dépend que des scores: sans reprise, sans
+// rebond et sans pénalité des pertes à répétition.
+pub fn regles_etudiables(regles: &Regles) -> bool {
+    regles.nb_tours == 0
+        && !regles.dernier_tour
+        && !regles.reprise
+        && !regles.rebond
+        && regles.penalite_pertes == 0
+}
+
+// Distribution de probabilité de la mise encaissée en fin de tour, sous forme
+// de couples (mise, probabilité) triés par mise croissante
+type Distribution = Vec<(Valeur, Flottant)>;
+
+// Etude d'une course entre deux joueurs: je cherche à atteindre le score
+// maximal avant mon adversaire, qui joue chaque tour de façon à maximiser son
+// espérance de gain, ou selon une stratégie donnée.
+//
+// On note V(a, b) la probabilité que je gagne quand c'est à moi de jouer avec
+// un score a face à un adversaire au score b, et U(a, b) la même probabilité
+// quand c'est à l'adversaire de jouer. Si l'adversaire encaisse h points avec
+// une probabilité Q_b(h), on a U(a, b) = somme(h) Q_b(h) * V(a, b + h), où
+// V(a, b + h) est nul si l'adversaire a atteint le score maximal. De mon côté,
+// je joue mon tour de façon à maximiser l'espérance de U(a + g, b), où g est
+// la mise que j'encaisse et où U(a + g, b) vaut 1 si j'ai atteint le score
+// maximal.
+//
+// Comme un tour perdu par chacun des deux joueurs nous ramène à l'état de
+// départ, V(a, b) dépend de lui-même. On le calcule donc par itération de
+// point fixe, une fois connus les états où l'un des scores est plus élevé.
+//
+// Le nombre d'états à étudier croît très vite quand les scores diminuent,
+// donc cette étude est surtout praticable en fin de partie.
+//
+struct Course<'stats> {
+    // Ce qu'on sait sur les lancers de dés
+    stats: &'stats Stats,
+
+    // Stratégie suivie par l'adversaire, s'il ne maximise pas son espérance
+    adversaire: Option<&'stats dyn Strategie>,
+
+    // Q_b(h) pour les états déjà étudiés, quand l'adversaire suit une stratégie
+    // donnée (qui peut dépendre de mon score, d'où la clé (a, b))
+    distributions_adverses: RefCell<HashMap<(Valeur, Valeur), Distribution>>,
+
+    // V(a, b) pour les états déjà étudiés
+    proba_victoire: RefCell<HashMap<(Valeur, Valeur), Flottant>>,
+
+    // U(a, b) pour les états déjà étudiés
+    proba_victoire_adverse: RefCell<HashMap<(Valeur, Valeur), Flottant>>,
+}
+
+impl<'stats> Course<'stats> {
+    // Préparer l'étude d'une course
+    fn new(stats: &'stats Stats, adversaire: Option<&'stats dyn Strategie>) -> Self {
+        Self {
+            stats,
+            adversaire,
+            distributions_adverses: RefCell::new(HashMap::new()),
+            proba_victoire: RefCell::new(HashMap::new()),
+            proba_victoire_adverse: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // Probabilité V(a, b) que je gagne quand c'est à moi de jouer
+    fn proba_victoire(&self, mon_score: Valeur, score_adverse: Valeur) -> Flottant {
+        let score_max = self.stats.regles().score_max;
+        if mon_score >= score_max { return 1.; }
+        if score_adverse >= score_max { return 0.; }
+        if let Some(&proba) = self.proba_victoire.borrow()
+                                                 .get(&(mon_score, score_adverse)) {
+            return proba;
+        }
+        let (proba, _) = self.resoudre(mon_score, score_adverse);
+        assert_eq!(self.proba_victoire.borrow_mut()
+                       .insert((mon_score, score_adverse), proba),
+                   None);
+        proba
+    }
+
+    // Probabilité U(a, b) que je gagne quand c'est à l'adversaire de jouer
+    fn proba_victoire_adverse(&self, mon_score: Valeur, score_adverse: Valeur) -> Flottant {
+        if mon_score >= self.stats.regles().score_max { return 1.; }
+        if let Some(&proba) = self.proba_victoire_adverse.borrow()
+                                                         .get(&(mon_score, score_adverse)) {
+            return proba;
+        }
+        let proba = self.distribution_adverse(mon_score, score_adverse)
+            .into_iter()
+            .map(|(gain, proba_gain)| {
+                proba_gain * self.proba_victoire(mon_score, score_adverse + gain)
+            }).sum();
+        assert_eq!(self.proba_victoire_adverse.borrow_mut()
+                       .insert((mon_score, score_adverse), proba),
+                   None);
+        proba
+    }
+
+    // Distribution Q_b(h) de ce qu'encaisse l'adversaire lors de son tour
+    fn distribution_adverse(&self,
+                            mon_score: Valeur,
+                            score_adverse: Valeur) -> Distribution
+    {
+        let nb_des_tot = self.stats.regles().nb_des_tot;
+        let adversaire = match self.adversaire {
+            Some(adversaire) => adversaire,
+            None => return self.stats.distribution_tour(score_adverse, nb_des_tot, 0),
+        };
+        if let Some(distribution) = self.distributions_adverses.borrow()
+                                                               .get(&(mon_score, score_adverse)) {
+            return distribution.clone();
+        }
+        let etat = EtatTour {
+            score_adverse: mon_score,
+            ..EtatTour::new(score_adverse, nb_des_tot, 0)
+        };
+        let distribution = self.stats.distribution_strategie(adversaire, &etat);
+        self.distributions_adverses.borrow_mut()
+                                   .insert((mon_score, score_adverse), distribution.clone());
+        distribution
+    }
+
+    // Calcul de V(a, b) par itération de point fixe. On retourne aussi U(a, b),
+    // qui est l'utilité d'un tour où je n'encaisse rien.
+    fn resoudre(&self, mon_score: Valeur, score_adverse: Valeur) -> (Flottant, Flottant) {
+        // Si je perds mon tour, on a U(a, b) = Q_b(0) * V(a, b) + reste, où le
+        // reste ne dépend que d'états où l'adversaire a un score plus élevé
+        let regles = self.stats.regles();
+        let mut proba_rien_adverse = 0.;
+        let mut reste = 0.;
+        for (gain, proba) in self.distribution_adverse(mon_score, score_adverse) {
+            if gain == 0 {
+                proba_rien_adverse += proba;
+            } else {
+                reste += proba * self.proba_victoire(mon_score, score_adverse + gain);
+            }
+        }
+
+        // On part d'une probabilité de victoire nulle, et on optimise mon tour
+        // en conséquence jusqu'à ce que le résultat ne bouge plus
+        let mut proba = 0.;
+        loop {
+            let utilite_perte = proba_rien_adverse * proba + reste;
+            let optimiseur = self.optimiseur(mon_score, score_adverse, utilite_perte);
+            let nouvelle_proba = optimiseur.esperance(regles.nb_des_tot, 0);
+            let convergee = (nouvelle_proba - proba).abs() < PRECISION;
+            proba = nouvelle_proba;
+            if convergee {
+                return (proba, proba_rien_adverse * proba + reste);
+            }
+        }
+    }
+
+    // Optimisation de mon tour, connaissant l'utilité d'un tour perdu
+    fn optimiseur<'course>(&'course self,
+                           mon_score: Valeur,
+                           score_adverse: Valeur,
+                           utilite_perte: Flottant)
+        -> OptimiseurUtilite<'stats, impl Fn(Valeur) -> Flottant + 'course>
+    {
+        OptimiseurUtilite::new(self.stats, mon_score, move |mise| {
+            if mise == 0 {
+                utilite_perte
+            } else {
+                self.proba_victoire_adverse(mon_score + mise, score_adverse)
+            }
+        })
+    }
+}
+
+// Stratégie de tour qui maximise ma probabilité de gagner une course contre un
+// adversaire qui maximise son espérance de gain (ou suit une stratégie donnée),
+// à partir de nos scores
+pub struct PolitiqueCourse<'stats> {
+    // Etude de la course sur laquelle on s'appuie
+    course: Course<'stats>,
+
+    // Mon score acquis lors des tours précédents
+    mon_score: Valeur,
+
+    // Score acquis par l'adversaire
+    score_adverse: Valeur,
+
+    // Probabilité de victoire si je perds ce tour
+    proba_victoire_perte: Flottant,
+
+    // Probabilité de victoire au début de mon tour
+    proba_victoire: Flottant,
+}
+
+impl<'stats> PolitiqueCourse<'stats> {
+    // Etudier la course à partir d'un certain état
+    pub fn new(stats: &'stats Stats, mon_score: Valeur, score_adverse: Valeur) -> Self {
+        Self::etudier(Course::new(stats, None), mon_score, score_adverse)
+    }
+
+    // Même chose, face à un adversaire qui suit une certaine stratégie
+    pub fn contre(stats: &'stats Stats,
+                  adversaire: &'stats dyn Strategie,
+                  mon_score: Valeur,
+                  score_adverse: Valeur) -> Self
+    {
+        Self::etudier(Course::new(stats, Some(adversaire)), mon_score, score_adverse)
+    }
+
+    // Résolution de la course à partir d'un certain état
+    fn etudier(course: Course<'stats>, mon_score: Valeur, score_adverse: Valeur) -> Self {
+        let (proba_victoire, proba_victoire_perte) =
+            course.resoudre(mon_score, score_adverse);
+        Self {
+            course,
+            mon_score,
+            score_adverse,
+            proba_victoire_perte,
+            proba_victoire,
+        }
+    }
+
+    // Probabilité de gagner la course, en début de tour
+    pub fn proba_victoire(&self) -> Flottant {
+        self.proba_victoire
+    }
+
+    // Probabilité de gagner la course si on finit le tour en encaissant une
+    // certaine mise (la mise nulle correspondant à un tour perdu)
+    pub fn proba_victoire_arret(&self, mise: Valeur) -> Flottant {
+        self.optimiseur().utilite(mise)
+    }
+
+    // Probabilité de gagner la course si on lance un certain nombre de dés
+    // avec une certaine mise, puis qu'on joue au mieux
+    pub fn proba_victoire_relance(&self, nb_des: usize, mise: Valeur) -> Flottant {
+        self.optimiseur().esperance(nb_des, mise)
+    }
+
+    // Décision qui maximise la probabilité de gagner la course face à un jet
+    // de dés. L'espérance associée est une probabilité de victoire.
+    pub fn action_optimale(&self,
+                           nb_des: usize,
+                           mise: Valeur,
+                           choix: &[Combinaison]) -> Option<Decision>
+    {
+        self.optimiseur().action_optimale(nb_des, mise, choix)
+    }
+
+    // Optimisation de mon tour pour l'état de la course étudié
+    fn optimiseur(&self) -> OptimiseurUtilite<'stats, impl Fn(Valeur) -> Flottant + '_> {
+        self.course.optimiseur(self.mon_score,
+                               self.score_adverse,
+                               self.proba_victoire_perte)
+    }
+}
+
+// Etude d'une course entre deux joueurs qui jouent tous deux chaque tour de
+// façon à maximiser leur espérance de gain, ce qui donne l'avantage que le
+// premier joueur tire du fait de commencer.
+//
+// On note W(a, b) la probabilité que le joueur dont c'est le tour gagne, avec
+// un score a face à un adversaire au score b. S'il encaisse h points avec une
+// probabilité P_a(h), on a W(a, b) = somme(h) P_a(h) * (1 - W(b, a + h)), où
+// 1 - W(b, a + h) vaut 1 s'il a atteint le score maximal.
+//
+// Si les deux joueurs perdent leur tour, on revient à l'état de départ, donc
+// W(a, b) et W(b, a) dépendent l'un de l'autre. Mais en isolant les tours
+// perdus, on obtient deux équations linéaires qu'on résout directement:
+//
+//   W(a, b) = P_a(0) * (1 - W(b, a)) + R(a, b)
+//   W(b, a) = P_b(0) * (1 - W(a, b)) + R(b, a)
+//
+// où R(a, b) ne dépend que d'états dont la somme des scores est plus élevée.
+//
+// Contrairement à PolitiqueCourse, les tours se jouent sans tenir compte de la
+// course, donc la distribution de ce qu'on encaisse ne dépend que du score du
+// joueur, et on ne la calcule qu'une fois par score. Le reste est peu coûteux,
+// ce qui permet d'étudier la partie entière.
+//
+pub struct Duel<'stats> {
+    // Ce qu'on sait sur les lancers de dés
+    stats: &'stats Stats,
+
+    // P_a(h) pour les scores déjà étudiés
+    distributions: RefCell<HashMap<Valeur, Distribution>>,
+
+    // W(a, b) pour les états déjà étudiés
+    proba_victoire: RefCell<HashMap<(Valeur, Valeur), Flottant>>,
+}
+
+impl<'stats> Duel<'stats> {
+    // Préparer l'étude d'un duel
+    pub fn new(stats: &'stats Stats) -> Self {
+        Self {
+            stats,
+            distributions: RefCell::new(HashMap::new()),
+            proba_victoire: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // Probabilité W(a, b) que le joueur dont c'est le tour gagne
+    pub fn proba_victoire(&self, score_joueur: Valeur, score_adverse: Valeur) -> Flottant {
+        let score_max = self.stats.regles().score_max;
+        if score_joueur >= score_max { return 1.; }
+        if score_adverse >= score_max { return 0.; }
+        if let Some(&proba) = self.proba_victoire.borrow()
+                                                 .get(&(score_joueur, score_adverse)) {
+            return proba;
+        }
+
+        // On résout les deux équations couplées, et on garde les deux résultats
+        let (proba_rien, reste) = self.decomposer(score_joueur, score_adverse);
+        let (proba_rien_adverse, reste_adverse) = self.decomposer(score_adverse, score_joueur);
+        let proba = (proba_rien * (1. - proba_rien_adverse - reste_adverse) + reste)
+                    / (1. - proba_rien * proba_rien_adverse);
+        let proba_adverse = proba_rien_adverse * (1. - proba) + reste_adverse;
+        let mut probas = self.proba_victoire.borrow_mut();
+        probas.insert((score_joueur, score_adverse), proba);
+        probas.insert((score_adverse, score_joueur), proba_adverse);
+        proba
+    }
+
+    // Probabilité P_a(0) de perdre son tour, et terme R(a, b) regroupant les
+    // tours où le joueur encaisse quelque chose
+    fn decomposer(&self, score_joueur: Valeur, score_adverse: Valeur) -> (Flottant, Flottant) {
+        let mut proba_rien = 0.;
+        let mut reste = 0.;
+        for (gain, proba) in self.distribution(score_joueur) {
+            if gain == 0 {
+                proba_rien += proba;
+            } else {
+                reste += proba * (1. - self.proba_victoire(score_adverse, score_joueur + gain));
+            }
+        }
+        (proba_rien, reste)
+    }
+
+    // Distribution P_a(h) de ce qu'encaisse un joueur lors de son tour
+    fn distribution(&self, score: Valeur) -> Distribution {
+        if let Some(distribution) = self.distributions.borrow().get(&score) {
+            return distribution.clone();
+        }
+        let nb_des_tot = self.stats.regles().nb_des_tot;
+        let distribution = self.stats.distribution_tour(score, nb_des_tot, 0);
+        self.distributions.borrow_mut().insert(score, distribution.clone());
+        distribution
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        choix::{Enumeration, Histogramme},
+        regles::Regles,
+    };
+
+    // Scores de fin de partie étudiés, à 1000 points
+    const SCORES : [Valeur; 6] = [500, 600, 700, 800, 850, 900];
+
+    // V(a, b) est une probabilité, qui croît avec mon score et décroît avec
+    // celui de l'adversaire quand on peut dépasser le score maximal (avec
+    // l'atterrissage exact, se rapprocher de l'arrivée peut la compliquer)
+    #[test]
+    fn course_monotone() {
+        let stats = Stats::with_regles(Regles {
+            score_max: 1000,
+            atterrissage_exact: false,
+            ..Regles::default()
+        });
+        let course = Course::new(&stats, None);
+        for (i, &a) in SCORES.iter().enumerate() {
+            for (j, &b) in SCORES.iter().enumerate() {
+                let proba = course.proba_victoire(a, b);
+                assert!((0. ..=1.).contains(&proba), "V({}, {}) = {}", a, b, proba);
+                if let Some(&a_plus) = SCORES.get(i + 1) {
+                    assert!(course.proba_victoire(a_plus, b) >= proba - PRECISION,
+                            "V({}, {}) < V({}, {})", a_plus, b, a, b);
+                }
+                if let Some(&b_plus) = SCORES.get(j + 1) {
+                    assert!(course.proba_victoire(a, b_plus) <= proba + PRECISION,
+                            "V({}, {}) > V({}, {})", a, b_plus, a, b);
+                }
+            }
+        }
+    }
+
+    // Les probabilités du duel vérifient les équations couplées dont on les
+    // tire, le joueur qui commence à égalité de scores a l'avantage, et jouer
+    // pour gagner la course fait au moins aussi bien que maximiser son
+    // espérance face au même adversaire
+    #[test]
+    fn duel_coherent() {
+        let stats = Stats::with_score_max(1000);
+        let duel = stats.duel();
+        for &a in SCORES.iter() {
+            for &b in SCORES.iter() {
+                let proba = duel.proba_victoire(a, b);
+                let proba_adverse = duel.proba_victoire(b, a);
+                assert!((0. ..=1.).contains(&proba), "W({}, {}) = {}", a, b, proba);
+                let (proba_rien, reste) = duel.decomposer(a, b);
+                let ecart = proba - proba_rien * (1. - proba_adverse) - reste;
+                assert!(ecart.abs() <= 1e-5, "W({}, {}): écart {}", a, b, ecart);
+                let course = stats.politique_course(a, b).proba_victoire();
+                assert!(course >= proba - 1e-5, "V({}, {}) = {} < W = {}", a, b, course, proba);
+            }
+            assert!(duel.proba_victoire(a, a) > 0.5, "W({}, {})", a, a);
+        }
+    }
+
+    // Quand l'adversaire est sur le point de finir, il faut parfois relancer
+    // là où la stratégie qui maximise l'espérance de gain encaisserait
+    #[test]
+    fn course_plus_audacieuse() {
+        let stats = Stats::with_score_max(1000);
+        let regles = stats.regles();
+        let politique = stats.politique_course(500, 900);
+        let mut divergences = 0;
+        for faces in ["1 2 3 4 6 6", "1 1 2 3 4 6", "5 2 3 4 6 6", "2 2 2 3 4 6"] {
+            let histo = Histogramme::parse(faces, regles).unwrap();
+            let choix = histo.combinaisons_possibles(regles, Enumeration::Elaguee);
+            for mise in stats.mises_atteignables().mises(6).filter(|&mise| mise < 500) {
+                let esperance = stats.action_optimale(500, 6, mise, &choix);
+                let course = politique.action_optimale(6, mise, &choix);
+                if matches!(esperance, Some(Decision::Encaisser { .. }))
+                   && matches!(course, Some(Decision::Relancer { .. }))
+                {
+                    divergences += 1;
+                }
+            }
+        }
+        assert!(divergences > 0);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{
+    simulation::{Jeu, Optimale, Strategie, TAILLE_LOT},
+    stats::Stats,
+};
+
+use rayon::prelude::*;
+
+
+// Durée de parties à plusieurs joueurs qui jouent tous de façon à maximiser
+// leur espérance de gain, et avantage éventuel de chaque place autour de la
+// table (le joueur 0 commence)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResultatsDuree {
+    // Nombre de joueurs autour de la table
+    pub nb_joueurs: usize,
+
+    // Nombre total de tours joués lors de chaque partie terminée, en comptant
+    // les tours de chaque joueur, par ordre croissant
+    pub nb_tours: Vec<usize>,
+
+    // victoires[i] est le nombre de parties gagnées par le joueur i
+    pub victoires: Vec<usize>,
+
+    // Nombre de parties qui n'ont pas fini en NB_TOURS_MAX tours
+    pub nb_interrompues: usize,
+
+    // Avec un dernier tour d'égalisation (voir Regles::dernier_tour), nombre
+    // de parties où un joueur a atteint le score maximal en premier, et nombre
+    // de celles qu'il a quand même fini par perdre
+    pub nb_egalisations: usize,
+    pub nb_remontees: usize,
+}
+
+impl ResultatsDuree {
+    // Nombre de parties jouées, terminées ou non
+    pub fn nb_parties(&self) -> usize {
+        self.nb_tours.len() + self.nb_interrompues
+    }
+
+    // Nombre moyen de tours par partie terminée (None si aucune ne l'est)
+    pub fn nb_tours_moyen(&self) -> Option<f64> {
+        if self.nb_tours.is_empty() { return None; }
+        Some(self.nb_tours.iter().sum::<usize>() as f64 / self.nb_tours.len() as f64)
+    }
+
+    // Nombre de tours en-dessous duquel se terminent une certaine proportion
+    // (entre 0 et 1) des parties terminées (None si aucune ne l'est)
+    pub fn centile(&self, proportion: f64) -> Option<usize> {
+        if self.nb_tours.is_empty() { return None; }
+        let rang = (proportion * self.nb_tours.len() as f64).ceil() as usize;
+        Some(self.nb_tours[rang.clamp(1, self.nb_tours.len()) - 1])
+    }
+
+    // Proportion des parties gagnées par le joueur i
+    pub fn taux_victoire(&self, joueur: usize) -> f64 {
+        self.victoires[joueur] as f64 / self.nb_parties() as f64
+    }
+
+    // Proportion des parties où le premier à atteindre le score maximal
+    // se fait dépasser pendant le dernier tour (None s'il n'y en a eu aucune
+    // où le dernier tour a été joué)
+    pub fn taux_remontee(&self) -> Option<f64> {
+        if self.nb_egalisations == 0 { return None; }
+        Some(self.nb_remontees as f64 / self.nb_egalisations as f64)
+    }
+}
+
+// Jouer un certain nombre de parties entre joueurs suivant tous la stratégie
+// optimale, et mesurer leur durée
+pub fn mesurer_duree(stats: &Stats,
+                     nb_joueurs: usize,
+                     nb_parties: usize,
+                     graine: u64) -> ResultatsDuree
+{
+    // On joue les parties par lots, en parallèle...
+    let lots = (0..nb_parties).step_by(TAILLE_LOT).collect::<Vec<_>>();
+    let resultats_lots = lots.par_iter().enumerate().map(|(num_lot, &debut)| {
+        let optimale = Optimale::new(stats);
+        let joueurs = vec![&optimale as &dyn Strategie; nb_joueurs];
+        let mut jeu = Jeu::with_regles(stats.regles().clone(),
+                                       graine.wrapping_add(num_lot as u64));
+        let fin = (debut + TAILLE_LOT).min(nb_parties);
+        (debut..fin).map(|_| {
+            let resultat = jeu.jouer_partie_a_plusieurs(&joueurs);
+            (resultat.gagnant, resultat.premier_arrive, resultat.nb_tours())
+        }).collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+
+    // ...et on rassemble les résultats
+    let mut resultats = ResultatsDuree {
+        nb_joueurs,
+        nb_tours: Vec::with_capacity(nb_parties),
+        victoires: vec![0; nb_joueurs],
+        nb_interrompues: 0,
+        nb_egalisations: 0,
+        nb_remontees: 0,
+    };
+    for (gagnant, premier_arrive, nb_tours) in resultats_lots.into_iter().flatten() {
+        if let Some(premier) = premier_arrive {
+            resultats.nb_egalisations += 1;
+            if gagnant != Some(premier) { resultats.nb_remontees += 1; }
+        }
+        match gagnant {
+            Some(gagnant) => {
+                resultats.victoires[gagnant] += 1;
+                resultats.nb_tours.push(nb_tours);
+            },
+            None => resultats.nb_interrompues += 1,
+        }
+    }
+    resultats.nb_tours.sort_unstable();
+    resultats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regles::Regles;
+
+    // Les parties sont reproductibles, et les statistiques de durée n'ont de
+    // sens que si des parties se sont terminées
+    #[test]
+    fn durees() {
+        let stats = Stats::with_regles(Regles { score_max: 1000, ..Regles::default() });
+        let resultats = mesurer_duree(&stats, 3, 200, 42);
+        assert_eq!(mesurer_duree(&stats, 3, 200, 42), resultats);
+        assert_eq!(resultats.nb_parties(), 200);
+        assert_eq!(resultats.victoires.iter().sum::<usize>(), resultats.nb_tours.len());
+        let (min, max) = (resultats.nb_tours[0], *resultats.nb_tours.last().unwrap());
+        let moyen = resultats.nb_tours_moyen().unwrap();
+        assert!(min as f64 <= moyen && moyen <= max as f64);
+        assert_eq!(resultats.centile(0.), Some(min));
+        assert_eq!(resultats.centile(1.), Some(max));
+        assert!(resultats.centile(0.5) <= resultats.centile(0.9));
+        assert_eq!(resultats.taux_remontee(), None);
+
+        let vide = ResultatsDuree {
+            nb_joueurs: 2,
+            nb_tours: Vec::new(),
+            victoires: vec![0; 2],
+            nb_interrompues: 3,
+            nb_egalisations: 0,
+            nb_remontees: 0,
+        };
+        assert_eq!(vide.nb_parties(), 3);
+        assert_eq!(vide.nb_tours_moyen(), None);
+        assert_eq!(vide.centile(0.5), None);
+        assert_eq!(vide.taux_victoire(0), 0.);
+    }
+}
@@ -0,0 +1,370 @@
+use crate::{
+    choix::ErreurLancer,
+    etat::{EtatPartie, EtatTour},
+    regles::Regles,
+    simulation::{Action, Jeu, ResultatPartie, Strategie},
+};
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    cell::{Cell, RefCell},
+    error::Error,
+    fmt::{self, Display},
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+
+// Version du format des enregistrements, à incrémenter quand on change la
+// structure de EnregistrementPartie ou de ce qu'elle contient
+pub const VERSION_ENREGISTREMENT : u32 = 1;
+
+// Enregistrement complet d'une partie, qu'on peut sauvegarder en JSON puis
+// rejouer à travers le moteur de jeu pour vérifier qu'elle respecte la règle.
+// Le document JSON a la forme suivante:
+//
+//   {
+//     "version": 1,                  // VERSION_ENREGISTREMENT
+//     "regles": { ... },             // Regles, champ par champ
+//     "graine": 42,                  // Graine du jeu qui a joué la partie
+//     "num_partie": 0,               // Numéro de la partie dans ce jeu
+//     "partie": {                    // ResultatPartie
+//       "nb_joueurs": 2,
+//       "gagnant": 1,                // ou null si la partie est interrompue
+//       "tours": [                   // Un ResultatTour par tour de jeu
+//         { "mise": 350,
+//           "lancers": [             // Un Lancer par jet de dés
+//             { "etat": { "score": 0, "score_adverse": 0, "nb_des": 6,
+//                         "mise": 0, "pertes": 0, "cible": null },
+//               "histo": [1, 0, 2, 0, 1, 2],   // Nombre de dés par face
+//               "actions": [ ... ],            // Actions autorisées
+//               "action": { "Relancer": { ... } } },  // ou null si perdu
+//             ... ] },
+//         ... ],
+//       "premier_arrive": null,
+//       "scores": [3200, 10000] } }
+//
+// La graine et le numéro de partie ne servent pas au rejeu, qui utilise les dés
+// enregistrés: ils disent seulement d'où vient la partie.
+//
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct EnregistrementPartie {
+    // Version du format (voir VERSION_ENREGISTREMENT)
+    pub version: u32,
+
+    // Règles du jeu suivies pendant la partie
+    pub regles: Regles,
+
+    // Graine du jeu qui a joué la partie, et numéro de la partie parmi celles
+    // que ce jeu a jouées
+    pub graine: u64,
+    pub num_partie: u64,
+
+    // Déroulé de la partie: dés obtenus et décisions prises à chaque lancer
+    pub partie: ResultatPartie,
+}
+
+// Erreurs qui peuvent survenir quand on rejoue une partie enregistrée
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ErreurRejeu {
+    // L'enregistrement a été fait avec une autre version du format
+    Version(u32),
+
+    // Les règles enregistrées ne sont pas jouables par le moteur de jeu
+    Regles(String),
+
+    // La partie n'a aucun joueur, ou son gagnant, son premier arrivé ou ses
+    // scores finaux ne correspondent pas à son nombre de joueurs
+    Joueurs,
+
+    // Les dés enregistrés lors de ce lancer ne sont pas valides pour les règles
+    // de la partie
+    Lancer { num_tour: usize, num_lancer: usize, erreur: ErreurLancer },
+
+    // L'état du tour enregistré lors de ce lancer est hors des limites de la
+    // règle (score déjà gagnant, ou autant de dés que ceux qu'on a lancés)
+    Etat { num_tour: usize, num_lancer: usize },
+
+    // L'action enregistrée lors de ce lancer de ce tour (numérotés à partir
+    // de 0) n'est pas autorisée par la règle
+    CoupIllegal { num_tour: usize, num_lancer: usize },
+
+    // Le moteur ne retrouve pas le déroulé enregistré de ce tour (dés, états
+    // ou actions possibles), ou s'arrête avant ou après lui
+    Divergence { num_tour: usize },
+
+    // Les tours sont bien ceux enregistrés, mais pas le gagnant ou les scores
+    Resultat,
+}
+
+impl Display for ErreurRejeu {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            ErreurRejeu::Version(version) =>
+                write!(formatter, "Version d'enregistrement {} non prise en charge (attendu: {})",
+                       version, VERSION_ENREGISTREMENT),
+            ErreurRejeu::Regles(erreur) =>
+                write!(formatter, "Règles invalides: {}", erreur),
+            ErreurRejeu::Joueurs =>
+                write!(formatter, "Nombre de joueurs incohérent avec le gagnant ou les scores"),
+            ErreurRejeu::Lancer { num_tour, num_lancer, erreur } =>
+                write!(formatter, "Dés invalides au lancer {} du tour {}: {}",
+                       num_lancer+1, num_tour+1, erreur),
+            ErreurRejeu::Etat { num_tour, num_lancer } =>
+                write!(formatter, "Etat du tour invalide au lancer {} du tour {}",
+                       num_lancer+1, num_tour+1),
+            ErreurRejeu::CoupIllegal { num_tour, num_lancer } =>
+                write!(formatter, "Coup illégal au lancer {} du tour {}",
+                       num_lancer+1, num_tour+1),
+            ErreurRejeu::Divergence { num_tour } =>
+                write!(formatter, "Le tour {} ne se rejoue pas comme enregistré", num_tour+1),
+            ErreurRejeu::Resultat =>
+                write!(formatter, "Gagnant ou scores finaux différents de ceux enregistrés"),
+        }
+    }
+}
+
+impl Error for ErreurRejeu {}
+
+impl EnregistrementPartie {
+    // Enregistrer une partie jouée avec certaines règles (voir aussi
+    // Jeu::enregistrer, qui sait d'où vient la partie)
+    pub fn new(regles: Regles, graine: u64, num_partie: u64, partie: &ResultatPartie) -> Self {
+        Self {
+            version: VERSION_ENREGISTREMENT,
+            regles,
+            graine,
+            num_partie,
+            partie: partie.clone(),
+        }
+    }
+
+    // Sauvegarder l'enregistrement dans un fichier JSON
+    pub fn ecrire(&self, chemin: impl AsRef<Path>) -> io::Result<()> {
+        let mut sortie = BufWriter::new(File::create(chemin)?);
+        serde_json::to_writer_pretty(&mut sortie, self)?;
+        writeln!(sortie)?;
+        sortie.flush()
+    }
+
+    // Relire un enregistrement sauvegardé. On rejette les enregistrements
+    // faits avec une autre version du format.
+    pub fn lire(chemin: impl AsRef<Path>) -> io::Result<Self> {
+        let entree = BufReader::new(File::open(chemin)?);
+        let enregistrement : Self = serde_json::from_reader(entree)?;
+        if enregistrement.version != VERSION_ENREGISTREMENT {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      ErreurRejeu::Version(enregistrement.version)));
+        }
+        Ok(enregistrement)
+    }
+
+    // Vérifier que l'enregistrement est lisible par le moteur de jeu, sans
+    // rejouer la partie (voir verifier_partie)
+    pub fn verifier(&self) -> Result<(), ErreurRejeu> {
+        if self.version != VERSION_ENREGISTREMENT {
+            return Err(ErreurRejeu::Version(self.version));
+        }
+        self.regles.verifier().map_err(ErreurRejeu::Regles)?;
+        verifier_partie(&self.regles, &self.partie)
+    }
+
+    // Rejouer la partie à travers le moteur de jeu, en lui imposant les dés
+    // enregistrés et en faisant prendre à chaque joueur les décisions
+    // enregistrées. Le moteur vérifie ainsi que chaque décision est autorisée
+    // par la règle, et recalcule les scores et le gagnant. On renvoie la
+    // partie rejouée si elle est identique à celle qui a été enregistrée.
+    pub fn rejouer(&self) -> Result<ResultatPartie, ErreurRejeu> {
+        self.verifier()?;
+        let des = self.partie.tours.iter()
+                                   .flat_map(|tour| tour.lancers.iter())
+                                   .map(|lancer| lancer.histo.clone());
+        let mut jeu = Jeu::with_des_imposes(self.regles.clone(), des);
+        let rejeu = Rejeu::new(&self.partie);
+        let joueurs = vec![&rejeu as &dyn Strategie; self.partie.nb_joueurs];
+        let partie = jeu.jouer_partie_a_plusieurs(&joueurs);
+        if let Some(erreur) = rejeu.erreur.into_inner() {
+            return Err(erreur);
+        }
+
+        // Le premier tour qui diffère indique où le rejeu a divergé
+        let tours_enregistres = &self.partie.tours;
+        let nb_tours = partie.tours.len().max(tours_enregistres.len());
+        if let Some(num_tour) = (0..nb_tours).find(|&num_tour| {
+            partie.tours.get(num_tour) != tours_enregistres.get(num_tour)
+        }) {
+            return Err(ErreurRejeu::Divergence { num_tour });
+        }
+        if partie != self.partie {
+            return Err(ErreurRejeu::Resultat);
+        }
+        Ok(partie)
+    }
+}
+
+// Vérifier qu'une partie enregistrée ou saisie à la main a une forme que le
+// moteur de jeu et l'analyse statistique peuvent lire avec ces règles: des
+// joueurs, des dés valides et des états de tour dans les limites de la règle.
+// On ne vérifie pas que la partie respecte la règle, c'est le rôle du rejeu.
+pub fn verifier_partie(regles: &Regles, partie: &ResultatPartie) -> Result<(), ErreurRejeu> {
+    let nb_joueurs = partie.nb_joueurs;
+    let joueur_valide = |joueur: Option<usize>| joueur.is_none_or(|joueur| joueur < nb_joueurs);
+    if nb_joueurs == 0
+        || !joueur_valide(partie.gagnant)
+        || !joueur_valide(partie.premier_arrive)
+        || !(partie.scores.is_empty() || partie.scores.len() == nb_joueurs)
+    {
+        return Err(ErreurRejeu::Joueurs);
+    }
+    for (num_tour, tour) in partie.tours.iter().enumerate() {
+        for (num_lancer, lancer) in tour.lancers.iter().enumerate() {
+            lancer.histo.verifier(regles).map_err(|erreur| {
+                ErreurRejeu::Lancer { num_tour, num_lancer, erreur }
+            })?;
+            let etat = &lancer.etat;
+            if etat.score >= regles.score_max
+                || (etat.cible.is_none() && etat.score_adverse >= regles.score_max)
+                || etat.nb_des != lancer.histo.nb_des()
+            {
+                return Err(ErreurRejeu::Etat { num_tour, num_lancer });
+            }
+        }
+    }
+    Ok(())
+}
+
+// Stratégie qui rejoue les décisions d'une partie enregistrée, pour tous les
+// joueurs à la fois. Elle note la première décision enregistrée qui n'est pas
+// autorisée, et joue alors la première action possible pour finir la partie.
+struct Rejeu<'partie> {
+    // Partie enregistrée
+    partie: &'partie ResultatPartie,
+
+    // Tour en cours, et numéro du prochain lancer dans ce tour
+    num_tour: Cell<usize>,
+    num_lancer: Cell<usize>,
+
+    // Premier problème rencontré
+    erreur: RefCell<Option<ErreurRejeu>>,
+}
+
+impl<'partie> Rejeu<'partie> {
+    // Préparer le rejeu d'une partie
+    fn new(partie: &'partie ResultatPartie) -> Self {
+        Self {
+            partie,
+            num_tour: Cell::new(0),
+            num_lancer: Cell::new(0),
+            erreur: RefCell::new(None),
+        }
+    }
+
+    // Noter un problème, si c'est le premier
+    fn signaler(&self, erreur: ErreurRejeu) {
+        self.erreur.borrow_mut().get_or_insert(erreur);
+    }
+}
+
+impl Strategie for Rejeu<'_> {
+    fn choisir(&self, _etat: &EtatTour, actions: &[Action]) -> Action {
+        let (num_tour, num_lancer) = (self.num_tour.get(), self.num_lancer.get());
+        self.num_lancer.set(num_lancer + 1);
+        let enregistree = self.partie.tours.get(num_tour)
+                                           .and_then(|tour| tour.lancers.get(num_lancer))
+                                           .and_then(|lancer| lancer.action);
+        match enregistree {
+            Some(action) if actions.contains(&action) => action,
+            Some(_) => {
+                self.signaler(ErreurRejeu::CoupIllegal { num_tour, num_lancer });
+                actions[0]
+            },
+            None => {
+                self.signaler(ErreurRejeu::Divergence { num_tour });
+                actions[0]
+            },
+        }
+    }
+
+    fn reprendre(&self, _etat: &EtatTour) -> bool {
+        self.partie.tours.get(self.num_tour.get())
+                         .and_then(|tour| tour.lancers.first())
+                         .is_some_and(|lancer| lancer.etat.mise > 0)
+    }
+
+    fn commencer_tour(&self, partie: &EtatPartie) {
+        self.num_tour.set(partie.num_tour);
+        self.num_lancer.set(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{choix::Histogramme, simulation::SeuilFixe};
+
+    // Partie courte entre deux joueurs qui s'arrêtent dès 300 points, avec son
+    // enregistrement
+    fn enregistrement() -> EnregistrementPartie {
+        let regles = Regles { score_max: 2000, ..Regles::default() };
+        let mut jeu = Jeu::with_regles(regles, 42);
+        let joueur = SeuilFixe(300);
+        let partie = jeu.jouer_partie([&joueur, &joueur]);
+        jeu.enregistrer(&partie)
+    }
+
+    // Une partie sauvegardée puis relue se rejoue à l'identique
+    #[test]
+    fn sauvegarde_relecture_rejeu() {
+        let enregistrement = enregistrement();
+        let chemin = std::env::temp_dir().join("mitraillette-test-enregistrement.json");
+        enregistrement.ecrire(&chemin).unwrap();
+        let relu = EnregistrementPartie::lire(&chemin).unwrap();
+        std::fs::remove_file(&chemin).unwrap();
+        assert_eq!(relu, enregistrement);
+        assert_eq!(relu.rejouer(), Ok(enregistrement.partie));
+    }
+
+    // Un enregistrement mal formé est rejeté au lieu de faire paniquer le
+    // moteur de jeu
+    #[test]
+    fn enregistrement_mal_forme() {
+        let enregistrement = enregistrement();
+
+        // Dés impossibles à relire
+        let mut json = serde_json::to_value(&enregistrement).unwrap();
+        json["partie"]["tours"][0]["lancers"][0]["histo"] = serde_json::json!([6]);
+        assert!(serde_json::from_value::<EnregistrementPartie>(json).is_err());
+
+        // Dés relus, mais pas valides pour les règles de la partie
+        let mut invalide = enregistrement.clone();
+        let huit_faces = Regles { nb_faces: 8, ..Regles::default() };
+        let histo = Histogramme::parse("7 8", &huit_faces).unwrap();
+        invalide.partie.tours[0].lancers[0].histo = histo;
+        assert_eq!(invalide.rejouer(),
+                   Err(ErreurRejeu::Lancer { num_tour: 0,
+                                             num_lancer: 0,
+                                             erreur: ErreurLancer::NombreFaces(8) }));
+
+        // Règles que le moteur de jeu ne sait pas jouer, même sans aucun tour
+        let mut invalide = enregistrement.clone();
+        invalide.regles.nb_des_tot = 0;
+        invalide.partie.tours.clear();
+        assert!(matches!(invalide.rejouer(), Err(ErreurRejeu::Regles(_))));
+        let mut json = serde_json::to_value(&enregistrement).unwrap();
+        json["regles"]["nb_des_tot"] = serde_json::json!(7);
+        let invalide = serde_json::from_value::<EnregistrementPartie>(json).unwrap();
+        assert!(matches!(invalide.verifier(), Err(ErreurRejeu::Regles(_))));
+
+        // Partie sans joueurs
+        let mut invalide = enregistrement.clone();
+        invalide.partie.nb_joueurs = 0;
+        assert_eq!(invalide.rejouer(), Err(ErreurRejeu::Joueurs));
+
+        // Etat du tour qui ne correspond pas aux dés lancés
+        let mut invalide = enregistrement;
+        invalide.partie.tours[0].lancers[0].etat.nb_des = 3;
+        assert_eq!(invalide.rejouer(), Err(ErreurRejeu::Etat { num_tour: 0, num_lancer: 0 }));
+    }
+}
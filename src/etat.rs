@@ -0,0 +1,362 @@
+use crate::{
+    Valeur,
+    choix::{Enumeration, Histogramme},
+    combinaison::Combinaison,
+    mises::MisesAtteignables,
+    regles::Regles,
+};
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+
+// Etats du jeu partagés par l'analyse statistique, le simulateur et le mode
+// conseil: l'état d'un tour, au moment de lancer des dés, et celui de la
+// partie, au moment où un joueur commence son tour.
+
+// Etat du tour au moment où un joueur doit décider quoi faire d'un jet de dés
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct EtatTour {
+    // Score acquis par le joueur lors des tours précédents
+    pub score: Valeur,
+
+    // Score acquis par l'adversaire (le meilleur des adversaires, si on joue
+    // à plus de deux)
+    pub score_adverse: Valeur,
+
+    // Nombre de dés à lancer
+    pub nb_des: usize,
+
+    // Mise accumulée pendant ce tour avant ce lancer
+    pub mise: Valeur,
+
+    // Nombre de tours que le joueur vient de perdre d'affilée, qui compte
+    // quand la règle pénalise les pertes à répétition
+    #[serde(default)]
+    pub pertes: usize,
+
+    // Score qu'il faut dépasser pendant le dernier tour d'égalisation (voir
+    // Regles::dernier_tour), seul objectif qui compte alors (None en temps
+    // normal)
+    #[serde(default)]
+    pub cible: Option<Valeur>,
+}
+
+impl EtatTour {
+    // Etat d'un tour qui commence avec tous les dés et aucune mise, à partir
+    // d'un certain score, sans rien savoir des adversaires
+    pub fn debut(regles: &Regles, score: Valeur) -> Self {
+        Self::new(score, regles.nb_des_tot, 0)
+    }
+
+    // Etat d'un tour où l'on s'apprête à lancer un certain nombre de dés avec
+    // une certaine mise, sans rien savoir des adversaires
+    pub fn new(score: Valeur, nb_des: usize, mise: Valeur) -> Self {
+        Self { score, score_adverse: 0, nb_des, mise, pertes: 0, cible: None }
+    }
+
+    // Est-ce que cet état a un sens avec ces règles? On vérifie que les
+    // valeurs sont compatibles avec le barème et les dés, puis qu'on peut
+    // vraiment se retrouver à lancer ces dés avec cette mise (voir
+    // jet_impossible).
+    pub fn verifier(&self, regles: &Regles) -> Result<(), String> {
+        let valeur_min_de = regles.bareme.valeur_min_de();
+        if !self.score.is_multiple_of(valeur_min_de) {
+            return Err(format!("le score {} n'est pas un multiple de {}",
+                               self.score, valeur_min_de));
+        }
+        if self.score >= regles.score_max {
+            return Err(format!("le score {} atteint déjà le score maximal {}",
+                               self.score, regles.score_max));
+        }
+        if !self.mise.is_multiple_of(valeur_min_de) {
+            return Err(format!("la mise {} n'est pas un multiple de {}",
+                               self.mise, valeur_min_de));
+        }
+        if self.nb_des < 1 || self.nb_des > regles.nb_des_tot {
+            return Err(format!("on ne peut pas lancer {} dés, il faut en lancer entre 1 et {}",
+                               self.nb_des, regles.nb_des_tot));
+        }
+        if self.jet_impossible(regles) {
+            return Err(format!("on ne peut pas se retrouver à lancer {} dés avec une mise de \
+                                {} points quand on a {} points",
+                               self.nb_des, self.mise, self.score));
+        }
+        Ok(())
+    }
+
+    // Est-ce qu'on ne peut jamais se retrouver dans cet état avec ces règles
+    // (voir MisesAtteignables::jet_impossible)? On recense les mises
+    // atteignables à chaque appel, donc pour tester beaucoup de situations, il
+    // vaut mieux passer par Stats::mises_atteignables.
+    pub fn jet_impossible(&self, regles: &Regles) -> bool {
+        MisesAtteignables::new(regles).jet_impossible(self.score, self.nb_des, self.mise)
+    }
+
+    // Etat du tour après avoir pris une combinaison, sans vérifier que les dés
+    // ou la règle le permettent. On met de côté les dés pris et on ajoute la
+    // valeur de la combinaison à la mise, et comme pour une relance, si tous
+    // les dés ont été utilisés, on les reprend tous. Une sexte qui fait
+    // gagner amène directement la mise à ce qui manque pour atteindre le score
+    // maximal (voir Regles::fait_gagner).
+    pub fn prendre(&self, regles: &Regles, comb: Combinaison) -> EtatTour {
+        let mise = if regles.fait_gagner(&comb) {
+            regles.score_max - self.score
+        } else {
+            self.mise + regles.bareme.valeur(&comb)
+        };
+        let des_restants = self.nb_des - comb.nb_des();
+        let nb_des = if des_restants == 0 { regles.nb_des_tot } else { des_restants };
+        EtatTour { nb_des, mise, ..*self }
+    }
+
+    // Même chose pour une combinaison annoncée par un joueur face à un jet de
+    // dés, en vérifiant que les dés et la règle permettent bien de la prendre
+    pub fn appliquer(&self,
+                     regles: &Regles,
+                     histo: &Histogramme,
+                     comb: Combinaison) -> Result<EtatTour, ErreurCoup> {
+        if histo.nb_des() != self.nb_des {
+            return Err(ErreurCoup::NombreDes(histo.nb_des()));
+        }
+        if !comb.est_realisable(histo) {
+            return Err(ErreurCoup::CombinaisonAbsente(comb));
+        }
+        if histo.combinaisons_possibles(regles, Enumeration::Complete).binary_search(&comb).is_err() {
+            return Err(ErreurCoup::CombinaisonInterdite(comb));
+        }
+        let suivant = self.prendre(regles, comb);
+        if regles.atterrissage_exact && !regles.rebond && self.score + suivant.mise > regles.score_max {
+            return Err(ErreurCoup::Depassement(self.score + suivant.mise));
+        }
+        Ok(suivant)
+    }
+
+    // Score du joueur s'il encaisse la mise en jeu (voir Regles::score_apres)
+    pub fn encaisser(&self, regles: &Regles) -> Valeur {
+        regles.score_apres(self.score, self.mise)
+    }
+
+    // Score du joueur s'il perd le tour, en comptant la pénalité éventuelle
+    // des pertes à répétition (voir Regles::cout_perte)
+    pub fn perdre(&self, regles: &Regles) -> Valeur {
+        self.score - regles.cout_perte(self.score, self.pertes)
+    }
+}
+
+// Erreurs qui peuvent survenir quand un joueur annonce ce qu'il prend
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ErreurCoup {
+    // Le jet n'a pas autant de dés que l'état du tour en prévoit
+    NombreDes(usize),
+
+    // Les dés tirés ne contiennent pas la combinaison annoncée
+    CombinaisonAbsente(Combinaison),
+
+    // La combinaison est dans les dés, mais la règle ne l'autorise pas
+    CombinaisonInterdite(Combinaison),
+
+    // Prendre la combinaison ferait dépasser le score maximal, alors que la
+    // règle exige d'y atterrir exactement (on donne le score qu'on atteindrait)
+    Depassement(Valeur),
+}
+
+impl Display for ErreurCoup {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            ErreurCoup::NombreDes(nb_des) =>
+                write!(formatter, "Nombre de dés inattendu: {}", nb_des),
+            ErreurCoup::CombinaisonAbsente(comb) =>
+                write!(formatter, "Combinaison absente des dés: {}", comb),
+            ErreurCoup::CombinaisonInterdite(comb) =>
+                write!(formatter, "Combinaison interdite par la règle: {}", comb),
+            ErreurCoup::Depassement(score) =>
+                write!(formatter, "Dépassement du score maximal: {}", score),
+        }
+    }
+}
+
+impl Error for ErreurCoup {}
+
+// Etat d'une partie au début d'un tour, qui donne le tableau des scores aux
+// stratégies qui tiennent compte de chacun de leurs adversaires et pas
+// seulement du meilleur
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EtatPartie {
+    // Score de chaque joueur, dans l'ordre où ils jouent
+    pub scores: Vec<Valeur>,
+
+    // Numéro du joueur dont c'est le tour
+    pub joueur: usize,
+
+    // Nombre de tours déjà joués, en comptant les tours de chaque joueur
+    pub num_tour: usize,
+}
+
+impl EtatPartie {
+    // Partie qui commence entre un certain nombre de joueurs
+    pub fn debut(nb_joueurs: usize) -> Self {
+        assert!(nb_joueurs > 0, "Il faut au moins un joueur");
+        Self { scores: vec![0; nb_joueurs], joueur: 0, num_tour: 0 }
+    }
+
+    // Nombre de joueurs autour de la table
+    pub fn nb_joueurs(&self) -> usize {
+        self.scores.len()
+    }
+
+    // Scores des adversaires du joueur dont c'est le tour
+    pub fn scores_adverses(&self) -> impl Iterator<Item = Valeur> + '_ {
+        self.scores.iter()
+                   .enumerate()
+                   .filter(move |&(autre, _)| autre != self.joueur)
+                   .map(|(_, &score)| score)
+    }
+
+    // Meilleur score adverse (nul si on joue seul)
+    pub fn score_adverse(&self) -> Valeur {
+        self.scores_adverses().max().unwrap_or(0)
+    }
+
+    // Etat du tour qui commence, avec tous les dés et aucune mise
+    pub fn etat_tour(&self, regles: &Regles) -> EtatTour {
+        EtatTour {
+            score_adverse: self.score_adverse(),
+            ..EtatTour::debut(regles, self.scores[self.joueur])
+        }
+    }
+
+    // Passer au tour suivant, une fois que le joueur a fini le sien avec un
+    // certain score
+    pub fn finir_tour(&mut self, score: Valeur) {
+        self.scores[self.joueur] = score;
+        self.num_tour += 1;
+        self.joueur = self.num_tour % self.nb_joueurs();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regles::NB_PERTES_PENALITE;
+
+    // Une combinaison annoncée est appliquée si les dés et la règle la
+    // permettent, et sinon on apprend pourquoi elle est refusée
+    #[test]
+    fn application_coup() {
+        let regles = Regles::default();
+        let jet = |faces: &[usize]| Histogramme::depuis_faces(faces, &regles).unwrap();
+        let etat = |score, nb_des, mise| {
+            EtatTour { score, score_adverse: 0, nb_des, mise, pertes: 0, cible: None }
+        };
+        let debut = etat(0, 6, 0);
+        let brelan = Combinaison::BrelanSimple { idx_face: 1, nb_un: 0, nb_cinq: 1 };
+        assert_eq!(debut.appliquer(&regles, &jet(&[2, 2, 2, 5, 3, 4]), brelan),
+                   Ok(etat(0, 2, 250)));
+
+        // Quand on a pris tous les dés, on les reprend tous
+        assert_eq!(debut.appliquer(&regles, &jet(&[1, 2, 3, 4, 5, 6]), Combinaison::Suite),
+                   Ok(etat(0, 6, 500)));
+
+        let un = Combinaison::FacesSimples { nb_un: 1, nb_cinq: 0 };
+        assert_eq!(etat(0, 5, 100).appliquer(&regles, &jet(&[1, 2, 3, 4, 6, 6]), un),
+                   Err(ErreurCoup::NombreDes(6)));
+        assert_eq!(debut.appliquer(&regles, &jet(&[2, 2, 3, 4, 6, 6]), un),
+                   Err(ErreurCoup::CombinaisonAbsente(un)));
+        let carre = Combinaison::Carre { idx_face: 2, nb_un: 0, nb_cinq: 0 };
+        assert_eq!(debut.appliquer(&regles, &jet(&[3, 3, 3, 3, 2, 4]), carre),
+                   Err(ErreurCoup::CombinaisonInterdite(carre)));
+        assert_eq!(ErreurCoup::CombinaisonInterdite(carre).to_string(),
+                   "Combinaison interdite par la règle: Carré de 3 (600 points, 4 dés)");
+
+        // Dépasser le score maximal n'est refusé que si la règle exige d'y
+        // atterrir exactement
+        let deux_un = Combinaison::FacesSimples { nb_un: 2, nb_cinq: 0 };
+        let fin = etat(9900, 2, 0);
+        assert_eq!(fin.appliquer(&regles, &jet(&[1, 1]), deux_un),
+                   Err(ErreurCoup::Depassement(10100)));
+        let depasser = Regles { atterrissage_exact: false, ..regles.clone() };
+        assert_eq!(fin.appliquer(&depasser, &jet(&[1, 1]), deux_un), Ok(etat(9900, 6, 200)));
+    }
+
+    // Prendre une combinaison ajoute sa valeur à la mise et met ses dés de
+    // côté, sans toucher au reste de l'état. Quand tous les dés ont servi, on
+    // les reprend tous, et une sexte qui fait gagner amène la mise à ce qui
+    // manque pour atteindre le score maximal.
+    #[test]
+    fn prise() {
+        let regles = Regles::default();
+        let debut = EtatTour::debut(&regles, 1000);
+        let un = Combinaison::FacesSimples { nb_un: 1, nb_cinq: 0 };
+        let cinq = Combinaison::FacesSimples { nb_un: 0, nb_cinq: 1 };
+        assert_eq!(debut.prendre(&regles, un), EtatTour::new(1000, 5, 100));
+        assert_eq!(EtatTour::new(1000, 1, 300).prendre(&regles, cinq),
+                   EtatTour::new(1000, 6, 350));
+        assert_eq!(debut.prendre(&regles, Combinaison::TriplePaire), EtatTour::new(1000, 6, 500));
+        let suivi = EtatTour { score_adverse: 3000, pertes: 2, cible: Some(5000), ..debut };
+        assert_eq!(suivi.prendre(&regles, un), EtatTour { nb_des: 5, mise: 100, ..suivi });
+
+        // En nombre fixe de tours, la sexte ne vaut que ce que lui donne le
+        // barème (huit fois un brelan de 2)
+        let sexte = Combinaison::Sexte { idx_face: 1 };
+        let carres = Regles { carres_et_plus: true, ..regles };
+        assert_eq!(debut.prendre(&carres, sexte), EtatTour::new(1000, 6, 9000));
+        assert_eq!(debut.prendre(&carres.en_tours_fixes(10), sexte),
+                   EtatTour::new(1000, 6, 1600));
+    }
+
+    // Encaisser ajoute la mise au score, au rebond près, et perdre ne coûte
+    // rien de plus que la mise, sauf après une série de pertes pénalisée, qui
+    // ne peut pas faire perdre l'ouverture
+    #[test]
+    fn encaissement_et_perte() {
+        let regles = Regles::default();
+        let etat = EtatTour::new(9000, 2, 600);
+        assert_eq!(etat.encaisser(&regles), 9600);
+        let rebond = Regles { rebond: true, ..regles.clone() };
+        assert_eq!(EtatTour::new(9800, 2, 600).encaisser(&rebond), 9600);
+
+        assert_eq!(etat.perdre(&regles), 9000);
+        let penalite = Regles { penalite_pertes: 500, ..regles.clone() };
+        assert_eq!(etat.perdre(&penalite), 9000);
+        let serie = EtatTour { pertes: NB_PERTES_PENALITE - 1, ..etat };
+        assert_eq!(serie.perdre(&regles), 9000);
+        assert_eq!(serie.perdre(&penalite), 8500);
+        assert_eq!(EtatTour { score: 300, ..serie }.perdre(&penalite), 0);
+
+        // Avec un seuil d'ouverture, la pénalité ne fait pas perdre l'ouverture
+        let ouverture = Regles { seuil_ouverture: 500, ..penalite.clone() };
+        assert_eq!(serie.perdre(&ouverture), 8500);
+        let score = EtatTour { score: 300, ..serie }.perdre(&ouverture);
+        assert_eq!(score, 50);
+        assert!(ouverture.ouvert(score));
+        assert_eq!(EtatTour { score: 0, ..serie }.perdre(&ouverture), 0);
+        assert!(!ouverture.ouvert(0));
+    }
+
+    // Un état n'est accepté que si l'on peut vraiment s'y retrouver
+    #[test]
+    fn verification() {
+        let regles = Regles::default();
+        for etat in [EtatTour::debut(&regles, 0), EtatTour::new(1000, 2, 300)] {
+            assert_eq!(etat.verifier(&regles), Ok(()), "{:?}", etat);
+        }
+        for etat in [
+            EtatTour::new(1020, 6, 0),   // Score hors barème
+            EtatTour::new(10000, 6, 0),  // Partie déjà finie
+            EtatTour::new(0, 6, 30),     // Mise hors barème
+            EtatTour::new(0, 0, 0),      // Aucun dé à lancer
+            EtatTour::new(0, 7, 0),      // Plus de dés qu'on n'en a
+            EtatTour::new(0, 1, 50),     // Cinq dés pris pour 50 points
+            EtatTour::new(9800, 2, 200), // La mise atteint déjà le score maximal
+        ] {
+            assert!(etat.verifier(&regles).is_err(), "{:?}", etat);
+        }
+        assert!(EtatTour::new(0, 1, 50).jet_impossible(&regles));
+        assert!(!EtatTour::new(1000, 2, 300).jet_impossible(&regles));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{
+    Flottant,
+    sortie::{LigneAtterrissage, LigneEsperance, Tables},
+};
+
+use plotters::prelude::*;
+
+use std::{
+    fs,
+    io,
+    path::Path,
+};
+
+
+// Taille des images produites, en pixels
+const TAILLE : (u32, u32) = (1024, 768);
+
+// Tracer les courbes correspondant aux tables calculées par défaut dans un
+// certain dossier (créé au besoin): esperances.svg pour le gain moyen en
+// fonction de la mise, et atterrissages.svg pour la probabilité de gagner ce
+// tour-ci en fonction du score.
+pub fn tracer(tables: &Tables, dossier: &Path) -> io::Result<()> {
+    fs::create_dir_all(dossier)?;
+    tracer_esperances(&tables.esperances, &dossier.join("esperances.svg"))
+        .map_err(io::Error::other)?;
+    tracer_atterrissages(&tables.atterrissages, &dossier.join("atterrissages.svg"))
+        .map_err(io::Error::other)
+}
+
+// Gain moyen en fonction de la mise, avec une courbe par nombre de dés (les
+// conversions en f64 sont inutiles quand on calcule en double précision)
+#[allow(clippy::unnecessary_cast)]
+fn tracer_esperances(esperances: &[LigneEsperance],
+                     chemin: &Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let nb_des_max = esperances.iter().map(|l| l.nb_des).max().unwrap_or(0);
+    let mise_max = esperances.iter().map(|l| l.mise).max().unwrap_or(0);
+    let (gain_min, gain_max) = bornes(esperances.iter().map(|l| l.gain_moyen));
+
+    let zone = SVGBackend::new(chemin, TAILLE).into_drawing_area();
+    zone.fill(&WHITE)?;
+    let mut graphique = ChartBuilder::on(&zone)
+        .caption("Gain moyen à score nul", ("sans-serif", 24))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0f64..mise_max as f64, gain_min..gain_max)?;
+    graphique.configure_mesh()
+             .x_desc("Mise")
+             .y_desc("Gain moyen")
+             .draw()?;
+
+    for nb_des in 1..=nb_des_max {
+        let couleur = Palette99::pick(nb_des - 1).to_rgba();
+        let points = esperances.iter()
+                               .filter(|l| l.nb_des == nb_des)
+                               .map(|l| (l.mise as f64, l.gain_moyen as f64));
+        graphique.draw_series(LineSeries::new(points, couleur.stroke_width(2)))?
+                 .label(format!("{} dé{}", nb_des, if nb_des > 1 { "s" } else { "" }))
+                 .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], couleur));
+    }
+    graphique.configure_series_labels()
+             .background_style(WHITE)
+             .border_style(BLACK)
+             .draw()?;
+    zone.present()?;
+    Ok(())
+}
+
+// Probabilité de gagner lors du tour qui commence en fonction du score
+#[allow(clippy::unnecessary_cast)]
+fn tracer_atterrissages(atterrissages: &[LigneAtterrissage],
+                        chemin: &Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let score_min = atterrissages.iter().map(|l| l.score).min().unwrap_or(0);
+    let score_max = atterrissages.iter().map(|l| l.score).max().unwrap_or(0);
+
+    let zone = SVGBackend::new(chemin, TAILLE).into_drawing_area();
+    zone.fill(&WHITE)?;
+    let mut graphique = ChartBuilder::on(&zone)
+        .caption("Probabilité de gagner ce tour-ci", ("sans-serif", 24))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(score_min as f64..score_max as f64, 0f64..1f64)?;
+    graphique.configure_mesh()
+             .x_desc("Score")
+             .y_desc("Probabilité")
+             .draw()?;
+
+    // Les lignes de la table sont par score décroissant, on les remet dans
+    // l'ordre pour tracer la courbe
+    let mut points = atterrissages.iter()
+                                  .map(|l| (l.score as f64, l.proba as f64))
+                                  .collect::<Vec<_>>();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    graphique.draw_series(LineSeries::new(points, BLUE.stroke_width(2)))?;
+    zone.present()?;
+    Ok(())
+}
+
+// Bornes de l'axe des ordonnées, avec un peu de marge autour des valeurs
+#[allow(clippy::unnecessary_cast)]
+fn bornes(valeurs: impl Iterator<Item=Flottant>) -> (f64, f64) {
+    let (min, max) = valeurs.fold((0., 0.), |(min, max) : (f64, f64), valeur| {
+        (min.min(valeur as f64), max.max(valeur as f64))
+    });
+    let marge = (max - min).max(1.) * 0.05;
+    (min - marge, max + marge)
+}
@@ -0,0 +1,71 @@
+use crate::{
+    Flottant,
+    comparaison::{self, Comparaison},
+    etat::EtatTour,
+    simulation::{Optimale, Strategie},
+    stats::{PerteEtat, Stats},
+};
+
+
+// Ce que coûte une heuristique de jeu (règle de maison, habitude de joueur)
+// par rapport à la stratégie optimale. L'espérance de gain d'un tour se
+// calcule exactement pour toute stratégie qui ne dépend que de l'état du tour
+// (voir Stats::esperance_strategie), mais le taux de victoire sur une partie
+// entière ne s'obtient qu'en simulant des parties.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvaluationHeuristique {
+    // Espérance de gain d'un tour à score nul, selon l'heuristique et selon
+    // la stratégie optimale
+    pub esperance_tour: Flottant,
+    pub esperance_optimale: Flottant,
+
+    // Etats du tour à score nul par lesquels passe l'heuristique, avec ce
+    // qu'elle y perd par rapport à la stratégie optimale, par ordre décroissant
+    // de leur contribution à sa perte totale (voir Stats::pertes_strategie)
+    pub pires_etats: Vec<PerteEtat>,
+
+    // Somme des contributions de tous ces états, qui doit retrouver la perte
+    // par tour aux erreurs de convergence près
+    pub perte_etats: Flottant,
+
+    // Comparaison sur des parties entières entre la stratégie optimale (A) et
+    // l'heuristique (B), aux dés appariés
+    pub comparaison: Comparaison,
+}
+
+impl EvaluationHeuristique {
+    // Espérance de gain perdue à chaque tour à score nul
+    pub fn perte_tour(&self) -> Flottant {
+        self.esperance_optimale - self.esperance_tour
+    }
+}
+
+// Evaluer une heuristique construite par "creer_heuristique" (une fois par
+// tâche parallèle, comme pour comparaison::comparer), en gardant les
+// "nb_etats" états où elle perd le plus et en jouant "nb_paires" paires de
+// parties contre la stratégie optimale
+pub fn evaluer_heuristique(stats: &Stats,
+                           creer_heuristique: impl Fn() -> Box<dyn Strategie> + Sync,
+                           nb_etats: usize,
+                           nb_paires: usize,
+                           graine: u64) -> EvaluationHeuristique
+{
+    let debut = EtatTour::debut(stats.regles(), 0);
+    let heuristique = creer_heuristique();
+    let mut pires_etats = stats.pertes_strategie(&*heuristique, &debut);
+    let perte_etats = pires_etats.iter().map(PerteEtat::contribution).sum();
+    pires_etats.sort_by(|a, b| b.contribution().total_cmp(&a.contribution()));
+    pires_etats.truncate(nb_etats);
+
+    let comparaison = comparaison::comparer(stats, |stats| {
+        [Box::new(Optimale::new(stats)) as Box<dyn Strategie>, creer_heuristique()]
+    }, nb_paires, graine);
+
+    EvaluationHeuristique {
+        esperance_tour: stats.esperance_strategie(&*heuristique, &debut),
+        esperance_optimale: stats.esperance_etat(&debut),
+        pires_etats,
+        perte_etats,
+        comparaison,
+    }
+}
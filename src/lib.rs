@@ -0,0 +1,68 @@
+pub mod analyse;
+pub mod choix;
+pub mod combinaison;
+pub mod comparaison;
+pub mod course;
+pub mod duree;
+pub mod enregistrement;
+pub mod etat;
+#[cfg(feature = "plots")]
+pub mod graphiques;
+pub mod heuristiques;
+pub mod mises;
+pub mod ordre;
+pub mod partie;
+#[cfg(feature = "python")]
+mod python;
+pub mod regles;
+pub mod scenarios;
+pub mod simulation;
+pub mod sortie;
+pub mod stats;
+pub mod stats_exact;
+pub mod table;
+pub mod tournoi;
+pub mod tours_fixes;
+pub mod verification;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use crate::{
+    choix::{Enumeration, Histogramme, enumerer_choix},
+    combinaison::{Bareme, Combinaison, VALEUR_MIN_DE},
+    course::{Duel, PolitiqueCourse},
+    etat::{EtatPartie, EtatTour},
+    regles::Regles,
+    stats::{Arbre, Bilan, Decision, OptimiseurUtilite, OptimiseurVariance, OptionEvaluee, PerteEtat,
+            ProbaFin, Progression, Stats, StatistiquesCache, StatistiquesGain},
+};
+
+
+// Type flottant utilisé pour les probabilités et les espérances. La simple
+// précision est plus rapide, mais la fonctionnalité "f64" permet de passer en
+// double précision quand on s'intéresse à des probabilités très faibles. Toutes
+// les signatures publiques utilisent ce type, donc elles suivent ce choix.
+#[cfg(not(feature = "f64"))]
+pub type Flottant = f32;
+#[cfg(feature = "f64")]
+pub type Flottant = f64;
+
+// Type destiné à stocker des valeurs de combinaisons, de mises, de scores...
+// On additionne souvent scores, mises et valeurs de combinaisons, donc on prend
+// un type assez large pour que ces sommes ne débordent pas avec des barèmes ou
+// des scores maximaux plus élevés que d'habitude.
+pub type Valeur = u32;
+
+// Nombre de dés maximum qu'on peut lancer
+pub const NB_DES_TOT : usize = 6;
+
+// Nombre de faces par dé
+pub const NB_FACES : usize = 6;
+
+// Nombre maximal de relances considéré, utile pour éviter d'explorer des
+// régions trop improbables de l'arbre des possibles
+pub const NB_RELANCES_MAX : usize = 15;
+
+// Score maximal atteignable par défaut. On doit l'atteindre exactement pour
+// terminer, mais d'autres cibles et variantes peuvent être choisies via Regles.
+pub const SCORE_MAX : Valeur = 10000;
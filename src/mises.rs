@@ -0,0 +1,78 @@
+use crate::{
+    Valeur,
+    choix,
+    regles::Regles,
+    stats::Possibilite,
+};
+
+use std::collections::{BTreeSet, HashSet};
+
+
+// Ensemble des mises qu'on peut avoir accumulées au moment de lancer un certain
+// nombre de dés, en enchaînant des combinaisons depuis le début du tour
+#[derive(Clone, Debug, PartialEq)]
+pub struct MisesAtteignables {
+    // Score maximal, que la mise ne peut pas atteindre si on relance
+    score_max: Valeur,
+
+    // Mises atteignables pour chaque nombre de dés à lancer
+    mises: Box<[BTreeSet<Valeur>]>,
+}
+
+impl MisesAtteignables {
+    // Recenser les mises atteignables pour certaines règles du jeu
+    pub fn new(regles: &Regles) -> Self {
+        // Ce qui compte pour chaque nombre de dés lancés, c'est la valeur des
+        // combinaisons qu'on peut tirer et le nombre de dés avec lequel elles
+        // nous laissent relancer. On écarte la sexte quand elle termine la
+        // partie (voir Regles::fait_gagner).
+        let transitions = (1..=regles.nb_des_tot).map(|nb_des| {
+            choix::compter_choix(nb_des, regles).into_keys()
+                .flatten()
+                .filter(|comb| !regles.fait_gagner(comb))
+                .map(|comb| {
+                    let poss = Possibilite::new(comb, nb_des, regles);
+                    (poss.valeur, poss.nb_des_relance)
+                }).collect::<HashSet<_>>()
+        }).collect::<Vec<_>>();
+
+        // En partant du début du tour (tous les dés, aucune mise), on explore
+        // tout ce qu'on peut atteindre en relançant sans atteindre le score
+        // maximal. La mise croît à chaque relance, donc ça termine.
+        let mut mises = vec![BTreeSet::new(); regles.nb_des_tot].into_boxed_slice();
+        mises[regles.nb_des_tot-1].insert(0);
+        let mut a_explorer = vec![(regles.nb_des_tot, 0)];
+        while let Some((nb_des, mise)) = a_explorer.pop() {
+            for &(valeur, nb_des_relance) in transitions[nb_des-1].iter() {
+                let nouvelle_mise = mise + valeur;
+                if nouvelle_mise >= regles.score_max { continue; }
+                if mises[nb_des_relance-1].insert(nouvelle_mise) {
+                    a_explorer.push((nb_des_relance, nouvelle_mise));
+                }
+            }
+        }
+        Self {
+            score_max: regles.score_max,
+            mises,
+        }
+    }
+
+    // Mises atteignables en lançant N dés, par ordre croissant
+    pub fn mises(&self, nb_des: usize) -> impl DoubleEndedIterator<Item=Valeur> + '_ {
+        self.mises[nb_des-1].iter().copied()
+    }
+
+    // Toutes les combinaisons (score, mise, nb de dés) ne sont pas
+    // vraisemblables. Par exemple, si on lance un seul dé, on a nécessairement
+    // accumulé 250 points, et si on a gagné, on ne relance pas
+    pub fn jet_impossible(&self,
+                          score: Valeur,
+                          nb_des: usize,
+                          mise: Valeur) -> bool
+    {
+        score + mise >= self.score_max
+            || !self.mises.get(nb_des.wrapping_sub(1))
+                          .is_some_and(|mises| mises.contains(&mise))
+    }
+}
+
@@ -0,0 +1,165 @@
+use crate::{
+    simulation::{Jeu, Optimale, QUANTILE_95, SourceDes, Strategie, TAILLE_LOT},
+    stats::Stats,
+};
+
+use rayon::prelude::*;
+
+
+// Avantage de chaque place autour de la table quand tous les joueurs jouent
+// de façon à maximiser leur espérance de gain (le joueur 0 commence).
+//
+// Compter les victoires de chaque place demande beaucoup de parties pour
+// distinguer des écarts de quelques dixièmes de pourcent, car le vainqueur
+// dépend surtout des dés qu'il a reçus. On joue donc les parties par blocs de
+// N parties, une pour chaque rotation des suites de dés entre les places (voir
+// SourceDes::Tournante): chaque suite de dés passe par chaque place, et au sein
+// d'un bloc, le vainqueur ne change de place que si l'ordre de jeu compte.
+//
+#[derive(Clone, Debug, PartialEq)]
+pub struct AvantagePlaces {
+    // Nombre de joueurs autour de la table
+    pub nb_joueurs: usize,
+
+    // Nombre de blocs de parties jouées (chaque bloc compte une partie par
+    // joueur)
+    pub nb_blocs: usize,
+
+    // victoires[i] est le nombre de parties gagnées par le joueur i
+    pub victoires: Vec<usize>,
+
+    // Nombre de parties qui n'ont pas fini en NB_TOURS_MAX tours
+    pub nb_interrompues: usize,
+
+    // Somme des carrés, sur les blocs, de la proportion des parties du bloc
+    // gagnées par chaque place
+    carres_blocs: Vec<f64>,
+}
+
+impl AvantagePlaces {
+    // Nombre de parties jouées
+    pub fn nb_parties(&self) -> usize {
+        self.nb_blocs * self.nb_joueurs
+    }
+
+    // Proportion des parties gagnées par le joueur i, et demi-largeur de son
+    // intervalle de confiance à 95%, calculée à partir de la variance entre
+    // blocs
+    pub fn taux_victoire(&self, joueur: usize) -> (f64, f64) {
+        let taux = self.victoires[joueur] as f64 / self.nb_parties() as f64;
+        (taux, QUANTILE_95 * (self.variance_bloc(joueur) / self.nb_blocs as f64).sqrt())
+    }
+
+    // Facteur par lequel la rotation des dés réduit la variance de ce taux,
+    // par rapport à autant de parties indépendantes: c'est aussi le facteur
+    // d'économie sur le nombre de parties à jouer. Retourne None quand les
+    // blocs n'ont montré aucune variance, ce qui arrive avec trop peu de blocs
+    // et ne permet pas d'estimer ce facteur.
+    pub fn gain_rotation(&self, joueur: usize) -> Option<f64> {
+        let taux = self.victoires[joueur] as f64 / self.nb_parties() as f64;
+        let variance_independante = taux * (1. - taux) / self.nb_joueurs as f64;
+        let variance_bloc = self.variance_bloc(joueur);
+        (variance_bloc > 0.).then(|| variance_independante / variance_bloc)
+    }
+
+    // Variance empirique (non biaisée), sur les blocs, de la proportion des
+    // parties du bloc gagnées par le joueur i
+    fn variance_bloc(&self, joueur: usize) -> f64 {
+        let n = self.nb_blocs as f64;
+        let moyenne = self.victoires[joueur] as f64 / self.nb_parties() as f64;
+        (self.carres_blocs[joueur] / n - moyenne * moyenne).max(0.) * n / (n - 1.)
+    }
+}
+
+// Jouer un certain nombre de blocs de parties entre joueurs qui maximisent
+// tous leur espérance de gain, et mesurer l'avantage de chaque place
+pub fn avantage_places(stats: &Stats,
+                       nb_joueurs: usize,
+                       nb_blocs: usize,
+                       graine: u64) -> AvantagePlaces
+{
+    assert!(nb_blocs > 1, "Il faut au moins deux blocs de parties");
+    let regles = stats.regles();
+
+    // On joue les blocs par lots, en parallèle, avec un jeu par rotation. Les
+    // jeux d'un lot ont la même graine et jouent autant de parties, donc la
+    // k-ième partie de chacun utilise les mêmes suites de dés.
+    let lots = (0..nb_blocs).step_by(TAILLE_LOT).collect::<Vec<_>>();
+    let resultats_lots = lots.par_iter().enumerate().map(|(num_lot, &debut)| {
+        let optimale = Optimale::new(stats);
+        let joueurs = vec![&optimale as &dyn Strategie; nb_joueurs];
+        let graine_lot = graine.wrapping_add(num_lot as u64);
+        let mut jeux = (0..nb_joueurs).map(|rotation| {
+            Jeu::with_source(regles.clone(), graine_lot, SourceDes::Tournante(rotation))
+        }).collect::<Vec<_>>();
+        let fin = (debut + TAILLE_LOT).min(nb_blocs);
+        (debut..fin).map(|_| {
+            jeux.iter_mut()
+                .map(|jeu| jeu.jouer_partie_a_plusieurs(&joueurs).gagnant)
+                .collect::<Vec<_>>()
+        }).collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+
+    // ...et on rassemble les résultats
+    let mut resultats = AvantagePlaces {
+        nb_joueurs,
+        nb_blocs,
+        victoires: vec![0; nb_joueurs],
+        nb_interrompues: 0,
+        carres_blocs: vec![0.; nb_joueurs],
+    };
+    for bloc in resultats_lots.into_iter().flatten() {
+        let mut victoires_bloc = vec![0; nb_joueurs];
+        for gagnant in bloc {
+            match gagnant {
+                Some(gagnant) => victoires_bloc[gagnant] += 1,
+                None => resultats.nb_interrompues += 1,
+            }
+        }
+        for (joueur, victoires) in victoires_bloc.into_iter().enumerate() {
+            resultats.victoires[joueur] += victoires;
+            let proportion = victoires as f64 / nb_joueurs as f64;
+            resultats.carres_blocs[joueur] += proportion * proportion;
+        }
+    }
+    resultats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Avec une graine fixée, la mesure est reproductible, chaque partie jouée
+    // est gagnée par une place ou interrompue, et les estimations de
+    // variance restent finies
+    #[test]
+    fn avantage_reproductible() {
+        let stats = Stats::with_score_max(1000);
+        for nb_joueurs in 2..=3 {
+            let resultats = avantage_places(&stats, nb_joueurs, 20, 42);
+            assert_eq!(resultats, avantage_places(&stats, nb_joueurs, 20, 42));
+            assert_eq!(resultats.nb_parties(), 20 * nb_joueurs);
+            assert_eq!(resultats.victoires.iter().sum::<usize>() + resultats.nb_interrompues,
+                       resultats.nb_parties());
+            for joueur in 0..nb_joueurs {
+                let (taux, largeur) = resultats.taux_victoire(joueur);
+                assert!((0. ..=1.).contains(&taux), "{}", taux);
+                assert!(largeur.is_finite() && largeur >= 0., "{}", largeur);
+                assert!(resultats.variance_bloc(joueur).is_finite());
+                assert!(resultats.gain_rotation(joueur).is_none_or(|gain| gain.is_finite()));
+            }
+        }
+
+        // Des blocs tous identiques n'ont aucune variance, et on ne prétend
+        // alors pas savoir ce que fait gagner la rotation
+        let constant = AvantagePlaces {
+            nb_joueurs: 2,
+            nb_blocs: 2,
+            victoires: vec![2, 2],
+            nb_interrompues: 0,
+            carres_blocs: vec![0.5, 0.5],
+        };
+        assert_eq!(constant.variance_bloc(0), 0.);
+        assert_eq!(constant.gain_rotation(0), None);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{
+    Valeur,
+    simulation::{Jeu, Strategie, TAILLE_LOT},
+    stats::Stats,
+};
+
+use rayon::prelude::*;
+
+
+// Résultats de parties entre plusieurs joueurs qui suivent chacun leur propre
+// stratégie, toujours assis à la même place (le joueur 0 commence)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResultatsParties {
+    // Nombre de joueurs autour de la table
+    pub nb_joueurs: usize,
+
+    // victoires[i] est le nombre de parties gagnées par le joueur i
+    pub victoires: Vec<usize>,
+
+    // Nombre de parties qui n'ont pas fini en NB_TOURS_MAX tours
+    pub nb_interrompues: usize,
+
+    // scores_finaux[i] contient le score du joueur i à la fin de chaque partie
+    // terminée, par ordre croissant
+    pub scores_finaux: Vec<Vec<Valeur>>,
+}
+
+impl ResultatsParties {
+    // Nombre de parties jouées, terminées ou non
+    pub fn nb_parties(&self) -> usize {
+        self.victoires.iter().sum::<usize>() + self.nb_interrompues
+    }
+
+    // Proportion des parties gagnées par le joueur i
+    pub fn taux_victoire(&self, joueur: usize) -> f64 {
+        self.victoires[joueur] as f64 / self.nb_parties() as f64
+    }
+
+    // Score moyen du joueur i à la fin des parties terminées
+    pub fn score_moyen(&self, joueur: usize) -> f64 {
+        let scores = &self.scores_finaux[joueur];
+        scores.iter().map(|&score| score as f64).sum::<f64>() / scores.len() as f64
+    }
+
+    // Score final du joueur i en-dessous duquel finissent une certaine
+    // proportion (entre 0 et 1) des parties terminées
+    pub fn centile(&self, joueur: usize, proportion: f64) -> Valeur {
+        let scores = &self.scores_finaux[joueur];
+        let rang = (proportion * scores.len() as f64).ceil() as usize;
+        scores[rang.clamp(1, scores.len()) - 1]
+    }
+}
+
+// Jouer un certain nombre de parties entre les stratégies construites par
+// "creer_strategies", une par place autour de la table. Comme pour les
+// tournois, chaque tâche parallèle construit ses propres stratégies.
+pub fn jouer_parties<'stats>(stats: &'stats Stats,
+                             creer_strategies: impl Fn(&'stats Stats) -> Vec<Box<dyn Strategie + 'stats>> + Sync,
+                             nb_parties: usize,
+                             graine: u64) -> ResultatsParties
+{
+    // On joue les parties par lots, en parallèle...
+    let nb_joueurs = creer_strategies(stats).len();
+    let lots = (0..nb_parties).step_by(TAILLE_LOT).collect::<Vec<_>>();
+    let resultats_lots = lots.par_iter().enumerate().map(|(num_lot, &debut)| {
+        let strategies = creer_strategies(stats);
+        let joueurs = strategies.iter().map(|strategie| &**strategie).collect::<Vec<_>>();
+        let mut jeu = Jeu::with_regles(stats.regles().clone(),
+                                       graine.wrapping_add(num_lot as u64));
+        let fin = (debut + TAILLE_LOT).min(nb_parties);
+        (debut..fin).map(|_| {
+            let resultat = jeu.jouer_partie_a_plusieurs(&joueurs);
+            (resultat.gagnant, resultat.scores)
+        }).collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+
+    // ...et on rassemble les résultats
+    let mut resultats = ResultatsParties {
+        nb_joueurs,
+        victoires: vec![0; nb_joueurs],
+        nb_interrompues: 0,
+        scores_finaux: vec![Vec::with_capacity(nb_parties); nb_joueurs],
+    };
+    for (gagnant, scores) in resultats_lots.into_iter().flatten() {
+        match gagnant {
+            Some(gagnant) => {
+                resultats.victoires[gagnant] += 1;
+                for (scores_finaux, score) in resultats.scores_finaux.iter_mut().zip(scores) {
+                    scores_finaux.push(score);
+                }
+            },
+            None => resultats.nb_interrompues += 1,
+        }
+    }
+    for scores_finaux in resultats.scores_finaux.iter_mut() {
+        scores_finaux.sort_unstable();
+    }
+    resultats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        regles::Regles,
+        simulation::{Optimale, SeuilFixe},
+    };
+
+    // Une partie à quatre, entre la stratégie optimale et trois joueurs qui
+    // s'arrêtent dès 300 points, se rejoue à l'identique avec la même graine.
+    // Chacun joue à son tour, et son score final est la somme de ses mises.
+    #[test]
+    fn partie_a_quatre() {
+        let stats = Stats::with_regles(Regles { score_max: 1000, ..Regles::default() });
+        let optimale = Optimale::new(&stats);
+        let seuil = SeuilFixe(300);
+        let joueurs : [&dyn Strategie; 4] = [&optimale, &seuil, &seuil, &seuil];
+        let jouer = || {
+            Jeu::with_regles(stats.regles().clone(), 42).jouer_partie_a_plusieurs(&joueurs)
+        };
+        let resultat = jouer();
+        assert_eq!(resultat, jouer());
+        assert_eq!(resultat.nb_joueurs, 4);
+        let gagnant = resultat.gagnant.unwrap();
+        assert_eq!(resultat.scores[gagnant], 1000);
+        for (joueur, &score) in resultat.scores.iter().enumerate() {
+            let mises = resultat.tours.iter().skip(joueur).step_by(4).map(|tour| tour.mise);
+            assert_eq!(mises.sum::<Valeur>(), score, "joueur {}", joueur);
+        }
+        assert_eq!(resultat.nb_tours() % 4, (gagnant + 1) % 4);
+    }
+
+    // Les statistiques par place sont elles aussi reproductibles, et la
+    // stratégie optimale gagne plus souvent que chacune des autres
+    #[test]
+    fn parties_a_quatre() {
+        let stats = Stats::with_regles(Regles { score_max: 1000, ..Regles::default() });
+        let creer = |stats| {
+            let mut strategies = vec![Box::new(Optimale::new(stats)) as Box<dyn Strategie>];
+            strategies.extend((0..3).map(|_| Box::new(SeuilFixe(300)) as Box<dyn Strategie>));
+            strategies
+        };
+        let resultats = jouer_parties(&stats, creer, 200, 42);
+        assert_eq!(resultats, jouer_parties(&stats, creer, 200, 42));
+        assert_eq!(resultats.nb_joueurs, 4);
+        assert_eq!((resultats.nb_parties(), resultats.nb_interrompues), (200, 0));
+        assert!(resultats.scores_finaux.iter().all(|scores| scores.len() == 200));
+        assert!((1..4).all(|joueur| resultats.victoires[0] > resultats.victoires[joueur]),
+                "{:?}", resultats.victoires);
+    }
+}
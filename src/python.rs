@@ -0,0 +1,149 @@
+use crate::{
+    Flottant,
+    NB_RELANCES_MAX,
+    Valeur,
+    choix::{Enumeration, Histogramme},
+    regles::Regles,
+    stats::{Decision, Stats},
+};
+
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::PyDict,
+};
+
+
+// Statistiques utilisables depuis Python. Les calculs se font sans le GIL, on
+// peut donc étudier plusieurs variantes des règles en parallèle depuis des
+// threads Python.
+#[pyclass(name = "Stats", frozen)]
+struct StatsPython {
+    stats: Stats,
+}
+
+#[pymethods]
+impl StatsPython {
+    // Préparer les calculs pour une variante des règles, dont tous les champs
+    // sont optionnels et prennent leur valeur par défaut s'ils sont omis
+    #[new]
+    #[pyo3(signature = (*, score_max=None, atterrissage_exact=None, seuil_ouverture=None,
+                        nb_des_tot=None, nb_faces=None, carres_et_plus=None, full=None,
+                        petite_suite=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(py: Python<'_>,
+           score_max: Option<Valeur>,
+           atterrissage_exact: Option<bool>,
+           seuil_ouverture: Option<Valeur>,
+           nb_des_tot: Option<usize>,
+           nb_faces: Option<usize>,
+           carres_et_plus: Option<bool>,
+           full: Option<bool>,
+           petite_suite: Option<bool>) -> PyResult<Self>
+    {
+        let defaut = Regles::default();
+        let regles = Regles {
+            score_max: score_max.unwrap_or(defaut.score_max),
+            atterrissage_exact: atterrissage_exact.unwrap_or(defaut.atterrissage_exact),
+            seuil_ouverture: seuil_ouverture.unwrap_or(defaut.seuil_ouverture),
+            nb_des_tot: nb_des_tot.unwrap_or(defaut.nb_des_tot),
+            nb_faces: nb_faces.unwrap_or(defaut.nb_faces),
+            carres_et_plus: carres_et_plus.unwrap_or(defaut.carres_et_plus),
+            full: full.unwrap_or(defaut.full),
+            petite_suite: petite_suite.unwrap_or(defaut.petite_suite),
+            ..defaut
+        };
+        regles.verifier().map_err(PyValueError::new_err)?;
+        let stats = py.detach(|| Stats::with_regles(regles));
+        Ok(Self { stats })
+    }
+
+    // Espérance de gain pour une stratégie qui la maximise
+    fn esperance(&self, py: Python<'_>, score: Valeur, nb_des: usize, mise: Valeur) -> PyResult<Flottant> {
+        self.verifier_des(nb_des)?;
+        Ok(py.detach(|| self.stats.esperance(score, nb_des, mise)))
+    }
+
+    // Gain moyen quand on risque "mise" points en lançant "nb_des" dés
+    fn gain_moyen(&self, py: Python<'_>, score: Valeur, nb_des: usize, mise: Valeur) -> PyResult<Flottant> {
+        self.verifier_des(nb_des)?;
+        Ok(py.detach(|| self.stats.gain_moyen(score, nb_des, mise)))
+    }
+
+    // Probabilité de gagner la partie ce tour-ci en s'autorisant N relances
+    #[pyo3(signature = (score, nb_des, mise, max_relances=NB_RELANCES_MAX))]
+    fn proba_fin(&self,
+                 py: Python<'_>,
+                 score: Valeur,
+                 nb_des: usize,
+                 mise: Valeur,
+                 max_relances: usize) -> PyResult<Flottant>
+    {
+        self.verifier_des(nb_des)?;
+        Ok(py.detach(|| self.stats.proba_fin(score, nb_des, mise, max_relances)))
+    }
+
+    // Histogramme d'un jet de dés donné face par face, tel que le prennent
+    // les autres méthodes
+    fn histogramme(&self, faces: Vec<usize>) -> PyResult<Vec<usize>> {
+        Histogramme::depuis_faces(&faces, self.stats.regles())
+                    .map(|histo| histo.to_vec())
+                    .map_err(|erreur| PyValueError::new_err(erreur.to_string()))
+    }
+
+    // Décision optimale face à un jet de dés donné face par face, sous forme
+    // de dictionnaire (None si le tour est perdu)
+    fn action_optimale<'py>(&self,
+                            py: Python<'py>,
+                            score: Valeur,
+                            mise: Valeur,
+                            faces: Vec<usize>) -> PyResult<Option<Bound<'py, PyDict>>>
+    {
+        let regles = self.stats.regles();
+        let histo = Histogramme::depuis_faces(&faces, regles)
+                                .map_err(|erreur| PyValueError::new_err(erreur.to_string()))?;
+        let nb_des = histo.nb_des();
+        let decision = py.detach(|| {
+            let combinaisons = histo.combinaisons_possibles(regles, Enumeration::Elaguee);
+            self.stats.action_optimale(score, nb_des, mise, &combinaisons)
+        });
+        let decision = match decision {
+            Some(decision) => decision,
+            None => return Ok(None),
+        };
+        let resultat = PyDict::new(py);
+        resultat.set_item("combinaison", decision.combinaison().to_string())?;
+        match decision {
+            Decision::Encaisser { .. } => {
+                resultat.set_item("action", "encaisser")?;
+            },
+            Decision::Relancer { nb_des, .. } => {
+                resultat.set_item("action", "relancer")?;
+                resultat.set_item("nb_des", nb_des)?;
+            },
+        }
+        resultat.set_item("mise", decision.mise())?;
+        resultat.set_item("esperance", decision.esperance())?;
+        Ok(Some(resultat))
+    }
+}
+
+impl StatsPython {
+    // Les calculs supposent un nombre de dés valide, on le vérifie donc avant
+    // de s'y lancer plutôt que de planter l'interpréteur
+    fn verifier_des(&self, nb_des: usize) -> PyResult<()> {
+        let nb_des_tot = self.stats.regles().nb_des_tot;
+        if (1..=nb_des_tot).contains(&nb_des) {
+            Ok(())
+        } else {
+            Err(PyValueError::new_err(format!("On ne peut pas lancer {} dés (entre 1 et {})",
+                                              nb_des, nb_des_tot)))
+        }
+    }
+}
+
+// Module Python "mitraillette"
+#[pymodule]
+fn mitraillette(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<StatsPython>()
+}
@@ -0,0 +1,273 @@
+use crate::{
+    Flottant,
+    NB_DES_TOT,
+    NB_FACES,
+    SCORE_MAX,
+    Valeur,
+    combinaison::{Bareme, Combinaison},
+};
+
+use serde::{Deserialize, Serialize};
+
+
+// Nombre de tours perdus d'affilée qui coûtent une pénalité quand la règle le
+// prévoit (voir Regles::penalite_pertes)
+pub const NB_PERTES_PENALITE : usize = 3;
+
+// Score maximal utilisé quand la partie se joue en un nombre fixe de tours
+// (voir Regles::nb_tours), assez élevé pour qu'on ne l'approche jamais en
+// quelques dizaines de tours et qu'il ne contraigne donc pas le jeu
+pub const SCORE_ILLIMITE : Valeur = 200_000;
+
+// Variante des règles de la mitraillette qu'on étudie. Les champs absents
+// lors de la désérialisation prennent leur valeur par défaut.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Regles {
+    // Score maximal atteignable, qu'il faut atteindre pour terminer
+    pub score_max: Valeur,
+
+    // Faut-il atteindre exactement le score maximal pour gagner? Sinon, il
+    // suffit de l'atteindre ou de le dépasser, et on ne peut plus être bloqué
+    // par une combinaison qui le dépasse.
+    pub atterrissage_exact: bool,
+
+    // Variante du rebond: on peut toujours s'arrêter, mais si ce qu'on encaisse
+    // fait dépasser le score maximal, on retranche l'excédent de celui-ci. Il
+    // faut alors toujours atterrir exactement sur le score maximal pour gagner,
+    // quoi que dise atterrissage_exact.
+    pub rebond: bool,
+
+    // Score qu'il faut encaisser d'un coup lors de son premier tour gagnant
+    // pour "ouvrir", avant quoi on ne peut pas s'arrêter avec moins (0 si la
+    // règle n'impose pas de seuil d'ouverture)
+    pub seuil_ouverture: Valeur,
+
+    // Mise minimale qu'il faut avoir accumulée pour s'arrêter, à chaque tour
+    // et pas seulement à l'ouverture (0 si la règle n'en impose pas). On peut
+    // toujours s'arrêter en atteignant le score maximal, sans quoi il serait
+    // impossible de finir quand il manque moins que cette mise.
+    pub mise_minimale_pour_stopper: Valeur,
+
+    // Autoriser la reprise: quand un joueur encaisse, le suivant peut choisir
+    // de continuer son tour avec les dés restants et la mise encaissée, au lieu
+    // de relancer tous les dés sans mise
+    pub reprise: bool,
+
+    // Egalisation en fin de partie: quand un joueur atteint le score maximal,
+    // chacun des autres joue encore un tour, et c'est le meilleur score final
+    // qui gagne. Ça n'a de sens que si l'on peut dépasser le score maximal, et
+    // on encaisse alors toute sa mise, sans la limiter à ce qui manquait (les
+    // statistiques des tours ordinaires l'ignorent). Une sexte fait toujours
+    // gagner directement.
+    pub dernier_tour: bool,
+
+    // Nombre de tours que joue chaque joueur quand la partie se joue en un
+    // nombre fixe de tours, après quoi le meilleur total gagne (0 pour jouer
+    // jusqu'au score maximal). Le score maximal ne doit alors plus compter,
+    // voir en_tours_fixes.
+    pub nb_tours: usize,
+
+    // Points que perd un joueur qui perd NB_PERTES_PENALITE tours d'affilée,
+    // sans que son score puisse devenir négatif (0 si la règle ne pénalise pas
+    // les pertes à répétition). Sa série de pertes repart ensuite de zéro. Avec
+    // un seuil d'ouverture, un joueur qui a ouvert garde au moins la valeur du
+    // plus petit dé, pour ne pas avoir à ouvrir de nouveau.
+    pub penalite_pertes: Valeur,
+
+    // Nombre de dés avec lesquels on joue (entre 1 et NB_DES_TOT). C'est aussi
+    // le nombre de dés qu'on récupère quand tous les dés ont été utilisés.
+    pub nb_des_tot: usize,
+
+    // Nombre de faces par dé (au moins NB_FACES). Seuls les 1 et les 5 valent
+    // des points tout seuls, et une suite demande que chaque face sorte une
+    // fois, ce qui n'est possible qu'avec au moins autant de dés que de faces.
+    pub nb_faces: usize,
+
+    // Probabilité de sortir de chaque face (la face 1 en premier), si les dés
+    // sont pipés. Il en faut une par face, et elles doivent faire 1 au total.
+    // Sans ça, chaque face sort avec la même probabilité.
+    pub poids_faces: Option<Vec<Flottant>>,
+
+    // Nombre de points rapporté par chaque combinaison
+    pub bareme: Bareme,
+
+    // Autoriser les carrés, les quintes et les sextes (qui font gagner)
+    pub carres_et_plus: bool,
+
+    // Autoriser les fulls (brelan + paire)
+    pub full: bool,
+
+    // Autoriser les petites suites (cinq faces consécutives)
+    pub petite_suite: bool,
+
+    // Obliger à relancer quand tous les dés ont servi (suite, triple paire,
+    // double brelan, ou prises successives qui utilisent les six dés), sauf si
+    // ça fait atteindre le score maximal
+    pub relance_plein: bool,
+}
+
+impl Default for Regles {
+    fn default() -> Self {
+        Self {
+            score_max: SCORE_MAX,
+            atterrissage_exact: true,
+            rebond: false,
+            seuil_ouverture: 0,
+            mise_minimale_pour_stopper: 0,
+            reprise: false,
+            dernier_tour: false,
+            nb_tours: 0,
+            penalite_pertes: 0,
+            nb_des_tot: NB_DES_TOT,
+            nb_faces: NB_FACES,
+            poids_faces: None,
+            bareme: Bareme::default(),
+            carres_et_plus: false,
+            full: false,
+            petite_suite: false,
+            relance_plein: false,
+        }
+    }
+}
+
+impl Regles {
+    // Même variante des règles, mais en jouant un nombre fixe de tours sans
+    // score à atteindre: on repousse le score maximal hors d'atteinte et on
+    // autorise à le dépasser, pour qu'aucune de ses contraintes ne joue
+    pub fn en_tours_fixes(&self, nb_tours: usize) -> Self {
+        Self {
+            score_max: SCORE_ILLIMITE,
+            atterrissage_exact: false,
+            rebond: false,
+            dernier_tour: false,
+            nb_tours,
+            ..self.clone()
+        }
+    }
+
+    // Est-ce que les probabilités de sortie des faces ont un sens? C'est
+    // toujours le cas quand les dés ne sont pas pipés.
+    pub fn poids_faces_valides(&self) -> bool {
+        match &self.poids_faces {
+            None => true,
+            Some(poids) => {
+                poids.len() == self.nb_faces
+                    && poids.iter().all(|&proba| proba >= 0.)
+                    && (poids.iter().sum::<Flottant>() - 1.).abs() < 1e-4
+            },
+        }
+    }
+
+    // Est-ce que le moteur de jeu et l'analyse statistique savent jouer avec
+    // ces règles? Sinon, on indique pourquoi.
+    pub fn verifier(&self) -> Result<(), String> {
+        // L'énumération des combinaisons ne gère pas plus de NB_DES_TOT dés
+        if !(1..=NB_DES_TOT).contains(&self.nb_des_tot) {
+            return Err(format!("Nombre de dés non supporté: {}", self.nb_des_tot));
+        }
+        // On a besoin d'au moins 6 faces pour avoir des 1 et des 5
+        if self.nb_faces < NB_FACES {
+            return Err(format!("Nombre de faces non supporté: {}", self.nb_faces));
+        }
+        // Des dés pipés doivent avoir une probabilité par face, faisant 1
+        if !self.poids_faces_valides() {
+            return Err(format!("Probabilités des faces invalides: {:?}", self.poids_faces));
+        }
+        // Le score et la mise restent inférieurs au score maximal, donc leur
+        // somme avec la valeur d'une combinaison ne doit pas pouvoir déborder.
+        // On majore cette valeur sans l'arithmétique de Valeur, qui pourrait
+        // elle-même déborder sur un barème démesuré.
+        let bareme = &self.bareme;
+        let brelan_max = (bareme.brelan_de_un as u64)
+            .max((self.nb_faces as u64).saturating_mul(bareme.brelan_par_face as u64));
+        let multiplicateur_max = bareme.multiplicateur_carre
+            .max(bareme.multiplicateur_quinte)
+            .max(bareme.multiplicateur_sexte)
+            .max(2) as u64;
+        let faces_simples_max = (NB_DES_TOT as u64) * (bareme.un.max(bareme.cinq) as u64);
+        let valeur_max = (bareme.suite as u64)
+            .max(bareme.triple_paire as u64)
+            .max(bareme.petite_suite as u64 + faces_simples_max)
+            .max(brelan_max.saturating_mul(multiplicateur_max) + faces_simples_max)
+            .max(brelan_max + bareme.bonus_full as u64);
+        if 2 * (self.score_max as u64) + valeur_max > Valeur::MAX as u64 {
+            return Err(format!("Score maximal {} et combinaisons à {} points trop élevés",
+                               self.score_max, valeur_max));
+        }
+        Ok(())
+    }
+
+    // Est-ce que prendre cette combinaison fait gagner la partie? C'est le cas
+    // de la sexte, sauf quand on joue un nombre fixe de tours: il n'y a alors
+    // pas de score à atteindre, et elle compte comme une autre combinaison,
+    // pour ce que lui donne le barème.
+    pub fn fait_gagner(&self, comb: &Combinaison) -> bool {
+        comb.fait_gagner() && self.nb_tours == 0
+    }
+
+    // Est-ce qu'on gagne la partie en encaissant une mise qui amène à ce score?
+    pub fn score_gagnant(&self, score: Valeur) -> bool {
+        if self.atterrissage_exact || self.rebond {
+            score == self.score_max
+        } else {
+            score >= self.score_max
+        }
+    }
+
+    // Score qu'on atteint en encaissant une mise. Avec la règle du rebond,
+    // l'excédent au-delà du score maximal est retranché de celui-ci, sans
+    // qu'on puisse descendre sous zéro.
+    pub fn score_apres(&self, score: Valeur, mise: Valeur) -> Valeur {
+        let total = score + mise;
+        if self.rebond && total > self.score_max {
+            (2 * self.score_max).saturating_sub(total)
+        } else {
+            total
+        }
+    }
+
+    // Points que rapporte le fait d'encaisser une mise, tels que les compte
+    // l'analyse statistique. Celle-ci ne représente pas de gain négatif, donc
+    // un rebond qui ferait reculer compte comme un tour perdu.
+    pub fn gain_encaisse(&self, score: Valeur, mise: Valeur) -> Valeur {
+        self.score_apres(score, mise).saturating_sub(score)
+    }
+
+    // Est-ce qu'un joueur a ouvert, et peut donc encaisser n'importe quelle
+    // mise? Comme on ne peut rien encaisser avant d'avoir ouvert, c'est le cas
+    // dès que son score n'est plus nul.
+    pub fn ouvert(&self, score: Valeur) -> bool {
+        score > 0 || self.seuil_ouverture == 0
+    }
+
+    // Est-ce que la règle d'ouverture et la mise minimale autorisent à
+    // encaisser une certaine mise?
+    pub fn encaissement_autorise(&self, score: Valeur, mise: Valeur) -> bool {
+        (self.ouvert(score) || mise >= self.seuil_ouverture)
+            && (mise >= self.mise_minimale_pour_stopper || self.score_gagnant(score + mise))
+    }
+
+    // Est-ce qu'une combinaison qui nous laisse relancer autant de dés oblige à
+    // relancer? C'est le cas quand la règle interdit de s'arrêter après avoir
+    // utilisé tous les dés, qu'on récupère alors tous.
+    pub fn relance_obligatoire(&self, nb_des_relance: usize) -> bool {
+        self.relance_plein && nb_des_relance == self.nb_des_tot
+    }
+
+    // Points qu'on perd en plus de sa mise si on perd ce tour-ci, après avoir
+    // déjà perdu un certain nombre de tours d'affilée. Comme ouvert se fie au
+    // score, celui d'un joueur qui a ouvert ne doit pas retomber à zéro.
+    pub fn cout_perte(&self, score: Valeur, pertes: usize) -> Valeur {
+        if self.penalite_pertes > 0 && pertes + 1 >= NB_PERTES_PENALITE {
+            let plancher = if self.seuil_ouverture > 0 {
+                self.bareme.valeur_min_de().min(score)
+            } else {
+                0
+            };
+            self.penalite_pertes.min(score - plancher)
+        } else {
+            0
+        }
+    }
+}
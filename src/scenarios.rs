@@ -0,0 +1,215 @@
+use crate::{
+    Flottant,
+    Valeur,
+    choix::{Enumeration, Histogramme},
+    combinaison::Combinaison,
+    etat::EtatTour,
+    stats::{Decision, Stats},
+};
+
+
+// Scénarios de jeu qui reviennent souvent dans les discussions entre joueurs:
+// face à un jet de dés précis, on hésite entre quelques options, et on veut
+// savoir ce que chacune rapporte en espérance de gain selon le score et la
+// mise. On ne compare que les options contestées, pas toutes celles que le
+// jet permet (pour ça, voir Stats::evaluer_options).
+
+// Option contestée dans un scénario: prendre une combinaison, puis relancer
+// les dés restants ou encaisser la mise
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OptionContestee {
+    // Description de l'option, pour l'affichage
+    pub libelle: &'static str,
+
+    // Combinaison prise
+    pub comb: Combinaison,
+
+    // Est-ce qu'on relance ensuite (sinon on encaisse)?
+    pub relancer: bool,
+}
+
+// Scénario de jeu prédéfini
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scenario {
+    // Nom court, qui sert à choisir le scénario en ligne de commande
+    pub nom: &'static str,
+
+    // Question que pose le scénario
+    pub question: &'static str,
+
+    // Faces du jet de dés étudié (numérotées à partir de 1)
+    pub faces: &'static [usize],
+
+    // Options entre lesquelles on hésite
+    pub options: Vec<OptionContestee>,
+
+    // Numéro de l'option qui doit être la meilleure au début d'une partie,
+    // avec une mise nulle et les règles par défaut: c'est ce qui fait de ces
+    // scénarios des lieux communs, et ça permet de vérifier que les calculs
+    // d'espérance n'ont pas changé de sens
+    pub meilleure_attendue: usize,
+}
+
+// Evaluation des options d'un scénario dans un certain état du tour
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvaluationScenario {
+    // Score du joueur et mise accumulée avant le jet
+    pub score: Valeur,
+    pub mise: Valeur,
+
+    // Espérance de gain de chaque option, dans l'ordre du scénario (None si la
+    // règle ne permet pas cette option dans cet état)
+    pub esperances: Vec<Option<Flottant>>,
+}
+
+impl EvaluationScenario {
+    // Numéro de la meilleure option permise (la première en cas d'égalité),
+    // ou None si aucune ne l'est
+    pub fn meilleure(&self) -> Option<usize> {
+        let mut meilleure : Option<(usize, Flottant)> = None;
+        for (num_option, esperance) in self.esperances.iter().enumerate() {
+            if let Some(esperance) = *esperance {
+                if meilleure.is_none_or(|(_, meilleure)| esperance > meilleure) {
+                    meilleure = Some((num_option, esperance));
+                }
+            }
+        }
+        meilleure.map(|(num_option, _)| num_option)
+    }
+
+    // Ce que l'option i fait perdre en espérance de gain par rapport à la
+    // meilleure, si elle est permise
+    pub fn ecart(&self, num_option: usize) -> Option<Flottant> {
+        let meilleure = self.esperances[self.meilleure()?]?;
+        self.esperances[num_option].map(|esperance| meilleure - esperance)
+    }
+}
+
+// Scénarios prédéfinis, avec les règles par défaut en tête: un 1 compte 100
+// points, un 5 compte 50, un brelan de 2 compte 200 et une suite 500
+pub fn scenarios() -> Vec<Scenario> {
+    let un = Combinaison::FacesSimples { nb_un: 1, nb_cinq: 0 };
+    let cinq = Combinaison::FacesSimples { nb_un: 0, nb_cinq: 1 };
+    let deux_cinq = Combinaison::FacesSimples { nb_un: 0, nb_cinq: 2 };
+    let brelan = Combinaison::BrelanSimple { idx_face: 1, nb_un: 0, nb_cinq: 0 };
+    let brelan_un = Combinaison::BrelanSimple { idx_face: 1, nb_un: 1, nb_cinq: 0 };
+    let un_cinq = Combinaison::FacesSimples { nb_un: 1, nb_cinq: 1 };
+    vec![
+        Scenario {
+            nom: "cinq-seul",
+            question: "Un seul 5: relancer les 5 dés restants, ou encaisser?",
+            faces: &[2, 3, 4, 5, 6, 6],
+            options: vec![
+                OptionContestee { libelle: "5, relancer 5 dés", comb: cinq, relancer: true },
+                OptionContestee { libelle: "5, encaisser", comb: cinq, relancer: false },
+            ],
+            meilleure_attendue: 0,
+        },
+        Scenario {
+            nom: "un-seul",
+            question: "Un seul 1: relancer les 5 dés restants, ou encaisser?",
+            faces: &[1, 2, 3, 4, 6, 6],
+            options: vec![
+                OptionContestee { libelle: "1, relancer 5 dés", comb: un, relancer: true },
+                OptionContestee { libelle: "1, encaisser", comb: un, relancer: false },
+            ],
+            meilleure_attendue: 0,
+        },
+        Scenario {
+            nom: "deux-cinq",
+            question: "Deux 5: n'en prendre qu'un pour relancer 5 dés, ou les deux?",
+            faces: &[2, 3, 4, 5, 5, 6],
+            options: vec![
+                OptionContestee { libelle: "5, relancer 5 dés", comb: cinq, relancer: true },
+                OptionContestee { libelle: "5+5, relancer 4 dés", comb: deux_cinq, relancer: true },
+                OptionContestee { libelle: "5+5, encaisser", comb: deux_cinq, relancer: false },
+            ],
+            meilleure_attendue: 0,
+        },
+        Scenario {
+            nom: "brelan-plus-un",
+            question: "Brelan de 2 et un 1: tout prendre, ou garder des dés à relancer?",
+            faces: &[1, 2, 2, 2, 3, 4],
+            options: vec![
+                OptionContestee { libelle: "Brelan+1, encaisser", comb: brelan_un, relancer: false },
+                OptionContestee { libelle: "Brelan+1, relancer 2 dés", comb: brelan_un, relancer: true },
+                OptionContestee { libelle: "Brelan, relancer 3 dés", comb: brelan, relancer: true },
+                OptionContestee { libelle: "1, relancer 5 dés", comb: un, relancer: true },
+            ],
+            meilleure_attendue: 3,
+        },
+        Scenario {
+            nom: "suite-ou-relance",
+            question: "Une suite: l'encaisser, ou relancer les 6 dés avec?",
+            faces: &[1, 2, 3, 4, 5, 6],
+            options: vec![
+                OptionContestee { libelle: "Suite, encaisser", comb: Combinaison::Suite, relancer: false },
+                OptionContestee { libelle: "Suite, relancer 6 dés", comb: Combinaison::Suite, relancer: true },
+                OptionContestee { libelle: "1+5, relancer 4 dés", comb: un_cinq, relancer: true },
+            ],
+            meilleure_attendue: 1,
+        },
+    ]
+}
+
+// Evaluer les options d'un scénario quand on a un certain score et une
+// certaine mise avant le jet. Retourne None si le jet ne peut pas se produire
+// dans cet état avec ces règles (voir EtatTour::verifier), ou s'il ne contient
+// pas les combinaisons du scénario.
+pub fn evaluer_scenario(stats: &Stats,
+                        scenario: &Scenario,
+                        score: Valeur,
+                        mise: Valeur) -> Option<EvaluationScenario>
+{
+    let regles = stats.regles();
+    let histo = Histogramme::depuis_faces(scenario.faces, regles).ok()?;
+    EtatTour::new(score, histo.nb_des(), mise).verifier(regles).ok()?;
+    let choix = histo.combinaisons_possibles(regles, Enumeration::Complete);
+    if scenario.options.iter().any(|option| !choix.contains(&option.comb)) {
+        return None;
+    }
+    let esperances = scenario.options.iter().map(|option| {
+        stats.decisions_possibles(score, histo.nb_des(), mise, &[option.comb])
+             .into_iter()
+             .find(|decision| matches!(decision, Decision::Relancer { .. }) == option.relancer)
+             .map(|decision| decision.esperance())
+    }).collect();
+    Some(EvaluationScenario { score, mise, esperances })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Au début d'une partie, avec les règles par défaut, c'est bien l'option
+    // attendue qui est la meilleure dans chaque scénario
+    #[test]
+    fn meilleures_options() {
+        let stats = Stats::new();
+        for scenario in scenarios() {
+            let evaluation = evaluer_scenario(&stats, &scenario, 0, 0).unwrap();
+            assert_eq!(evaluation.esperances.len(), scenario.options.len());
+            assert!(evaluation.esperances.iter().all(Option::is_some), "{}", scenario.nom);
+            assert_eq!(evaluation.meilleure(), Some(scenario.meilleure_attendue),
+                       "{}: {:?}", scenario.nom, evaluation.esperances);
+            assert_eq!(evaluation.ecart(scenario.meilleure_attendue), Some(0.));
+            assert!((0..scenario.options.len()).all(|option| {
+                evaluation.ecart(option) >= Some(0.)
+            }));
+        }
+    }
+
+    // La meilleure option est la première des plus rentables parmi celles que
+    // la règle permet
+    #[test]
+    fn meilleure_option_permise() {
+        let evaluation = |esperances| EvaluationScenario { score: 0, mise: 0, esperances };
+        let egalite = evaluation(vec![None, Some(100.), Some(100.), Some(50.)]);
+        assert_eq!(egalite.meilleure(), Some(1));
+        assert_eq!((egalite.ecart(0), egalite.ecart(2), egalite.ecart(3)),
+                   (None, Some(0.), Some(50.)));
+        let interdites = evaluation(vec![None, None]);
+        assert_eq!((interdites.meilleure(), interdites.ecart(0)), (None, None));
+    }
+
+}
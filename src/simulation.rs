@@ -0,0 +1,1064 @@
+use crate::{
+    Flottant,
+    NB_RELANCES_MAX,
+    Valeur,
+    choix::{Enumeration, Histogramme},
+    combinaison::Combinaison,
+    regles::{NB_PERTES_PENALITE, Regles},
+    stats::{Decision, OptimiseurVariance, Stats},
+};
+
+pub use crate::{
+    enregistrement::EnregistrementPartie,
+    etat::{ErreurCoup, EtatPartie, EtatTour},
+};
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fmt::{self, Display},
+    rc::Rc,
+};
+
+use rand::{
+    SeedableRng,
+    rngs::Xoshiro256PlusPlus,
+};
+
+
+// Nombre de tours au-delà duquel on considère qu'une partie ne finira jamais
+// (ce qui peut arriver avec des stratégies vraiment mauvaises)
+pub const NB_TOURS_MAX : usize = 100_000;
+
+// Nombre de parties (ou de paires, de blocs de parties) que joue chaque tâche
+// parallèle quand on simule beaucoup de parties. Chaque lot a son propre jeu,
+// dont la graine est déduite du numéro du lot, donc le résultat ne dépend pas
+// de l'ordre d'exécution des threads.
+pub const TAILLE_LOT : usize = 1000;
+
+// Quantile de la loi normale pour les intervalles de confiance à 95% qu'on
+// donne sur les résultats de ces simulations
+pub const QUANTILE_95 : f64 = 1.959964;
+
+// Action autorisée par la règle face à un jet de dés
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Action {
+    // Prendre cette combinaison et encaisser la mise qui en résulte
+    Encaisser { comb: Combinaison, mise: Valeur },
+
+    // Prendre cette combinaison, puis relancer nb_des dés avec la mise qui en
+    // résulte
+    Relancer { comb: Combinaison, mise: Valeur, nb_des: usize },
+}
+
+impl Action {
+    // Combinaison prise lors de cette action
+    pub fn combinaison(&self) -> Combinaison {
+        match *self {
+            Action::Encaisser { comb, .. } | Action::Relancer { comb, .. } => comb,
+        }
+    }
+
+    // Mise qu'on encaisse ou avec laquelle on relance après cette action
+    pub fn mise(&self) -> Valeur {
+        match *self {
+            Action::Encaisser { mise, .. } | Action::Relancer { mise, .. } => mise,
+        }
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Action::Encaisser { comb, mise } => {
+                write!(formatter, "{}, encaisser {} points", comb, mise)
+            },
+            Action::Relancer { comb, mise, nb_des } => {
+                write!(formatter, "{}, relancer {} dés avec {} points",
+                       comb, nb_des, mise)
+            },
+        }
+    }
+}
+
+// Manière de jouer à la mitraillette. Une stratégie ne doit dépendre que de
+// l'état du tour et des actions autorisées, afin qu'on puisse aussi bien la
+// simuler que calculer analytiquement ce qu'elle rapporte. Lors d'une partie,
+// elle est aussi prévenue au début de chacun de ses tours du tableau des scores.
+pub trait Strategie {
+    // Choisir une action parmi celles que la règle autorise (il y en a au
+    // moins une, sinon le tour est perdu et on ne demande rien au joueur)
+    fn choisir(&self, etat: &EtatTour, actions: &[Action]) -> Action;
+
+    // Quand la règle de reprise l'autorise, décider si on reprend les dés et
+    // la mise laissés par l'adversaire (décrits par "etat") ou si on repart de
+    // zéro avec tous les dés. Par défaut, on repart de zéro.
+    fn reprendre(&self, _etat: &EtatTour) -> bool {
+        false
+    }
+
+    // Prendre connaissance de l'état de la partie au début d'un de ses tours,
+    // avant toute décision. Par défaut, on s'en tient à l'état du tour.
+    fn commencer_tour(&self, _partie: &EtatPartie) {}
+}
+
+// Stratégie qui maximise l'espérance de gain de chaque tour
+pub struct Optimale<'stats> {
+    stats: &'stats Stats,
+}
+
+impl<'stats> Optimale<'stats> {
+    // Jouer de façon optimale selon certaines statistiques
+    pub fn new(stats: &'stats Stats) -> Self {
+        Self { stats }
+    }
+}
+
+// Pendant le dernier tour d'égalisation, il ne s'agit plus d'accumuler des
+// points mais de dépasser le score à battre, donc on maximise la probabilité
+// d'y arriver (voir Stats::proba_depasser)
+impl Strategie for Optimale<'_> {
+    fn choisir(&self, etat: &EtatTour, actions: &[Action]) -> Action {
+        let esperance = |action: &Action| -> Flottant {
+            match (*action, etat.cible) {
+                (Action::Encaisser { comb, mise }, Some(cible)) => {
+                    if self.stats.regles().fait_gagner(&comb) || etat.score + mise > cible {
+                        1.
+                    } else {
+                        0.
+                    }
+                },
+                (Action::Encaisser { mise, .. }, None) => {
+                    self.stats.regles().gain_encaisse(etat.score, mise) as Flottant
+                },
+                (Action::Relancer { mise, nb_des, .. }, _) => {
+                    let relance = EtatTour { nb_des, mise, ..*etat };
+                    self.stats.proba_depasser_etat(&relance)
+                              .unwrap_or_else(|| self.stats.esperance_etat(&relance))
+                },
+            }
+        };
+        let mut meilleure = actions[0];
+        for action in &actions[1..] {
+            if esperance(action) > esperance(&meilleure) {
+                meilleure = *action;
+            }
+        }
+        meilleure
+    }
+
+    fn reprendre(&self, etat: &EtatTour) -> bool {
+        let depart = EtatTour { nb_des: self.stats.regles().nb_des_tot, mise: 0, ..*etat };
+        match (self.stats.proba_depasser_etat(etat), self.stats.proba_depasser_etat(&depart)) {
+            (Some(reprise), Some(depart)) => reprise > depart,
+            _ => self.stats.esperance_etat(etat) > self.stats.esperance_etat(&depart),
+        }
+    }
+}
+
+// Stratégie hybride qui maximise l'espérance de gain, sauf quand l'adversaire
+// a de bonnes chances de finir la partie à son prochain tour: accumuler des
+// points ne sert alors plus à rien, et on joue le tout pour le tout pour gagner
+// ce tour-ci (voir Stats::politique_derniere_chance). A plus de deux joueurs,
+// on tient compte du risque que n'importe lequel des adversaires finisse.
+pub struct DerniereChance<'stats> {
+    stats: &'stats Stats,
+
+    // Probabilité que l'adversaire finisse au prochain tour à partir de
+    // laquelle on ne cherche plus qu'à finir ce tour-ci
+    seuil_crise: Flottant,
+
+    // Scores des adversaires au début du tour en cours, s'ils sont connus
+    scores_adverses: RefCell<Vec<Valeur>>,
+}
+
+impl<'stats> DerniereChance<'stats> {
+    // Jouer selon certaines statistiques, en passant en mode "dernière chance"
+    // quand l'adversaire a au moins une certaine probabilité de finir au
+    // prochain tour
+    pub fn new(stats: &'stats Stats, seuil_crise: Flottant) -> Self {
+        Self { stats, seuil_crise, scores_adverses: RefCell::new(Vec::new()) }
+    }
+
+    // Est-ce qu'un adversaire risque de finir au prochain tour? On évalue la
+    // probabilité de finir de chacun avec autant de relances que pour
+    // l'espérance de gain, pour profiter des calculs déjà faits. Sans tableau
+    // des scores, on ne connaît que le meilleur adversaire.
+    fn en_crise(&self, etat: &EtatTour) -> bool {
+        let nb_des_tot = self.stats.regles().nb_des_tot;
+        let proba_fin = |score_adverse| {
+            let relances = self.stats.relances_necessaires(score_adverse, nb_des_tot, 0)
+                                     .unwrap_or(NB_RELANCES_MAX);
+            self.stats.proba_fin(score_adverse, nb_des_tot, 0, relances)
+        };
+        let scores_adverses = self.scores_adverses.borrow();
+        let proba_aucun_fini = if scores_adverses.is_empty() {
+            1. - proba_fin(etat.score_adverse)
+        } else {
+            scores_adverses.iter().map(|&score| 1. - proba_fin(score)).product()
+        };
+        1. - proba_aucun_fini >= self.seuil_crise
+    }
+}
+
+impl Strategie for DerniereChance<'_> {
+    fn choisir(&self, etat: &EtatTour, actions: &[Action]) -> Action {
+        if etat.cible.is_some() || !self.en_crise(etat) {
+            return Optimale::new(self.stats).choisir(etat, actions);
+        }
+
+        // On retrouve les combinaisons tirées à partir des actions possibles,
+        // puis l'action qui correspond à la décision de dernière chance
+        let mut choix = actions.iter().map(|action| match *action {
+            Action::Encaisser { comb, .. } | Action::Relancer { comb, .. } => comb,
+        }).collect::<Vec<_>>();
+        choix.sort_unstable();
+        choix.dedup();
+        let decision = self.stats.politique_derniere_chance(etat.score,
+                                                            etat.nb_des,
+                                                            etat.mise,
+                                                            &choix);
+        actions.iter()
+               .copied()
+               .find(|action| match (decision, *action) {
+                   (Some(Decision::Encaisser { comb, .. }), Action::Encaisser { comb: c, .. })
+                   | (Some(Decision::Relancer { comb, .. }), Action::Relancer { comb: c, .. }) => {
+                       comb == c
+                   },
+                   _ => false,
+               })
+               .unwrap_or(actions[0])
+    }
+
+    fn reprendre(&self, etat: &EtatTour) -> bool {
+        Optimale::new(self.stats).reprendre(etat)
+    }
+
+    fn commencer_tour(&self, partie: &EtatPartie) {
+        *self.scores_adverses.borrow_mut() = partie.scores_adverses().collect();
+    }
+}
+
+// Stratégie qui maximise l'espérance de gain de chaque tour pénalisée par sa
+// variance, E - k * V (voir OptimiseurVariance): plus k est grand, plus elle
+// joue prudemment. Pendant le dernier tour d'égalisation, elle joue comme
+// Optimale pour dépasser le score à battre.
+pub struct MoyenneVariance<'stats> {
+    stats: &'stats Stats,
+
+    // Coefficient de pénalisation de la variance
+    k: Flottant,
+
+    // Optimisations déjà faites pour chaque score de départ
+    optimiseurs: RefCell<HashMap<Valeur, Rc<OptimiseurVariance<'stats>>>>,
+}
+
+impl<'stats> MoyenneVariance<'stats> {
+    // Jouer selon certaines statistiques, avec une certaine pénalité de
+    // variance
+    pub fn new(stats: &'stats Stats, k: Flottant) -> Self {
+        Self {
+            stats,
+            k,
+            optimiseurs: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // Optimisation du tour pour un certain score de départ
+    fn optimiseur(&self, score: Valeur) -> Rc<OptimiseurVariance<'stats>> {
+        self.optimiseurs.borrow_mut()
+                        .entry(score)
+                        .or_insert_with(|| Rc::new(OptimiseurVariance::new(self.stats, score, self.k)))
+                        .clone()
+    }
+}
+
+impl Strategie for MoyenneVariance<'_> {
+    fn choisir(&self, etat: &EtatTour, actions: &[Action]) -> Action {
+        if etat.cible.is_some() {
+            return Optimale::new(self.stats).choisir(etat, actions);
+        }
+        let optimiseur = self.optimiseur(etat.score);
+        let valeur = |action: &Action| -> Flottant {
+            match *action {
+                Action::Encaisser { mise, .. } => {
+                    self.stats.regles().gain_encaisse(etat.score, mise) as Flottant
+                },
+                Action::Relancer { mise, nb_des, .. } => optimiseur.valeur(nb_des, mise),
+            }
+        };
+        let mut meilleure = actions[0];
+        for action in &actions[1..] {
+            if valeur(action) > valeur(&meilleure) {
+                meilleure = *action;
+            }
+        }
+        meilleure
+    }
+
+    fn reprendre(&self, etat: &EtatTour) -> bool {
+        if etat.cible.is_some() {
+            return Optimale::new(self.stats).reprendre(etat);
+        }
+        let optimiseur = self.optimiseur(etat.score);
+        optimiseur.valeur(etat.nb_des, etat.mise)
+            > optimiseur.valeur(self.stats.regles().nb_des_tot, 0)
+    }
+}
+
+// Stratégie naïve qui relance tant que la mise n'a pas atteint un certain
+// seuil, puis encaisse dès que possible
+pub struct SeuilFixe(pub Valeur);
+
+impl Strategie for SeuilFixe {
+    fn choisir(&self, _etat: &EtatTour, actions: &[Action]) -> Action {
+        // On prend toujours la combinaison qui rapporte le plus de points
+        let mise_max = actions.iter().map(Action::mise).max().unwrap();
+        let encaisser = mise_max >= self.0;
+        actions.iter()
+               .copied()
+               .find(|action| match *action {
+                   Action::Encaisser { mise, .. } => encaisser && mise == mise_max,
+                   Action::Relancer { mise, .. } => !encaisser && mise == mise_max,
+               })
+               .unwrap_or(actions[0])
+    }
+}
+
+// Règle de maison qui encaisse dès que la mise atteint un certain seuil, comme
+// SeuilFixe, sauf s'il reste assez de dés pour que la relance vaille le coup
+pub struct SeuilSaufDes {
+    // Mise à partir de laquelle on encaisse
+    pub seuil: Valeur,
+
+    // Nombre de dés à relancer à partir duquel on relance quand même
+    pub nb_des_relance: usize,
+}
+
+impl Strategie for SeuilSaufDes {
+    fn choisir(&self, etat: &EtatTour, actions: &[Action]) -> Action {
+        // On prend toujours la combinaison qui rapporte le plus de points
+        let mise_max = actions.iter().map(Action::mise).max().unwrap();
+        let relance = actions.iter().copied().find(|action| {
+            matches!(*action, Action::Relancer { mise, nb_des, .. }
+                              if mise == mise_max && nb_des >= self.nb_des_relance)
+        });
+        relance.unwrap_or_else(|| SeuilFixe(self.seuil).choisir(etat, actions))
+    }
+}
+
+// Règle de maison qui ne relance jamais moins d'un certain nombre de dés: on
+// relance tant qu'on peut garder assez de dés, en prenant alors la combinaison
+// qui rapporte le plus, et on encaisse le plus possible sinon
+pub struct DesMinimum(pub usize);
+
+impl Strategie for DesMinimum {
+    fn choisir(&self, _etat: &EtatTour, actions: &[Action]) -> Action {
+        let relance_max = actions.iter()
+                                 .filter(|action| {
+                                     matches!(action, Action::Relancer { nb_des, .. }
+                                                      if *nb_des >= self.0)
+                                 })
+                                 .max_by_key(|action| action.mise());
+        let encaissement_max = actions.iter()
+                                      .filter(|action| matches!(action, Action::Encaisser { .. }))
+                                      .max_by_key(|action| action.mise());
+        match (relance_max, encaissement_max) {
+            (Some(&action), _) | (None, Some(&action)) => action,
+            // Si la règle interdit d'encaisser, on relance ce qu'on peut
+            (None, None) => *actions.iter().max_by_key(|action| action.mise()).unwrap(),
+        }
+    }
+}
+
+// Stratégie suicidaire qui relance tant que la règle le permet, en prenant la
+// combinaison qui rapporte le plus de points
+pub struct ToujoursRelancer;
+
+impl Strategie for ToujoursRelancer {
+    fn choisir(&self, _etat: &EtatTour, actions: &[Action]) -> Action {
+        let relance_max = actions.iter()
+                                 .filter(|action| matches!(action, Action::Relancer { .. }))
+                                 .max_by_key(|action| action.mise());
+        match relance_max {
+            Some(&relance) => relance,
+            None => *actions.iter().max_by_key(|action| action.mise()).unwrap(),
+        }
+    }
+}
+
+// Stratégie qui choisit une action au hasard parmi celles qui sont autorisées.
+//
+// Pour que la stratégie ne dépende que de la situation de jeu, le tirage est
+// fait en mélangeant la situation avec une graine (voir deriver_graine): on
+// joue toujours de la même façon dans une situation donnée, mais deux graines
+// donnent deux stratégies aléatoires différentes.
+//
+pub struct Aleatoire(pub u64);
+
+impl Strategie for Aleatoire {
+    fn choisir(&self, etat: &EtatTour, actions: &[Action]) -> Action {
+        let mut situation = vec![
+            self.0,
+            etat.score as u64,
+            etat.score_adverse as u64,
+            etat.nb_des as u64,
+            etat.mise as u64,
+            etat.pertes as u64,
+            etat.cible.map_or(u64::MAX, u64::from),
+        ];
+        for action in actions {
+            situation.extend_from_slice(&match *action {
+                Action::Encaisser { comb, mise } => [0, mise as u64, comb.nb_des() as u64],
+                Action::Relancer { mise, nb_des, .. } => [1, mise as u64, nb_des as u64],
+            });
+        }
+        actions[(deriver_graine(&situation) % actions.len() as u64) as usize]
+    }
+}
+
+// Graine déduite d'une suite d'entiers, en les mélangeant un par un avec la
+// fonction de splitmix64. Contrairement aux hacheurs de la bibliothèque
+// standard, le résultat ne change pas d'une version de Rust à l'autre, donc
+// les parties simulées non plus.
+fn deriver_graine(valeurs: &[u64]) -> u64 {
+    valeurs.iter().fold(0, |graine, &valeur| {
+        let mut z = (graine ^ valeur).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    })
+}
+
+// Lancer de dés effectué au cours d'un tour
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Lancer {
+    // Etat du tour au moment du lancer
+    pub etat: EtatTour,
+
+    // Dés obtenus
+    pub histo: Histogramme,
+
+    // Actions que la règle autorisait face à ces dés (aucune si le tour est
+    // perdu)
+    pub actions: Vec<Action>,
+
+    // Action choisie par le joueur (None si le tour est perdu)
+    pub action: Option<Action>,
+}
+
+impl Lancer {
+    // Etat du tour au lancer suivant, si le joueur a choisi de relancer
+    pub fn etat_suivant(&self) -> Option<EtatTour> {
+        match self.action? {
+            Action::Relancer { mise, nb_des, .. } => Some(EtatTour { mise, nb_des, ..self.etat }),
+            Action::Encaisser { .. } => None,
+        }
+    }
+}
+
+// En format alternatif ("{:#}"), on liste aussi toutes les actions qui
+// s'offraient au joueur, en marquant celle qu'il a choisie d'une étoile
+impl Display for Lancer {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.action {
+            None => write!(formatter, "{} -> perdu", self.histo)?,
+            Some(action) => write!(formatter, "{} -> {}", self.histo, action)?,
+        }
+        if formatter.alternate() {
+            for action in self.actions.iter() {
+                let marque = if Some(*action) == self.action { '*' } else { ' ' };
+                write!(formatter, "\n    {} {}", marque, action)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Résultat d'un tour de jeu
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ResultatTour {
+    // Mise encaissée à la fin du tour (nulle si le tour est perdu)
+    pub mise: Valeur,
+
+    // Lancers effectués pendant le tour
+    pub lancers: Vec<Lancer>,
+}
+
+impl ResultatTour {
+    // Est-ce que le joueur a commencé ce tour en reprenant la mise de son
+    // adversaire?
+    pub fn a_repris(&self) -> bool {
+        self.lancers[0].etat.mise > 0
+    }
+}
+
+// Résultat d'une partie entre plusieurs joueurs, qui en garde la trace
+// complète: chaque lancer, les actions possibles et celle qui a été choisie
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ResultatPartie {
+    // Nombre de joueurs ayant participé à la partie
+    pub nb_joueurs: usize,
+
+    // Numéro du joueur qui a gagné (None si la partie ne finit pas en
+    // NB_TOURS_MAX tours)
+    pub gagnant: Option<usize>,
+
+    // Tours joués, chaque joueur jouant à son tour (le joueur 0 commence)
+    pub tours: Vec<ResultatTour>,
+
+    // Numéro du premier joueur à avoir atteint le score maximal, quand la
+    // règle laisse ensuite les autres jouer un dernier tour (None sinon)
+    #[serde(default)]
+    pub premier_arrive: Option<usize>,
+
+    // Score de chaque joueur à la fin de la partie
+    #[serde(default)]
+    pub scores: Vec<Valeur>,
+}
+
+impl ResultatPartie {
+    // Nombre de tours joués, en comptant les tours de chaque joueur
+    pub fn nb_tours(&self) -> usize {
+        self.tours.len()
+    }
+}
+
+impl Display for ResultatPartie {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for (num_tour, tour) in self.tours.iter().enumerate() {
+            let etat = tour.lancers[0].etat;
+            write!(formatter, "Tour {}, joueur {} (score {}, adversaire {}",
+                   num_tour+1, num_tour % self.nb_joueurs, etat.score, etat.score_adverse)?;
+            if let Some(cible) = etat.cible {
+                write!(formatter, ", dernier tour pour dépasser {}", cible)?;
+            }
+            if tour.a_repris() {
+                write!(formatter, ", reprise de {} points avec {} dés",
+                       etat.mise, etat.nb_des)?;
+            }
+            writeln!(formatter, "):")?;
+            for lancer in tour.lancers.iter() {
+                if formatter.alternate() {
+                    writeln!(formatter, "  {:#}", lancer)?;
+                } else {
+                    writeln!(formatter, "  {}", lancer)?;
+                }
+            }
+        }
+        match self.gagnant {
+            Some(gagnant) => writeln!(formatter, "Le joueur {} gagne", gagnant),
+            None => writeln!(formatter, "Partie interrompue"),
+        }
+    }
+}
+
+// Origine des dés lancés par un jeu
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SourceDes {
+    // Un seul générateur pseudo-aléatoire, dont l'état avance au fil des
+    // lancers: les dés d'un tour dépendent de tout ce qui a été joué avant
+    Continue,
+
+    // Un générateur réinitialisé au début de chaque tour d'une partie, à
+    // partir de la graine, du numéro de la partie dans ce jeu, du tour de table
+    // et de la place du joueur (voir deriver_graine). Deux jeux de même graine
+    // donnent alors les mêmes dés au premier lancer de chaque tour, quelles
+    // que soient les décisions prises avant, et les lancers d'un tour ne
+    // divergent que si les joueurs décident autrement. Les écarts de résultat
+    // viennent donc surtout des décisions.
+    Appariee,
+
+    // Comme Appariee, mais le joueur assis à la place j reçoit les dés de la
+    // place (j + rotation) modulo le nombre de joueurs. En rejouant une partie
+    // avec chaque rotation, chaque suite de dés passe par chaque place, et les
+    // écarts de résultat entre places viennent surtout de l'ordre de jeu.
+    Tournante(usize),
+}
+
+// Moteur de jeu, qui lance de vrais dés. Tout le hasard du jeu passe par son
+// générateur de nombres pseudo-aléatoires, donc deux jeux créés avec la même
+// graine jouent exactement les mêmes parties.
+pub struct Jeu {
+    // Règles du jeu
+    regles: Regles,
+
+    // Générateur de nombres pseudo-aléatoires utilisé pour lancer les dés
+    rng: Xoshiro256PlusPlus,
+
+    // Façon dont ce générateur est initialisé, graine d'origine, et nombre de
+    // parties jouées jusqu'ici (utiles avec des dés appariés)
+    source: SourceDes,
+    graine: u64,
+    nb_parties: u64,
+
+    // Lancers imposés, qu'on utilise dans l'ordre avant de lancer de vrais dés
+    // (voir EnregistrementPartie::rejouer)
+    des_imposes: VecDeque<Histogramme>,
+}
+
+impl Jeu {
+    // Préparer un jeu avec les règles par défaut, dont les lancers sont
+    // déterminés par une graine
+    pub fn with_seed(graine: u64) -> Self {
+        Self::with_regles(Regles::default(), graine)
+    }
+
+    // Même chose, avec une variante arbitraire des règles
+    pub fn with_regles(regles: Regles, graine: u64) -> Self {
+        Self::with_source(regles, graine, SourceDes::Continue)
+    }
+
+    // Même chose, en choisissant d'où viennent les dés
+    pub fn with_source(regles: Regles, graine: u64, source: SourceDes) -> Self {
+        Self {
+            regles,
+            rng: Xoshiro256PlusPlus::seed_from_u64(graine),
+            source,
+            graine,
+            nb_parties: 0,
+            des_imposes: VecDeque::new(),
+        }
+    }
+
+    // Préparer un jeu dont les premiers lancers sont imposés. Un lancer imposé
+    // qui n'a pas le nombre de dés demandé est remplacé par un vrai lancer,
+    // donc une partie qui s'écarte des lancers imposés ne les suit plus.
+    pub(crate) fn with_des_imposes(regles: Regles,
+                                   des: impl IntoIterator<Item = Histogramme>) -> Self {
+        Self {
+            des_imposes: des.into_iter().collect(),
+            ..Self::with_regles(regles, 0)
+        }
+    }
+
+    // Règles du jeu utilisées
+    pub fn regles(&self) -> &Regles {
+        &self.regles
+    }
+
+    // Enregistrement de la dernière partie jouée avec ce jeu, avec ses règles
+    // et la graine du jeu
+    pub fn enregistrer(&self, partie: &ResultatPartie) -> EnregistrementPartie {
+        assert!(self.nb_parties > 0, "Aucune partie n'a été jouée");
+        EnregistrementPartie::new(self.regles.clone(), self.graine, self.nb_parties - 1, partie)
+    }
+
+    // Lancer un certain nombre de dés
+    pub fn lancer(&mut self, nb_des: usize) -> Histogramme {
+        if let Some(histo) = self.des_imposes.pop_front() {
+            if histo.nb_des() == nb_des { return histo; }
+            self.des_imposes.clear();
+        }
+        Histogramme::aleatoire(nb_des, &self.regles, &mut self.rng).expect("Nombre de dés invalide")
+    }
+
+    // Jouer un tour en suivant une certaine stratégie à partir d'un certain
+    // état (nombre de dés à lancer et mise préalable)
+    pub fn jouer_tour(&mut self, strategie: &dyn Strategie, etat: &EtatTour) -> ResultatTour {
+        let mut etat = *etat;
+        let mut lancers = Vec::new();
+        loop {
+            // On lance les dés, et on regarde ce que la règle autorise
+            let histo = self.lancer(etat.nb_des);
+            let choix = histo.combinaisons_possibles(&self.regles, Enumeration::Elaguee);
+            let actions = actions_possibles(&self.regles, &etat, &choix);
+
+            // ...puis on laisse le joueur choisir, s'il a le choix, en
+            // vérifiant qu'il ne triche pas
+            let action = if actions.is_empty() {
+                None
+            } else {
+                let action = strategie.choisir(&etat, &actions);
+                assert!(action.combinaison().est_realisable(&histo),
+                        "La stratégie a pris {:?}, qui n'est pas dans les dés {:?}",
+                        action.combinaison(), histo);
+                assert!(actions.contains(&action),
+                        "La stratégie a choisi une action interdite: {:?}", action);
+                Some(action)
+            };
+            let lancer = Lancer { etat, histo, actions, action };
+            let suivant = lancer.etat_suivant();
+            lancers.push(lancer);
+            match (action, suivant) {
+                (Some(_), Some(suivant)) => etat = suivant,
+                (Some(action), None) => return ResultatTour { mise: action.mise(), lancers },
+                (None, _) => return ResultatTour { mise: 0, lancers },
+            }
+        }
+    }
+
+    // Jouer une partie entre deux stratégies, jusqu'à ce que l'un des joueurs
+    // atteigne le score maximal (le joueur 0 commence)
+    pub fn jouer_partie(&mut self, strategies: [&dyn Strategie; 2]) -> ResultatPartie {
+        self.jouer_partie_a_plusieurs(&strategies)
+    }
+
+    // Même chose avec un nombre quelconque de joueurs, qui jouent chacun à
+    // leur tour dans l'ordre de "strategies". Si la règle prévoit un dernier
+    // tour d'égalisation, le premier à atteindre le score maximal ne gagne pas
+    // tout de suite: chacun des autres joue encore un tour pour dépasser le
+    // meilleur score, et à égalité, celui qui y est arrivé le premier gagne.
+    // Si la règle fixe le nombre de tours, la partie s'arrête quand chacun les
+    // a joués, et le meilleur total gagne.
+    pub fn jouer_partie_a_plusieurs(&mut self, strategies: &[&dyn Strategie]) -> ResultatPartie {
+        let nb_joueurs = strategies.len();
+        let num_partie = self.nb_parties;
+        self.nb_parties += 1;
+        let mut partie = EtatPartie::debut(nb_joueurs);
+        let mut pertes = vec![0; nb_joueurs];
+        let mut tours = Vec::new();
+        let mut reprise = None;
+        let mut premier_arrive = None;
+
+        // Pendant le dernier tour, on note qui mène et combien de joueurs
+        // doivent encore jouer
+        let mut egalisation : Option<(usize, usize)> = None;
+
+        // En un nombre fixe de tours, chaque joueur cherche à son dernier tour
+        // à dépasser le meilleur total des autres. Ce n'est le bon objectif que
+        // pour le dernier joueur, les autres ne sachant pas encore ce que
+        // feront ceux qui jouent après eux.
+        let nb_tours_fixe = self.regles.nb_tours * nb_joueurs;
+        let nb_tours_max = if nb_tours_fixe > 0 { nb_tours_fixe } else { NB_TOURS_MAX };
+        for num_tour in 0..nb_tours_max {
+            // Chaque joueur repart de zéro, à moins qu'il ne choisisse de
+            // reprendre ce que le joueur précédent vient d'encaisser
+            let joueur = partie.joueur;
+            strategies[joueur].commencer_tour(&partie);
+            let dernier_tour_fixe = nb_tours_fixe > 0
+                                    && nb_joueurs > 1
+                                    && num_tour + nb_joueurs >= nb_tours_fixe;
+            let mut etat = EtatTour {
+                pertes: pertes[joueur],
+                cible: egalisation.map(|(meneur, _)| partie.scores[meneur])
+                                  .or(dernier_tour_fixe.then_some(partie.score_adverse())),
+                ..partie.etat_tour(&self.regles)
+            };
+            if let Some((nb_des, mise)) = reprise {
+                let etat_reprise = EtatTour { nb_des, mise, ..etat };
+                if strategies[joueur].reprendre(&etat_reprise) {
+                    etat = etat_reprise;
+                }
+            }
+            let place_des = match self.source {
+                SourceDes::Continue => None,
+                SourceDes::Appariee => Some(joueur),
+                SourceDes::Tournante(rotation) => Some((joueur + rotation) % nb_joueurs),
+            };
+            if let Some(place_des) = place_des {
+                let graine = deriver_graine(&[self.graine,
+                                             num_partie,
+                                             (num_tour / nb_joueurs) as u64,
+                                             place_des as u64]);
+                self.rng = Xoshiro256PlusPlus::seed_from_u64(graine);
+            }
+            let tour = self.jouer_tour(strategies[joueur], &etat);
+
+            // Perdre trop de tours d'affilée peut coûter des points, après quoi
+            // la série de pertes repart de zéro
+            let etat_final = EtatTour { mise: tour.mise, ..etat };
+            if tour.mise == 0 {
+                partie.finir_tour(etat_final.perdre(&self.regles));
+                pertes[joueur] += 1;
+                if self.regles.penalite_pertes > 0 && pertes[joueur] >= NB_PERTES_PENALITE {
+                    pertes[joueur] = 0;
+                }
+            } else {
+                partie.finir_tour(etat_final.encaisser(&self.regles));
+                pertes[joueur] = 0;
+            }
+            let scores = &partie.scores;
+            reprise = self.reprise_possible(&tour);
+            let sexte = tour.lancers.last()
+                                    .and_then(|lancer| lancer.action)
+                                    .is_some_and(|action| {
+                                        self.regles.fait_gagner(&action.combinaison())
+                                    });
+            tours.push(tour);
+
+            // Une sexte fait toujours gagner. Sinon, pendant le dernier tour,
+            // on ne prend la tête qu'en dépassant strictement le meneur.
+            let gagnant = if let Some((meneur, restants)) = &mut egalisation {
+                if scores[joueur] > scores[*meneur] { *meneur = joueur; }
+                *restants -= 1;
+                if sexte {
+                    Some(joueur)
+                } else {
+                    (*restants == 0).then_some(*meneur)
+                }
+            } else if self.regles.score_gagnant(scores[joueur]) {
+                if self.regles.dernier_tour {
+                    premier_arrive = Some(joueur);
+                    egalisation = Some((joueur, nb_joueurs - 1));
+                }
+                (sexte || !self.regles.dernier_tour || nb_joueurs == 1).then_some(joueur)
+            } else {
+                None
+            };
+            if gagnant.is_some() {
+                let scores = partie.scores;
+                return ResultatPartie { nb_joueurs, gagnant, tours, premier_arrive, scores };
+            }
+        }
+
+        // A la fin d'une partie en un nombre fixe de tours, le meilleur total
+        // gagne, et à égalité, c'est celui qui a joué le premier
+        let scores = partie.scores;
+        let gagnant = (nb_tours_fixe > 0).then(|| {
+            (0..nb_joueurs).rev().max_by_key(|&joueur| scores[joueur]).unwrap()
+        });
+        ResultatPartie { nb_joueurs, gagnant, tours, premier_arrive, scores }
+    }
+
+    // Dés restants et mise encaissée qu'un tour laisse au joueur suivant, si
+    // la règle de reprise l'autorise à continuer à partir de là. Comme pour
+    // une relance, si tous les dés ont été utilisés, on les reprend tous.
+    fn reprise_possible(&self, tour: &ResultatTour) -> Option<(usize, Valeur)> {
+        if !self.regles.reprise { return None; }
+        let dernier_lancer = tour.lancers.last()?;
+        match dernier_lancer.action? {
+            Action::Encaisser { comb, mise } => {
+                let des_restants = dernier_lancer.etat.nb_des - comb.nb_des();
+                let nb_des = if des_restants == 0 { self.regles.nb_des_tot } else { des_restants };
+                Some((nb_des, mise))
+            },
+            Action::Relancer { .. } => None,
+        }
+    }
+}
+
+// Actions que la règle autorise face à certaines combinaisons. On applique les
+// mêmes règles que l'analyse statistique: on ne peut s'arrêter que si la
+// combinaison la plus chère ne fait pas dépasser le score maximal (à moins que
+// la règle n'autorise le dépassement), si on a atteint le seuil d'ouverture et
+// si la règle n'oblige pas à relancer tous les dés, on ne peut relancer que si
+// on n'a pas atteint le score maximal, et une sexte fait gagner la partie
+// (sauf en un nombre fixe de tours, voir Regles::fait_gagner). Pendant le
+// dernier tour d'égalisation, on peut relancer tant qu'on n'a pas dépassé le
+// score à battre. Avec la règle du rebond, on peut toujours s'arrêter, et on
+// encaisse toute la mise: c'est en l'ajoutant au score qu'on rebondit sur le
+// score maximal (voir Regles::score_apres).
+pub fn actions_possibles(regles: &Regles,
+                         etat: &EtatTour,
+                         choix: &[Combinaison]) -> Vec<Action> {
+    let mut actions = Vec::new();
+    if choix.is_empty() { return actions; }
+    let valeur_max = choix.iter()
+                          .filter(|comb| !regles.fait_gagner(comb))
+                          .map(|comb| regles.bareme.valeur(comb))
+                          .max()
+                          .unwrap_or(0);
+    let arret_possible = !regles.atterrissage_exact
+                         || regles.rebond
+                         || etat.score + etat.mise + valeur_max <= regles.score_max;
+    for &comb in choix {
+        if regles.fait_gagner(&comb) {
+            actions.push(Action::Encaisser { comb, mise: regles.score_max - etat.score });
+            continue;
+        }
+        let mise = etat.mise + regles.bareme.valeur(&comb);
+        let gain = if regles.dernier_tour || regles.rebond || regles.nb_tours > 0 {
+            mise
+        } else {
+            mise.min(regles.score_max - etat.score)
+        };
+        let des_restants = etat.nb_des - comb.nb_des();
+        let nb_des = if des_restants == 0 { regles.nb_des_tot } else { des_restants };
+        let gagnant = regles.score_gagnant(etat.score + mise);
+        if (arret_possible || gagnant)
+           && regles.encaissement_autorise(etat.score, gain)
+           && (gagnant || !regles.relance_obligatoire(nb_des))
+        {
+            actions.push(Action::Encaisser { comb, mise: gain });
+        }
+        if etat.score + mise < regles.score_max
+           || etat.cible.is_some_and(|cible| etat.score + mise <= cible)
+        {
+            actions.push(Action::Relancer { comb, mise, nb_des });
+        }
+    }
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Une stratégie qui relance toujours perd presque tous ses tours, donc
+    // presque toutes ses parties contre la stratégie optimale, qu'elle
+    // commence ou non
+    #[test]
+    fn mauvaise_strategie_perdante() {
+        let stats = Stats::with_score_max(2000);
+        let optimale = Optimale::new(&stats);
+        let mut jeu = Jeu::with_regles(stats.regles().clone(), 42);
+        let mut victoires_optimale = 0;
+        for num_partie in 0..50 {
+            let optimale_commence = num_partie % 2 == 0;
+            let strategies: [&dyn Strategie; 2] = if optimale_commence {
+                [&optimale, &ToujoursRelancer]
+            } else {
+                [&ToujoursRelancer, &optimale]
+            };
+            let partie = jeu.jouer_partie(strategies);
+            if partie.gagnant == Some(if optimale_commence { 0 } else { 1 }) {
+                victoires_optimale += 1;
+            }
+        }
+        assert!(victoires_optimale >= 40, "{} victoires sur 50", victoires_optimale);
+    }
+
+    // Une graine donnée joue toujours la même partie, lancer par lancer
+    #[test]
+    fn partie_reproductible() {
+        let regles = Regles { score_max: 1000, ..Regles::default() };
+        let jouer = || Jeu::with_regles(regles.clone(), 42).jouer_partie([&SeuilFixe(300); 2]);
+        let partie = jouer();
+        assert_eq!(jouer(), partie);
+        assert_eq!(partie.to_string(), "\
+Tour 1, joueur 0 (score 0, adversaire 0):
+  2 4 5 5 5 6 -> Brelan de 5 (500 points, 3 dés), encaisser 500 points
+Tour 2, joueur 1 (score 0, adversaire 500):
+  1 2 4 4 6 6 -> un 1 (100 points, 1 dé), relancer 5 dés avec 100 points
+  1 2 3 4 5 -> un 1 + un 5 (150 points, 2 dés), relancer 3 dés avec 250 points
+  1 3 4 -> un 1 (100 points, 1 dé), encaisser 350 points
+Tour 3, joueur 0 (score 500, adversaire 350):
+  1 1 4 4 5 6 -> deux 1 + un 5 (250 points, 3 dés), relancer 3 dés avec 250 points
+  3 4 6 -> perdu
+Tour 4, joueur 1 (score 350, adversaire 500):
+  1 2 4 5 6 6 -> un 1 + un 5 (150 points, 2 dés), relancer 4 dés avec 150 points
+  3 4 5 5 -> deux 5 (100 points, 2 dés), relancer 2 dés avec 250 points
+  5 6 -> un 5 (50 points, 1 dé), encaisser 300 points
+Tour 5, joueur 0 (score 500, adversaire 650):
+  1 2 3 3 4 4 -> un 1 (100 points, 1 dé), relancer 5 dés avec 100 points
+  2 3 4 5 5 -> deux 5 (100 points, 2 dés), relancer 3 dés avec 200 points
+  3 4 5 -> un 5 (50 points, 1 dé), relancer 2 dés avec 250 points
+  2 5 -> un 5 (50 points, 1 dé), encaisser 300 points
+Tour 6, joueur 1 (score 650, adversaire 800):
+  1 2 4 4 6 6 -> un 1 (100 points, 1 dé), relancer 5 dés avec 100 points
+  4 4 5 6 6 -> un 5 (50 points, 1 dé), relancer 4 dés avec 150 points
+  1 1 2 6 -> deux 1 (200 points, 2 dés), encaisser 350 points
+Le joueur 1 gagne
+");
+    }
+
+    // Au-delà du seuil, SeuilSaufDes relance quand même avec la meilleure
+    // combinaison si elle laisse assez de dés, et encaisse comme SeuilFixe sinon
+    #[test]
+    fn seuil_sauf_des() {
+        let (un, cinq) = (Combinaison::FacesSimples { nb_un: 1, nb_cinq: 0 },
+                          Combinaison::FacesSimples { nb_un: 0, nb_cinq: 1 });
+        let strategie = SeuilSaufDes { seuil: 300, nb_des_relance: 4 };
+        for (nb_des, mise, attendu) in [
+            (5, 300, Action::Relancer { comb: un, mise: 400, nb_des: 4 }),
+            (4, 300, Action::Encaisser { comb: un, mise: 400 }),
+            (4, 0, Action::Relancer { comb: un, mise: 100, nb_des: 3 }),
+        ] {
+            let etat = EtatTour::new(0, nb_des, mise);
+            let actions = [
+                Action::Encaisser { comb: cinq, mise: mise + 50 },
+                Action::Relancer { comb: cinq, mise: mise + 50, nb_des: nb_des - 1 },
+                Action::Encaisser { comb: un, mise: mise + 100 },
+                Action::Relancer { comb: un, mise: mise + 100, nb_des: nb_des - 1 },
+            ];
+            assert_eq!(strategie.choisir(&etat, &actions), attendu,
+                       "{} dés, mise {}", nb_des, mise);
+        }
+    }
+
+    // DesMinimum relance avec la combinaison qui rapporte le plus parmi celles
+    // qui laissent assez de dés, même si une autre rapporte plus, et encaisse
+    // le plus possible quand aucune n'en laisse assez
+    #[test]
+    fn des_minimum() {
+        let mut actions = Vec::new();
+        for (nb_un, nb_cinq, mise, nb_des) in [(1, 0, 100, 4), (2, 0, 200, 3), (2, 1, 250, 2)] {
+            let comb = Combinaison::FacesSimples { nb_un, nb_cinq };
+            actions.push(Action::Encaisser { comb, mise });
+            actions.push(Action::Relancer { comb, mise, nb_des });
+        }
+        let etat = EtatTour::new(0, 5, 0);
+        let deux_un = Combinaison::FacesSimples { nb_un: 2, nb_cinq: 0 };
+        assert_eq!(DesMinimum(3).choisir(&etat, &actions),
+                   Action::Relancer { comb: deux_un, mise: 200, nb_des: 3 });
+        assert_eq!(DesMinimum(5).choisir(&etat, &actions),
+                   Action::Encaisser { comb: Combinaison::FacesSimples { nb_un: 2, nb_cinq: 1 },
+                                       mise: 250 });
+    }
+
+    // Joueur qui s'arrête à 300 points, et reprend toujours ce que lui laisse
+    // le joueur précédent
+    struct Repreneur;
+
+    impl Strategie for Repreneur {
+        fn choisir(&self, etat: &EtatTour, actions: &[Action]) -> Action {
+            SeuilFixe(300).choisir(etat, actions)
+        }
+
+        fn reprendre(&self, _etat: &EtatTour) -> bool {
+            true
+        }
+    }
+
+    // Avec la règle de reprise, chaque tour qui suit un tour encaissé part des
+    // dés restants et de la mise encaissée, et les autres tours partent de
+    // zéro avec tous les dés. Le score de chacun est la somme de ses mises.
+    #[test]
+    fn reprise_des_et_mise() {
+        for reprise in [false, true] {
+            let regles = Regles { score_max: 2000, reprise, ..Regles::default() };
+            let partie = Jeu::with_regles(regles, 42).jouer_partie([&Repreneur; 2]);
+            let mut nb_reprises = 0;
+            for (precedent, tour) in partie.tours.iter().zip(&partie.tours[1..]) {
+                let dernier = precedent.lancers.last().unwrap();
+                let depart = match dernier.action {
+                    Some(Action::Encaisser { comb, mise }) if reprise => {
+                        let des_restants = dernier.etat.nb_des - comb.nb_des();
+                        (if des_restants == 0 { 6 } else { des_restants }, mise)
+                    },
+                    _ => (6, 0),
+                };
+                let etat = tour.lancers[0].etat;
+                assert_eq!((etat.nb_des, etat.mise), depart);
+                assert_eq!(tour.a_repris(), depart.1 > 0);
+                nb_reprises += tour.a_repris() as usize;
+            }
+            assert_eq!(nb_reprises > 0, reprise);
+
+            let mut scores = [0; 2];
+            for (num_tour, tour) in partie.tours.iter().enumerate() {
+                let joueur = num_tour % 2;
+                assert_eq!(tour.lancers[0].etat.score, scores[joueur]);
+                scores[joueur] += tour.mise;
+            }
+            assert_eq!(scores[partie.gagnant.unwrap()], 2000);
+        }
+    }
+
+    // Les graines dérivées ne dépendent pas de la version de Rust
+    #[test]
+    fn graines_stables() {
+        assert_eq!(deriver_graine(&[0]), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(deriver_graine(&[42, 0, 3, 1]), 0x2b35_707c_269c_7871);
+    }
+
+    // Aux dés appariés, chaque place reçoit les mêmes dés au premier lancer
+    // de chaque tour, quels que soient les joueurs qui y sont assis
+    #[test]
+    fn des_apparies() {
+        let regles = Regles { score_max: 2000, ..Regles::default() };
+        let (prudent, audacieux) = (SeuilFixe(300), SeuilFixe(1000));
+        let jouer = |strategies: [&dyn Strategie; 2]| {
+            Jeu::with_source(regles.clone(), 42, SourceDes::Appariee).jouer_partie(strategies)
+        };
+        let premiere = jouer([&prudent, &audacieux]);
+        let seconde = jouer([&audacieux, &prudent]);
+        assert_ne!(premiere, seconde);
+        for (tour_1, tour_2) in premiere.tours.iter().zip(&seconde.tours) {
+            assert_eq!(tour_1.lancers[0].histo, tour_2.lancers[0].histo);
+        }
+    }
+}
@@ -0,0 +1,412 @@
+use crate::{
+    Flottant,
+    Valeur,
+    stats::Stats,
+};
+
+use rayon::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+};
+
+
+// Précision avec laquelle on calcule les probabilités de gagner lors du tour
+// qui commence
+const PRECISION_ATTERRISSAGE : Flottant = 1e-6;
+
+// Une ligne de la table des espérances de gain: ce que rapporte en moyenne le
+// fait de lancer un certain nombre de dés avec une certaine mise
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LigneEsperance {
+    // Score acquis lors des tours précédents
+    pub score: Valeur,
+
+    // Nombre de dés qu'on lance
+    pub nb_des: usize,
+
+    // Mise accumulée pendant ce tour
+    pub mise: Valeur,
+
+    // Gain moyen du lancer par rapport au fait d'encaisser la mise
+    pub gain_moyen: Flottant,
+
+    // Ecart-type du gain autour de cette moyenne
+    pub ecart_type: Flottant,
+
+    // Probabilité de finir le tour sans rien encaisser
+    pub proba_perte: Flottant,
+
+    // Gain moyen par rapport au fait d'encaisser la mise, quand on ne perd pas
+    pub gain_moyen_si_reussite: Flottant,
+}
+
+// Une ligne de la table des "atterrissages": probabilité de gagner la partie
+// lors de ce tour, en partant d'un certain score, et en lançant un certain
+// nombre de dés avec une certaine mise (par défaut, en début de tour)
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LigneAtterrissage {
+    // Score acquis lors des tours précédents
+    pub score: Valeur,
+
+    // Nombre de dés qu'on lance
+    pub nb_des: usize,
+
+    // Mise accumulée pendant ce tour
+    pub mise: Valeur,
+
+    // Probabilité de gagner la partie ce tour-ci
+    pub proba: Flottant,
+
+    // Nombre de relances autorisées qu'il a fallu étudier pour le calcul
+    pub relances: usize,
+
+    // Augmentation de la probabilité lors de la dernière relance étudiée
+    pub precision: Flottant,
+
+    // Est-ce que le calcul a atteint la précision voulue? Sinon, la
+    // probabilité est sous-estimée.
+    pub converge: bool,
+}
+
+// Une case de la grille dense des gains moyens, qui couvre tous les scores
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LigneGrille {
+    // Score acquis lors des tours précédents
+    pub score: Valeur,
+
+    // Nombre de dés qu'on lance
+    pub nb_des: usize,
+
+    // Mise accumulée pendant ce tour
+    pub mise: Valeur,
+
+    // Gain moyen du lancer par rapport au fait d'encaisser la mise
+    pub gain_moyen: Flottant,
+}
+
+// Ensemble des tables calculées par défaut
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Tables {
+    // Espérances de gain à score nul
+    pub esperances: Vec<LigneEsperance>,
+
+    // Probabilités de gagner lors du tour en fin de partie
+    pub atterrissages: Vec<LigneAtterrissage>,
+}
+
+impl Tables {
+    // Calculer les tables (en parallèle, mais les lignes restent bien ordonnées)
+    pub fn new(stats: &Stats) -> Self {
+        Self {
+            esperances: table_esperances(stats, 0),
+            atterrissages: table_atterrissages(stats),
+        }
+    }
+}
+
+// Table des espérances de gain à un certain score, par nombre de dés croissant
+// puis par mise croissante, en omettant les situations impossibles
+pub fn table_esperances(stats: &Stats, score: Valeur) -> Vec<LigneEsperance> {
+    let regles = stats.regles();
+    let mises = stats.mises_atteignables();
+    let etats = (1..=regles.nb_des_tot)
+        .flat_map(|nb_des| mises.mises(nb_des).map(move |mise| (nb_des, mise)))
+        .filter(|&(nb_des, mise)| !mises.jet_impossible(score, nb_des, mise))
+        .collect::<Vec<_>>();
+    etats.par_iter().map(|&(nb_des, mise)| {
+        let bilan = stats.bilan(score, nb_des, mise);
+        LigneEsperance {
+            score,
+            nb_des,
+            mise,
+            gain_moyen: bilan.esperance,
+            ecart_type: stats.variance_gain(score, nb_des, mise).sqrt(),
+            proba_perte: bilan.proba_perte,
+            gain_moyen_si_reussite: bilan.gain_moyen_si_reussite,
+        }
+    }).collect()
+}
+
+// Table des probabilités de gagner lors du tour qui commence, pour les scores
+// des 2000 derniers points par ordre décroissant
+pub fn table_atterrissages(stats: &Stats) -> Vec<LigneAtterrissage> {
+    let nb_des_tot = stats.regles().nb_des_tot;
+    let scores = scores_atterrissage(stats).collect::<Vec<_>>();
+    scores.par_iter().map(|&score| {
+        ligne_atterrissage(stats, score, nb_des_tot, 0)
+    }).collect()
+}
+
+// Grille des probabilités de gagner ce tour-ci pour les mêmes scores, mais en
+// lançant n'importe quel nombre de dés avec toutes les mises atteignables
+// multiples de "pas_mise" jusqu'à "mise_max", en omettant les situations
+// impossibles. La table précédente en est la colonne à 6 dés et mise nulle.
+// Comme pour la grille des gains, on produit une rangée par score, calculée
+// en parallèle, par nombre de dés croissant puis mise croissante.
+pub fn grille_atterrissages(stats: &Stats,
+                            pas_mise: Valeur,
+                            mise_max: Valeur) -> impl Iterator<Item=Vec<LigneAtterrissage>> + '_
+{
+    let nb_des_tot = stats.regles().nb_des_tot;
+    let mises = stats.mises_atteignables();
+    scores_atterrissage(stats).map(move |score| {
+        let etats_rangee = (1..=nb_des_tot)
+            .flat_map(|nb_des| mises.mises(nb_des).map(move |mise| (nb_des, mise)))
+            .filter(|&(_, mise)| mise % pas_mise == 0 && mise <= mise_max)
+            .filter(|&(nb_des, mise)| !mises.jet_impossible(score, nb_des, mise))
+            .collect::<Vec<_>>();
+        etats_rangee.par_iter().map(|&(nb_des, mise)| {
+            ligne_atterrissage(stats, score, nb_des, mise)
+        }).collect()
+    })
+}
+
+// Scores de fin de partie pour lesquels on étudie les atterrissages, par
+// ordre décroissant
+fn scores_atterrissage(stats: &Stats) -> impl Iterator<Item=Valeur> {
+    let score_max = stats.regles().score_max;
+    (score_max.saturating_sub(2000)..score_max).rev().filter(|s| s % 50 == 0)
+}
+
+// Probabilité de gagner ce tour-ci dans une certaine situation
+fn ligne_atterrissage(stats: &Stats,
+                      score: Valeur,
+                      nb_des: usize,
+                      mise: Valeur) -> LigneAtterrissage
+{
+    let proba_fin = stats.proba_fin_convergee(score, nb_des, mise, PRECISION_ATTERRISSAGE);
+    LigneAtterrissage {
+        score,
+        nb_des,
+        mise,
+        proba: proba_fin.proba,
+        relances: proba_fin.relances_utilisees,
+        precision: proba_fin.ecart,
+        converge: proba_fin.converge,
+    }
+}
+
+// Grille des gains moyens en lançant N dés, pour des scores espacés de
+// "pas_score" et toutes les mises atteignables multiples de "pas_mise", en
+// omettant les situations impossibles. On calcule les rangées de score une par
+// une, chacune en parallèle, pour qu'on puisse les écrire au fur et à mesure.
+// Toutes les rangées passent par les mêmes caches, donc une rangée profite de
+// ce qui a été calculé pour ses voisines.
+pub fn grille_gains(stats: &Stats,
+                    nb_des: usize,
+                    pas_score: Valeur,
+                    pas_mise: Valeur) -> impl Iterator<Item=Vec<LigneGrille>> + '_
+{
+    let mises = stats.mises_atteignables();
+    (0..stats.regles().score_max).step_by(pas_score as usize).map(move |score| {
+        let mises_rangee = mises.mises(nb_des)
+            .filter(|&mise| mise % pas_mise == 0)
+            .filter(|&mise| !mises.jet_impossible(score, nb_des, mise))
+            .collect::<Vec<_>>();
+        mises_rangee.par_iter().map(|&mise| {
+            LigneGrille {
+                score,
+                nb_des,
+                mise,
+                gain_moyen: stats.gain_moyen(score, nb_des, mise),
+            }
+        }).collect()
+    })
+}
+
+// Ecriture des tables au format JSON
+pub fn ecrire_json(tables: &Tables, sortie: impl Write) -> io::Result<()> {
+    serde_json::to_writer_pretty(sortie, tables).map_err(io::Error::from)
+}
+
+// Ecriture des tables au format CSV: une section par table, chacune avec sa
+// ligne d'en-tête, séparées par une ligne vide. Comme en JSON, les situations
+// impossibles sont omises plutôt qu'écrites comme des cellules vides.
+pub fn ecrire_csv(tables: &Tables, mut sortie: impl Write) -> io::Result<()> {
+    writeln!(sortie, "score,nb_des,mise,gain_moyen,ecart_type,proba_perte,gain_moyen_si_reussite")?;
+    for ligne in tables.esperances.iter() {
+        writeln!(sortie, "{},{},{},{},{},{},{}",
+                 ligne.score, ligne.nb_des, ligne.mise,
+                 ligne.gain_moyen, ligne.ecart_type, ligne.proba_perte,
+                 ligne.gain_moyen_si_reussite)?;
+    }
+    writeln!(sortie)?;
+    writeln!(sortie, "score,nb_des,mise,proba,relances,precision,converge")?;
+    for ligne in tables.atterrissages.iter() {
+        writeln!(sortie, "{},{},{},{},{},{},{}",
+                 ligne.score, ligne.nb_des, ligne.mise, ligne.proba, ligne.relances,
+                 ligne.precision, ligne.converge)?;
+    }
+    Ok(())
+}
+
+// Ecriture des tables au format Markdown, pour les coller dans un wiki ou un
+// ticket. Les espérances sont présentées sous forme de grille (une ligne par
+// mise, une colonne par nombre de dés), les situations impossibles donnant des
+// cellules vides.
+pub fn ecrire_markdown(tables: &Tables, mut sortie: impl Write) -> io::Result<()> {
+    // Les lignes et les colonnes de la grille sont celles présentes dans la table
+    let mises = tables.esperances.iter().map(|l| l.mise).collect::<BTreeSet<_>>();
+    let nb_des_max = tables.esperances.iter().map(|l| l.nb_des).max().unwrap_or(0);
+    let mut entetes = vec!["Mise".to_owned()];
+    entetes.extend((1..=nb_des_max).map(|nb_des| {
+        format!("{} dé{}", nb_des, if nb_des > 1 { "s" } else { "" })
+    }));
+    let lignes = mises.into_iter().map(|mise| {
+        let mut ligne = vec![mise.to_string()];
+        ligne.extend((1..=nb_des_max).map(|nb_des| {
+            tables.esperances.iter()
+                  .find(|l| l.nb_des == nb_des && l.mise == mise)
+                  .map(|l| format!("{:+.1}", l.gain_moyen))
+                  .unwrap_or_default()
+        }));
+        ligne
+    }).collect::<Vec<_>>();
+    writeln!(sortie, "## Gain moyen à score nul\n")?;
+    write!(sortie, "{}", tableau_markdown(&entetes, &lignes))?;
+
+    // Les atterrissages tiennent en deux colonnes
+    let entetes = ["Score".to_owned(), "Probabilité de gagner ce tour".to_owned()];
+    let lignes = tables.atterrissages.iter().map(|l| {
+        vec![l.score.to_string(), format!("{:.2}%", l.proba * 100.)]
+    }).collect::<Vec<_>>();
+    writeln!(sortie, "\n## Probabilité de gagner ce tour-ci\n")?;
+    write!(sortie, "{}", tableau_markdown(&entetes, &lignes))
+}
+
+// Mise en forme d'un tableau Markdown aux colonnes alignées. Tout le contenu
+// étant numérique, on aligne les cellules à droite.
+fn tableau_markdown(entetes: &[String], lignes: &[Vec<String>]) -> String {
+    // On mesure chaque colonne en caractères (et pas en octets, à cause des
+    // accents des en-têtes)
+    let largeur = |texte: &str| texte.chars().count();
+    let mut largeurs = entetes.iter().map(|e| largeur(e).max(3)).collect::<Vec<_>>();
+    for ligne in lignes {
+        for (largeur_col, cellule) in largeurs.iter_mut().zip(ligne) {
+            *largeur_col = (*largeur_col).max(largeur(cellule));
+        }
+    }
+
+    // Puis on écrit l'en-tête, la ligne de séparation, et le contenu
+    let ecrire_ligne = |cellules: &[String]| {
+        let cellules = cellules.iter().zip(&largeurs).map(|(cellule, &largeur_col)| {
+            format!("{:>1$}", cellule, largeur_col)
+        }).collect::<Vec<_>>();
+        format!("| {} |\n", cellules.join(" | "))
+    };
+    let separateurs = largeurs.iter().map(|&l| format!("{}:", "-".repeat(l-1)))
+                                     .collect::<Vec<_>>();
+    let mut resultat = ecrire_ligne(entetes);
+    resultat.push_str(&ecrire_ligne(&separateurs));
+    for ligne in lignes {
+        resultat.push_str(&ecrire_ligne(ligne));
+    }
+    resultat
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        regles::Regles,
+        simulation::{Jeu, Optimale},
+    };
+
+    // Deux statistiques préparées séparément donnent exactement les mêmes
+    // tables, et font jouer exactement la même partie à la stratégie optimale
+    // pour une même graine
+    #[test]
+    fn tables_reproductibles() {
+        let regles = Regles { score_max: 1000, ..Regles::default() };
+        let stats = [Stats::with_regles(regles.clone()), Stats::with_regles(regles.clone())];
+        let tables = stats.iter().map(Tables::new).collect::<Vec<_>>();
+        assert_eq!(tables[0], tables[1]);
+        let rendus = tables.iter().map(|tables| {
+            let mut sortie = Vec::new();
+            ecrire_markdown(tables, &mut sortie).unwrap();
+            sortie
+        }).collect::<Vec<_>>();
+        assert_eq!(rendus[0], rendus[1]);
+
+        let parties = stats.iter().map(|stats| {
+            let optimale = Optimale::new(stats);
+            Jeu::with_regles(regles.clone(), 42).jouer_partie([&optimale, &optimale])
+        }).collect::<Vec<_>>();
+        assert_eq!(parties[0], parties[1]);
+    }
+
+    // Petite grille des gains moyens: une rangée par score, avec seulement
+    // les mises multiples du pas demandé qui restent sous le score maximal
+    #[test]
+    fn petite_grille() {
+        let stats = Stats::with_score_max(1000);
+        let grille = grille_gains(&stats, 6, 500, 250).collect::<Vec<_>>();
+        assert_eq!(grille.len(), 2);
+        for (rangee, score) in grille.iter().zip([0, 500]) {
+            assert_eq!(rangee[0].mise, 0);
+            assert!(rangee.windows(2).all(|paire| paire[0].mise < paire[1].mise));
+            for ligne in rangee {
+                assert_eq!((ligne.score, ligne.nb_des, ligne.mise % 250), (score, 6, 0));
+                assert!(score + ligne.mise < 1000);
+                assert_eq!(ligne.gain_moyen, stats.gain_moyen(score, 6, ligne.mise));
+            }
+        }
+    }
+
+    // Rendu Markdown de petites tables, avec une situation impossible (1 dé
+    // sans mise) et un gain négatif
+    #[test]
+    fn rendu_markdown() {
+        let esperance = |nb_des, mise, gain_moyen| LigneEsperance {
+            score: 0,
+            nb_des,
+            mise,
+            gain_moyen,
+            ecart_type: 0.,
+            proba_perte: 0.,
+            gain_moyen_si_reussite: 0.,
+        };
+        let atterrissage = |score, proba| LigneAtterrissage {
+            score,
+            nb_des: 6,
+            mise: 0,
+            proba,
+            relances: 1,
+            precision: 0.,
+            converge: true,
+        };
+        let tables = Tables {
+            esperances: vec![
+                esperance(1, 50, 12.34),
+                esperance(1, 1000, -245.),
+                esperance(2, 0, 132.05),
+                esperance(2, 50, 98.7),
+                esperance(2, 1000, -56.78),
+            ],
+            atterrissages: vec![atterrissage(9950, 0.6651), atterrissage(9000, 0.0123)],
+        };
+        let mut sortie = Vec::new();
+        ecrire_markdown(&tables, &mut sortie).unwrap();
+        assert_eq!(String::from_utf8(sortie).unwrap(), "\
+## Gain moyen à score nul
+
+| Mise |   1 dé |  2 dés |
+| ---: | -----: | -----: |
+|    0 |        | +132.1 |
+|   50 |  +12.3 |  +98.7 |
+| 1000 | -245.0 |  -56.8 |
+
+## Probabilité de gagner ce tour-ci
+
+| Score | Probabilité de gagner ce tour |
+| ----: | ----------------------------: |
+|  9950 |                        66.51% |
+|  9000 |                         1.23% |
+");
+    }
+}
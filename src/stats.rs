@@ -1,41 +1,152 @@
 use crate::{
     Flottant,
-    NB_DES_TOT,
-    SCORE_MAX,
+    NB_RELANCES_MAX,
     Valeur,
-    choix,
+    choix::{self, Enumeration, Histogramme},
     combinaison::Combinaison,
+    course::{Duel, PolitiqueCourse},
+    etat::EtatTour,
+    mises::MisesAtteignables,
+    regles::Regles,
+    simulation::{self, Action, SeuilFixe, Strategie},
 };
 
+use log::info;
+
+use rayon::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
 use std::{
-    cell::RefCell,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     fmt::{self, Debug},
+    sync::{Arc, Mutex},
 };
 
+mod arbre;
+mod cache;
+mod progression;
+mod sauvegarde;
+mod utilite;
+mod variance;
+pub use arbre::{Arbre, ChoixArbre, OptionArbre, ecrire_dot};
+pub use cache::StatistiquesCache;
+pub use progression::Progression;
+pub use utilite::OptimiseurUtilite;
+pub use variance::OptimiseurVariance;
+use cache::Cache;
+use progression::SuiviProgression;
+
+
+// Ecart relatif en-dessous duquel on considère que l'espérance de gain a
+// convergé quand on autorise une relance de plus. On garde une marge de
+// quelques ULPs en simple précision, car les erreurs d'arrondi peuvent faire
+// osciller le résultat, et en double précision il faudrait beaucoup trop de
+// relances pour aller au bout de la convergence.
+const PRECISION_CONVERGENCE : Flottant = 1e-6;
+
+// Nombre maximal de relances autorisées qu'on s'accorde pour faire converger la
+// probabilité de finir la partie. En fin de partie, elle converge bien avant,
+// et plus tôt dans la partie, les calculs deviendraient de toute façon
+// interminables.
+const NB_RELANCES_CONVERGENCE_MAX : usize = 40;
+
+// Ecart d'espérance de gain en-dessous duquel on considère que deux options
+// face à un jet de dés se valent en pratique
+const ECART_NEGLIGEABLE : Flottant = 1.;
 
 // Ce qu'on sait sur les lancers de dés à la mitraillette
 pub struct Stats {
+    // Règles du jeu étudiées
+    regles: Regles,
+
     // Données pour chaque nombre de dés
     stats_jets: Box<[StatsJet]>,
+
+    // Mises qu'on peut avoir accumulées en lançant chaque nombre de dés
+    mises: MisesAtteignables,
+
+    // Suivi de l'avancement des calculs
+    suivi: SuiviProgression,
 }
 
 // Ce qu'on sait sur le lancer d'un certain nombre de dés
+#[derive(Deserialize, Serialize)]
 struct StatsJet {
     // Choix auxquels on peut faire face si on tire des combinaisons
     stats_choix: Box<[StatsChoix]>,
 
-    // On garde en cache l'espérance de gain pour un certain score de départ,
-    // une mise qu'on possédait avant de lancer les dés, et un nombre de
-    // relances maximal. Cela évite de recalculer plein de fois la même chose en
-    // étudiant les relances de dés.
-    esperance: RefCell<HashMap<(Valeur, Valeur, usize), Flottant>>,
+    // Probabilité de ne tirer aucune combinaison
+    proba_rien: Flottant,
+
+    // On garde en cache l'espérance de gain et la probabilité de finir la
+    // partie pour un certain score de départ, une mise qu'on possédait avant de
+    // lancer les dés, et un nombre de relances maximal. Cela évite de
+    // recalculer plein de fois la même chose en étudiant les relances de dés.
+    // Les caches sont protégés par des mutex pour qu'on puisse utiliser les
+    // statistiques depuis plusieurs threads.
+    esperance_fin: Mutex<Cache<(Valeur, Valeur, usize), EsperanceFin>>,
+
+    // Même topo avec l'espérance du carré de la mise encaissée en fin de tour,
+    // pour une stratégie qui maximise l'espérance (utile pour la variance)
+    moment2: CacheFlottants,
+
+    // Même topo avec la probabilité de finir le tour sans rien encaisser, pour
+    // une stratégie qui maximise l'espérance
+    proba_perte: CacheFlottants,
+
+    // Même topo avec la distribution de probabilité de la mise encaissée en
+    // fin de tour, pour une stratégie qui maximise l'espérance
+    distribution: Mutex<Cache<(Valeur, Valeur, usize), Distribution>>,
+
+    // Même topo avec l'espérance de l'utilité exponentielle de la mise
+    // encaissée, pour une stratégie qui la maximise. Le nombre de relances
+    // n'est pas limité (voir OptimiseurUtilite), mais il faut savoir pour
+    // quelle aversion au risque on a calculé, d'où la représentation binaire
+    // de ce paramètre dans la clé.
+    utilite_exponentielle: Mutex<Cache<(Valeur, Valeur, u64), Flottant>>,
+
+    // Même topo avec l'espérance de gain quand perdre ce tour-ci coûte une
+    // pénalité en plus de la mise (voir esperance_pertes), pour une stratégie
+    // qui la maximise. La pénalité ne dépend que du score, d'où la clé (score,
+    // mise), et comme pour l'utilité, le nombre de relances n'est pas limité.
+    esperance_penalisee: Mutex<Cache<(Valeur, Valeur), Flottant>>,
 
-    // Même topo avec la probabilité de finir la partie
-    proba_fin: RefCell<HashMap<(Valeur, Valeur, usize), Flottant>>,
+    // Même topo avec la probabilité de dépasser un score cible (voir
+    // proba_depasser), qui ne dépend que des points qui manquent encore
+    proba_depasser: Mutex<Cache<Valeur, Flottant>>,
 }
 
+// Espérance de gain et probabilité de finir la partie pour un état du tour.
+// Les deux se calculent en parcourant le même arbre de relances, on les calcule
+// donc ensemble, chacune pour la stratégie qui la maximise.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+struct EsperanceFin {
+    esperance: Flottant,
+    proba_fin: Flottant,
+}
+
+// Utilité exponentielle d'une mise encaissée, pour une aversion au risque λ
+fn utilite_exponentielle(lambda: Flottant, mise: Valeur) -> Flottant {
+    1. - (-lambda * mise as Flottant).exp()
+}
+
+// Représentation binaire d'un flottant, qu'on peut utiliser comme clé de cache
+// (la conversion est inutile quand on calcule en double précision)
+#[allow(clippy::useless_conversion)]
+fn bits_flottant(x: Flottant) -> u64 {
+    u64::from(x.to_bits())
+}
+
+// Cache associant une grandeur à un état (score, mise, relances max) du tour
+type CacheFlottants = Mutex<Cache<(Valeur, Valeur, usize), Flottant>>;
+
+// Distribution de probabilité de la mise encaissée en fin de tour, sous forme
+// de couples (mise, probabilité) triés par mise croissante
+type Distribution = Arc<[(Valeur, Flottant)]>;
+
 // L'un dex choix face auxquels un jet de dés peut nous placer
+#[derive(Deserialize, Serialize)]
 struct StatsChoix {
     // Combinaisons entre lesquels il faut choisir
     choix: Box<[Possibilite]>,
@@ -45,15 +156,16 @@ struct StatsChoix {
 }
 
 // L'une des possibilités entre lesquelles il faut alors choisir
-struct Possibilite {
+#[derive(Deserialize, Serialize)]
+pub(crate) struct Possibilite {
     // Combinaison qu'on décide ou non de choisir
-    comb: Combinaison,
+    pub(crate) comb: Combinaison,
 
     // Valeur de cette combinaison
-    valeur: Valeur,
+    pub(crate) valeur: Valeur,
 
     // Nombre de dés avec lequel on peut relancer ensuite
-    nb_des_relance: usize,
+    pub(crate) nb_des_relance: usize,
 }
 
 impl Debug for Possibilite {
@@ -63,16 +175,164 @@ impl Debug for Possibilite {
     }
 }
 
+impl Possibilite {
+    // Caractériser une combinaison tirée en lançant un certain nombre de dés
+    pub(crate) fn new(comb: Combinaison, nb_des: usize, regles: &Regles) -> Self {
+        let valeur = regles.bareme.valeur(&comb);
+        let des_restants = nb_des - comb.nb_des();
+        let nb_des_relance = if des_restants == 0 {
+            regles.nb_des_tot
+        } else {
+            des_restants
+        };
+        Self {
+            comb,
+            valeur,
+            nb_des_relance,
+        }
+    }
+}
+
+// Décision à prendre face à un jet de dés, pour une stratégie qui maximise
+// l'espérance de gain
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decision {
+    // Prendre cette combinaison et encaisser la mise qui en résulte
+    Encaisser {
+        comb: Combinaison,
+        mise: Valeur,
+        esperance: Flottant,
+    },
+
+    // Prendre cette combinaison, puis relancer nb_des dés avec la mise qui en
+    // résulte, en s'autorisant encore max_relances relances par la suite
+    // (usize::MAX si le nombre de relances n'est pas limité)
+    Relancer {
+        comb: Combinaison,
+        mise: Valeur,
+        nb_des: usize,
+        max_relances: usize,
+        esperance: Flottant,
+    },
+}
+
+impl Decision {
+    // Combinaison qu'on a décidé de prendre
+    pub fn combinaison(&self) -> Combinaison {
+        match *self {
+            Decision::Encaisser { comb, .. } | Decision::Relancer { comb, .. } => comb,
+        }
+    }
+
+    // Mise qu'on encaisse ou avec laquelle on relance après cette décision
+    pub fn mise(&self) -> Valeur {
+        match *self {
+            Decision::Encaisser { mise, .. } | Decision::Relancer { mise, .. } => mise,
+        }
+    }
+
+    // Espérance de gain associée à cette décision (ou espérance d'utilité, si
+    // la décision vient d'un OptimiseurUtilite)
+    pub fn esperance(&self) -> Flottant {
+        match *self {
+            Decision::Encaisser { esperance, .. }
+            | Decision::Relancer { esperance, .. } => esperance,
+        }
+    }
+}
+
+// Une option qui s'offre au joueur face à un jet de dés, comparée à la
+// meilleure d'entre elles
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OptionEvaluee {
+    // Décision correspondante, avec son espérance de gain
+    pub decision: Decision,
+
+    // Ce qu'on perd en espérance de gain par rapport à la meilleure option
+    pub ecart: Flottant,
+
+    // Est-ce que cette option vaut à peu près la meilleure, au point de ne pas
+    // valoir la peine d'hésiter entre les deux?
+    pub equivalente: bool,
+}
+
+// Gain moyen d'un lancer de dés, accompagné de son écart-type
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatistiquesGain {
+    // Gain moyen par rapport au fait d'encaisser la mise
+    pub moyenne: Flottant,
+
+    // Ecart-type du gain autour de cette moyenne
+    pub ecart_type: Flottant,
+}
+
+// Gain moyen d'un lancer de dés, décomposé en ce que les joueurs veulent
+// savoir: à quel point on risque de tout perdre, et ce qu'on gagne sinon. On a
+// esperance = (1 - proba_perte) * gain_moyen_si_reussite - proba_perte * mise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bilan {
+    // Gain moyen par rapport au fait d'encaisser la mise (comme gain_moyen)
+    pub esperance: Flottant,
+
+    // Probabilité de finir le tour sans rien encaisser
+    pub proba_perte: Flottant,
+
+    // Gain moyen par rapport au fait d'encaisser la mise, parmi les tours où
+    // on encaisse quelque chose (0 si on perd à coup sûr)
+    pub gain_moyen_si_reussite: Flottant,
+}
+
+// Ce qu'une stratégie perd en espérance de gain dans un état du tour, par
+// rapport à la stratégie optimale (voir Stats::pertes_strategie)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PerteEtat {
+    // Etat du tour, au moment de lancer les dés
+    pub etat: EtatTour,
+
+    // Probabilité que la stratégie passe par cet état
+    pub proba: Flottant,
+
+    // Espérance de gain perdue par la décision prise face aux dés, quand la
+    // stratégie passe par cet état
+    pub perte: Flottant,
+}
+
+impl PerteEtat {
+    // Part de cet état dans ce que la stratégie perd au total
+    pub fn contribution(&self) -> Flottant {
+        self.proba * self.perte
+    }
+}
+
+// Probabilité de gagner la partie, accompagnée de ce qu'on sait de la
+// convergence du calcul par rapport au nombre de relances autorisées
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProbaFin {
+    // Probabilité de gagner la partie
+    pub proba: Flottant,
+
+    // Nombre de relances autorisées qu'on a dû étudier
+    pub relances_utilisees: usize,
+
+    // Est-ce que la probabilité a convergé avant le nombre maximal de
+    // relances autorisées? Sinon, elle est probablement sous-estimée.
+    pub converge: bool,
+
+    // Augmentation de la probabilité lors de la dernière relance autorisée
+    // étudiée, qui donne une idée de la précision atteinte
+    pub ecart: Flottant,
+}
+
 impl StatsJet {
     // Initialiser les statistiques à N dés
-    pub fn new(nb_des: usize) -> Self {
+    pub fn new(nb_des: usize, regles: &Regles) -> Self {
         // On énumère les choix de combinaisons face auxquels on peut se
         // retrouver en lançant ce nombre de dés, et avec quelle probabilité.
-        let mut choix_et_probas = choix::enumerer_choix(nb_des);
+        let mut choix_et_probas = choix::enumerer_choix(nb_des, regles);
 
         // On retire le cas perdant, car il est spécial à plusieurs égards
         // (on perd la mise précédente, on ne peut pas choisir de continuer)
-        choix_et_probas.remove(&[][..]);
+        let proba_rien = choix_et_probas.remove(&[][..]).unwrap_or(0.);
 
         // Pour les autres choix, on note quelques compléments
         let stats_choix =
@@ -80,20 +340,8 @@ impl StatsJet {
                 .map(|(choix, proba)| {
                     // Valeur de chaque combinaison, nombre de dés si on relance
                     let choix = choix.into_iter()
-                        .map(|comb| {
-                            let valeur = comb.valeur();
-                            let des_restants = nb_des - comb.nb_des();
-                            let nb_des_relance = if des_restants == 0 {
-                                NB_DES_TOT
-                            } else {
-                                des_restants
-                            };
-                            Possibilite {
-                                comb,
-                                valeur,
-                                nb_des_relance,
-                            }
-                        }).collect::<Box<[_]>>();
+                        .map(|comb| Possibilite::new(comb, nb_des, regles))
+                        .collect::<Box<[_]>>();
 
                     // ...et, bien sûr, on garde la proba de côté
                     StatsChoix {
@@ -105,49 +353,1045 @@ impl StatsJet {
         // ...et avec ça on est paré
         Self {
             stats_choix,
-            esperance: RefCell::new(HashMap::new()),
-            proba_fin: RefCell::new(HashMap::new()),
+            proba_rien,
+            esperance_fin: Mutex::new(Cache::new()),
+            moment2: Mutex::new(Cache::new()),
+            proba_perte: Mutex::new(Cache::new()),
+            distribution: Mutex::new(Cache::new()),
+            utilite_exponentielle: Mutex::new(Cache::new()),
+            esperance_penalisee: Mutex::new(Cache::new()),
+            proba_depasser: Mutex::new(Cache::new()),
         }
     }
 }
 
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Stats {
     // Initialiser les calculs statistiques à la mitraillette
     pub fn new() -> Self {
+        Self::with_regles(Regles::default())
+    }
+
+    // Même chose, mais en visant un autre score que 10000
+    pub fn with_score_max(score_max: Valeur) -> Self {
+        Self::with_regles(Regles { score_max, ..Regles::default() })
+    }
+
+    // Même chose, mais en jouant avec un autre nombre de dés que 6
+    pub fn new_avec_des(nb_des_tot: usize) -> Self {
+        Self::with_regles(Regles { nb_des_tot, ..Regles::default() })
+    }
+
+    // Même chose, avec une variante arbitraire des règles
+    pub fn with_regles(regles: Regles) -> Self {
+        if let Err(erreur) = regles.verifier() {
+            panic!("{}", erreur);
+        }
+        let stats_jets = (1..=regles.nb_des_tot)
+            .map(|nb_des| StatsJet::new(nb_des, &regles))
+            .collect::<Box<[_]>>();
+        let mises = MisesAtteignables::new(&regles);
+        info!("Statistiques préparées pour {} dés à {} faces, score maximal {}",
+              regles.nb_des_tot, regles.nb_faces, regles.score_max);
         Self {
-            stats_jets: (1..=NB_DES_TOT).map(StatsJet::new)
-                                        .collect::<Box<[_]>>(),
+            mises,
+            regles,
+            stats_jets,
+            suivi: SuiviProgression::default(),
         }
     }
 
-    // Gain moyen quand on risque "mise" points en lançant "nb_des" dés
+    // Règles du jeu utilisées pour les calculs
+    pub fn regles(&self) -> &Regles {
+        &self.regles
+    }
+
+    // Mises qu'on peut avoir accumulées en lançant chaque nombre de dés, qui
+    // indiquent quelles situations de jeu valent la peine d'être étudiées
+    pub fn mises_atteignables(&self) -> &MisesAtteignables {
+        &self.mises
+    }
+
+    // Probabilité de ne tirer aucune combinaison en lançant N dés (voir aussi
+    // choix::proba_rien, qui ne demande pas de préparer des statistiques)
+    pub fn proba_rien(&self, nb_des: usize) -> Flottant {
+        self.stats_jets[nb_des-1].proba_rien
+    }
+
+    // Oublier tous les résultats en cache, pour borner la mémoire utilisée
+    // (voir taille_caches). Les calculs suivants seront plus lents, puisqu'il
+    // faudra refaire ce qui avait été fait.
+    pub fn vider_caches(&mut self) {
+        for stats_jet in self.stats_jets.iter_mut() {
+            stats_jet.esperance_fin.get_mut().unwrap().clear();
+            stats_jet.moment2.get_mut().unwrap().clear();
+            stats_jet.proba_perte.get_mut().unwrap().clear();
+            stats_jet.distribution.get_mut().unwrap().clear();
+            stats_jet.utilite_exponentielle.get_mut().unwrap().clear();
+            stats_jet.esperance_penalisee.get_mut().unwrap().clear();
+            stats_jet.proba_depasser.get_mut().unwrap().clear();
+        }
+    }
+
+    // Borner le nombre de résultats que garde chacun des caches, pour chaque
+    // nombre de dés, en oubliant les moins récemment utilisés (None pour ne pas
+    // les borner, ce qui est le cas par défaut). Ça ne change pas les
+    // résultats, seulement le temps qu'il faut pour les obtenir, puisqu'un
+    // résultat oublié sera recalculé si on en a de nouveau besoin. Mais une
+    // capacité trop faible pour les états qu'on étudie peut rendre les calculs
+    // extrêmement lents, puisqu'ils passent leur temps à se refaire.
+    pub fn limiter_caches(&mut self, capacite: Option<usize>) {
+        for stats_jet in self.stats_jets.iter_mut() {
+            stats_jet.esperance_fin.get_mut().unwrap().limiter(capacite);
+            stats_jet.moment2.get_mut().unwrap().limiter(capacite);
+            stats_jet.proba_perte.get_mut().unwrap().limiter(capacite);
+            stats_jet.distribution.get_mut().unwrap().limiter(capacite);
+            stats_jet.utilite_exponentielle.get_mut().unwrap().limiter(capacite);
+            stats_jet.esperance_penalisee.get_mut().unwrap().limiter(capacite);
+            stats_jet.proba_depasser.get_mut().unwrap().limiter(capacite);
+        }
+    }
+
+    // Est-ce que l'état n'a ni cible, ni pénalité de pertes en jeu? Seuls
+    // le score, les dés et la mise comptent alors, voir ci-dessous.
+    pub fn etat_ordinaire(&self, etat: &EtatTour) -> bool {
+        etat.cible.is_none() && self.regles.cout_perte(etat.score, etat.pertes) == 0
+    }
+
+    // Les grandeurs qui suivent se calculent à partir de l'état d'un tour (voir
+    // etat::EtatTour), pour que le simulateur, le mode conseil et l'analyse
+    // partent du même état. Chacune existe aussi en version qui prend le
+    // score, le nombre de dés à lancer et la mise préalable, pour un état sans
+    // adversaire, sans pertes préalables et sans cible (voir EtatTour::new).
+    // L'espérance et le gain moyen tiennent compte de la pénalité des pertes à
+    // répétition (voir esperance_etat), mais les autres grandeurs ne savent
+    // pas la représenter, et aucune ne sait viser la cible du dernier tour
+    // (voir proba_depasser_etat): elles demandent un état ordinaire (voir
+    // etat_ordinaire), ce qu'on vérifie en mode debug.
+    //
+    // Gain moyen quand on risque la mise en jeu en lançant les dés de l'état,
+    // en suivant la stratégie qui maximise l'espérance de gain
+    pub fn gain_moyen_etat(&self, etat: &EtatTour) -> Flottant {
+        debug_assert!(etat.cible.is_none(), "Gain moyen demandé avec une cible: {:?}", etat);
+        self.esperance_etat(etat) - etat.mise as Flottant
+    }
+
+    // Même chose, sans passer par l'état du tour
     pub fn gain_moyen(&self,
                       score: Valeur,
                       nb_des: usize,
                       mise: Valeur) -> Flottant
     {
-        self.esperance(score, nb_des, mise) - mise as Flottant
+        self.gain_moyen_etat(&EtatTour::new(score, nb_des, mise))
     }
 
-    // Espérance de gain pour une stratégie qui la maximise, en partant d'un
-    // certain nombre de dés et d'une certaine mise préalable
+    // Même chose, mais en indiquant aussi l'écart-type du gain
+    pub fn statistiques_gain_etat(&self, etat: &EtatTour) -> StatistiquesGain {
+        debug_assert!(self.etat_ordinaire(etat), "Etat non pris en charge: {:?}", etat);
+        StatistiquesGain {
+            moyenne: self.gain_moyen_etat(etat),
+            ecart_type: self.variance_gain(etat.score, etat.nb_des, etat.mise).sqrt(),
+        }
+    }
+
+    // Même chose, sans passer par l'état du tour
+    pub fn statistiques_gain(&self,
+                             score: Valeur,
+                             nb_des: usize,
+                             mise: Valeur) -> StatistiquesGain
+    {
+        self.statistiques_gain_etat(&EtatTour::new(score, nb_des, mise))
+    }
+
+    // Variance du gain quand on risque "mise" points en lançant "nb_des" dés,
+    // pour une stratégie qui maximise l'espérance de gain
+    pub fn variance_gain(&self,
+                         score: Valeur,
+                         nb_des: usize,
+                         mise: Valeur) -> Flottant
+    {
+        let (esperance, num_relances) = self.esperance_convergee(score, nb_des, mise);
+        let moment2 = self.calcul_moment2(score, nb_des, mise, num_relances);
+        (moment2 - esperance * esperance).max(0.)
+    }
+
+    // Probabilité de finir le tour sans rien encaisser quand on risque la
+    // mise en jeu en lançant les dés de l'état, pour une stratégie qui
+    // maximise l'espérance de gain (chaque relance risque toute la mise
+    // accumulée)
+    pub fn proba_perte_etat(&self, etat: &EtatTour) -> Flottant {
+        debug_assert!(self.etat_ordinaire(etat), "Etat non pris en charge: {:?}", etat);
+        let EtatTour { score, nb_des, mise, .. } = *etat;
+        let (_, num_relances) = self.esperance_convergee(score, nb_des, mise);
+        self.calcul_proba_perte(score, nb_des, mise, num_relances)
+    }
+
+    // Même chose, sans passer par l'état du tour
+    pub fn proba_perte(&self,
+                       score: Valeur,
+                       nb_des: usize,
+                       mise: Valeur) -> Flottant
+    {
+        self.proba_perte_etat(&EtatTour::new(score, nb_des, mise))
+    }
+
+    // Gain moyen, probabilité de perte et gain moyen quand on ne perd pas,
+    // pour une stratégie qui maximise l'espérance de gain
+    pub fn bilan_etat(&self, etat: &EtatTour) -> Bilan {
+        debug_assert!(self.etat_ordinaire(etat), "Etat non pris en charge: {:?}", etat);
+        let EtatTour { score, nb_des, mise, .. } = *etat;
+        let (esperance, num_relances) = self.esperance_convergee(score, nb_des, mise);
+        let proba_perte = self.calcul_proba_perte(score, nb_des, mise, num_relances);
+
+        // Quand on perd, on encaisse 0, donc toute l'espérance de la mise
+        // encaissée vient des tours réussis
+        let proba_reussite = 1. - proba_perte;
+        let gain_moyen_si_reussite = if proba_reussite > 0. {
+            esperance / proba_reussite - mise as Flottant
+        } else {
+            0.
+        };
+        Bilan {
+            esperance: esperance - mise as Flottant,
+            proba_perte,
+            gain_moyen_si_reussite,
+        }
+    }
+
+    // Même chose, sans passer par l'état du tour
+    pub fn bilan(&self,
+                 score: Valeur,
+                 nb_des: usize,
+                 mise: Valeur) -> Bilan
+    {
+        self.bilan_etat(&EtatTour::new(score, nb_des, mise))
+    }
+
+    // Espérance de la mise encaissée pour une stratégie qui la maximise, en
+    // partant de la mise et des dés de l'état. Si le joueur a déjà perdu des
+    // tours d'affilée et que perdre ce tour-ci coûte une pénalité (voir
+    // Regles::penalite_pertes), on la compte comme une mise encaissée
+    // négative, ce qui rend la perte plus chère et pousse à s'arrêter plus
+    // tôt. On ne s'intéresse qu'au tour en cours: la série de pertes qu'on
+    // laisse aux tours suivants n'entre pas en ligne de compte.
+    pub fn esperance_etat(&self, etat: &EtatTour) -> Flottant {
+        let EtatTour { score, nb_des, mise, pertes, .. } = *etat;
+        let cout = self.regles.cout_perte(score, pertes);
+        if cout == 0 {
+            return self.esperance_convergee(score, nb_des, mise).0;
+        }
+
+        // Est-ce que, par chance, j'ai déjà étudié ce cas précédemment?
+        if let Some(&esperance) = self.stats_jets[nb_des-1].esperance_penalisee
+                                      .lock().unwrap()
+                                      .get(&(score, mise)) {
+            return esperance;
+        }
+
+        // Sinon, on optimise le tour comme pour une utilité dont la valeur
+        // pour un tour perdu serait négative, et on garde tout ce qu'il a fallu
+        // calculer au passage pour les prochaines fois
+        let optimiseur = OptimiseurUtilite::new(self, score, |gain| {
+            if gain == 0 { -(cout as Flottant) } else { gain as Flottant }
+        });
+        let esperance = optimiseur.esperance(nb_des, mise);
+        for ((nb_des, mise), esperance) in optimiseur.into_esperances() {
+            self.stats_jets[nb_des-1].esperance_penalisee.lock().unwrap()
+                                     .insert((score, mise), esperance);
+        }
+        esperance
+    }
+
+    // Même chose, sans passer par l'état du tour
     pub fn esperance(&self,
                      score: Valeur,
                      nb_des: usize,
                      mise: Valeur) -> Flottant
     {
-        let mut num_relances = 0;
+        self.esperance_etat(&EtatTour::new(score, nb_des, mise))
+    }
+
+    // Même chose, quand on a déjà perdu un certain nombre de tours d'affilée
+    pub fn esperance_pertes(&self,
+                            score: Valeur,
+                            nb_des: usize,
+                            mise: Valeur,
+                            pertes: usize) -> Flottant
+    {
+        self.esperance_etat(&EtatTour { pertes, ..EtatTour::new(score, nb_des, mise) })
+    }
+
+    // Nombre de relances qu'il faut s'autoriser pour que l'espérance de gain
+    // converge, ou None si elle n'a pas convergé au bout de NB_RELANCES_MAX
+    pub fn relances_necessaires(&self,
+                                score: Valeur,
+                                nb_des: usize,
+                                mise: Valeur) -> Option<usize>
+    {
+        self.convergence_esperance(score, nb_des, mise).1
+    }
+
+    // Espérance de gain pour chaque nombre de relances autorisées entre 0 et N,
+    // pour voir comment elle converge quand on autorise plus de relances
+    pub fn courbe_convergence(&self,
+                              score: Valeur,
+                              nb_des: usize,
+                              mise: Valeur,
+                              max_relances: usize) -> Vec<Flottant>
+    {
+        (0..=max_relances).map(|num_relances| {
+            self.calcul_esperance(score, nb_des, mise, num_relances)
+        }).collect()
+    }
+
+    // Est-ce que la règle nous laisse encaisser la mise de l'état au lieu de
+    // lancer ses dés? Il faut avoir misé quelque chose sans avoir atteint le
+    // score maximal (sinon il n'y a plus rien à lancer), avoir ouvert ou
+    // atteindre le seuil d'ouverture et la mise minimale (voir
+    // Regles::encaissement_autorise), et ne pas être obligé de relancer tous
+    // les dés. Avec l'atterrissage exact, on suppose que la combinaison la plus
+    // chère du jet précédent ne faisait pas dépasser le score maximal, comme
+    // c'est le cas quand on a pu relancer (voir enumerer_decisions).
+    pub fn arret_possible(&self, etat: &EtatTour) -> bool {
+        etat.mise > 0
+            && etat.score + etat.mise < self.regles.score_max
+            && self.regles.encaissement_autorise(etat.score, etat.mise)
+            && !self.regles.relance_obligatoire(etat.nb_des)
+    }
+
+    // Seuil d'arrêt quand une combinaison nous laisse relancer N dés: plus
+    // petite mise atteignable à partir de laquelle il vaut mieux encaisser que
+    // relancer, en ignorant les mises où la règle interdit de s'arrêter (voir
+    // arret_possible). C'est la mise d'équilibre, à partir de laquelle le gain
+    // moyen d'une relance devient négatif ou nul. Retourne None s'il vaut
+    // toujours mieux relancer.
+    pub fn seuil_arret(&self, score: Valeur, nb_des: usize) -> Option<Valeur> {
+        self.mises.mises(nb_des).find(|&mise| {
+            self.arret_possible(&EtatTour::new(score, nb_des, mise))
+                && mise as Flottant >= self.esperance(score, nb_des, mise)
+        })
+    }
+
+    // Autre nom du seuil d'arrêt (voir seuil_arret)
+    pub fn mise_equilibre(&self, score: Valeur, nb_des: usize) -> Option<Valeur> {
+        self.seuil_arret(score, nb_des)
+    }
+
+    // Seuil d'arrêt (voir seuil_arret) quand on a déjà perdu un certain nombre
+    // de tours d'affilée, ce qui peut rendre la perte de ce tour-ci plus chère
+    // (voir esperance_pertes)
+    pub fn seuil_arret_pertes(&self,
+                              score: Valeur,
+                              nb_des: usize,
+                              pertes: usize) -> Option<Valeur>
+    {
+        self.mises.mises(nb_des).find(|&mise| {
+            self.arret_possible(&EtatTour::new(score, nb_des, mise))
+                && mise as Flottant >= self.esperance_pertes(score, nb_des, mise, pertes)
+        })
+    }
+
+    // Espérance de l'utilité 1 - exp(-λ·mise) de la mise encaissée en fin de
+    // tour, pour une stratégie qui la maximise. Plus λ est grand, plus on a peur
+    // de perdre ce qu'on a misé et plus on s'arrête tôt. Quand λ tend vers 0, on
+    // retrouve la stratégie qui maximise l'espérance de gain.
+    pub fn esperance_utilite(&self,
+                             score: Valeur,
+                             nb_des: usize,
+                             mise: Valeur,
+                             lambda: Flottant) -> Flottant
+    {
+        // Est-ce que, par chance, j'ai déjà étudié ce cas précédemment?
+        assert!(lambda > 0., "L'aversion au risque doit être positive: {}", lambda);
+        let cle_lambda = bits_flottant(lambda);
+        if let Some(&esperance) = self.stats_jets[nb_des-1].utilite_exponentielle
+                                      .lock().unwrap()
+                                      .get(&(score, mise, cle_lambda)) {
+            return esperance;
+        }
+
+        // Sinon, on optimise le tour, et on garde tout ce qu'il a fallu
+        // calculer au passage pour les prochaines fois
+        let optimiseur = OptimiseurUtilite::new(self, score, |mise| {
+            utilite_exponentielle(lambda, mise)
+        });
+        let esperance = optimiseur.esperance(nb_des, mise);
+        for ((nb_des, mise), esperance) in optimiseur.into_esperances() {
+            self.stats_jets[nb_des-1].utilite_exponentielle.lock().unwrap()
+                                     .insert((score, mise, cle_lambda), esperance);
+        }
+        esperance
+    }
+
+    // Seuil d'arrêt (voir seuil_arret) pour une stratégie qui maximise
+    // l'espérance de l'utilité 1 - exp(-λ·mise) plutôt que celle du gain
+    pub fn seuil_arret_utilite(&self,
+                               score: Valeur,
+                               nb_des: usize,
+                               lambda: Flottant) -> Option<Valeur>
+    {
+        self.mises.mises(nb_des).find(|&mise| {
+            self.arret_possible(&EtatTour::new(score, nb_des, mise))
+                && utilite_exponentielle(lambda, mise)
+                       >= self.esperance_utilite(score, nb_des, mise, lambda)
+        })
+    }
+
+    // Seuil d'arrêt (voir seuil_arret) pour une stratégie qui maximise
+    // E - k * V, l'espérance de la mise encaissée pénalisée par sa variance
+    // (voir OptimiseurVariance). Avec k nul, c'est le seuil de la stratégie
+    // qui maximise l'espérance, aux relances près que celle-ci ne s'autorise
+    // pas au-delà de la convergence.
+    pub fn seuil_arret_variance(&self,
+                                score: Valeur,
+                                nb_des: usize,
+                                k: Flottant) -> Option<Valeur>
+    {
+        let optimiseur = OptimiseurVariance::new(self, score, k);
+        self.mises.mises(nb_des).find(|&mise| {
+            self.arret_possible(&EtatTour::new(score, nb_des, mise))
+                && mise as Flottant >= optimiseur.valeur(nb_des, mise)
+        })
+    }
+
+    // Décision optimale face à un jet de dés qui nous laisse le choix entre
+    // certaines combinaisons. Retourne None si on a perdu, soit parce qu'on n'a
+    // tiré aucune combinaison, soit parce qu'elles dépassent toutes le score
+    // maximal.
+    pub fn action_optimale_etat(&self,
+                                etat: &EtatTour,
+                                choix: &[Combinaison]) -> Option<Decision>
+    {
+        // On s'autorise autant de relances qu'il en faut pour que l'espérance
+        // de gain du lancer qu'on vient de faire ait convergé
+        let EtatTour { score, nb_des, mise, .. } = *etat;
+        let (_, num_relances) = self.esperance_convergee(score, nb_des, mise);
+        let possibilites = choix.iter()
+                                .map(|&comb| Possibilite::new(comb, nb_des, &self.regles))
+                                .collect::<Box<[_]>>();
+        self.decision_optimale(score, mise, &possibilites, num_relances)
+    }
+
+    // Même chose, sans passer par l'état du tour
+    pub fn action_optimale(&self,
+                           score: Valeur,
+                           nb_des: usize,
+                           mise: Valeur,
+                           choix: &[Combinaison]) -> Option<Decision>
+    {
+        self.action_optimale_etat(&EtatTour::new(score, nb_des, mise), choix)
+    }
+
+    // Décision qui maximise la probabilité de gagner la partie dès ce tour-ci,
+    // quoi qu'il en coûte en espérance de gain. C'est ce qu'il faut jouer
+    // quand l'adversaire va très probablement finir au prochain tour, et en
+    // fin de partie ça peut vouloir dire prendre exprès une combinaison moins
+    // chère pour atterrir pile sur le score maximal. L'espérance associée est
+    // une probabilité de finir la partie. Retourne None si on a perdu.
+    pub fn politique_derniere_chance(&self,
+                                     score: Valeur,
+                                     nb_des: usize,
+                                     mise: Valeur,
+                                     choix: &[Combinaison]) -> Option<Decision>
+    {
+        // On s'autorise autant de relances que pour la stratégie qui maximise
+        // l'espérance, ce qui permet de réutiliser les probabilités calculées
+        // en même temps qu'elle. Aller jusqu'à la convergence de la
+        // probabilité de finir coûterait très cher à faible score, pour des
+        // probabilités de toute façon infimes.
+        let (_, num_relances) = self.esperance_convergee(score, nb_des, mise);
+        let possibilites = choix.iter()
+                                .map(|&comb| Possibilite::new(comb, nb_des, &self.regles))
+                                .collect::<Box<[_]>>();
+
+        // La probabilité de finir croît avec le nombre de relances autorisées,
+        // donc il suffit d'envisager la relance qui en autorise le plus
+        let mut meilleure_decision : Option<Decision> = None;
+        self.enumerer_decisions_generiques(
+            score,
+            mise,
+            &possibilites,
+            num_relances.checked_sub(1).into_iter(),
+            |gain| if self.regles.score_gagnant(score + gain) { 1. } else { 0. },
+            |nb_des, mise, max_relances| self.calcul_proba_fin(score, nb_des, mise, max_relances),
+            |decision| {
+                if meilleure_decision.is_none_or(|meilleure| {
+                    decision.esperance() > meilleure.esperance()
+                }) {
+                    meilleure_decision = Some(decision);
+                }
+            }
+        );
+        meilleure_decision
+    }
+
+    // Toutes les décisions qu'on peut prendre face à un jet de dés, avec
+    // leur espérance de gain. Pour chaque combinaison, on indique ce que
+    // rapporte l'arrêt (si la règle le permet) et la relance (si on n'a pas
+    // atteint le score maximal). Les combinaisons qui ne permettent ni l'un ni
+    // l'autre n'apparaissent pas.
+    pub fn decisions_possibles(&self,
+                               score: Valeur,
+                               nb_des: usize,
+                               mise: Valeur,
+                               choix: &[Combinaison]) -> Vec<Decision>
+    {
+        let (_, num_relances) = self.esperance_convergee(score, nb_des, mise);
+        let possibilites = choix.iter()
+                                .map(|&comb| Possibilite::new(comb, nb_des, &self.regles))
+                                .collect::<Box<[_]>>();
+        let mut decisions = Vec::<Decision>::new();
+        self.enumerer_decisions(score, mise, &possibilites, num_relances, |decision| {
+            // On ne garde que le meilleur nombre de relances autorisées
+            let meme_relance = match (decisions.last(), decision) {
+                (Some(Decision::Relancer { comb, .. }),
+                 Decision::Relancer { comb: nouv_comb, .. }) => *comb == nouv_comb,
+                _ => false,
+            };
+            if !meme_relance {
+                decisions.push(decision);
+            } else {
+                let derniere = decisions.last_mut().unwrap();
+                if decision.esperance() > derniere.esperance() {
+                    *derniere = decision;
+                }
+            }
+        });
+        decisions
+    }
+
+    // Toutes les options qui s'offrent au joueur face à un jet de dés (donné
+    // sous forme d'histogramme), de la meilleure à la moins bonne, avec ce que
+    // chacune fait perdre par rapport à la meilleure. Les options qui valent
+    // la meilleure à ECART_NEGLIGEABLE près sont signalées comme équivalentes,
+    // pour qu'on sache quand le choix n'a pas d'importance. La liste est vide
+    // si le tour est perdu.
+    pub fn evaluer_options(&self,
+                           score: Valeur,
+                           mise: Valeur,
+                           histo: &Histogramme) -> Vec<OptionEvaluee>
+    {
+        let choix = histo.combinaisons_possibles(&self.regles, Enumeration::Elaguee);
+        let nb_des = histo.nb_des();
+        let mut decisions = self.decisions_possibles(score, nb_des, mise, &choix);
+        decisions.sort_by(|a, b| b.esperance().total_cmp(&a.esperance()));
+        let meilleure = match decisions.first() {
+            Some(decision) => decision.esperance(),
+            None => return Vec::new(),
+        };
+        decisions.into_iter().map(|decision| {
+            let ecart = meilleure - decision.esperance();
+            OptionEvaluee {
+                decision,
+                ecart,
+                equivalente: ecart <= ECART_NEGLIGEABLE,
+            }
+        }).collect()
+    }
+
+    // Distribution de probabilité de la mise encaissée à la fin du tour, en
+    // partant d'un certain nombre de dés et d'une certaine mise préalable et
+    // en suivant la stratégie qui maximise l'espérance de gain. On obtient des
+    // couples (mise, probabilité) triés par mise croissante, où la mise nulle
+    // correspond aux tours perdus.
+    pub fn distribution_tour_etat(&self, etat: &EtatTour) -> Vec<(Valeur, Flottant)> {
+        debug_assert!(self.etat_ordinaire(etat), "Etat non pris en charge: {:?}", etat);
+        let EtatTour { score, nb_des, mise, .. } = *etat;
+        let (_, num_relances) = self.esperance_convergee(score, nb_des, mise);
+        self.calcul_distribution(score, nb_des, mise, num_relances).to_vec()
+    }
+
+    // Même chose, sans passer par l'état du tour
+    pub fn distribution_tour(&self,
+                             score: Valeur,
+                             nb_des: usize,
+                             mise: Valeur) -> Vec<(Valeur, Flottant)>
+    {
+        self.distribution_tour_etat(&EtatTour::new(score, nb_des, mise))
+    }
+
+    // Espérance de gain d'une stratégie quelconque, en partant d'un certain
+    // état du tour (nombre de dés à lancer et mise préalable)
+    pub fn esperance_strategie(&self,
+                               strategie: &dyn Strategie,
+                               etat: &EtatTour) -> Flottant
+    {
+        self.calcul_esperance_strategie(strategie, etat, &mut HashMap::new())
+    }
+
+    // Ce que perd une stratégie quelconque par rapport à la stratégie optimale,
+    // état du tour par état du tour, en partant d'un certain état. Dans
+    // chaque état où elle peut passer, on compare sa décision face à chaque
+    // jet de dés à la meilleure décision, en supposant qu'on joue au mieux
+    // ensuite. La somme des pertes de chaque état, pondérées par la
+    // probabilité d'y passer, est ce que la stratégie perd au total (aux
+    // erreurs de convergence près). Les états sont triés par mise croissante.
+    pub fn pertes_strategie(&self,
+                            strategie: &dyn Strategie,
+                            etat: &EtatTour) -> Vec<PerteEtat>
+    {
+        // Chaque relance augmente la mise, donc en traitant les états par mise
+        // croissante, on connaît la probabilité d'un état avant de l'étudier
+        let mut probas = BTreeMap::from([((etat.mise, etat.nb_des), 1.)]);
+        let mut pertes = Vec::new();
+        while let Some(((mise, nb_des), proba)) = probas.pop_first() {
+            let courant = EtatTour { nb_des, mise, ..*etat };
+            let valeur = |action: &Action| -> Flottant {
+                match *action {
+                    Action::Encaisser { mise, .. } => {
+                        self.regles.gain_encaisse(courant.score, mise) as Flottant
+                    },
+                    Action::Relancer { mise, nb_des, .. } => {
+                        self.esperance_etat(&EtatTour { nb_des, mise, ..courant })
+                    },
+                }
+            };
+            let mut perte = 0.;
+            for stats_choix in self.stats_jets[nb_des-1].stats_choix.iter() {
+                let choix = stats_choix.choix.iter().map(|poss| poss.comb).collect::<Vec<_>>();
+                let actions = simulation::actions_possibles(&self.regles, &courant, &choix);
+                if actions.is_empty() { continue; }
+                let meilleure = actions.iter().map(valeur).fold(Flottant::NEG_INFINITY, Flottant::max);
+                let action = strategie.choisir(&courant, &actions);
+                perte += stats_choix.proba * (meilleure - valeur(&action));
+                if let Action::Relancer { mise, nb_des, .. } = action {
+                    *probas.entry((mise, nb_des)).or_insert(0.) += proba * stats_choix.proba;
+                }
+            }
+            pertes.push(PerteEtat { etat: courant, proba, perte });
+        }
+        pertes
+    }
+
+    // Distribution de probabilité de la mise encaissée à la fin du tour par
+    // une stratégie quelconque, en partant d'un certain état du tour, sous la
+    // même forme que distribution_tour
+    pub fn distribution_strategie(&self,
+                                  strategie: &dyn Strategie,
+                                  etat: &EtatTour) -> Vec<(Valeur, Flottant)>
+    {
+        self.calcul_distribution_strategie(strategie, etat, &mut HashMap::new())
+    }
+
+    // Espérance de gain de la stratégie humaine qui consiste à encaisser dès
+    // que la mise atteint un certain seuil, en partant d'un certain état du
+    // tour. Comme pour la stratégie optimale, c'est un calcul exact.
+    pub fn esperance_seuil(&self,
+                           score: Valeur,
+                           nb_des: usize,
+                           mise: Valeur,
+                           seuil: Valeur) -> Flottant
+    {
+        let etat = EtatTour::new(score, nb_des, mise);
+        self.esperance_strategie(&SeuilFixe(seuil), &etat)
+    }
+
+    // Probabilité de dépasser un score cible d'ici la fin du tour, pour la
+    // stratégie qui la maximise, c'est-à-dire qui relance tant que la cible
+    // n'est pas dépassée. C'est le seul objectif qui compte pendant le dernier
+    // tour d'égalisation (voir Regles::dernier_tour), où une sexte fait aussi
+    // gagner. Comme cette règle autorise le dépassement, seuls comptent les
+    // points qui manquent encore pour dépasser la cible, et le nombre de
+    // relances n'est pas limité puisque la mise croît à chaque relance.
+    // Retourne None si l'état n'a pas de cible.
+    pub fn proba_depasser_etat(&self, etat: &EtatTour) -> Option<Flottant> {
+        let cible = etat.cible?;
+        Some(match (cible + 1).checked_sub(etat.score + etat.mise) {
+            Some(manque) if manque > 0 => self.calcul_proba_depasser(etat.nb_des, manque),
+            _ => 1.,
+        })
+    }
+
+    // Même chose, sans passer par l'état du tour
+    pub fn proba_depasser(&self,
+                          score: Valeur,
+                          nb_des: usize,
+                          mise: Valeur,
+                          cible: Valeur) -> Flottant
+    {
+        let etat = EtatTour { cible: Some(cible), ..EtatTour::new(score, nb_des, mise) };
+        self.proba_depasser_etat(&etat).expect("L'état a une cible")
+    }
+
+    // Espérance d'un objectif quelconque, fonction de la mise encaissée en fin
+    // de tour (0 si on perd le tour), pour la stratégie qui la maximise en
+    // s'autorisant N relances. L'espérance de gain en est le cas particulier où
+    // l'objectif est la mise elle-même, et la probabilité de finir la partie
+    // celui où l'objectif vaut 1 si la mise fait gagner et 0 sinon. Ces deux
+    // cas, dont on se sert beaucoup, sont calculés ensemble et gardés en cache
+    // (voir calcul_esperance_fin). Ici, on ne garde les résultats
+    // intermédiaires que le temps de l'appel, puisqu'on ne sait pas comparer
+    // deux objectifs.
+    pub fn optimiser(&self,
+                     score: Valeur,
+                     nb_des: usize,
+                     mise: Valeur,
+                     max_relances: usize,
+                     objectif: impl Fn(Valeur) -> Flottant) -> Flottant
+    {
+        self.calcul_optimum(score, nb_des, mise, max_relances, &objectif, &mut HashMap::new())
+    }
+
+    // Calcul récursif de l'espérance d'un objectif, en gardant en mémoire ce
+    // qu'on a déjà calculé pour chaque (nombre de dés, mise, relances)
+    fn calcul_optimum(&self,
+                      score: Valeur,
+                      nb_des: usize,
+                      mise: Valeur,
+                      max_relances: usize,
+                      objectif: &impl Fn(Valeur) -> Flottant,
+                      deja_calcule: &mut HashMap<(usize, Valeur, usize), Flottant>) -> Flottant
+    {
+        if let Some(&esperance) = deja_calcule.get(&(nb_des, mise, max_relances)) {
+            return esperance;
+        }
+
+        // On suit la décision optimale pour chaque lancer gagnant, sans oublier
+        // les lancers perdants
+        let stats_jet = &self.stats_jets[nb_des-1];
+        let valeur_perte = objectif(0);
+        let mut esperance = valeur_perte * stats_jet.proba_rien;
+        for stats_choix in stats_jet.stats_choix.iter() {
+            let mut esperance_max : Option<Flottant> = None;
+            self.enumerer_decisions_generiques(
+                score,
+                mise,
+                &stats_choix.choix,
+                0..max_relances,
+                objectif,
+                |nb_des, mise, max_relances| {
+                    self.calcul_optimum(score, nb_des, mise, max_relances, objectif, deja_calcule)
+                },
+                |decision| {
+                    if esperance_max.is_none_or(|max| decision.esperance() > max) {
+                        esperance_max = Some(decision.esperance());
+                    }
+                }
+            );
+            esperance += esperance_max.unwrap_or(valeur_perte) * stats_choix.proba;
+        }
+        deja_calcule.insert((nb_des, mise, max_relances), esperance);
+        esperance
+    }
+
+    // Calcul de l'espérance de gain d'une stratégie, en gardant en mémoire ce
+    // qu'on a déjà calculé pour les mises supérieures
+    fn calcul_esperance_strategie(&self,
+                                  strategie: &dyn Strategie,
+                                  etat: &EtatTour,
+                                  deja_calcule: &mut HashMap<(usize, Valeur), Flottant>) -> Flottant
+    {
+        if let Some(&esperance) = deja_calcule.get(&(etat.nb_des, etat.mise)) {
+            return esperance;
+        }
+
+        // On suit la décision de la stratégie pour chaque lancer gagnant, les
+        // lancers perdants ne rapportant rien
+        let mut esperance = 0.;
+        for stats_choix in self.stats_jets[etat.nb_des-1].stats_choix.iter() {
+            let choix = stats_choix.choix.iter().map(|poss| poss.comb).collect::<Vec<_>>();
+            let actions = simulation::actions_possibles(&self.regles, etat, &choix);
+            if actions.is_empty() { continue; }
+            let esperance_choix = match strategie.choisir(etat, &actions) {
+                Action::Encaisser { mise, .. } => self.regles.gain_encaisse(etat.score, mise) as Flottant,
+                Action::Relancer { mise, nb_des, .. } => {
+                    let etat_relance = EtatTour { nb_des, mise, ..*etat };
+                    self.calcul_esperance_strategie(strategie, &etat_relance, deja_calcule)
+                },
+            };
+            esperance += esperance_choix * stats_choix.proba;
+        }
+        deja_calcule.insert((etat.nb_des, etat.mise), esperance);
+        esperance
+    }
+
+    // Calcul de la probabilité de marquer encore au moins "manque" points
+    // (ou de tirer une sexte) avant la fin du tour
+    fn calcul_proba_depasser(&self, nb_des: usize, manque: Valeur) -> Flottant {
+        let stats_jet = &self.stats_jets[nb_des-1];
+        if let Some(&proba) = stats_jet.proba_depasser.lock().unwrap().get(&manque) {
+            return proba;
+        }
+        let mut proba = 0.;
+        for stats_choix in stats_jet.stats_choix.iter() {
+            let mut proba_max : Flottant = 0.;
+            for poss in stats_choix.choix.iter() {
+                if self.regles.fait_gagner(&poss.comb) || poss.valeur >= manque {
+                    proba_max = 1.;
+                    break;
+                }
+                let proba_relance = self.calcul_proba_depasser(poss.nb_des_relance,
+                                                               manque - poss.valeur);
+                proba_max = proba_max.max(proba_relance);
+            }
+            proba += proba_max * stats_choix.proba;
+        }
+        stats_jet.proba_depasser.lock().unwrap().insert(manque, proba);
+        proba
+    }
+
+    // Calcul de la distribution de la mise encaissée par une stratégie, en
+    // gardant en mémoire ce qu'on a déjà calculé pour les mises supérieures
+    fn calcul_distribution_strategie(&self,
+                                     strategie: &dyn Strategie,
+                                     etat: &EtatTour,
+                                     deja_calcule: &mut HashMap<(usize, Valeur), Vec<(Valeur, Flottant)>>)
+        -> Vec<(Valeur, Flottant)>
+    {
+        if let Some(distribution) = deja_calcule.get(&(etat.nb_des, etat.mise)) {
+            return distribution.clone();
+        }
+
+        // On suit la décision de la stratégie pour chaque lancer gagnant, en
+        // accumulant les probabilités de chaque mise finale
+        let stats_jet = &self.stats_jets[etat.nb_des-1];
+        let mut distribution = BTreeMap::new();
+        let mut ajouter = |mise, proba| *distribution.entry(mise).or_insert(0.) += proba;
+        ajouter(0, stats_jet.proba_rien);
+        for stats_choix in stats_jet.stats_choix.iter() {
+            let choix = stats_choix.choix.iter().map(|poss| poss.comb).collect::<Vec<_>>();
+            let actions = simulation::actions_possibles(&self.regles, etat, &choix);
+            if actions.is_empty() {
+                ajouter(0, stats_choix.proba);
+                continue;
+            }
+            match strategie.choisir(etat, &actions) {
+                Action::Encaisser { mise, .. } => {
+                    ajouter(self.regles.gain_encaisse(etat.score, mise), stats_choix.proba);
+                },
+                Action::Relancer { mise, nb_des, .. } => {
+                    let etat_relance = EtatTour { nb_des, mise, ..*etat };
+                    for (mise_finale, proba) in
+                        self.calcul_distribution_strategie(strategie, &etat_relance, deja_calcule)
+                    {
+                        ajouter(mise_finale, proba * stats_choix.proba);
+                    }
+                },
+            }
+        }
+        let distribution = distribution.into_iter().collect::<Vec<_>>();
+        deja_calcule.insert((etat.nb_des, etat.mise), distribution.clone());
+        distribution
+    }
+
+    // Stratégie de tour qui maximise la probabilité d'atteindre le score
+    // maximal avant un adversaire qui, lui, maximise son espérance de gain
+    pub fn politique_course(&self,
+                            mon_score: Valeur,
+                            score_adverse: Valeur) -> PolitiqueCourse<'_>
+    {
+        PolitiqueCourse::new(self, mon_score, score_adverse)
+    }
+
+    // Même chose, mais face à un adversaire qui suit une stratégie donnée, par
+    // exemple une heuristique humaine qu'on cherche à exploiter. La
+    // probabilité de victoire de cette "meilleure réponse" mesure à quel point
+    // l'heuristique est exploitable.
+    pub fn meilleure_reponse<'stats>(&'stats self,
+                                     adversaire: &'stats dyn Strategie,
+                                     mon_score: Valeur,
+                                     score_adverse: Valeur) -> PolitiqueCourse<'stats>
+    {
+        PolitiqueCourse::contre(self, adversaire, mon_score, score_adverse)
+    }
+
+    // Course entre deux joueurs qui maximisent tous deux leur espérance de
+    // gain, pour savoir quel avantage on a à jouer en premier
+    pub fn duel(&self) -> Duel<'_> {
+        Duel::new(self)
+    }
+
+    // Nombre moyen de tours qu'il faut pour finir la partie en partant d'un
+    // certain score, si on joue chaque tour de façon à maximiser l'espérance
+    // de gain. Peut être infini si on n'a aucune chance de finir.
+    pub fn esperance_tours(&self, score: Valeur) -> Flottant {
+        self.calcul_esperance_tours(score, &mut HashMap::new())
+    }
+
+    // Nombre moyen de tours qu'il faut pour ouvrir, c'est à dire encaisser un
+    // premier tour au moins égal au seuil d'ouverture, si on joue chaque tour
+    // de façon à maximiser l'espérance de gain. Chaque tour réussit avec la
+    // même probabilité, donc le nombre de tours suit une loi géométrique.
+    pub fn tours_pour_ouvrir(&self) -> Flottant {
+        let proba_perte = self.proba_perte(0, self.regles.nb_des_tot, 0);
+        if proba_perte < 1. {
+            1. / (1. - proba_perte)
+        } else {
+            Flottant::INFINITY
+        }
+    }
+
+    // Calcul du nombre moyen de tours pour finir, en gardant en mémoire ce
+    // qu'on a déjà calculé pour les scores supérieurs
+    fn calcul_esperance_tours(&self,
+                              score: Valeur,
+                              deja_calcule: &mut HashMap<Valeur, Flottant>) -> Flottant
+    {
+        if let Some(&esperance) = deja_calcule.get(&score) { return esperance; }
+
+        // Si on note T(s) le nombre moyen de tours partant du score s et P(g)
+        // la probabilité d'encaisser g points ce tour-ci, on a
+        // T(s) = 1 + P(0) * T(s) + somme(g > 0) P(g) * T(s + g),
+        // où T(s + g) est nul si on a atteint le score maximal.
+        let distribution = self.distribution_tour(score, self.regles.nb_des_tot, 0);
+        let mut proba_zero = 0.;
+        let mut somme = 1.;
+        for &(gain, proba) in distribution.iter() {
+            if gain == 0 {
+                proba_zero += proba;
+            } else if score + gain < self.regles.score_max {
+                somme += proba * self.calcul_esperance_tours(score + gain, deja_calcule);
+            }
+        }
+        let esperance = if proba_zero < 1. {
+            somme / (1. - proba_zero)
+        } else {
+            Flottant::INFINITY
+        };
+        deja_calcule.insert(score, esperance);
+        esperance
+    }
+
+    // Probabilité de finir la partie en au plus k tours en partant d'un certain
+    // score, si on joue chaque tour de façon à maximiser l'espérance de gain
+    pub fn proba_fin_en_tours(&self, score: Valeur, nb_tours: usize) -> Flottant {
+        self.calcul_proba_fin_en_tours(score, nb_tours, &mut HashMap::new())
+    }
+
+    // Score moyen après chacun des T prochains tours, en partant d'un certain
+    // score et en jouant chaque tour de façon à maximiser l'espérance de gain.
+    // Ça tient compte du fait que les tours se jouent différemment à l'approche
+    // du score maximal. Une fois ce dernier atteint, la partie est finie et le
+    // score ne bouge plus.
+    pub fn projection(&self, score_initial: Valeur, tours: usize) -> Vec<Flottant> {
+        self.evolution_scores(score_initial, tours).into_iter().map(|probas_scores| {
+            probas_scores.into_iter().map(|(score, proba)| score as Flottant * proba).sum()
+        }).collect()
+    }
+
+    // Probabilité de gagner la partie en exactement k tours, pour k allant de
+    // 1 à T, en partant d'un certain score et en jouant chaque tour de façon à
+    // maximiser l'espérance de gain. La somme tend vers 1 quand T augmente, si
+    // on a une chance de finir.
+    pub fn distribution_tours_victoire(&self,
+                                       score_initial: Valeur,
+                                       tours_max: usize) -> Vec<Flottant>
+    {
+        let score_max = self.regles.score_max;
+        let mut proba_fin_precedente = 0.;
+        self.evolution_scores(score_initial, tours_max).into_iter().map(|probas_scores| {
+            let proba_fin = probas_scores.range(score_max..).map(|(_, &proba)| proba).sum();
+            let proba_tour = proba_fin - proba_fin_precedente;
+            proba_fin_precedente = proba_fin;
+            proba_tour
+        }).collect()
+    }
+
+    // Distribution de probabilité du score après chacun des T prochains tours,
+    // pour les deux précédents. Une fois le score maximal atteint, la partie
+    // est finie et le score ne bouge plus.
+    fn evolution_scores(&self,
+                        score_initial: Valeur,
+                        tours: usize) -> Vec<BTreeMap<Valeur, Flottant>>
+    {
+        let score_max = self.regles.score_max;
+        let mut distributions = HashMap::<Valeur, Vec<(Valeur, Flottant)>>::new();
+        let mut probas_scores = BTreeMap::from([(score_initial, 1.)]);
+        (0..tours).map(|_| {
+            // On calcule en parallèle la distribution de ce qu'on encaisse à
+            // partir des scores qu'on n'avait encore jamais atteints...
+            let nouveaux_scores = probas_scores.keys()
+                .copied()
+                .filter(|&score| score < score_max && !distributions.contains_key(&score))
+                .collect::<Vec<_>>();
+            distributions.par_extend(nouveaux_scores.into_par_iter().map(|score| {
+                (score, self.distribution_tour(score, self.regles.nb_des_tot, 0))
+            }));
+
+            // ...puis on fait avancer la distribution du score d'un tour
+            let mut suivantes = BTreeMap::new();
+            for (&score, &proba) in probas_scores.iter() {
+                if score >= score_max {
+                    *suivantes.entry(score).or_insert(0.) += proba;
+                    continue;
+                }
+                for &(gain, proba_gain) in distributions[&score].iter() {
+                    *suivantes.entry(score + gain).or_insert(0.) += proba * proba_gain;
+                }
+            }
+            probas_scores = suivantes;
+            probas_scores.clone()
+        }).collect()
+    }
+
+    // Calcul de la probabilité de finir en au plus k tours, en gardant en
+    // mémoire ce qu'on a déjà calculé pour les scores supérieurs
+    fn calcul_proba_fin_en_tours(&self,
+                                 score: Valeur,
+                                 nb_tours: usize,
+                                 deja_calcule: &mut HashMap<(Valeur, usize), Flottant>) -> Flottant
+    {
+        if score == self.regles.score_max { return 1.; }
+        if nb_tours == 0 { return 0.; }
+        if let Some(&proba) = deja_calcule.get(&(score, nb_tours)) { return proba; }
+
+        // Chaque tour, on encaisse g points avec une probabilité P(g), puis on
+        // a un tour de moins pour finir à partir du score s + g
+        let distribution = self.distribution_tour(score, self.regles.nb_des_tot, 0);
+        let proba = distribution.iter()
+            .map(|&(gain, proba_gain)| {
+                proba_gain * self.calcul_proba_fin_en_tours(score + gain,
+                                                            nb_tours - 1,
+                                                            deja_calcule)
+            }).sum();
+        deja_calcule.insert((score, nb_tours), proba);
+        proba
+    }
+
+    // Calcul de l'espérance en augmentant le nombre de relances autorisé
+    // jusqu'à ce qu'elle converge. Retourne aussi ce nombre de relances, ou
+    // NB_RELANCES_MAX si l'espérance n'a pas convergé avant.
+    fn esperance_convergee(&self,
+                           score: Valeur,
+                           nb_des: usize,
+                           mise: Valeur) -> (Flottant, usize)
+    {
+        let (esperance, num_relances) = self.convergence_esperance(score, nb_des, mise);
+        (esperance, num_relances.unwrap_or(NB_RELANCES_MAX))
+    }
+
+    // Même chose, mais en indiquant explicitement si l'espérance a convergé
+    // avant NB_RELANCES_MAX relances (sinon, on retourne la dernière valeur)
+    fn convergence_esperance(&self,
+                             score: Valeur,
+                             nb_des: usize,
+                             mise: Valeur) -> (Flottant, Option<usize>)
+    {
         let mut ancienne_esperance = 0.;
-        loop {
+        for num_relances in 0..=NB_RELANCES_MAX {
             let esperance = self.calcul_esperance(score, nb_des, mise, num_relances);
-            assert!(esperance >= ancienne_esperance);
-            if esperance == ancienne_esperance { return esperance; }
+            let ecart = esperance - ancienne_esperance;
+
+            // Une espérance nulle n'a pas forcément convergé: il faut parfois
+            // plusieurs relances avant de pouvoir encaisser quoi que ce soit
+            let converge = num_relances > 0
+                           && esperance > 0.
+                           && ecart <= PRECISION_CONVERGENCE * esperance;
+            if converge {
+                return (esperance, Some(num_relances));
+            }
             ancienne_esperance = esperance;
-            num_relances += 1;
         }
+        (ancienne_esperance, None)
     }
 
-    // Probabilité de gagner (atteindre 10000) en continuant à lancer les dés.
+    // Probabilité de gagner (atteindre le score maximal) en continuant à lancer
+    // les dés.
     //
     // Pour des scores faibles, les régions de l'arbre des lancer de dés où on
     // gagne sont très profondes, donc il vaut mieux s'arrêter à une certaine
@@ -158,15 +1402,63 @@ impl Stats {
                      nb_des: usize,
                      mise: Valeur,
                      max_relances: usize) -> Flottant
+    {
+        self.proba_fin_detaillee(score, nb_des, mise, max_relances).proba
+    }
+
+    // Même chose, mais en indiquant aussi combien de relances il a fallu
+    // étudier et si la probabilité a convergé (cessé d'augmenter)
+    pub fn proba_fin_detaillee(&self,
+                               score: Valeur,
+                               nb_des: usize,
+                               mise: Valeur,
+                               max_relances: usize) -> ProbaFin
+    {
+        self.convergence_proba_fin(score, nb_des, mise, max_relances, 0.)
+    }
+
+    // Même chose, mais en autorisant des relances supplémentaires jusqu'à ce
+    // que la probabilité n'augmente plus que d'au plus "precision" d'une
+    // relance à l'autre, plutôt que de fixer leur nombre. Si ça n'arrive pas
+    // avant un nombre maximal de relances, on l'indique dans le résultat.
+    pub fn proba_fin_convergee(&self,
+                               score: Valeur,
+                               nb_des: usize,
+                               mise: Valeur,
+                               precision: Flottant) -> ProbaFin
+    {
+        assert!(precision >= 0., "La précision doit être positive: {}", precision);
+        self.convergence_proba_fin(score, nb_des, mise, NB_RELANCES_CONVERGENCE_MAX, precision)
+    }
+
+    // Augmentation du nombre de relances autorisées jusqu'à ce que la
+    // probabilité de finir la partie converge à une certaine précision, ou
+    // qu'on atteigne un nombre maximal de relances. Une probabilité nulle n'a
+    // pas convergé, car il faut parfois plusieurs relances pour pouvoir gagner.
+    fn convergence_proba_fin(&self,
+                             score: Valeur,
+                             nb_des: usize,
+                             mise: Valeur,
+                             max_relances: usize,
+                             precision: Flottant) -> ProbaFin
     {
         let mut ancienne_proba = 0.;
-        for num_relances in 0..max_relances {
+        for num_relances in 0..=max_relances {
             let proba = self.calcul_proba_fin(score, nb_des, mise, num_relances);
             assert!(proba >= ancienne_proba);
-            if proba > 0. && proba == ancienne_proba { return proba; }
+            let ecart = proba - ancienne_proba;
+            let converge = proba > 0. && num_relances > 0 && ecart <= precision;
+            if converge || num_relances == max_relances {
+                return ProbaFin {
+                    proba,
+                    relances_utilisees: num_relances,
+                    converge,
+                    ecart,
+                };
+            }
             ancienne_proba = proba;
         }
-        self.calcul_proba_fin(score, nb_des, mise, max_relances)
+        unreachable!()
     }
 
     // Calcul de l'espérance de gain en s'autorisant à relancer les dés N fois
@@ -175,120 +1467,1032 @@ impl Stats {
                         nb_des: usize,
                         mise: Valeur,
                         max_relances: usize) -> Flottant
+    {
+        self.calcul_esperance_fin(score, nb_des, mise, max_relances).esperance
+    }
+
+    // Calcul de la probabilité de gagner la partie avec N relances
+    fn calcul_proba_fin(&self,
+                        score: Valeur,
+                        nb_des: usize,
+                        mise: Valeur,
+                        max_relances: usize) -> Flottant
+    {
+        self.calcul_esperance_fin(score, nb_des, mise, max_relances).proba_fin
+    }
+
+    // Calcul simultané des deux précédents.
+    //
+    // Plutôt que de descendre récursivement dans l'arbre des relances, ce qui
+    // peut aller très profond, on recense d'abord les états (nombre de dés,
+    // mise) qu'on peut atteindre en relançant, puis on remplit le cache en
+    // partant des cas où l'on a le moins de relances autorisées, dont les
+    // autres dépendent.
+    fn calcul_esperance_fin(&self,
+                            score: Valeur,
+                            nb_des: usize,
+                            mise: Valeur,
+                            max_relances: usize) -> EsperanceFin
     {
         // Est-ce que, par chance, j'ai déjà étudié ce cas précédemment?
         let stats_jet = &self.stats_jets[nb_des-1];
-        if let Some(&esperance_lancer) = stats_jet.esperance.borrow()
-                                                  .get(&(score, mise, max_relances)) {
-            return esperance_lancer;
+        if let Some(&esperance_fin) = stats_jet.esperance_fin.lock().unwrap()
+                                               .get(&(score, mise, max_relances)) {
+            return esperance_fin;
+        }
+
+        // Sinon, on calcule tout ce dont on a besoin avant de calculer ce cas,
+        // une relance autorisée après l'autre
+        let etats = self.etats_a_calculer(score, nb_des, mise, max_relances);
+        for num_relances in 0..=max_relances {
+            self.calcul_esperances_relance(score, &etats, num_relances);
         }
+        self.calcul_esperance_fin_couche(score, nb_des, mise, max_relances)
+    }
 
-        // Le but est de déterminer une espérance de gain pour un certain lancer
+    // Recensement des états (nombre de dés, mise) dont il faut connaître
+    // l'espérance de gain avant d'étudier un certain état avec N relances
+    // autorisées, y compris ce dernier, avec le nombre minimal de relances qu'il
+    // faut pour les atteindre. Les états sont triés par nombre de relances
+    // croissant.
+    //
+    // Le cache est toujours rempli par nombre de relances autorisées croissant,
+    // donc si un état atteint au bout de k relances est déjà en cache avec N-k
+    // relances autorisées, tout ce dont il dépend y est déjà aussi.
+    fn etats_a_calculer(&self,
+                        score: Valeur,
+                        nb_des: usize,
+                        mise: Valeur,
+                        max_relances: usize) -> Vec<(usize, Valeur, usize)>
+    {
+        // On explore en largeur d'abord, donc on atteint chaque état pour la
+        // première fois par le plus court chemin
+        let mut etats = Vec::new();
+        let mut deja_vus = HashSet::new();
+        let mut a_explorer = VecDeque::from([(nb_des, mise, 0)]);
+        deja_vus.insert((nb_des, mise));
+        while let Some((nb_des, mise, profondeur)) = a_explorer.pop_front() {
+            let relances = max_relances - profondeur;
+            if self.stats_jets[nb_des-1].esperance_fin.lock().unwrap()
+                                        .contains_key(&(score, mise, relances)) {
+                continue;
+            }
+            etats.push((nb_des, mise, profondeur));
+            if relances == 0 { continue; }
+            for stats_choix in self.stats_jets[nb_des-1].stats_choix.iter() {
+                for poss in stats_choix.choix.iter() {
+                    // Une sexte fait gagner, et on ne relance pas si on atteint
+                    // ou dépasse le score maximal
+                    if self.regles.fait_gagner(&poss.comb) { continue; }
+                    let nouvelle_mise = mise + poss.valeur;
+                    if score + nouvelle_mise >= self.regles.score_max { continue; }
+                    if deja_vus.insert((poss.nb_des_relance, nouvelle_mise)) {
+                        a_explorer.push_back((poss.nb_des_relance,
+                                              nouvelle_mise,
+                                              profondeur + 1));
+                    }
+                }
+            }
+        }
+        etats
+    }
+
+    // Calcul des espérances de gain (et probabilités de fin) d'états atteignables par relance depuis un
+    // état de départ, dont on a besoin pour étudier ce dernier avec plus de N
+    // relances autorisées, en supposant que ce qu'il faut pour l'étudier avec
+    // N relances est en cache. Un état qu'on atteint au bout de k relances y
+    // est étudié avec N-k relances autorisées, et ce en commençant par les
+    // états les plus profonds dont les autres dépendent. Les états d'une même
+    // profondeur ne dépendent pas les uns des autres, donc on les étudie en
+    // parallèle.
+    fn calcul_esperances_relance(&self,
+                                 score: Valeur,
+                                 etats: &[(usize, Valeur, usize)],
+                                 num_relances: usize)
+    {
+        for etats_profondeur in etats.chunk_by(|a, b| a.2 == b.2).rev() {
+            let profondeur = etats_profondeur[0].2;
+            if profondeur > num_relances { continue; }
+            etats_profondeur.par_iter().for_each(|&(nb_des, mise, _)| {
+                self.calcul_esperance_fin_couche(score, nb_des, mise, num_relances - profondeur);
+            });
+        }
+    }
+
+    // Calcul de l'espérance de gain et de la probabilité de finir la partie
+    // avec N relances, une fois que celles des états qu'on peut atteindre avec
+    // moins de relances sont en cache
+    fn calcul_esperance_fin_couche(&self,
+                                   score: Valeur,
+                                   nb_des: usize,
+                                   mise: Valeur,
+                                   max_relances: usize) -> EsperanceFin
+    {
+        // Est-ce que, par chance, j'ai déjà étudié ce cas précédemment?
+        let stats_jet = &self.stats_jets[nb_des-1];
+        if let Some(&esperance_fin) = stats_jet.esperance_fin.lock().unwrap()
+                                               .get(&(score, mise, max_relances)) {
+            return esperance_fin;
+        }
+
+        // Le but est de déterminer une espérance de gain et une probabilité de
+        // gagner la partie pour un certain lancer
         let mut esperance_lancer = 0.;
+        let mut proba_fin_partie = 0.;
 
         // On passe en revue tous les résultats de lancers gagnants
         for stats_choix in stats_jet.stats_choix.iter() {
-            // On note la valeur de la combinaison la plus chère. Si elle nous
-            // amène à plus de 10000, on ne peut pas s'arrêter là.
-            let valeur_max = stats_choix.choix.iter()
-                                              .map(|poss| poss.valeur)
-                                              .max()
-                                              .unwrap();
-            let arret_possible = score + mise + valeur_max <= SCORE_MAX;
-
-            // On cherche la stratégie qui maximise l'espérance
-            let mut esperance_max : Flottant = 0.;
-
-            // On considère la possibilité de prendre chaque combinaison...
-            for poss in stats_choix.choix.iter() {
-                let nouvelle_mise = mise + poss.valeur;
+            // Si l'une des combinaisons nous fait atteindre le score maximal (ou
+            // si c'est une sexte), on a gagné, même si une combinaison plus
+            // chère le dépasserait
+            let atterrissage = stats_choix.choix.iter().any(|poss| {
+                self.regles.fait_gagner(&poss.comb)
+                    || self.regles.score_gagnant(score + mise + poss.valeur)
+            });
+            let mut proba_fin_max : Flottant = if atterrissage { 1. } else { 0. };
 
-                // Si la règle nous y autorise, on peut s'arrêter là
-                if arret_possible {
-                    esperance_max = esperance_max.max(nouvelle_mise as Flottant);
+            // Pour chaque choix, on cherche la décision qui maximise
+            // l'espérance. Au passage, on note ce que chaque relance nous
+            // donne comme chance de gagner la partie, pour garder la meilleure.
+            let mut meilleure_decision : Option<Decision> = None;
+            self.enumerer_decisions_generiques(
+                score,
+                mise,
+                &stats_choix.choix,
+                0..max_relances,
+                |mise| mise as Flottant,
+                |nb_des, mise, max_relances| {
+                    let suite = self.calcul_esperance_fin(score, nb_des, mise, max_relances);
+                    proba_fin_max = proba_fin_max.max(suite.proba_fin);
+                    suite.esperance
+                },
+                |decision| {
+                    if meilleure_decision.is_none_or(|meilleure| {
+                        decision.esperance() > meilleure.esperance()
+                    }) {
+                        meilleure_decision = Some(decision);
+                    }
                 }
+            );
+            let esperance_max = meilleure_decision.map_or(0., |decision| decision.esperance());
 
-                // Si prendre cette combinaison ne nous fait pas atteindre ou
-                // dépasser le score maximal, on peut aussi relancer <= N fois
-                if score + nouvelle_mise >= SCORE_MAX { continue; }
-                for num_relances in 1..=max_relances {
-                    let esperance =
-                        self.calcul_esperance(score,
-                                              poss.nb_des_relance,
-                                              nouvelle_mise,
-                                              num_relances - 1);
-                    esperance_max = esperance_max.max(esperance);
-                }
+            // On pondère le résultat par la chance de faire face à ce choix
+            esperance_lancer += esperance_max * stats_choix.proba;
+            proba_fin_partie += proba_fin_max * stats_choix.proba;
+        }
+
+        // On met en cache ce résultat, sauf si un autre thread l'a calculé en
+        // même temps que nous, auquel cas on garde le sien
+        let esperance_fin = EsperanceFin {
+            esperance: esperance_lancer,
+            proba_fin: proba_fin_partie,
+        };
+        let esperance_fin = stats_jet.esperance_fin.lock().unwrap()
+                                     .inserer_si_absent((score, mise, max_relances),
+                                                        esperance_fin);
+        self.signaler_calcul(score, nb_des, max_relances);
+        esperance_fin
+    }
+
+    // Recherche de la décision qui maximise l'espérance de gain face à un
+    // certain choix de combinaisons, en s'autorisant à relancer N fois
+    fn decision_optimale(&self,
+                         score: Valeur,
+                         mise: Valeur,
+                         choix: &[Possibilite],
+                         max_relances: usize) -> Option<Decision>
+    {
+        let mut meilleure_decision : Option<Decision> = None;
+        self.enumerer_decisions(score, mise, choix, max_relances, |decision| {
+            if meilleure_decision.is_none_or(|meilleure| {
+                decision.esperance() > meilleure.esperance()
+            }) {
+                meilleure_decision = Some(decision);
             }
+        });
+        meilleure_decision
+    }
 
-            // A la fin, on pondère l'espérance maximale calculée par la
-            // probabilité de faire face au choix qu'on a considéré
-            esperance_lancer += esperance_max * stats_choix.proba;
+    // Enumération des décisions qu'on peut prendre face à un certain choix de
+    // combinaisons, en s'autorisant à relancer N fois, avec leur espérance
+    fn enumerer_decisions(&self,
+                          score: Valeur,
+                          mise: Valeur,
+                          choix: &[Possibilite],
+                          max_relances: usize,
+                          traiter: impl FnMut(Decision))
+    {
+        self.enumerer_decisions_generiques(
+            score,
+            mise,
+            choix,
+            0..max_relances,
+            |mise| mise as Flottant,
+            |nb_des, mise, max_relances| {
+                self.calcul_esperance(score, nb_des, mise, max_relances)
+            },
+            traiter
+        )
+    }
+
+    // Même chose, mais en laissant l'appelant choisir ce que vaut le fait
+    // d'encaisser une mise et le fait de relancer avec une certaine mise et un
+    // certain nombre de relances autorisées par la suite. On propose une
+    // relance pour chaque nombre de relances autorisées par "relances".
+    #[allow(clippy::too_many_arguments)]
+    fn enumerer_decisions_generiques(&self,
+                                     score: Valeur,
+                                     mise: Valeur,
+                                     choix: &[Possibilite],
+                                     relances: impl Iterator<Item=usize> + Clone,
+                                     valeur_arret: impl Fn(Valeur) -> Flottant,
+                                     mut valeur_relance: impl FnMut(usize, Valeur, usize) -> Flottant,
+                                     mut traiter: impl FnMut(Decision))
+    {
+        // On note la valeur de la combinaison la plus chère. Si elle nous
+        // amène au-delà du score maximal, on ne peut pas s'arrêter là.
+        if choix.is_empty() { return; }
+        let valeur_max = choix.iter()
+                              .filter(|poss| !self.regles.fait_gagner(&poss.comb))
+                              .map(|poss| poss.valeur)
+                              .max()
+                              .unwrap_or(0);
+        let arret_possible = !self.regles.atterrissage_exact
+                             || self.regles.rebond
+                             || score + mise + valeur_max <= self.regles.score_max;
+
+        // On considère la possibilité de prendre chaque combinaison...
+        for poss in choix.iter() {
+            let comb = poss.comb;
+
+            // Une sexte fait gagner la partie, ce qui revient à encaisser
+            // exactement ce qui nous manque pour atteindre le score maximal
+            // (sauf en un nombre fixe de tours, voir Regles::fait_gagner)
+            if self.regles.fait_gagner(&comb) {
+                let gain = self.regles.score_max - score;
+                traiter(Decision::Encaisser {
+                    comb,
+                    mise: gain,
+                    esperance: valeur_arret(gain),
+                });
+                continue;
+            }
+            let nouvelle_mise = mise + poss.valeur;
+
+            // Si la règle nous y autorise, on peut s'arrêter là. Atteindre
+            // exactement le score maximal fait toujours gagner la partie, et
+            // si on peut le dépasser, on n'encaisse que ce qui manquait (avec
+            // la règle du rebond, on ne gagne que ce qu'il reste après avoir
+            // rebondi). Mais tant qu'on n'a pas ouvert, il faut atteindre le
+            // seuil d'ouverture, et la règle peut nous obliger à relancer tous
+            // les dés.
+            let mise_encaissee = if self.regles.rebond || self.regles.nb_tours > 0 {
+                nouvelle_mise
+            } else {
+                nouvelle_mise.min(self.regles.score_max - score)
+            };
+            let gain = self.regles.gain_encaisse(score, mise_encaissee);
+            let gagnant = self.regles.score_gagnant(score + nouvelle_mise);
+            if (arret_possible || gagnant)
+               && self.regles.encaissement_autorise(score, mise_encaissee)
+               && (gagnant || !self.regles.relance_obligatoire(poss.nb_des_relance))
+            {
+                traiter(Decision::Encaisser {
+                    comb,
+                    mise: gain,
+                    esperance: valeur_arret(gain),
+                });
+            }
+
+            // Si prendre cette combinaison ne nous fait pas atteindre ou
+            // dépasser le score maximal, on peut aussi relancer
+            if score + nouvelle_mise >= self.regles.score_max { continue; }
+            for max_relances in relances.clone() {
+                let esperance = valeur_relance(poss.nb_des_relance,
+                                               nouvelle_mise,
+                                               max_relances);
+                traiter(Decision::Relancer {
+                    comb,
+                    mise: nouvelle_mise,
+                    nb_des: poss.nb_des_relance,
+                    max_relances,
+                    esperance,
+                });
+            }
         }
+    }
 
-        // On met en cache ce résultat
-        assert_eq!(stats_jet.esperance.borrow_mut()
-                            .insert((score, mise, max_relances), esperance_lancer),
-                   None);
+    // Calcul de l'espérance du carré de la mise encaissée à la fin du tour, en
+    // suivant la stratégie qui maximise l'espérance avec N relances
+    fn calcul_moment2(&self,
+                      score: Valeur,
+                      nb_des: usize,
+                      mise: Valeur,
+                      max_relances: usize) -> Flottant
+    {
+        self.calcul_moyenne_issues(|stats_jet| &stats_jet.moment2,
+                                   |mise| (mise as Flottant) * (mise as Flottant),
+                                   0.,
+                                   score,
+                                   nb_des,
+                                   mise,
+                                   max_relances)
+    }
 
-        // On retourne ce résultat à l'appelant
-        esperance_lancer
+    // Calcul de la probabilité de finir le tour sans rien encaisser, en suivant
+    // la stratégie qui maximise l'espérance avec N relances
+    fn calcul_proba_perte(&self,
+                          score: Valeur,
+                          nb_des: usize,
+                          mise: Valeur,
+                          max_relances: usize) -> Flottant
+    {
+        self.calcul_moyenne_issues(|stats_jet| &stats_jet.proba_perte,
+                                   |_mise| 0.,
+                                   1.,
+                                   score,
+                                   nb_des,
+                                   mise,
+                                   max_relances)
     }
 
-    // Calcul de la probabilité de gagner la partie avec N relances
-    fn calcul_proba_fin(&self,
-                        score: Valeur,
-                        nb_des: usize,
-                        mise: Valeur,
-                        max_relances: usize) -> Flottant
+    // Calcul de la moyenne d'une grandeur qui dépend de l'issue du tour (mise
+    // encaissée ou perte), en suivant la stratégie qui maximise l'espérance
+    // avec N relances. On précise où ranger le résultat parmi les caches.
+    #[allow(clippy::too_many_arguments)]
+    fn calcul_moyenne_issues(&self,
+                             cache: impl Fn(&StatsJet) -> &CacheFlottants + Copy,
+                             valeur_arret: impl Fn(Valeur) -> Flottant + Copy,
+                             valeur_perte: Flottant,
+                             score: Valeur,
+                             nb_des: usize,
+                             mise: Valeur,
+                             max_relances: usize) -> Flottant
     {
         // Est-ce que, par chance, j'ai déjà étudié ce cas précédemment?
         let stats_jet = &self.stats_jets[nb_des-1];
-        if let Some(&proba_fin_partie) = stats_jet.proba_fin.borrow()
-                                                  .get(&(score, mise, max_relances)) {
-            return proba_fin_partie;
+        if let Some(&moyenne) = cache(stats_jet).lock().unwrap()
+                                                .get(&(score, mise, max_relances)) {
+            return moyenne;
         }
 
-        // Le but est de déterminer la probabilité de gagner la partie
-        let mut proba_fin_partie = 0.;
+        // On suit la décision optimale pour chaque lancer gagnant, sans oublier
+        // les lancers perdants
+        let mut moyenne = valeur_perte * stats_jet.proba_rien;
+        for stats_choix in stats_jet.stats_choix.iter() {
+            let decision = self.decision_optimale(score,
+                                                  mise,
+                                                  &stats_choix.choix,
+                                                  max_relances);
+            let valeur_choix = match decision {
+                None => valeur_perte,
+                Some(Decision::Encaisser { mise, .. }) => valeur_arret(mise),
+                Some(Decision::Relancer { mise, nb_des, max_relances, .. }) => {
+                    self.calcul_moyenne_issues(cache,
+                                               valeur_arret,
+                                               valeur_perte,
+                                               score,
+                                               nb_des,
+                                               mise,
+                                               max_relances)
+                },
+            };
+            moyenne += valeur_choix * stats_choix.proba;
+        }
 
-        // On passe en revue tous les résultats de lancers gagnants
+        // On met en cache ce résultat, sauf si un autre thread l'a calculé en
+        // même temps que nous, auquel cas on garde le sien
+        let moyenne = cache(stats_jet).lock().unwrap()
+                                      .inserer_si_absent((score, mise, max_relances), moyenne);
+        self.signaler_calcul(score, nb_des, max_relances);
+        moyenne
+    }
+
+    // Calcul de la distribution de probabilité de la mise encaissée à la fin du
+    // tour, en suivant la stratégie qui maximise l'espérance avec N relances
+    fn calcul_distribution(&self,
+                           score: Valeur,
+                           nb_des: usize,
+                           mise: Valeur,
+                           max_relances: usize) -> Distribution
+    {
+        // Est-ce que, par chance, j'ai déjà étudié ce cas précédemment?
+        let stats_jet = &self.stats_jets[nb_des-1];
+        if let Some(distribution) = stats_jet.distribution.lock().unwrap()
+                                             .get(&(score, mise, max_relances)) {
+            return distribution.clone();
+        }
+
+        // On accumule les probabilités de chaque mise finale, en commençant par
+        // le cas où on a tiré aucune combinaison et perdu la mise
+        let mut distribution = BTreeMap::new();
+        distribution.insert(0, stats_jet.proba_rien);
+        let mut ajouter = |mise: Valeur, proba: Flottant| {
+            *distribution.entry(mise).or_insert(0.) += proba;
+        };
+
+        // On passe en revue tous les résultats de lancers gagnants, et on suit
+        // la décision optimale pour chacun d'entre eux
         for stats_choix in stats_jet.stats_choix.iter() {
-            // On note la valeur de la combinaison la plus chère
-            let valeur_max = stats_choix.choix.iter()
-                                              .map(|poss| poss.valeur)
-                                              .max()
-                                              .unwrap();
-
-            // Si elle nous amène à 10000, on a gagné
-            let mut proba_fin_max : Flottant =
-                if score + mise + valeur_max == 10000 { 1. } else { 0. };
-
-            // Sinon, on peut tenter de prendre une combinaison qui nous amène
-            // à mons de 10000 et relancer.
-            for poss in stats_choix.choix.iter() {
-                let nouvelle_mise = mise + poss.valeur;
-                if score + nouvelle_mise >= SCORE_MAX { continue; }
-                for num_relances in 1..=max_relances {
-                    let proba_fin =
-                        self.calcul_proba_fin(score,
-                                              poss.nb_des_relance,
-                                              nouvelle_mise,
-                                              num_relances - 1);
-                    proba_fin_max = proba_fin_max.max(proba_fin);
+            let decision = self.decision_optimale(score,
+                                                  mise,
+                                                  &stats_choix.choix,
+                                                  max_relances);
+            match decision {
+                None => ajouter(0, stats_choix.proba),
+                Some(Decision::Encaisser { mise, .. }) => {
+                    ajouter(mise, stats_choix.proba);
+                },
+                Some(Decision::Relancer { mise, nb_des, max_relances, .. }) => {
+                    let distribution_relance =
+                        self.calcul_distribution(score, nb_des, mise, max_relances);
+                    for &(mise_finale, proba) in distribution_relance.iter() {
+                        ajouter(mise_finale, proba * stats_choix.proba);
+                    }
+                },
+            }
+        }
+        let distribution = distribution.into_iter().collect::<Distribution>();
+
+        // On met en cache ce résultat, sauf si un autre thread l'a calculé en
+        // même temps que nous, auquel cas on garde le sien
+        let distribution = stats_jet.distribution.lock().unwrap()
+                                    .inserer_si_absent((score, mise, max_relances),
+                                                       distribution);
+        self.signaler_calcul(score, nb_des, max_relances);
+        distribution
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combinaison::Bareme;
+
+    // Plus le score à atteindre est élevé, plus on peut prendre de risques
+    // avant de devoir atterrir, donc plus un tour à score nul rapporte
+    #[test]
+    fn esperance_croissante_avec_score_max() {
+        let esperances: Vec<Flottant> = [1000, 1500, 2000].iter()
+            .map(|&score_max| Stats::with_score_max(score_max).esperance(0, 6, 0))
+            .collect();
+        assert!(esperances[0] > 0., "{:?}", esperances);
+        assert!(esperances.windows(2).all(|paire| paire[0] < paire[1]), "{:?}", esperances);
+    }
+
+    // Un barème plus généreux pour les suites et les triples paires rapporte
+    // plus en espérance
+    #[test]
+    fn bareme_genereux() {
+        let regles = Regles { score_max: 2000, ..Regles::default() };
+        let genereux = Regles {
+            bareme: Bareme { suite: 1500, triple_paire: 1000, ..Bareme::default() },
+            ..regles.clone()
+        };
+        let esperance = Stats::with_regles(regles).esperance(0, 6, 0);
+        let esperance_genereuse = Stats::with_regles(genereux).esperance(0, 6, 0);
+        assert!(esperance_genereuse > esperance, "{} <= {}", esperance_genereuse, esperance);
+    }
+
+    // Avec le full, un jet de 5 ou 6 dés offre une combinaison de plus, qui
+    // ne peut qu'améliorer l'espérance
+    #[test]
+    fn full_a_cinq_et_six_des() {
+        let regles = Regles { score_max: 1000, ..Regles::default() };
+        let sans_full = Stats::with_regles(regles.clone());
+        let avec_full = Stats::with_regles(Regles { full: true, ..regles });
+        for nb_des in 5..=6 {
+            let sans = sans_full.esperance(0, nb_des, 0);
+            let avec = avec_full.esperance(0, nb_des, 0);
+            assert!(avec > sans, "{} dés: {} <= {}", nb_des, avec, sans);
+        }
+    }
+
+    // Face à un 1 et un 5, on peut prendre le 5 sans le 1: en fin de partie,
+    // ce sont parfois exactement 50 points qu'il faut pour atterrir
+    #[test]
+    fn cinq_sans_un() {
+        let stats = Stats::with_score_max(1000);
+        let un = Combinaison::FacesSimples { nb_un: 1, nb_cinq: 0 };
+        let cinq = Combinaison::FacesSimples { nb_un: 0, nb_cinq: 1 };
+        for nb_des in 2..=6 {
+            assert!(stats.stats_jets[nb_des-1].stats_choix.iter().any(|stats_choix| {
+                let choix = stats_choix.choix.iter().map(|poss| poss.comb).collect::<Vec<_>>();
+                choix.contains(&un) && choix.contains(&cinq)
+            }), "{} dés", nb_des);
+        }
+    }
+
+    // La distribution de la mise encaissée est normalisée, et sa moyenne est
+    // l'espérance calculée directement
+    #[test]
+    fn distribution_et_esperance() {
+        let stats = Stats::with_score_max(1000);
+        for &(score, nb_des, mise) in &[(0, 6, 0), (0, 3, 200), (500, 2, 300), (900, 6, 0)] {
+            let distribution = stats.distribution_tour(score, nb_des, mise);
+            assert!(distribution.windows(2).all(|paire| paire[0].0 < paire[1].0));
+            let total: Flottant = distribution.iter().map(|&(_, proba)| proba).sum();
+            let moyenne: Flottant =
+                distribution.iter().map(|&(mise, proba)| mise as Flottant * proba).sum();
+            let esperance = stats.esperance(score, nb_des, mise);
+            assert!((total - 1.).abs() <= 1e-5, "{:?}: {}", (score, nb_des, mise), total);
+            assert!((moyenne - esperance).abs() <= 1e-4 * esperance,
+                    "{:?}: {} != {}", (score, nb_des, mise), moyenne, esperance);
+        }
+    }
+
+    // La variance analytique du gain est celle de la distribution de la mise
+    // encaissée en suivant la stratégie optimale
+    #[test]
+    fn variance_et_distribution() {
+        let stats = Stats::with_score_max(1000);
+        for &(score, nb_des, mise) in &[(0, 6, 0), (0, 3, 200), (500, 2, 300), (900, 6, 0)] {
+            let distribution = stats.distribution_tour(score, nb_des, mise);
+            let moyenne: Flottant =
+                distribution.iter().map(|&(mise, proba)| mise as Flottant * proba).sum();
+            let variance: Flottant = distribution.iter()
+                .map(|&(mise, proba)| (mise as Flottant - moyenne).powi(2) * proba)
+                .sum();
+            let analytique = stats.variance_gain(score, nb_des, mise);
+            assert!((variance - analytique).abs() <= 1e-3 * analytique.max(1.),
+                    "{:?}: {} != {}", (score, nb_des, mise), variance, analytique);
+        }
+    }
+
+    // Les espérances à score nul, calculées en double précision (avec la
+    // fonctionnalité "f64"), sur lesquelles les deux précisions s'accordent
+    // à 1e-5 près (la conversion est inutile en double précision)
+    #[test]
+    #[allow(clippy::unnecessary_cast)]
+    fn esperances_double_precision() {
+        let stats = Stats::with_score_max(1000);
+        for &(nb_des, mise, attendue) in &[
+            (1, 0, 149.716_096_f64), (1, 500, 247.976_581),
+            (2, 0, 132.054_361), (2, 500, 342.804_166),
+            (3, 0, 143.603_766), (3, 500, 443.423_153),
+            (4, 0, 190.509_836), (4, 500, 532.714_348),
+            (5, 0, 284.719_960), (5, 500, 617.264_821),
+            (6, 0, 433.961_090), (6, 500, 737.961_947),
+        ] {
+            let esperance = stats.esperance(0, nb_des, mise) as f64;
+            assert!((esperance - attendue).abs() <= 1e-5 * attendue,
+                    "{} dés, mise {}: {} != {}", nb_des, mise, esperance, attendue);
+        }
+    }
+
+    // Calcul de référence de l'espérance de gain, en descendant récursivement
+    // dans l'arbre des relances comme on le faisait avant le calcul par
+    // nombre de relances croissant
+    fn esperance_recursive(stats: &Stats,
+                           score: Valeur,
+                           nb_des: usize,
+                           mise: Valeur,
+                           max_relances: usize,
+                           cache: &mut HashMap<(usize, Valeur, usize), Flottant>) -> Flottant
+    {
+        if let Some(&esperance) = cache.get(&(nb_des, mise, max_relances)) {
+            return esperance;
+        }
+        let mut esperance_lancer = 0.;
+        for stats_choix in stats.stats_jets[nb_des-1].stats_choix.iter() {
+            let mut esperance_max: Option<Flottant> = None;
+            stats.enumerer_decisions_generiques(
+                score,
+                mise,
+                &stats_choix.choix,
+                0..max_relances,
+                |mise| mise as Flottant,
+                |nb_des, mise, max_relances| {
+                    esperance_recursive(stats, score, nb_des, mise, max_relances, cache)
+                },
+                |decision| {
+                    if esperance_max.is_none_or(|max| decision.esperance() > max) {
+                        esperance_max = Some(decision.esperance());
+                    }
+                }
+            );
+            esperance_lancer += esperance_max.unwrap_or(0.) * stats_choix.proba;
+        }
+        cache.insert((nb_des, mise, max_relances), esperance_lancer);
+        esperance_lancer
+    }
+
+    // Le calcul par nombre de relances croissant donne exactement les mêmes
+    // espérances que le calcul récursif
+    #[test]
+    fn esperance_comme_recursion() {
+        let stats = Stats::with_score_max(1000);
+        for &score in &[0, 500, 900] {
+            let mut cache = HashMap::new();
+            for nb_des in 1..=6 {
+                for &mise in &[0, 50, 200] {
+                    for max_relances in 0..=4 {
+                        assert_eq!(stats.calcul_esperance(score, nb_des, mise, max_relances),
+                                   esperance_recursive(&stats, score, nb_des, mise,
+                                                       max_relances, &mut cache),
+                                   "{:?}", (score, nb_des, mise, max_relances));
+                    }
                 }
             }
+        }
+    }
 
-            // On pondère le résultat par la chance de tirer ce jet
-            proba_fin_partie += proba_fin_max * stats_choix.proba;
+    // En début de partie, l'espérance de gain ne converge qu'au bout de
+    // nombreuses relances, mais elle finit par converger avant la limite
+    #[test]
+    fn convergence_lente() {
+        let stats = Stats::new();
+        let relances = stats.relances_necessaires(0, 6, 0).expect("Pas de convergence");
+        assert!(relances >= 5, "{} relances", relances);
+        let esperance = stats.esperance(0, 6, 0);
+        assert_eq!(esperance, stats.calcul_esperance(0, 6, 0, relances));
+        let limite = stats.calcul_esperance(0, 6, 0, NB_RELANCES_MAX);
+        assert!(limite - esperance <= 1e-5 * esperance, "{} -> {}", esperance, limite);
+    }
+
+    // Le gain moyen d'un état est son espérance moins sa mise, y compris quand
+    // la pénalité des pertes à répétition est en jeu, et seuls les états sans
+    // cible ni pénalité sont ordinaires
+    #[test]
+    fn gain_moyen_penalise() {
+        use crate::regles::NB_PERTES_PENALITE;
+        let stats = Stats::with_regles(Regles {
+            score_max: 1000,
+            penalite_pertes: 500,
+            ..Regles::default()
+        });
+        let ordinaire = EtatTour::new(500, 6, 0);
+        let penalise = EtatTour { pertes: NB_PERTES_PENALITE - 1, ..ordinaire };
+        for etat in [ordinaire, penalise] {
+            assert_eq!(stats.gain_moyen_etat(&etat),
+                       stats.esperance_etat(&etat) - etat.mise as Flottant);
+        }
+        assert!(stats.gain_moyen_etat(&penalise) < stats.gain_moyen_etat(&ordinaire));
+        assert!(stats.etat_ordinaire(&ordinaire));
+        assert!(!stats.etat_ordinaire(&penalise));
+        assert!(!stats.etat_ordinaire(&EtatTour { cible: Some(800), ..ordinaire }));
+    }
+
+    // Les pertes d'une stratégie, pondérées par la probabilité de passer par
+    // chaque état, font ce qu'elle perd au total par rapport à la stratégie
+    // optimale, qui elle ne perd rien
+    #[test]
+    fn pertes_strategie() {
+        let stats = Stats::with_score_max(1000);
+        let etat = EtatTour::new(0, stats.regles().nb_des_tot, 0);
+        let pertes = stats.pertes_strategie(&SeuilFixe(300), &etat);
+        assert_eq!(pertes[0].etat, etat);
+        assert_eq!(pertes[0].proba, 1.);
+        assert!(pertes.windows(2).all(|paire| paire[0].etat.mise <= paire[1].etat.mise));
+        let total = pertes.iter().map(PerteEtat::contribution).sum::<Flottant>();
+        let attendu = stats.esperance_etat(&etat)
+                      - stats.esperance_strategie(&SeuilFixe(300), &etat);
+        assert!(total > 0.);
+        assert!((total - attendu).abs() < 1e-3 * attendu, "{} != {}", total, attendu);
+
+        let optimale = simulation::Optimale::new(&stats);
+        let pertes_optimale = stats.pertes_strategie(&optimale, &etat);
+        assert!(pertes_optimale.iter().all(|perte| perte.perte.abs() < 1e-6));
+    }
+
+    // On ne peut encaisser la mise au lieu de lancer les dés que si la règle
+    // le permet: seuil d'ouverture, mise minimale, relance obligatoire de
+    // tous les dés et score maximal
+    #[test]
+    fn arret_possible() {
+        let regles = Regles {
+            score_max: 1000,
+            seuil_ouverture: 500,
+            mise_minimale_pour_stopper: 200,
+            relance_plein: true,
+            ..Regles::default()
+        };
+        let stats = Stats::with_regles(regles);
+        for (score, nb_des, mise, attendu) in [
+            (0, 3, 300, false),   // Pas encore ouvert
+            (0, 3, 500, true),
+            (500, 3, 0, false),   // Rien à encaisser
+            (500, 3, 150, false), // Sous la mise minimale
+            (500, 3, 200, true),
+            (500, 6, 200, false), // Tous les dés ont servi, il faut relancer
+            (500, 2, 500, false), // Score maximal atteint, plus rien à lancer
+        ] {
+            assert_eq!(stats.arret_possible(&EtatTour::new(score, nb_des, mise)), attendu,
+                       "score {}, {} dés, mise {}", score, nb_des, mise);
+        }
+    }
+
+    // A la mise d'équilibre, relancer ne rapporte plus rien en moyenne, alors
+    // qu'à la mise atteignable juste en dessous, ça rapporte encore
+    #[test]
+    fn mise_equilibre() {
+        let stats = Stats::new();
+        let gain_relance = |score, nb_des, mise| {
+            stats.esperance(score, nb_des, mise) - mise as Flottant
+        };
+        assert!(stats.mise_equilibre(7000, 3).is_some());
+        for score in [0, 7000] {
+            for nb_des in 1..=stats.regles().nb_des_tot {
+                let equilibre = match stats.mise_equilibre(score, nb_des) {
+                    Some(mise) => mise,
+                    None => continue,
+                };
+                assert_eq!(stats.seuil_arret(score, nb_des), Some(equilibre));
+                assert!(gain_relance(score, nb_des, equilibre) <= 0.,
+                        "score {}, {} dés, mise {}", score, nb_des, equilibre);
+                let precedente = stats.mises_atteignables()
+                                      .mises(nb_des)
+                                      .rfind(|&mise| mise > 0 && mise < equilibre);
+                if let Some(mise) = precedente {
+                    assert!(gain_relance(score, nb_des, mise) > 0.,
+                            "score {}, {} dés, mise {}", score, nb_des, mise);
+                }
+            }
         }
+    }
 
-        // On met en cache ce résultat
-        assert_eq!(stats_jet.proba_fin.borrow_mut()
-                            .insert((score, mise, max_relances), proba_fin_partie),
-                   None);
+    // Un brelan de 1 à 66536 points dépasse le score maximal, comme un brelan
+    // à 1100 points, et ne doit pas être ramené à 1000 points par un
+    // débordement (comme il l'aurait été quand les valeurs tenaient sur 16 bits)
+    #[test]
+    fn bareme_sans_debordement() {
+        let regles = |brelan_de_un| Regles {
+            score_max: 1000,
+            bareme: Bareme { brelan_de_un, ..Bareme::default() },
+            ..Regles::default()
+        };
+        let enorme = Stats::with_regles(regles(66_536));
+        let trop_grand = Stats::with_regles(regles(1100));
+        for nb_des in 3..=6 {
+            assert_eq!(enorme.esperance(0, nb_des, 0), trop_grand.esperance(0, nb_des, 0));
+        }
+    }
 
-        // On retourne ce résultat à l'appelant
-        proba_fin_partie
+    // Des règles dont les sommes de scores et de mises pourraient déborder
+    // sont rejetées
+    #[test]
+    #[should_panic(expected = "trop élevés")]
+    fn regles_debordantes() {
+        Stats::with_regles(Regles { score_max: Valeur::MAX / 2, ..Regles::default() });
     }
-}
\ No newline at end of file
+
+    // Le nombre de relances indiqué est le premier à partir duquel la
+    // probabilité de finir n'augmente plus
+    #[test]
+    fn relances_proba_fin() {
+        let stats = Stats::with_score_max(1000);
+        for &(score, nb_des, mise) in &[(900, 6, 0), (500, 6, 0), (700, 2, 100)] {
+            let resultat = stats.proba_fin_detaillee(score, nb_des, mise, NB_RELANCES_MAX);
+            assert!(resultat.converge, "{:?}", resultat);
+            let relances = resultat.relances_utilisees;
+            let probas = (0..=relances).map(|num_relances| {
+                stats.calcul_proba_fin(score, nb_des, mise, num_relances)
+            }).collect::<Vec<_>>();
+            assert_eq!(resultat.proba, probas[relances]);
+            assert_eq!(probas[relances], probas[relances-1]);
+            for num_relances in 1..relances {
+                assert!(probas[num_relances] == 0.
+                        || probas[num_relances] > probas[num_relances-1],
+                        "{:?}: convergé dès {} relances",
+                        (score, nb_des, mise), num_relances);
+            }
+        }
+    }
+
+    // L'espérance ne diminue jamais quand on autorise plus de relances, et
+    // se stabilise bien avant le nombre maximal de relances
+    #[test]
+    fn courbe_croissante_et_convergente() {
+        let stats = Stats::with_score_max(1000);
+        for &(score, nb_des, mise) in &[(0, 6, 0), (0, 1, 0), (300, 3, 200), (800, 6, 100)] {
+            let courbe = stats.courbe_convergence(score, nb_des, mise, NB_RELANCES_MAX);
+            let etat = (score, nb_des, mise);
+            assert!(courbe.windows(2).all(|paire| paire[0] <= paire[1]),
+                    "{:?}: {:?}", etat, courbe);
+            let derniere = courbe[NB_RELANCES_MAX];
+            let avant_derniere = courbe[NB_RELANCES_MAX - 1];
+            assert!(derniere - avant_derniere <= 1e-5 * derniere, "{:?}: {:?}", etat, courbe);
+        }
+    }
+
+    // A 100 points du score maximal, avec 2 dés, on atterrit en un lancer avec
+    // au moins un 1 (11 jets sur 36) ou deux 5 (1 jet sur 36), y compris quand
+    // le 1 est accompagné d'un 5 qui ferait dépasser le score maximal si on
+    // prenait tout
+    #[test]
+    fn atterrissage_sans_tout_prendre() {
+        let stats = Stats::with_score_max(1000);
+        let proba = stats.proba_fin(900, 2, 0, 0);
+        assert!((proba - 12. / 36.).abs() <= 1e-6, "{}", proba);
+    }
+
+    // A 50 points du score maximal, avec 6 dés, on atterrit en un lancer dès
+    // qu'on tire au moins un 5, même quand d'autres combinaisons feraient
+    // dépasser le score maximal
+    #[test]
+    fn atterrissage_cinq_seul() {
+        let stats = Stats::with_score_max(1000);
+        let proba = stats.proba_fin(950, 6, 0, 0);
+        let attendue = 1. - (5. / 6. as Flottant).powi(6);
+        assert!((proba - attendue).abs() <= 1e-5, "{} != {}", proba, attendue);
+    }
+
+    // A 100 points du score maximal, avec 6 dés, on atterrit en un lancer avec
+    // au moins un 1 ou deux 5, même quand ils forment un brelan qu'il faut
+    // alors défaire: on ne rate que les jets sans 1 avec au plus un 5
+    #[test]
+    fn atterrissage_brelan_defait() {
+        let stats = Stats::with_score_max(1000);
+        let proba = stats.proba_fin(900, 6, 0, 0);
+        let ratee = (4. as Flottant).powi(6) + 6. * (4. as Flottant).powi(5);
+        let attendue = 1. - ratee / (6. as Flottant).powi(6);
+        assert!((proba - attendue).abs() <= 1e-5, "{} != {}", proba, attendue);
+    }
+
+    // Quand on peut dépasser le score maximal, on finit en un lancer de 2 dés
+    // à 50 points de l'arrivée dès qu'on tire un 1 ou un 5 (20 jets sur 36),
+    // au lieu de devoir tirer un 5 (11 jets sur 36), et l'espérance de gain ne
+    // compte que ce qui manque pour gagner
+    #[test]
+    fn atterrissage_non_exact() {
+        let exact = Stats::with_score_max(1000);
+        let regles = Regles { atterrissage_exact: false, ..exact.regles().clone() };
+        let depasser = Stats::with_regles(regles);
+        let proba = depasser.proba_fin(950, 2, 0, 0);
+        assert!((proba - 20. / 36.).abs() <= 1e-6, "{}", proba);
+        let proba_exacte = exact.proba_fin(950, 2, 0, 0);
+        assert!((proba_exacte - 11. / 36.).abs() <= 1e-6, "{}", proba_exacte);
+
+        let esperance = depasser.esperance(900, 6, 0);
+        assert!(esperance <= 100., "{}", esperance);
+        assert!(esperance > exact.esperance(900, 6, 0));
+    }
+
+    // Sous le seuil d'ouverture, les premières relances ne rapportent rien,
+    // mais l'espérance convergée tient compte des suivantes
+    #[test]
+    fn convergence_sous_seuil_ouverture() {
+        let regles = Regles { score_max: 2000, seuil_ouverture: 1000, ..Regles::default() };
+        let stats = Stats::with_regles(regles);
+        for (nb_des, mise) in [(1, 250), (2, 200)] {
+            let courbe = stats.courbe_convergence(0, nb_des, mise, NB_RELANCES_MAX);
+            assert_eq!(courbe[0], 0.);
+            let esperance = stats.esperance(0, nb_des, mise);
+            assert!(esperance > 100., "{} dés, mise {}: {}", nb_des, mise, esperance);
+            assert!(courbe[NB_RELANCES_MAX] - esperance <= 1e-5 * esperance);
+        }
+    }
+
+    // Le gain moyen se décompose entre les tours perdus, où l'on perd la mise,
+    // et les tours réussis
+    #[test]
+    fn bilan_decompose_gain() {
+        let stats = Stats::with_score_max(1000);
+        for &(score, nb_des, mise) in &[(0, 6, 0), (0, 1, 0), (0, 3, 300), (600, 2, 200)] {
+            let bilan = stats.bilan(score, nb_des, mise);
+            assert_eq!(bilan.esperance, stats.gain_moyen(score, nb_des, mise));
+            assert_eq!(bilan.proba_perte, stats.proba_perte(score, nb_des, mise));
+            assert!(bilan.proba_perte > 0. && bilan.proba_perte < 1., "{:?}", bilan);
+            let recomposee = (1. - bilan.proba_perte) * bilan.gain_moyen_si_reussite
+                             - bilan.proba_perte * mise as Flottant;
+            assert!((recomposee - bilan.esperance).abs() <= 1e-3, "{:?}", bilan);
+        }
+    }
+
+    // Avec la mise pour objectif, on retrouve l'espérance de gain, et avec
+    // l'indicatrice de la victoire, la probabilité de finir la partie
+    #[test]
+    fn objectifs_usuels() {
+        let stats = Stats::with_score_max(1000);
+        let regles = stats.regles();
+        for &(score, nb_des, mise) in &[(0, 6, 0), (300, 2, 100), (800, 6, 0), (950, 3, 0)] {
+            for max_relances in [0, 1, 4] {
+                let esperance = stats.optimiser(score, nb_des, mise, max_relances,
+                                                |mise| mise as Flottant);
+                let attendue = stats.courbe_convergence(score, nb_des, mise, max_relances)
+                                    [max_relances];
+                assert!((esperance - attendue).abs() <= 1e-5 * attendue.max(1.),
+                        "{:?}: {} != {}", (score, nb_des, mise, max_relances), esperance,
+                        attendue);
+
+                let proba = stats.optimiser(score, nb_des, mise, max_relances, |mise| {
+                    if regles.score_gagnant(score + mise) { 1. } else { 0. }
+                });
+                let attendue = stats.proba_fin(score, nb_des, mise, max_relances);
+                assert!((proba - attendue).abs() <= 1e-6,
+                        "{:?}: {} != {}", (score, nb_des, mise, max_relances), proba, attendue);
+            }
+        }
+    }
+
+    // Des caches de capacité trop faible pour tout garder ralentissent les
+    // calculs, mais n'en changent pas les résultats
+    #[test]
+    fn caches_bornes() {
+        let regles = Regles { score_max: 1000, ..Regles::default() };
+        let libres = Stats::with_regles(regles.clone());
+        let mut bornees = Stats::with_regles(regles);
+        bornees.limiter_caches(Some(200));
+        for score in (0..1000).step_by(250) {
+            for nb_des in 1..=6 {
+                for mise in [0, 100, 300] {
+                    if libres.mises_atteignables().jet_impossible(score, nb_des, mise) {
+                        continue;
+                    }
+                    let etat = (score, nb_des, mise);
+                    assert_eq!(bornees.esperance(score, nb_des, mise),
+                               libres.esperance(score, nb_des, mise), "{:?}", etat);
+                    assert_eq!(bornees.proba_fin(score, nb_des, mise, 3),
+                               libres.proba_fin(score, nb_des, mise, 3), "{:?}", etat);
+                }
+            }
+        }
+
+        // Seul le cache des espérances sert ici, et il a bien dû oublier des
+        // résultats pour respecter sa capacité
+        assert!(libres.tailles_caches().iter().any(|&taille| taille > 200));
+        assert!(bornees.tailles_caches().iter().all(|&taille| taille <= 200));
+    }
+
+    // La distribution du nombre de tours pour gagner se somme à 1 quand on
+    // regarde assez de tours, et sa moyenne est le nombre moyen de tours
+    #[test]
+    fn distribution_tours() {
+        let stats = Stats::with_regles(Regles { score_max: 1000, ..Regles::default() });
+        let distribution = stats.distribution_tours_victoire(0, 60);
+        assert_eq!(distribution.len(), 60);
+        assert!(distribution.iter().all(|&proba| proba >= 0.));
+        let total : Flottant = distribution.iter().sum();
+        assert!((total - 1.).abs() < 1e-4, "{}", total);
+        let moyenne : Flottant = (1..).zip(&distribution)
+                                      .map(|(tours, &proba)| tours as Flottant * proba)
+                                      .sum();
+        let esperance = stats.esperance_tours(0);
+        assert!((moyenne - esperance).abs() < 1e-3 * esperance, "{} != {}", moyenne, esperance);
+    }
+
+    // Avec une mise minimale pour s'arrêter, ni les calculs ni le moteur de
+    // jeu ne proposent d'encaisser moins, sauf pour finir la partie
+    #[test]
+    fn mise_minimale_respectee() {
+        use crate::simulation::{Action, EtatTour, actions_possibles};
+        let etat_tour = |score, nb_des, mise| {
+            EtatTour { score, score_adverse: 0, nb_des, mise, pertes: 0, cible: None }
+        };
+        let regles = Regles { score_max: 2000, mise_minimale_pour_stopper: 350,
+                              ..Regles::default() };
+        let stats = Stats::with_regles(regles.clone());
+        let mut nb_encaissements = 0;
+        for nb_des in 1..=6 {
+            for mise in [0, 50, 100, 200, 300] {
+                if stats.mises_atteignables().jet_impossible(0, nb_des, mise) { continue; }
+                let etat = etat_tour(0, nb_des, mise);
+                for stats_choix in stats.stats_jets[nb_des-1].stats_choix.iter() {
+                    let choix = stats_choix.choix.iter().map(|poss| poss.comb)
+                                                 .collect::<Vec<_>>();
+                    for decision in stats.decisions_possibles(0, nb_des, mise, &choix) {
+                        if let Decision::Encaisser { mise, .. } = decision {
+                            assert!(mise >= 350, "{:?}: {:?}", etat, decision);
+                            nb_encaissements += 1;
+                        }
+                    }
+                    for action in actions_possibles(&regles, &etat, &choix) {
+                        if let Action::Encaisser { mise, .. } = action {
+                            assert!(mise >= 350, "{:?}: {:?}", etat, action);
+                        }
+                    }
+                }
+            }
+        }
+        assert!(nb_encaissements > 0);
+
+        // Le dernier 1 qui fait gagner s'encaisse quand même
+        let un = Combinaison::FacesSimples { nb_un: 1, nb_cinq: 0 };
+        let encaisser_un = |decision: &Decision| {
+            matches!(decision, Decision::Encaisser { mise: 100, .. })
+        };
+        assert!(stats.decisions_possibles(1900, 1, 0, &[un]).iter().any(encaisser_un));
+        assert!(actions_possibles(&regles, &etat_tour(1900, 1, 0), &[un]).iter()
+                     .any(|action| matches!(action, Action::Encaisser { mise: 100, .. })));
+    }
+
+    // Avec une mise minimale pour s'arrêter, un seul dé ne rapporte rien sans
+    // relance: l'espérance n'a pas convergé pour autant
+    #[test]
+    fn convergence_esperance_nulle() {
+        let regles = Regles {
+            score_max: 1000,
+            mise_minimale_pour_stopper: 300,
+            ..Regles::default()
+        };
+        let stats = Stats::with_regles(regles);
+        let courbe = stats.courbe_convergence(0, 1, 0, NB_RELANCES_MAX);
+        assert_eq!(courbe[0], 0.);
+        let relances = stats.relances_necessaires(0, 1, 0).unwrap();
+        assert!(relances > 1);
+        let esperance = stats.esperance(0, 1, 0);
+        assert_eq!(esperance, courbe[relances]);
+        assert!(courbe[NB_RELANCES_MAX] - esperance <= 1e-5 * esperance);
+    }
+
+}
@@ -0,0 +1,426 @@
+use super::{Decision, Stats};
+use crate::{
+    Flottant,
+    Valeur,
+    combinaison::Combinaison,
+};
+
+use std::{
+    fmt::{self, Display},
+    io::{self, Write},
+};
+
+
+// Arbre des possibles à partir d'un lancer de dés, développé jusqu'à une
+// certaine profondeur pour une stratégie qui maximise l'espérance de gain. Ce
+// n'est pas utilisé par les calculs, mais ça permet de vérifier à la main ce
+// que la récursion fait dans une situation donnée (par exemple en fin de
+// partie, quand il faut atterrir pile sur le score maximal).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arbre {
+    // Score acquis lors des tours précédents
+    pub score: Valeur,
+
+    // Nombre de dés lancés
+    pub nb_des: usize,
+
+    // Mise accumulée avant ce lancer
+    pub mise: Valeur,
+
+    // Probabilité d'en arriver à ce lancer depuis la racine de l'arbre, en
+    // suivant les décisions qui y mènent
+    pub proba: Flottant,
+
+    // Espérance de gain de ce lancer
+    pub esperance: Flottant,
+
+    // Probabilité de ne tirer aucune combinaison
+    pub proba_rien: Flottant,
+
+    // Choix face auxquels ce lancer peut nous placer (aucun si on a atteint la
+    // profondeur demandée)
+    pub choix: Vec<ChoixArbre>,
+}
+
+// L'un des choix de combinaisons face auxquels un lancer peut nous placer
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChoixArbre {
+    // Combinaisons entre lesquelles il faut choisir
+    pub combinaisons: Vec<Combinaison>,
+
+    // Probabilité de faire face à ce choix lors du lancer
+    pub proba: Flottant,
+
+    // Décisions que la règle autorise face à ce choix (aucune si toutes les
+    // combinaisons font dépasser le score maximal, auquel cas le tour est
+    // perdu)
+    pub options: Vec<OptionArbre>,
+}
+
+// L'une des décisions qu'on peut prendre face à un choix de combinaisons
+#[derive(Clone, Debug, PartialEq)]
+pub struct OptionArbre {
+    // Décision, avec son espérance de gain
+    pub decision: Decision,
+
+    // Est-ce la décision qui maximise l'espérance de gain?
+    pub optimale: bool,
+
+    // Lancer suivant si on relance, tant qu'on n'a pas atteint la profondeur
+    // demandée
+    pub suite: Option<Arbre>,
+}
+
+impl Stats {
+    // Arbre des possibles à partir d'un certain lancer, où l'on développe
+    // "profondeur" lancers successifs (0 pour n'avoir que la racine). On
+    // développe toutes les relances, pas seulement les optimales, donc l'arbre
+    // grossit très vite avec la profondeur quand on lance beaucoup de dés.
+    pub fn arbre(&self,
+                 score: Valeur,
+                 nb_des: usize,
+                 mise: Valeur,
+                 profondeur: usize) -> Arbre
+    {
+        self.developper_arbre(score, nb_des, mise, 1., profondeur)
+    }
+
+    // Développement d'un noeud de l'arbre des possibles, qu'on atteint avec
+    // une certaine probabilité depuis la racine
+    fn developper_arbre(&self,
+                        score: Valeur,
+                        nb_des: usize,
+                        mise: Valeur,
+                        proba: Flottant,
+                        profondeur: usize) -> Arbre
+    {
+        let stats_jet = &self.stats_jets[nb_des-1];
+        let choix = if profondeur == 0 {
+            Vec::new()
+        } else {
+            stats_jet.stats_choix.iter().map(|stats_choix| {
+                let combinaisons = stats_choix.choix.iter()
+                                                    .map(|poss| poss.comb)
+                                                    .collect::<Vec<_>>();
+                let decisions = self.decisions_possibles(score, nb_des, mise, &combinaisons);
+                let meilleure = decisions.iter()
+                                         .map(|decision| decision.esperance())
+                                         .fold(Flottant::NEG_INFINITY, Flottant::max);
+                let options = decisions.into_iter().map(|decision| {
+                    let suite = match decision {
+                        Decision::Relancer { mise, nb_des, .. } if profondeur > 1 => {
+                            Some(self.developper_arbre(score, nb_des, mise,
+                                                       proba * stats_choix.proba,
+                                                       profondeur - 1))
+                        },
+                        _ => None,
+                    };
+                    OptionArbre {
+                        decision,
+                        optimale: decision.esperance() == meilleure,
+                        suite,
+                    }
+                }).collect();
+                ChoixArbre {
+                    combinaisons,
+                    proba: stats_choix.proba,
+                    options,
+                }
+            }).collect()
+        };
+        Arbre {
+            score,
+            nb_des,
+            mise,
+            proba,
+            esperance: self.esperance(score, nb_des, mise),
+            proba_rien: stats_jet.proba_rien,
+            choix,
+        }
+    }
+}
+
+impl Arbre {
+    // Nombre de lancers dans l'arbre, racine comprise
+    pub fn nb_noeuds(&self) -> usize {
+        1 + self.choix.iter()
+                      .flat_map(|choix| choix.options.iter())
+                      .filter_map(|option| option.suite.as_ref())
+                      .map(Arbre::nb_noeuds)
+                      .sum::<usize>()
+    }
+
+    // Ecriture de l'arbre, chaque niveau étant décalé d'un certain nombre de
+    // fois deux espaces par rapport au précédent
+    fn ecrire(&self, formatter: &mut fmt::Formatter, decalage: usize) -> Result<(), fmt::Error> {
+        let marge = "  ".repeat(decalage);
+        writeln!(formatter, "{}Lancer de {} dé{} avec {} points (score {}): \
+                             atteint à {:.2}%, espérance {:.1}",
+                 marge, self.nb_des, if self.nb_des > 1 { "s" } else { "" }, self.mise,
+                 self.score, self.proba * 100., self.esperance)?;
+        if self.choix.is_empty() { return Ok(()); }
+        writeln!(formatter, "{}  {:5.2}% rien", marge, self.proba_rien * 100.)?;
+        for choix in self.choix.iter() {
+            let combinaisons = choix.combinaisons.iter()
+                                                 .map(|comb| format!("{:?}", comb))
+                                                 .collect::<Vec<_>>();
+            write!(formatter, "{}  {:5.2}% {}", marge, choix.proba * 100.,
+                   combinaisons.join(" / "))?;
+            if choix.options.is_empty() {
+                writeln!(formatter, " -> perdu, tout dépasse")?;
+                continue;
+            }
+            writeln!(formatter)?;
+            for option in choix.options.iter() {
+                let marque = if option.optimale { '*' } else { ' ' };
+                let decision = option.decision;
+                match decision {
+                    Decision::Encaisser { comb, mise, esperance } => {
+                        writeln!(formatter, "{}    {} {:?}, encaisser {} points: espérance {:.1}",
+                                 marge, marque, comb, mise, esperance)?;
+                    },
+                    Decision::Relancer { comb, mise, nb_des, esperance, .. } => {
+                        writeln!(formatter, "{}    {} {:?}, relancer {} dés avec {} points: \
+                                             espérance {:.1}",
+                                 marge, marque, comb, nb_des, mise, esperance)?;
+                    },
+                }
+                if let Some(suite) = &option.suite {
+                    suite.ecrire(formatter, decalage + 3)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Arbre {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.ecrire(formatter, 0)
+    }
+}
+
+// Ecriture d'un arbre des possibles au format DOT de Graphviz. Les lancers sont
+// des ellipses, les choix de combinaisons des rectangles atteints avec une
+// certaine probabilité, et les décisions des flèches qui partent de ces
+// derniers, celles qui maximisent l'espérance étant mises en évidence.
+//
+// Pour que le graphe reste lisible, on regroupe les choix qu'on a moins de
+// "proba_min" chances de rencontrer depuis la racine, et on arrête de
+// développer l'arbre après "nb_noeuds_max" noeuds (à quelques-uns près, car on
+// finit le noeud en cours).
+pub fn ecrire_dot(arbre: &Arbre,
+                  sortie: &mut impl Write,
+                  nb_noeuds_max: usize,
+                  proba_min: Flottant) -> io::Result<()>
+{
+    writeln!(sortie, "digraph arbre {{")?;
+    writeln!(sortie, "    node [fontname=\"sans-serif\", fontsize=10];")?;
+    writeln!(sortie, "    edge [fontname=\"sans-serif\", fontsize=9];")?;
+    let mut ecrivain = EcrivainDot { sortie, nb_noeuds: 0, nb_noeuds_max, proba_min };
+    ecrivain.lancer(arbre)?;
+    writeln!(ecrivain.sortie, "}}")
+}
+
+// Etat de l'écriture d'un arbre au format DOT
+struct EcrivainDot<'sortie, W: Write> {
+    // Où on écrit le graphe
+    sortie: &'sortie mut W,
+
+    // Nombre de noeuds déjà écrits, qui sert aussi à les numéroter
+    nb_noeuds: usize,
+
+    // Nombre de noeuds au-delà duquel on arrête de développer l'arbre
+    nb_noeuds_max: usize,
+
+    // Probabilité en-dessous de laquelle on regroupe les choix
+    proba_min: Flottant,
+}
+
+impl<W: Write> EcrivainDot<'_, W> {
+    // Ecrire un noeud, et retourner son identifiant
+    fn noeud(&mut self, forme: &str, etiquette: &str) -> io::Result<usize> {
+        let id = self.nb_noeuds;
+        self.nb_noeuds += 1;
+        writeln!(self.sortie, "    n{} [shape={}, label=\"{}\"];", id, forme, etiquette)?;
+        Ok(id)
+    }
+
+    // Ecrire une flèche entre deux noeuds, mise en évidence si elle fait
+    // partie du chemin optimal
+    fn fleche(&mut self,
+              depart: usize,
+              arrivee: usize,
+              etiquette: &str,
+              optimale: bool) -> io::Result<()>
+    {
+        let style = if optimale { ", color=red, fontcolor=red, penwidth=2" } else { "" };
+        writeln!(self.sortie, "    n{} -> n{} [label=\"{}\"{}];",
+                 depart, arrivee, etiquette, style)
+    }
+
+    // Ecrire un lancer et ce qui en découle, et retourner son identifiant
+    fn lancer(&mut self, arbre: &Arbre) -> io::Result<usize> {
+        let id = self.noeud("ellipse", &format!("score {}\\n{} dé{}, mise {}\\nespérance {:.1}",
+                                                arbre.score, arbre.nb_des,
+                                                if arbre.nb_des > 1 { "s" } else { "" },
+                                                arbre.mise, arbre.esperance))?;
+        if arbre.choix.is_empty() { return Ok(id); }
+
+        // Pas la peine de détailler les façons de tout perdre
+        if arbre.proba_rien > 0. {
+            let perdu = self.noeud("plaintext", "perdu")?;
+            self.fleche(id, perdu, &format!("{:.1}% rien", arbre.proba_rien * 100.), false)?;
+        }
+
+        // On regroupe les choix improbables, ainsi que ceux qu'on n'a plus la
+        // place d'écrire
+        let mut nb_regroupes = 0;
+        let mut proba_regroupee = 0.;
+        for choix in arbre.choix.iter() {
+            if arbre.proba * choix.proba < self.proba_min || self.nb_noeuds >= self.nb_noeuds_max {
+                nb_regroupes += 1;
+                proba_regroupee += choix.proba;
+                continue;
+            }
+            let combinaisons = choix.combinaisons.iter()
+                                                 .map(|comb| format!("{:?}", comb))
+                                                 .collect::<Vec<_>>();
+            let mut etiquette = combinaisons.join(" / ");
+            if choix.options.is_empty() { etiquette.push_str("\\nperdu, tout dépasse"); }
+            let id_choix = self.noeud("box", &etiquette)?;
+            self.fleche(id, id_choix, &format!("{:.1}%", choix.proba * 100.), false)?;
+            for option in choix.options.iter() {
+                self.option(id_choix, option)?;
+            }
+        }
+        if nb_regroupes > 0 {
+            let autres = self.noeud("plaintext", &format!("{} autre{} choix", nb_regroupes,
+                                                          if nb_regroupes > 1 { "s" } else { "" }))?;
+            self.fleche(id, autres, &format!("{:.1}%", proba_regroupee * 100.), false)?;
+        }
+        Ok(id)
+    }
+
+    // Ecrire une décision prise face à un choix de combinaisons
+    fn option(&mut self, id_choix: usize, option: &OptionArbre) -> io::Result<()> {
+        let (etiquette, arrivee) = match option.decision {
+            Decision::Encaisser { comb, mise, esperance } => {
+                let arrivee = self.noeud("plaintext", &format!("encaisse {}", mise))?;
+                (format!("{:?}, encaisser\\n{:.1}", comb, esperance), arrivee)
+            },
+            Decision::Relancer { comb, mise, nb_des, esperance, .. } => {
+                let arrivee = match &option.suite {
+                    Some(suite) if self.nb_noeuds < self.nb_noeuds_max => self.lancer(suite)?,
+                    _ => self.noeud("plaintext", &format!("relance {} dé{} avec {}...",
+                                                          nb_des, if nb_des > 1 { "s" } else { "" },
+                                                          mise))?,
+                };
+                (format!("{:?}, relancer\\n{:.1}", comb, esperance), arrivee)
+            },
+        };
+        self.fleche(id_choix, arrivee, &etiquette, option.optimale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Petit arbre fait à la main: avec un dé et 900 points, un 1 fait gagner,
+    // et un 5 laisse le choix entre relancer six dés et encaisser
+    fn petit_arbre() -> Arbre {
+        let un = Combinaison::FacesSimples { nb_un: 1, nb_cinq: 0 };
+        let cinq = Combinaison::FacesSimples { nb_un: 0, nb_cinq: 1 };
+        let relance = Arbre {
+            score: 900, nb_des: 6, mise: 50, proba: 0.25,
+            esperance: 62.5, proba_rien: 0.03, choix: Vec::new(),
+        };
+        Arbre {
+            score: 900, nb_des: 1, mise: 0, proba: 1.,
+            esperance: 40.5, proba_rien: 0.5,
+            choix: vec![
+                ChoixArbre {
+                    combinaisons: vec![un],
+                    proba: 0.25,
+                    options: vec![OptionArbre {
+                        decision: Decision::Encaisser { comb: un, mise: 100, esperance: 100. },
+                        optimale: true,
+                        suite: None,
+                    }],
+                },
+                ChoixArbre {
+                    combinaisons: vec![cinq],
+                    proba: 0.25,
+                    options: vec![
+                        OptionArbre {
+                            decision: Decision::Relancer { comb: cinq, mise: 50, nb_des: 6,
+                                                           max_relances: 0, esperance: 62.5 },
+                            optimale: true,
+                            suite: Some(relance),
+                        },
+                        OptionArbre {
+                            decision: Decision::Encaisser { comb: cinq, mise: 50,
+                                                            esperance: 50. },
+                            optimale: false,
+                            suite: None,
+                        },
+                    ],
+                },
+            ],
+        }
+    }
+
+    fn dot(arbre: &Arbre, nb_noeuds_max: usize, proba_min: Flottant) -> String {
+        let mut sortie = Vec::new();
+        ecrire_dot(arbre, &mut sortie, nb_noeuds_max, proba_min).unwrap();
+        String::from_utf8(sortie).unwrap()
+    }
+
+    // Export complet d'un petit arbre
+    #[test]
+    fn export_dot() {
+        let arbre = petit_arbre();
+        assert_eq!(arbre.nb_noeuds(), 2);
+        assert_eq!(dot(&arbre, 100, 0.), "\
+digraph arbre {
+    node [fontname=\"sans-serif\", fontsize=10];
+    edge [fontname=\"sans-serif\", fontsize=9];
+    n0 [shape=ellipse, label=\"score 900\\n1 dé, mise 0\\nespérance 40.5\"];
+    n1 [shape=plaintext, label=\"perdu\"];
+    n0 -> n1 [label=\"50.0% rien\"];
+    n2 [shape=box, label=\"1x1\"];
+    n0 -> n2 [label=\"25.0%\"];
+    n3 [shape=plaintext, label=\"encaisse 100\"];
+    n2 -> n3 [label=\"1x1, encaisser\\n100.0\", color=red, fontcolor=red, penwidth=2];
+    n4 [shape=box, label=\"1x5\"];
+    n0 -> n4 [label=\"25.0%\"];
+    n5 [shape=ellipse, label=\"score 900\\n6 dés, mise 50\\nespérance 62.5\"];
+    n4 -> n5 [label=\"1x5, relancer\\n62.5\", color=red, fontcolor=red, penwidth=2];
+    n6 [shape=plaintext, label=\"encaisse 50\"];
+    n4 -> n6 [label=\"1x5, encaisser\\n50.0\"];
+}
+");
+    }
+
+    // Les choix improbables, et ceux qui dépassent le nombre de noeuds
+    // maximal, sont regroupés en un seul noeud
+    #[test]
+    fn export_dot_regroupe() {
+        let arbre = petit_arbre();
+        let improbables = dot(&arbre, 100, 0.3);
+        assert!(improbables.ends_with(concat!(
+            "    n2 [shape=plaintext, label=\"2 autres choix\"];\n",
+            "    n0 -> n2 [label=\"50.0%\"];\n}\n",
+        )));
+        assert!(!improbables.contains("shape=box"));
+
+        let tronque = dot(&arbre, 4, 0.);
+        assert!(tronque.contains("    n3 [shape=plaintext, label=\"encaisse 100\"];\n"));
+        assert!(tronque.ends_with(concat!(
+            "    n4 [shape=plaintext, label=\"1 autre choix\"];\n",
+            "    n0 -> n4 [label=\"25.0%\"];\n}\n",
+        )));
+        assert!(!tronque.contains("n5"));
+    }
+}
@@ -0,0 +1,257 @@
+use crate::Flottant;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+};
+
+
+// Cache de résultats, qui peut oublier les résultats les moins récemment
+// utilisés pour ne pas dépasser une certaine capacité. Par défaut, la capacité
+// n'est pas bornée, et on ne suit alors pas l'ordre d'utilisation des résultats
+// pour ne pas ralentir les calculs.
+pub(super) struct Cache<K, V> {
+    // Résultats en cache, avec la date de leur dernière utilisation
+    entrees: HashMap<K, Entree<V>>,
+
+    // Clés des résultats en cache par date de dernière utilisation (seulement
+    // quand la capacité est bornée)
+    ordre: BTreeMap<u64, K>,
+
+    // Date à laquelle aura lieu la prochaine utilisation
+    horloge: u64,
+
+    // Nombre maximal de résultats qu'on garde en cache
+    capacite: Option<usize>,
+
+    // Nombre de recherches fructueuses, infructueuses, et de mises en cache
+    // depuis la dernière remise à zéro des compteurs
+    succes: u64,
+    echecs: u64,
+    insertions: u64,
+}
+
+// Un résultat en cache
+struct Entree<V> {
+    valeur: V,
+    date: u64,
+}
+
+// Utilisation de l'un des caches, pour savoir s'il est efficace. Les compteurs
+// ne sont pas sauvegardés avec les résultats.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatistiquesCache {
+    // Nombre de dés lancés dans les états dont le cache garde les résultats
+    pub nb_des: usize,
+
+    // Grandeur dont le cache garde les résultats
+    pub grandeur: &'static str,
+
+    // Nombre de fois où l'on a trouvé un résultat dans le cache...
+    pub succes: u64,
+
+    // ...où on ne l'y a pas trouvé...
+    pub echecs: u64,
+
+    // ...et où l'on en a ajouté un
+    pub insertions: u64,
+
+    // Nombre de résultats actuellement en cache
+    pub taille: usize,
+}
+
+impl StatistiquesCache {
+    // Proportion des recherches qui ont trouvé leur résultat dans le cache
+    pub fn taux_succes(&self) -> Flottant {
+        let recherches = self.succes + self.echecs;
+        if recherches == 0 { return 0.; }
+        self.succes as Flottant / recherches as Flottant
+    }
+}
+
+impl<K: Copy + Eq + Hash, V: Clone> Cache<K, V> {
+    // Créer un cache vide, de capacité non bornée
+    pub fn new() -> Self {
+        Self {
+            entrees: HashMap::new(),
+            ordre: BTreeMap::new(),
+            horloge: 0,
+            capacite: None,
+            succes: 0,
+            echecs: 0,
+            insertions: 0,
+        }
+    }
+
+    // Nombre de résultats en cache
+    pub fn len(&self) -> usize {
+        self.entrees.len()
+    }
+
+    // Savoir si un résultat est en cache, sans que ça compte comme une
+    // utilisation
+    pub fn contains_key(&self, cle: &K) -> bool {
+        self.entrees.contains_key(cle)
+    }
+
+    // Retrouver un résultat en cache
+    pub fn get(&mut self, cle: &K) -> Option<&V> {
+        let trouve = self.entrees.contains_key(cle);
+        if trouve { self.succes += 1; } else { self.echecs += 1; }
+        self.utiliser(cle)
+    }
+
+    // Même chose, sans toucher aux compteurs
+    fn utiliser(&mut self, cle: &K) -> Option<&V> {
+        let entree = self.entrees.get_mut(cle)?;
+        if self.capacite.is_some() {
+            self.ordre.remove(&entree.date);
+            entree.date = self.horloge;
+            self.ordre.insert(self.horloge, *cle);
+            self.horloge += 1;
+        }
+        Some(&entree.valeur)
+    }
+
+    // Mettre un résultat en cache, en remplaçant celui qui s'y trouvait déjà
+    pub fn insert(&mut self, cle: K, valeur: V) {
+        self.insertions += 1;
+        let date = self.horloge;
+        self.horloge += 1;
+        if let Some(ancienne) = self.entrees.insert(cle, Entree { valeur, date }) {
+            self.ordre.remove(&ancienne.date);
+        }
+        if self.capacite.is_some() {
+            self.ordre.insert(date, cle);
+            self.oublier_excedent();
+        }
+    }
+
+    // Mettre un résultat en cache, sauf si on en avait déjà un, auquel cas on
+    // garde ce dernier. Dans les deux cas, on retourne le résultat en cache.
+    pub fn inserer_si_absent(&mut self, cle: K, valeur: V) -> V {
+        if let Some(existante) = self.utiliser(&cle) {
+            return existante.clone();
+        }
+        self.insert(cle, valeur.clone());
+        valeur
+    }
+
+    // Utilisation du cache depuis la dernière remise à zéro des compteurs, qui
+    // en contient les résultats pour un certain nombre de dés
+    pub fn statistiques(&self, nb_des: usize, grandeur: &'static str) -> StatistiquesCache {
+        StatistiquesCache {
+            nb_des,
+            grandeur,
+            succes: self.succes,
+            echecs: self.echecs,
+            insertions: self.insertions,
+            taille: self.len(),
+        }
+    }
+
+    // Remettre à zéro les compteurs d'utilisation du cache
+    pub fn remettre_compteurs_a_zero(&mut self) {
+        self.succes = 0;
+        self.echecs = 0;
+        self.insertions = 0;
+    }
+
+    // Oublier tous les résultats (mais pas les compteurs)
+    pub fn clear(&mut self) {
+        self.entrees.clear();
+        self.ordre.clear();
+    }
+
+    // Changer la capacité du cache, en oubliant les résultats les moins
+    // récemment utilisés si elle est dépassée. Quand on se met à borner la
+    // capacité, on ne sait pas dans quel ordre les résultats déjà en cache ont
+    // été utilisés, donc on en choisit un arbitrairement.
+    pub fn limiter(&mut self, capacite: Option<usize>) {
+        assert_ne!(capacite, Some(0), "Un cache doit pouvoir garder un résultat");
+        match (self.capacite, capacite) {
+            (None, Some(_)) => {
+                for (cle, entree) in self.entrees.iter_mut() {
+                    entree.date = self.horloge;
+                    self.ordre.insert(self.horloge, *cle);
+                    self.horloge += 1;
+                }
+            },
+            (Some(_), None) => self.ordre.clear(),
+            _ => {},
+        }
+        self.capacite = capacite;
+        self.oublier_excedent();
+    }
+
+    // Oublier les résultats les moins récemment utilisés jusqu'à ce que la
+    // capacité soit respectée
+    fn oublier_excedent(&mut self) {
+        let capacite = match self.capacite {
+            Some(capacite) => capacite,
+            None => return,
+        };
+        while self.entrees.len() > capacite {
+            let (_, cle) = self.ordre.pop_first()
+                                     .expect("Chaque résultat devrait avoir une date");
+            self.entrees.remove(&cle);
+        }
+    }
+}
+
+// On sauvegarde un cache comme la table des résultats qu'il contient, sans
+// l'ordre d'utilisation ni la capacité, qui ne concernent que l'exécution en
+// cours. Un cache rechargé a donc une capacité non bornée.
+impl<K: Serialize, V: Serialize> Serialize for Cache<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.entrees.iter().map(|(cle, entree)| (cle, &entree.valeur)))
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for Cache<K, V>
+    where K: Deserialize<'de> + Eq + Hash,
+          V: Deserialize<'de>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let valeurs = HashMap::<K, V>::deserialize(deserializer)?;
+        Ok(Self {
+            entrees: valeurs.into_iter()
+                            .map(|(cle, valeur)| (cle, Entree { valeur, date: 0 }))
+                            .collect(),
+            ordre: BTreeMap::new(),
+            horloge: 0,
+            capacite: None,
+            succes: 0,
+            echecs: 0,
+            insertions: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Au-delà de sa capacité, le cache oublie le résultat le moins récemment
+    // utilisé
+    #[test]
+    fn oubli_du_moins_recent() {
+        let mut cache = Cache::new();
+        cache.limiter(Some(2));
+        cache.insert('a', 1);
+        cache.insert('b', 2);
+        assert_eq!(cache.get(&'a'), Some(&1));
+        cache.insert('c', 3);
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.contains_key(&'b'));
+        assert_eq!(cache.get(&'a'), Some(&1));
+        assert_eq!(cache.get(&'c'), Some(&3));
+
+        // En réduisant la capacité, on garde le plus récemment utilisé
+        cache.limiter(Some(1));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&'c'), Some(&3));
+    }
+}
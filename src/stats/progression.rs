@@ -0,0 +1,164 @@
+use super::{Stats, StatistiquesCache};
+use crate::Valeur;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+// On ne signale l'avancement qu'une fois tous les N nouveaux calculs, pour que
+// le suivi de progression ne ralentisse pas les calculs
+const PERIODE_SIGNALEMENT : usize = 16384;
+
+// Avancement des calculs statistiques, tel que transmis au suivi de progression
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progression {
+    // Score de départ étudié par le dernier calcul effectué
+    pub score: Valeur,
+
+    // Nombre de dés lancés lors de ce calcul
+    pub nb_des: usize,
+
+    // Nombre de relances autorisées lors de ce calcul
+    pub relances: usize,
+
+    // Nombre de calculs effectués depuis la mise en place du suivi
+    pub nb_calculs: usize,
+
+    // Nombre total de résultats actuellement en cache
+    pub taille_caches: usize,
+}
+
+// Suivi optionnel de la progression des calculs
+#[derive(Default)]
+pub(super) struct SuiviProgression {
+    // Fonction à qui on signale l'avancement, s'il y en a une
+    signaler: Option<Box<dyn Fn(Progression) + Send + Sync>>,
+
+    // Nombre de calculs effectués depuis la mise en place du suivi
+    nb_calculs: AtomicUsize,
+}
+
+impl Stats {
+    // Demander à être tenu au courant de l'avancement des calculs, qui peuvent
+    // être longs. La fonction est appelée de temps en temps, et éventuellement
+    // depuis plusieurs threads.
+    pub fn set_progression(&mut self,
+                           signaler: impl Fn(Progression) + Send + Sync + 'static)
+    {
+        self.suivi = SuiviProgression {
+            signaler: Some(Box::new(signaler)),
+            nb_calculs: AtomicUsize::new(0),
+        };
+    }
+
+    // Noter qu'on vient de calculer (et mettre en cache) un nouveau résultat,
+    // et le signaler si c'est le moment. Il ne faut pas appeler cette fonction
+    // alors qu'on détient le verrou de l'un des caches.
+    pub(super) fn signaler_calcul(&self,
+                                  score: Valeur,
+                                  nb_des: usize,
+                                  relances: usize)
+    {
+        let signaler = match &self.suivi.signaler {
+            Some(signaler) => signaler,
+            None => return,
+        };
+        let nb_calculs = self.suivi.nb_calculs.fetch_add(1, Ordering::Relaxed) + 1;
+        if !nb_calculs.is_multiple_of(PERIODE_SIGNALEMENT) { return; }
+        signaler(Progression {
+            score,
+            nb_des,
+            relances,
+            nb_calculs,
+            taille_caches: self.taille_caches(),
+        });
+    }
+
+    // Nombre total de résultats en cache, tous nombres de dés confondus
+    pub fn taille_caches(&self) -> usize {
+        self.tailles_caches().into_iter().sum()
+    }
+
+    // Nombre de résultats en cache pour chaque nombre de dés, en commençant
+    // par 1 dé (voir aussi limiter_caches)
+    pub fn tailles_caches(&self) -> Vec<usize> {
+        self.stats_jets.iter().map(|stats_jet| {
+            stats_jet.esperance_fin.lock().unwrap().len()
+                + stats_jet.moment2.lock().unwrap().len()
+                + stats_jet.proba_perte.lock().unwrap().len()
+                + stats_jet.distribution.lock().unwrap().len()
+                + stats_jet.utilite_exponentielle.lock().unwrap().len()
+                + stats_jet.esperance_penalisee.lock().unwrap().len()
+                + stats_jet.proba_depasser.lock().unwrap().len()
+        }).collect()
+    }
+
+    // Utilisation de chacun des caches, pour chaque nombre de dés, depuis la
+    // création des statistiques ou la dernière remise à zéro des compteurs.
+    // Ne compte que les recherches de résultats, pas les simples vérifications
+    // de leur présence.
+    pub fn statistiques_cache(&self) -> Vec<StatistiquesCache> {
+        self.stats_jets.iter().enumerate().flat_map(|(indice, stats_jet)| {
+            let nb_des = indice + 1;
+            [
+                stats_jet.esperance_fin.lock().unwrap()
+                         .statistiques(nb_des, "espérance et proba de fin"),
+                stats_jet.moment2.lock().unwrap()
+                         .statistiques(nb_des, "moment d'ordre 2"),
+                stats_jet.proba_perte.lock().unwrap()
+                         .statistiques(nb_des, "proba de perte"),
+                stats_jet.distribution.lock().unwrap()
+                         .statistiques(nb_des, "distribution"),
+                stats_jet.utilite_exponentielle.lock().unwrap()
+                         .statistiques(nb_des, "utilité exponentielle"),
+                stats_jet.esperance_penalisee.lock().unwrap()
+                         .statistiques(nb_des, "espérance pénalisée"),
+                stats_jet.proba_depasser.lock().unwrap()
+                         .statistiques(nb_des, "proba de dépasser une cible"),
+            ]
+        }).collect()
+    }
+
+    // Remettre à zéro les compteurs d'utilisation des caches, par exemple pour
+    // n'étudier qu'une partie des calculs
+    pub fn remettre_statistiques_cache_a_zero(&self) {
+        for stats_jet in self.stats_jets.iter() {
+            stats_jet.esperance_fin.lock().unwrap().remettre_compteurs_a_zero();
+            stats_jet.moment2.lock().unwrap().remettre_compteurs_a_zero();
+            stats_jet.proba_perte.lock().unwrap().remettre_compteurs_a_zero();
+            stats_jet.distribution.lock().unwrap().remettre_compteurs_a_zero();
+            stats_jet.utilite_exponentielle.lock().unwrap().remettre_compteurs_a_zero();
+            stats_jet.esperance_penalisee.lock().unwrap().remettre_compteurs_a_zero();
+            stats_jet.proba_depasser.lock().unwrap().remettre_compteurs_a_zero();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regles::Regles;
+
+    // Une fois un résultat calculé, la même requête le retrouve dans le cache
+    // sans rien recalculer
+    #[test]
+    fn requete_repetee_en_cache() {
+        let stats = Stats::with_regles(Regles { score_max: 1000, ..Regles::default() });
+        let esperance = stats.esperance(0, 6, 0);
+        let cache_six_des = |stats: &Stats| {
+            stats.statistiques_cache().into_iter()
+                 .find(|s| (s.nb_des, s.grandeur) == (6, "espérance et proba de fin"))
+                 .unwrap()
+        };
+        let premiere = cache_six_des(&stats);
+        assert!(premiere.echecs > 0 && premiere.insertions > 0);
+
+        stats.remettre_statistiques_cache_a_zero();
+        assert!(stats.statistiques_cache().iter()
+                     .all(|s| (s.succes, s.echecs, s.insertions) == (0, 0, 0)));
+        assert_eq!(stats.esperance(0, 6, 0), esperance);
+        let seconde = cache_six_des(&stats);
+        assert!(seconde.succes > 0);
+        assert_eq!((seconde.echecs, seconde.insertions), (0, 0));
+        assert_eq!(seconde.taille, premiere.taille);
+    }
+}
@@ -0,0 +1,128 @@
+use super::{Stats, StatsJet, SuiviProgression};
+use crate::{
+    Flottant,
+    mises::MisesAtteignables,
+    regles::Regles,
+};
+
+use log::info;
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    mem,
+    path::Path,
+};
+
+
+// Version du format de sauvegarde, à incrémenter quand on change la structure
+// des statistiques ou la façon de les calculer
+const VERSION : u32 = 16;
+
+// En-tête d'une sauvegarde, qui permet de rejeter les sauvegardes faites avec
+// une autre version du programme, une autre précision ou d'autres règles
+#[derive(Deserialize, PartialEq, Serialize)]
+struct EnTete {
+    version: u32,
+    taille_flottant: usize,
+    regles: Regles,
+}
+
+impl EnTete {
+    // En-tête attendu pour certaines règles du jeu
+    fn new(regles: &Regles) -> Self {
+        Self {
+            version: VERSION,
+            taille_flottant: mem::size_of::<Flottant>(),
+            regles: regles.clone(),
+        }
+    }
+}
+
+impl Stats {
+    // Sauvegarder les statistiques, y compris tout ce qu'on a mis en cache, pour
+    // éviter de tout recalculer lors de la prochaine exécution
+    pub fn sauvegarder(&self, chemin: impl AsRef<Path>) -> io::Result<()> {
+        let mut sortie = BufWriter::new(File::create(chemin)?);
+        let configuration = bincode::config::standard();
+        bincode::serde::encode_into_std_write(EnTete::new(&self.regles),
+                                              &mut sortie,
+                                              configuration)
+            .and_then(|_| {
+                bincode::serde::encode_into_std_write(&self.stats_jets,
+                                                      &mut sortie,
+                                                      configuration)
+            })
+            .map_err(io::Error::other)?;
+        sortie.flush()
+    }
+
+    // Recharger des statistiques sauvegardées pour certaines règles du jeu. On
+    // rejette les sauvegardes qui ne correspondent pas à ces règles.
+    pub fn charger(chemin: impl AsRef<Path>, regles: &Regles) -> io::Result<Self> {
+        let mut entree = BufReader::new(File::open(&chemin)?);
+        let configuration = bincode::config::standard();
+        let donnees_invalides = |erreur| io::Error::new(io::ErrorKind::InvalidData, erreur);
+        let en_tete : EnTete =
+            bincode::serde::decode_from_std_read(&mut entree, configuration)
+                .map_err(donnees_invalides)?;
+        if en_tete != EnTete::new(regles) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "Sauvegarde faite pour d'autres règles ou \
+                                       une autre version du programme"));
+        }
+        let stats_jets : Box<[StatsJet]> =
+            bincode::serde::decode_from_std_read(&mut entree, configuration)
+                .map_err(donnees_invalides)?;
+        info!("Statistiques rechargées depuis {}", chemin.as_ref().display());
+        Ok(Self {
+            mises: MisesAtteignables::new(regles),
+            regles: regles.clone(),
+            stats_jets,
+            suivi: SuiviProgression::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Valeur;
+
+    // Quelques valeurs calculées à partir de statistiques, dans l'ordre donné
+    fn valeurs(stats: &Stats, etats: &[(Valeur, usize, Valeur)]) -> Vec<Flottant> {
+        etats.iter().flat_map(|&(score, nb_des, mise)| {
+            [stats.esperance(score, nb_des, mise),
+             stats.variance_gain(score, nb_des, mise),
+             stats.proba_fin(score, nb_des, mise, 3)]
+        }).collect()
+    }
+
+    // Des statistiques sauvegardées puis rechargées donnent exactement les
+    // mêmes résultats, qui ne dépendent pas non plus de l'ordre dans lequel les
+    // caches ont été remplis
+    #[test]
+    fn sauvegarde_rechargement() {
+        let regles = Regles { score_max: 1000, ..Regles::default() };
+        let etats = [(0, 6, 0), (0, 2, 300), (500, 4, 100), (900, 1, 50)];
+        let stats = Stats::with_regles(regles.clone());
+        let attendues = valeurs(&stats, &etats);
+
+        let chemin = std::env::temp_dir().join("mitraillette-test-sauvegarde.bin");
+        stats.sauvegarder(&chemin).unwrap();
+        let rechargees = Stats::charger(&chemin, &regles);
+        let autres_regles = Stats::charger(&chemin, &Regles::default());
+        std::fs::remove_file(&chemin).unwrap();
+        assert_eq!(valeurs(&rechargees.unwrap(), &etats), attendues);
+        let erreur = autres_regles.err().expect("Sauvegarde acceptée pour d'autres règles");
+        assert_eq!(erreur.kind(), io::ErrorKind::InvalidData);
+
+        let mut etats_inverses = etats;
+        etats_inverses.reverse();
+        let inverses = Stats::with_regles(regles);
+        valeurs(&inverses, &etats_inverses);
+        assert_eq!(valeurs(&inverses, &etats), attendues);
+    }
+}
@@ -0,0 +1,121 @@
+use super::{Decision, Possibilite, Stats};
+use crate::{
+    Flottant,
+    Valeur,
+    combinaison::Combinaison,
+};
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    iter,
+};
+
+
+// Recherche de la stratégie de tour qui maximise l'espérance d'une utilité
+// quelconque de la mise encaissée en fin de tour, plutôt que l'espérance de la
+// mise elle-même. L'utilité de la mise nulle est celle d'un tour perdu.
+//
+// Comme chaque combinaison prise fait croître la mise, l'arbre des lancers est
+// fini et on n'a pas besoin de limiter le nombre de relances. Les décisions de
+// relance qu'on produit ont donc un nombre de relances max de usize::MAX.
+//
+pub struct OptimiseurUtilite<'stats, F: Fn(Valeur) -> Flottant> {
+    // Ce qu'on sait sur les lancers de dés
+    stats: &'stats Stats,
+
+    // Score acquis lors des tours précédents
+    score: Valeur,
+
+    // Utilité associée au fait de finir le tour avec une certaine mise
+    utilite: F,
+
+    // Espérance d'utilité optimale pour chaque (nb_des, mise) avant le lancer
+    esperances: RefCell<HashMap<(usize, Valeur), Flottant>>,
+}
+
+impl<'stats, F: Fn(Valeur) -> Flottant> OptimiseurUtilite<'stats, F> {
+    // Préparer l'optimisation d'un tour joué à partir d'un certain score
+    pub fn new(stats: &'stats Stats, score: Valeur, utilite: F) -> Self {
+        Self {
+            stats,
+            score,
+            utilite,
+            esperances: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // Utilité du fait de finir le tour en encaissant une certaine mise
+    pub fn utilite(&self, mise: Valeur) -> Flottant {
+        (self.utilite)(mise)
+    }
+
+    // Espérance d'utilité pour une stratégie qui la maximise, en lançant un
+    // certain nombre de dés avec une certaine mise préalable
+    pub fn esperance(&self, nb_des: usize, mise: Valeur) -> Flottant {
+        // Est-ce que, par chance, j'ai déjà étudié ce cas précédemment?
+        if let Some(&esperance) = self.esperances.borrow().get(&(nb_des, mise)) {
+            return esperance;
+        }
+
+        // On suit la décision optimale pour chaque lancer gagnant, sans oublier
+        // les lancers perdants
+        let stats_jet = &self.stats.stats_jets[nb_des-1];
+        let utilite_perte = self.utilite(0);
+        let mut esperance = utilite_perte * stats_jet.proba_rien;
+        for stats_choix in stats_jet.stats_choix.iter() {
+            let esperance_max =
+                self.decision_optimale(mise, &stats_choix.choix)
+                    .map_or(utilite_perte, |decision| decision.esperance());
+            esperance += esperance_max * stats_choix.proba;
+        }
+
+        // On met en cache ce résultat
+        assert_eq!(self.esperances.borrow_mut().insert((nb_des, mise), esperance),
+                   None);
+        esperance
+    }
+
+    // Espérances d'utilité calculées jusqu'ici, pour chaque (nb_des, mise)
+    pub(super) fn into_esperances(self) -> HashMap<(usize, Valeur), Flottant> {
+        self.esperances.into_inner()
+    }
+
+    // Décision optimale face à un jet de dés qui nous laisse le choix entre
+    // certaines combinaisons. Retourne None si on a perdu le tour.
+    pub fn action_optimale(&self,
+                           nb_des: usize,
+                           mise: Valeur,
+                           choix: &[Combinaison]) -> Option<Decision>
+    {
+        let possibilites = choix.iter()
+                                .map(|&comb| Possibilite::new(comb, nb_des, &self.stats.regles))
+                                .collect::<Box<[_]>>();
+        self.decision_optimale(mise, &possibilites)
+    }
+
+    // Recherche de la décision qui maximise l'espérance d'utilité face à un
+    // certain choix de combinaisons
+    fn decision_optimale(&self,
+                         mise: Valeur,
+                         choix: &[Possibilite]) -> Option<Decision>
+    {
+        let mut meilleure_decision : Option<Decision> = None;
+        self.stats.enumerer_decisions_generiques(
+            self.score,
+            mise,
+            choix,
+            iter::once(usize::MAX),
+            |mise| self.utilite(mise),
+            |nb_des, mise, _| self.esperance(nb_des, mise),
+            |decision| {
+                if meilleure_decision.is_none_or(|meilleure| {
+                    decision.esperance() > meilleure.esperance()
+                }) {
+                    meilleure_decision = Some(decision);
+                }
+            }
+        );
+        meilleure_decision
+    }
+}
@@ -0,0 +1,204 @@
+use super::{Decision, Possibilite, Stats};
+use crate::{
+    Flottant,
+    Valeur,
+    combinaison::Combinaison,
+};
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    iter,
+};
+
+
+// Recherche d'une stratégie de tour qui maximise un compromis entre espérance
+// et variance de la mise encaissée en fin de tour, E - k * V, pour un
+// coefficient k positif. Avec k nul, on retrouve la stratégie qui maximise
+// l'espérance, et plus k est grand, plus on joue prudemment.
+//
+// Ce critère ne se décompose pas d'un lancer à l'autre comme une espérance:
+// on propage donc l'espérance et le moment d'ordre 2 de la mise encaissée avec
+// la stratégie suivie, et on choisit à chaque lancer l'option dont le E - k * V
+// est le meilleur. Comme pour OptimiseurUtilite, l'arbre des lancers est fini
+// et on ne limite pas le nombre de relances.
+pub struct OptimiseurVariance<'stats> {
+    // Ce qu'on sait sur les lancers de dés
+    stats: &'stats Stats,
+
+    // Score acquis lors des tours précédents
+    score: Valeur,
+
+    // Coefficient de pénalisation de la variance
+    k: Flottant,
+
+    // Espérance et moment d'ordre 2 de la mise encaissée pour chaque
+    // (nb_des, mise) avant le lancer
+    moments: RefCell<HashMap<(usize, Valeur), (Flottant, Flottant)>>,
+}
+
+impl<'stats> OptimiseurVariance<'stats> {
+    // Préparer l'optimisation d'un tour joué à partir d'un certain score
+    pub fn new(stats: &'stats Stats, score: Valeur, k: Flottant) -> Self {
+        assert!(k >= 0., "La pénalité de variance doit être positive: {}", k);
+        Self {
+            stats,
+            score,
+            k,
+            moments: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // Critère maximisé, pour une espérance et un moment d'ordre 2 donnés
+    pub fn objectif(&self, esperance: Flottant, moment2: Flottant) -> Flottant {
+        esperance - self.k * (moment2 - esperance * esperance).max(0.)
+    }
+
+    // Espérance et variance de la mise encaissée en suivant cette stratégie,
+    // en lançant un certain nombre de dés avec une certaine mise préalable
+    pub fn esperance_variance(&self, nb_des: usize, mise: Valeur) -> (Flottant, Flottant) {
+        let (esperance, moment2) = self.moments(nb_des, mise);
+        (esperance, (moment2 - esperance * esperance).max(0.))
+    }
+
+    // Valeur du critère pour un lancer de dés avec une mise préalable
+    pub fn valeur(&self, nb_des: usize, mise: Valeur) -> Flottant {
+        let (esperance, moment2) = self.moments(nb_des, mise);
+        self.objectif(esperance, moment2)
+    }
+
+    // Décision optimale face à un jet de dés qui nous laisse le choix entre
+    // certaines combinaisons. Retourne None si on a perdu le tour.
+    pub fn action_optimale(&self,
+                           nb_des: usize,
+                           mise: Valeur,
+                           choix: &[Combinaison]) -> Option<Decision>
+    {
+        let possibilites = choix.iter()
+                                .map(|&comb| Possibilite::new(comb, nb_des, &self.stats.regles))
+                                .collect::<Box<[_]>>();
+        self.decision_optimale(mise, &possibilites)
+    }
+
+    // Espérance et moment d'ordre 2 de la mise encaissée, calculés en suivant
+    // la décision optimale pour chaque lancer gagnant (un lancer perdant ne
+    // rapporte rien)
+    fn moments(&self, nb_des: usize, mise: Valeur) -> (Flottant, Flottant) {
+        // Est-ce que, par chance, j'ai déjà étudié ce cas précédemment?
+        if let Some(&moments) = self.moments.borrow().get(&(nb_des, mise)) {
+            return moments;
+        }
+
+        let mut esperance = 0.;
+        let mut moment2 = 0.;
+        for stats_choix in self.stats.stats_jets[nb_des-1].stats_choix.iter() {
+            let (esperance_choix, moment2_choix) =
+                match self.decision_optimale(mise, &stats_choix.choix) {
+                    None => (0., 0.),
+                    Some(Decision::Encaisser { mise, .. }) => {
+                        let mise = mise as Flottant;
+                        (mise, mise * mise)
+                    },
+                    Some(Decision::Relancer { nb_des, mise, .. }) => self.moments(nb_des, mise),
+                };
+            esperance += esperance_choix * stats_choix.proba;
+            moment2 += moment2_choix * stats_choix.proba;
+        }
+
+        // On met en cache ce résultat
+        assert_eq!(self.moments.borrow_mut().insert((nb_des, mise), (esperance, moment2)),
+                   None);
+        (esperance, moment2)
+    }
+
+    // Recherche de la décision qui maximise le critère face à un certain
+    // choix de combinaisons. Encaisser a une variance nulle, donc le critère
+    // vaut alors la mise encaissée.
+    fn decision_optimale(&self,
+                         mise: Valeur,
+                         choix: &[Possibilite]) -> Option<Decision>
+    {
+        let mut meilleure_decision : Option<Decision> = None;
+        self.stats.enumerer_decisions_generiques(
+            self.score,
+            mise,
+            choix,
+            iter::once(usize::MAX),
+            |mise| mise as Flottant,
+            |nb_des, mise, _| self.valeur(nb_des, mise),
+            |decision| {
+                if meilleure_decision.is_none_or(|meilleure| {
+                    decision.esperance() > meilleure.esperance()
+                }) {
+                    meilleure_decision = Some(decision);
+                }
+            }
+        );
+        meilleure_decision
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regles::Regles;
+
+    // Sans pénalité de variance, on retrouve la stratégie qui maximise
+    // l'espérance, avec son espérance et sa variance
+    #[test]
+    fn sans_penalite() {
+        let stats = Stats::with_regles(Regles { score_max: 1000, ..Regles::default() });
+        let proches = |a: Flottant, b: Flottant| (a - b).abs() <= 1e-3 * b.abs().max(1.);
+        for score in [0, 500] {
+            let optimiseur = OptimiseurVariance::new(&stats, score, 0.);
+            for nb_des in 1..=6 {
+                for mise in [0, 100, 300] {
+                    if stats.mises_atteignables().jet_impossible(score, nb_des, mise) {
+                        continue;
+                    }
+                    let etat = (score, nb_des, mise);
+                    let (esperance, variance) = optimiseur.esperance_variance(nb_des, mise);
+                    assert!(proches(esperance, stats.esperance(score, nb_des, mise)),
+                            "{:?}", etat);
+                    assert!(proches(variance, stats.variance_gain(score, nb_des, mise)),
+                            "{:?}", etat);
+                    assert_eq!(optimiseur.valeur(nb_des, mise), esperance);
+                }
+            }
+            assert_eq!(stats.seuil_arret_variance(score, 6, 0.), stats.seuil_arret(score, 6));
+        }
+
+        // Les décisions sont les mêmes face à chaque jet de 6 dés
+        let optimiseur = OptimiseurVariance::new(&stats, 0, 0.);
+        for stats_choix in stats.stats_jets[5].stats_choix.iter() {
+            let choix = stats_choix.choix.iter().map(|poss| poss.comb).collect::<Vec<_>>();
+            let attendue = stats.action_optimale(0, 6, 0, &choix);
+            let obtenue = optimiseur.action_optimale(6, 0, &choix);
+            let resume = |decision: Decision| {
+                (decision.combinaison(), matches!(decision, Decision::Relancer { .. }))
+            };
+            assert_eq!(obtenue.map(resume), attendue.map(resume), "{:?}", choix);
+        }
+    }
+
+    // Plus on pénalise la variance, plus on joue prudemment: la variance et
+    // l'espérance diminuent, et on s'arrête plus tôt
+    #[test]
+    fn penalite_croissante() {
+        let stats = Stats::with_regles(Regles { score_max: 1000, ..Regles::default() });
+        let mut precedent : Option<(Flottant, Flottant, Valeur)> = None;
+        for k in [0., 0.001, 0.003, 0.01] {
+            let optimiseur = OptimiseurVariance::new(&stats, 0, k);
+            let (esperance, variance) = optimiseur.esperance_variance(6, 0);
+            let seuil = stats.seuil_arret_variance(0, 6, k).unwrap();
+            if let Some((esperance_prec, variance_prec, seuil_prec)) = precedent {
+                assert!(esperance <= esperance_prec && variance <= variance_prec, "k = {}", k);
+                assert!(seuil <= seuil_prec, "k = {}", k);
+            }
+            precedent = Some((esperance, variance, seuil));
+        }
+        let (esperance_max, variance_max, _) = precedent.unwrap();
+        let sans_penalite = OptimiseurVariance::new(&stats, 0, 0.).esperance_variance(6, 0);
+        assert!(esperance_max < sans_penalite.0 && variance_max < sans_penalite.1);
+    }
+}
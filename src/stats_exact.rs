@@ -0,0 +1,256 @@
+use crate::{
+    Valeur,
+    choix,
+    combinaison::Combinaison,
+    regles::Regles,
+    stats::Possibilite,
+};
+
+use num_rational::BigRational;
+use num_traits::{One, Zero};
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+};
+
+
+// Probabilité calculée de façon exacte, sous forme de fraction
+pub type ProbaExacte = BigRational;
+
+// Variante des statistiques où toutes les probabilités sont calculées de façon
+// exacte. C'est beaucoup plus lent que les calculs flottants, donc ça ne sert
+// qu'à vérifier ceux-ci sur des cas simples (peu de relances).
+pub struct StatsExactes {
+    // Règles du jeu étudiées
+    regles: Regles,
+
+    // Données pour chaque nombre de dés
+    stats_jets: Box<[StatsJetExact]>,
+}
+
+// Ce qu'on sait sur le lancer d'un certain nombre de dés
+struct StatsJetExact {
+    // Choix auxquels on peut faire face, et probabilité de chacun
+    stats_choix: Box<[(Box<[Possibilite]>, ProbaExacte)]>,
+
+    // Probabilité de ne tirer aucune combinaison
+    proba_rien: ProbaExacte,
+
+    // Probabilité de finir la partie pour un certain score de départ, une
+    // certaine mise préalable et un certain nombre de relances maximal
+    proba_fin: RefCell<HashMap<(Valeur, Valeur, usize), ProbaExacte>>,
+}
+
+impl StatsJetExact {
+    // Initialiser les statistiques à N dés
+    fn new(nb_des: usize, regles: &Regles) -> Self {
+        // On compte les lancers menant à chaque choix de combinaisons...
+        let mut comptes = choix::compter_choix(nb_des, regles);
+        let nb_comb = (regles.nb_faces as u64).pow(nb_des as u32);
+        let proba = |compte: u64| ProbaExacte::new(compte.into(), nb_comb.into());
+
+        // ...et on en déduit les probabilités, en mettant à part le cas perdant
+        let proba_rien = proba(comptes.remove(&[][..]).unwrap_or(0));
+        let stats_choix = comptes.into_iter()
+            .map(|(choix, compte)| {
+                let choix = choix.into_iter()
+                    .map(|comb| Possibilite::new(comb, nb_des, regles))
+                    .collect::<Box<[_]>>();
+                (choix, proba(compte))
+            }).collect::<Box<[_]>>();
+        Self {
+            stats_choix,
+            proba_rien,
+            proba_fin: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl StatsExactes {
+    // Initialiser les calculs exacts pour certaines règles du jeu
+    pub fn with_regles(regles: Regles) -> Self {
+        // Les nombres de lancers ne donnent les probabilités que si toutes les
+        // faces sont équiprobables
+        assert!(regles.poids_faces.is_none(),
+                "Les calculs exacts ne gèrent pas les dés pipés");
+        let stats_jets = (1..=regles.nb_des_tot)
+            .map(|nb_des| StatsJetExact::new(nb_des, &regles))
+            .collect::<Box<[_]>>();
+        Self {
+            regles,
+            stats_jets,
+        }
+    }
+
+    // Probabilité de ne tirer aucune combinaison en lançant N dés
+    pub fn proba_rien(&self, nb_des: usize) -> ProbaExacte {
+        self.stats_jets[nb_des-1].proba_rien.clone()
+    }
+
+    // Probabilité qu'un lancer de N dés permette de prendre au moins une
+    // combinaison d'un certain type (voir choix::proba_combinaison)
+    pub fn proba_combinaison(&self,
+                             nb_des: usize,
+                             predicat: impl Fn(&Combinaison) -> bool) -> ProbaExacte
+    {
+        self.stats_jets[nb_des-1].stats_choix.iter()
+            .filter(|(choix, _)| choix.iter().any(|poss| predicat(&poss.comb)))
+            .map(|(_, proba)| proba)
+            .sum()
+    }
+
+    // Probabilité de gagner (atteindre le score maximal) en s'autorisant à
+    // relancer N fois, calculée comme le fait Stats::proba_fin
+    pub fn proba_fin(&self,
+                     score: Valeur,
+                     nb_des: usize,
+                     mise: Valeur,
+                     max_relances: usize) -> ProbaExacte
+    {
+        // Est-ce que, par chance, j'ai déjà étudié ce cas précédemment?
+        let stats_jet = &self.stats_jets[nb_des-1];
+        if let Some(proba) = stats_jet.proba_fin.borrow()
+                                      .get(&(score, mise, max_relances)) {
+            return proba.clone();
+        }
+
+        // On passe en revue tous les résultats de lancers gagnants
+        let mut proba_fin_partie = ProbaExacte::zero();
+        for (choix, proba_choix) in stats_jet.stats_choix.iter() {
+            // Si l'une des combinaisons nous fait atteindre le score maximal, on
+            // a gagné...
+            let atterrissage = choix.iter().any(|poss| {
+                self.regles.score_gagnant(score + mise + poss.valeur)
+            });
+            let mut proba_fin_max =
+                if atterrissage {
+                    ProbaExacte::one()
+                } else {
+                    ProbaExacte::zero()
+                };
+
+            // ...sinon, on peut tenter de prendre une combinaison qui nous
+            // amène en-dessous du score maximal et relancer
+            for poss in choix.iter() {
+                // Une sexte fait gagner la partie immédiatement
+                if self.regles.fait_gagner(&poss.comb) {
+                    proba_fin_max = ProbaExacte::one();
+                    continue;
+                }
+                let nouvelle_mise = mise + poss.valeur;
+                if score + nouvelle_mise >= self.regles.score_max { continue; }
+                for num_relances in 1..=max_relances {
+                    let proba_fin = self.proba_fin(score,
+                                                   poss.nb_des_relance,
+                                                   nouvelle_mise,
+                                                   num_relances - 1);
+                    if proba_fin > proba_fin_max { proba_fin_max = proba_fin; }
+                }
+            }
+
+            // On pondère le résultat par la chance de tirer ce jet
+            proba_fin_partie += proba_fin_max * proba_choix;
+        }
+
+        // On met en cache ce résultat
+        stats_jet.proba_fin.borrow_mut()
+                 .insert((score, mise, max_relances), proba_fin_partie.clone());
+        proba_fin_partie
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::Stats;
+
+    // Fraction a/b, pour écrire les probabilités attendues
+    fn fraction(a: u64, b: u64) -> ProbaExacte {
+        ProbaExacte::new(a.into(), b.into())
+    }
+
+    // Valeur approchée d'une probabilité exacte
+    fn approximer(proba: &ProbaExacte) -> f64 {
+        num_traits::ToPrimitive::to_f64(proba).unwrap()
+    }
+
+    // Les probabilités de perdre en lançant 1 ou 2 dés, qu'on compte à la main:
+    // il faut ne tirer ni 1 ni 5
+    #[test]
+    fn proba_rien_un_et_deux_des() {
+        let stats = StatsExactes::with_regles(Regles::default());
+        assert_eq!(stats.proba_rien(1), fraction(4, 6));
+        assert_eq!(stats.proba_rien(2), fraction(16, 36));
+    }
+
+    // A 50 points du score maximal, on finit en un lancer d'un dé si on tire
+    // un 5 (un 1 fait dépasser), et en un lancer de 6 dés dès qu'on tire au
+    // moins un 5
+    // Les probabilités de perdre pour chaque nombre de dés: il ne faut tirer
+    // ni 1, ni 5, ni brelan (4^3 - 4 jets sur 6^3 à 3 dés, 4^4 - 4 * 13 à 4
+    // dés...), et à 6 dés, ni suite ni triple paire. Les calculs flottants et
+    // les statistiques préparées y arrivent aussi (la conversion est inutile
+    // en double précision).
+    #[test]
+    #[allow(clippy::unnecessary_cast)]
+    fn proba_rien_par_nombre_de_des() {
+        let regles = Regles { score_max: 1000, ..Regles::default() };
+        let exactes = StatsExactes::with_regles(regles.clone());
+        let stats = Stats::with_regles(regles.clone());
+        for (nb_des, attendue) in [
+            (1, fraction(4, 6)),
+            (2, fraction(16, 36)),
+            (3, fraction(60, 216)),
+            (4, fraction(204, 1296)),
+            (5, fraction(600, 7776)),
+            (6, fraction(1080, 46656)),
+        ] {
+            assert_eq!(exactes.proba_rien(nb_des), attendue, "{} dés", nb_des);
+            let attendue = approximer(&attendue);
+            assert!((choix::proba_rien(nb_des, &regles) as f64 - attendue).abs() <= 1e-6);
+            assert!((stats.proba_rien(nb_des) as f64 - attendue).abs() <= 1e-6);
+        }
+    }
+
+    // Probabilités des combinaisons dont on discute le plus, comptées à la
+    // main, auxquelles le calcul flottant doit aussi arriver
+    #[test]
+    fn proba_combinaisons() {
+        let regles = Regles::default();
+        let stats = StatsExactes::with_regles(regles.clone());
+        let brelan = |comb: &Combinaison| matches!(comb, Combinaison::BrelanSimple { .. }
+                                                         | Combinaison::BrelanDouble { .. });
+        let suite = |comb: &Combinaison| *comb == Combinaison::Suite;
+        let triple_paire = |comb: &Combinaison| *comb == Combinaison::TriplePaire;
+        for (nb_des, attendue, predicat) in [
+            // 6! ordres des faces d'une suite
+            (6, fraction(720, 46656), &suite as &dyn Fn(&Combinaison) -> bool),
+            // Trois paires distinctes (20 choix de faces, 6!/(2!2!2!) ordres),
+            // un carré et une paire (6 * 5 choix de faces, 6!/(4!2!) ordres),
+            // ou six dés identiques, qui comptent aussi comme trois paires
+            (6, fraction(20 * 90 + 30 * 15 + 6, 46656), &triple_paire),
+            // 6 brelans
+            (3, fraction(6, 216), &brelan),
+            // Tout sauf 6 faces distinctes (720 jets), une paire (10800), deux
+            // paires (16200) ou trois paires (1800)
+            (6, fraction(46656 - 720 - 10800 - 16200 - 1800, 46656), &brelan),
+        ] {
+            assert_eq!(stats.proba_combinaison(nb_des, predicat), attendue);
+            let flottante = choix::proba_combinaison(nb_des, &regles, predicat);
+            assert!((flottante as f64 - approximer(&attendue)).abs() <= 1e-6);
+        }
+    }
+
+    // A 50 points du score maximal, on finit en un lancer de 6 dés dès qu'on
+    // tire au moins un 5
+    #[test]
+    fn proba_fin_atterrissage() {
+        let stats = StatsExactes::with_regles(Regles::default());
+        assert_eq!(stats.proba_fin(9950, 1, 0, 0), fraction(1, 6));
+        let nb_lancers = 6u64.pow(6);
+        let sans_cinq = 5u64.pow(6);
+        let attendue = fraction(nb_lancers - sans_cinq, nb_lancers);
+        assert_eq!(stats.proba_fin(9950, 6, 0, 0), attendue);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{
+    Flottant,
+    Valeur,
+    stats::Stats,
+};
+
+
+// Une ligne de la table de stratégie optimale: dans un certain état du tour
+// de jeu, faut-il relancer les dés ou encaisser la mise, et combien gagne-t-on
+// en moyenne à relancer?
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LigneStrategie {
+    // Score acquis lors des tours précédents
+    pub score: Valeur,
+
+    // Nombre de dés qu'on peut relancer
+    pub nb_des: usize,
+
+    // Mise accumulée pendant ce tour
+    pub mise: Valeur,
+
+    // Est-ce qu'il vaut mieux relancer (sinon, on encaisse la mise)
+    pub relancer: bool,
+
+    // Gain moyen d'une relance (par rapport au fait d'encaisser la mise)
+    pub gain_moyen: Flottant,
+}
+
+// Tabuler la stratégie optimale sur tout l'espace des états plausibles du
+// tour de jeu. Les lignes sont transmises au fur et à mesure de leur calcul.
+//
+// On parcourt les scores et les mises par valeur décroissante, car le calcul
+// pour une mise donnée réutilise les résultats pour les mises plus élevées qui
+// seront alors déjà en cache.
+//
+pub fn table_strategie(stats: &Stats, mut traiter: impl FnMut(LigneStrategie)) {
+    let regles = stats.regles();
+    let mises = stats.mises_atteignables();
+    let pas = regles.bareme.valeur_min_de() as usize;
+    for score in (0..regles.score_max).step_by(pas).rev() {
+        for nb_des in 1..=regles.nb_des_tot {
+            for mise in mises.mises(nb_des).rev() {
+                if mises.jet_impossible(score, nb_des, mise) { continue; }
+                let gain_moyen = stats.gain_moyen(score, nb_des, mise);
+                traiter(LigneStrategie {
+                    score,
+                    nb_des,
+                    mise,
+                    relancer: gain_moyen > 0.,
+                    gain_moyen,
+                });
+            }
+        }
+    }
+}
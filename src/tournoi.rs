@@ -0,0 +1,304 @@
+use crate::{
+    simulation::{Jeu, ResultatPartie, Strategie},
+    stats::Stats,
+};
+
+use rayon::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+
+// Nombre de parties fictives, partagées équitablement, ajoutées à chaque
+// confrontation lors du calcul des classements Elo. Sans elles, une stratégie
+// qui gagne toutes ses parties aurait un classement infini.
+const PARTIES_FICTIVES : f64 = 1.;
+
+// Précision visée sur les forces lors de l'ajustement du modèle de Bradley-Terry
+const PRECISION_ELO : f64 = 1e-12;
+
+// Résultats d'un tournoi où chaque stratégie affronte chacune des autres
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResultatsTournoi {
+    // Nombre de parties jouées lors de chaque confrontation
+    pub nb_parties: usize,
+
+    // victoires[i][j] est le nombre de parties où la stratégie i a battu la
+    // stratégie j
+    pub victoires: Vec<Vec<usize>>,
+
+    // nb_tours[i][j] est le nombre total de tours joués lors des parties entre
+    // les stratégies i et j
+    pub nb_tours: Vec<Vec<usize>>,
+
+    // Parties perdues par la stratégie dont on a demandé les traces, dans
+    // l'ordre des adversaires puis des parties
+    pub defaites: Vec<Defaite>,
+}
+
+// Classement Elo d'une stratégie, déduit des résultats d'un tournoi
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Elo {
+    // Classement, la moyenne des classements étant nulle
+    pub classement: f64,
+
+    // Erreur standard sur ce classement
+    pub erreur: f64,
+}
+
+// Trace d'une partie perdue par une stratégie lors d'un tournoi
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Defaite {
+    // Numéro de la stratégie qui a gagné la partie
+    pub adversaire: usize,
+
+    // Numéro de la partie au sein de la confrontation
+    pub num_partie: usize,
+
+    // Déroulé de la partie, où le joueur 0 est celui qui a commencé
+    pub partie: ResultatPartie,
+
+    // Numéro de joueur de la stratégie qui a perdu dans "partie"
+    pub joueur: usize,
+}
+
+impl ResultatsTournoi {
+    // Proportion des parties où la stratégie i a battu la stratégie j
+    pub fn taux_victoire(&self, i: usize, j: usize) -> f64 {
+        self.victoires[i][j] as f64 / self.nb_parties as f64
+    }
+
+    // Nombre moyen de tours joués lors des parties entre i et j
+    pub fn nb_tours_moyen(&self, i: usize, j: usize) -> f64 {
+        self.nb_tours[i][j] as f64 / self.nb_parties as f64
+    }
+
+    // Classement Elo de chaque stratégie, selon le modèle de Bradley-Terry:
+    // chaque stratégie i a une force p_i, et bat j avec une probabilité
+    // p_i / (p_i + p_j). On cherche les forces les plus vraisemblables au vu
+    // des victoires, et 400 points d'écart correspondent à des forces dans un
+    // rapport 10. Le résultat ne dépend que des victoires, pas du hasard.
+    //
+    // Les forces sont ajustées par l'algorithme MM de Hunter, et les erreurs
+    // se déduisent de l'inverse de la matrice d'information de Fisher, une
+    // fois imposé que la moyenne des classements soit nulle.
+    pub fn elo(&self) -> Vec<Elo> {
+        // Victoires de chaque stratégie contre chaque autre, en comptant les
+        // parties fictives qui régularisent les confrontations à sens unique
+        let nb_strategies = self.victoires.len();
+        if nb_strategies < 2 {
+            return vec![Elo { classement: 0., erreur: 0. }; nb_strategies];
+        }
+        let victoires = |i: usize, j: usize| self.victoires[i][j] as f64 + PARTIES_FICTIVES / 2.;
+        let nb_parties = |i: usize, j: usize| victoires(i, j) + victoires(j, i);
+
+        // Ajustement des forces, stables une fois normalisées pour que la
+        // moyenne de leurs logarithmes soit nulle
+        let mut forces = vec![1.; nb_strategies];
+        loop {
+            let mut suivantes = (0..nb_strategies).map(|i| {
+                let (gains, denominateur) = (0..nb_strategies).filter(|&j| j != i)
+                    .fold((0., 0.), |(gains, denominateur), j| {
+                        (gains + victoires(i, j),
+                         denominateur + nb_parties(i, j) / (forces[i] + forces[j]))
+                    });
+                gains / denominateur
+            }).collect::<Vec<_>>();
+            let log_moyen = suivantes.iter().map(|force| force.ln()).sum::<f64>()
+                            / nb_strategies as f64;
+            for force in suivantes.iter_mut() { *force /= log_moyen.exp(); }
+            let ecart_max = forces.iter()
+                                  .zip(suivantes.iter())
+                                  .map(|(avant, apres)| ((apres - avant) / avant).abs())
+                                  .fold(0., f64::max);
+            forces = suivantes;
+            if ecart_max < PRECISION_ELO { break; }
+        }
+
+        // Matrice d'information de Fisher sur les logarithmes des forces. Elle
+        // ne change pas si on multiplie toutes les forces par une constante,
+        // donc elle n'est pas inversible: en lui ajoutant 1/N partout, on
+        // obtient une matrice inversible dont l'inverse, moins 1/N partout,
+        // donne les covariances des classements de moyenne nulle.
+        let decalage = 1. / nb_strategies as f64;
+        let mut information = vec![vec![decalage; nb_strategies]; nb_strategies];
+        for i in 0..nb_strategies {
+            for j in (0..nb_strategies).filter(|&j| j != i) {
+                let proba = forces[i] / (forces[i] + forces[j]);
+                let terme = nb_parties(i, j) * proba * (1. - proba);
+                information[i][i] += terme;
+                information[i][j] -= terme;
+            }
+        }
+        let covariances = inverser(information);
+
+        let echelle = 400. / 10f64.ln();
+        (0..nb_strategies).map(|i| Elo {
+            classement: echelle * forces[i].ln(),
+            erreur: echelle * (covariances[i][i] - decalage).max(0.).sqrt(),
+        }).collect()
+    }
+}
+
+// Inversion d'une matrice carrée inversible, par la méthode de Gauss-Jordan
+// avec recherche du pivot le plus grand
+fn inverser(mut matrice: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    let taille = matrice.len();
+    let mut inverse = (0..taille).map(|i| {
+        (0..taille).map(|j| if i == j { 1. } else { 0. }).collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+    for colonne in 0..taille {
+        let pivot = (colonne..taille).max_by(|&a, &b| {
+            matrice[a][colonne].abs().total_cmp(&matrice[b][colonne].abs())
+        }).unwrap();
+        matrice.swap(colonne, pivot);
+        inverse.swap(colonne, pivot);
+        let facteur = matrice[colonne][colonne];
+        assert!(facteur != 0., "Matrice non inversible");
+        for j in 0..taille {
+            matrice[colonne][j] /= facteur;
+            inverse[colonne][j] /= facteur;
+        }
+        for ligne in (0..taille).filter(|&ligne| ligne != colonne) {
+            let facteur = matrice[ligne][colonne];
+            for j in 0..taille {
+                matrice[ligne][j] -= facteur * matrice[colonne][j];
+                inverse[ligne][j] -= facteur * inverse[colonne][j];
+            }
+        }
+    }
+    inverse
+}
+
+// Faire s'affronter chaque paire de stratégies lors d'un certain nombre de
+// parties, chacune commençant une partie sur deux.
+//
+// Les confrontations sont jouées en parallèle. Chacune construit ses propres
+// stratégies (toujours dans le même ordre) à l'aide de "creer_strategies", afin
+// que celles-ci n'aient pas besoin d'être partagées entre threads. Chaque
+// confrontation a aussi son propre jeu, dont la graine est déduite de celle du
+// tournoi, donc le résultat ne dépend pas de l'ordre d'exécution des threads.
+//
+pub fn tournoi<'stats>(stats: &'stats Stats,
+                       creer_strategies: impl Fn(&'stats Stats) -> Vec<Box<dyn Strategie + 'stats>> + Sync,
+                       nb_parties: usize,
+                       graine: u64) -> ResultatsTournoi {
+    tournoi_trace(stats, creer_strategies, nb_parties, graine, 0, 0)
+}
+
+// Même chose, en gardant la trace des "nb_defaites" premières parties perdues
+// par la stratégie numéro "strategie_tracee". On ne garde que les traces
+// demandées, pour ne pas remplir la mémoire lors des gros tournois.
+pub fn tournoi_trace<'stats>(stats: &'stats Stats,
+                             creer_strategies: impl Fn(&'stats Stats) -> Vec<Box<dyn Strategie + 'stats>> + Sync,
+                             nb_parties: usize,
+                             graine: u64,
+                             strategie_tracee: usize,
+                             nb_defaites: usize) -> ResultatsTournoi {
+    // On commence par déterminer le nombre de stratégies en jeu
+    let regles = stats.regles();
+    let nb_strategies = creer_strategies(stats).len();
+    let mut resultats = ResultatsTournoi {
+        nb_parties,
+        victoires: vec![vec![0; nb_strategies]; nb_strategies],
+        nb_tours: vec![vec![0; nb_strategies]; nb_strategies],
+        defaites: Vec::new(),
+    };
+
+    // On joue les confrontations en parallèle
+    let paires = (0..nb_strategies)
+        .flat_map(|i| (i+1..nb_strategies).map(move |j| (i, j)))
+        .collect::<Vec<_>>();
+    let resultats_paires = paires.par_iter().enumerate().map(|(num_paire, &(i, j))| {
+        let strategies = creer_strategies(stats);
+        let mut jeu = Jeu::with_regles(regles.clone(), graine.wrapping_add(num_paire as u64));
+        let mut victoires = [0; 2];
+        let mut nb_tours = 0;
+        let mut defaites = Vec::new();
+        for num_partie in 0..nb_parties {
+            // Les deux stratégies commencent chacune leur tour
+            let premier = num_partie % 2;
+            let joueurs = [&*strategies[i], &*strategies[j]];
+            let resultat = jeu.jouer_partie([joueurs[premier], joueurs[1-premier]]);
+            nb_tours += resultat.nb_tours();
+            if let Some(gagnant) = resultat.gagnant {
+                let (vainqueur, perdant) = if (gagnant + premier) % 2 == 0 { (i, j) } else { (j, i) };
+                victoires[(gagnant + premier) % 2] += 1;
+                if perdant == strategie_tracee && defaites.len() < nb_defaites {
+                    defaites.push(Defaite {
+                        adversaire: vainqueur,
+                        num_partie,
+                        joueur: 1 - gagnant,
+                        partie: resultat,
+                    });
+                }
+            }
+        }
+        (victoires, nb_tours, defaites)
+    }).collect::<Vec<_>>();
+
+    // ...et on rassemble les résultats
+    for (&(i, j), (victoires, nb_tours, defaites)) in paires.iter().zip(resultats_paires) {
+        resultats.victoires[i][j] = victoires[0];
+        resultats.victoires[j][i] = victoires[1];
+        resultats.nb_tours[i][j] = nb_tours;
+        resultats.nb_tours[j][i] = nb_tours;
+        resultats.defaites.extend(defaites);
+    }
+    resultats.defaites.sort_by_key(|defaite| (defaite.adversaire, defaite.num_partie));
+    resultats.defaites.truncate(nb_defaites);
+    resultats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Résultats d'un tournoi fictif, ramenés aux seules victoires
+    fn resultats(victoires: Vec<Vec<usize>>) -> ResultatsTournoi {
+        let nb_strategies = victoires.len();
+        ResultatsTournoi {
+            nb_parties: 0,
+            victoires,
+            nb_tours: vec![vec![0; nb_strategies]; nb_strategies],
+            defaites: Vec::new(),
+        }
+    }
+
+    // Entre deux stratégies, avec la partie fictive partagée, 3 victoires
+    // contre 1 donnent des forces dans un rapport 3.5 / 1.5, et l'erreur
+    // standard se calcule à la main à partir de l'information de Fisher
+    // 5 * 0.7 * 0.3 sur l'écart des logarithmes des forces
+    #[test]
+    fn elo_deux_strategies() {
+        let elo = resultats(vec![vec![0, 3], vec![1, 0]]).elo();
+        let ecart = 200. * (7f64 / 3.).log10();
+        let erreur = 400. / 10f64.ln() / (4. * 1.05f64).sqrt();
+        for (elo, classement) in elo.iter().zip([ecart, -ecart]) {
+            assert!((elo.classement - classement).abs() < 1e-9, "{:?}", elo);
+            assert!((elo.erreur - erreur).abs() < 1e-9, "{:?}", elo);
+        }
+    }
+
+    // Une stratégie qui gagne toutes ses parties a un classement fini: avec la
+    // partie fictive, 10.5 victoires contre 0.5 donnent une force 21 fois plus
+    // grande que celles des deux autres, qui font jeu égal. Le classement ne
+    // dépend pas de l'ordre des stratégies.
+    #[test]
+    fn elo_victoires_ecrasantes() {
+        let victoires = vec![vec![0, 10, 10], vec![0, 0, 5], vec![0, 5, 0]];
+        let elo = resultats(victoires.clone()).elo();
+        assert_eq!(elo, resultats(victoires).elo());
+        assert!(elo.iter().all(|elo| elo.classement.is_finite() && elo.erreur.is_finite()));
+        assert!(elo.iter().map(|elo| elo.classement).sum::<f64>().abs() < 1e-9);
+        let ecart = 400. * 21f64.log10();
+        assert!((elo[0].classement - 2. / 3. * ecart).abs() < 1e-6, "{:?}", elo);
+        assert!((elo[1].classement - elo[2].classement).abs() < 1e-9);
+        assert!((elo[1].erreur - elo[2].erreur).abs() < 1e-9);
+        assert!(elo[0].erreur > elo[1].erreur);
+
+        let permute = resultats(vec![vec![0, 5, 0], vec![5, 0, 0], vec![10, 10, 0]]).elo();
+        assert!((permute[2].classement - elo[0].classement).abs() < 1e-9);
+
+        assert_eq!(resultats(vec![vec![0]]).elo(), vec![Elo { classement: 0., erreur: 0. }]);
+    }
+}
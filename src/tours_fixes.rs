@@ -0,0 +1,114 @@
+use crate::{
+    Flottant,
+    Valeur,
+    stats::Stats,
+};
+
+use std::collections::BTreeMap;
+
+
+// Etude des parties qui se jouent en un nombre fixe de tours, sans score à
+// atteindre (voir Regles::en_tours_fixes). Chaque tour se joue alors de façon
+// à maximiser l'espérance de gain, sauf le dernier où l'on peut chercher à
+// dépasser le meilleur total adverse.
+//
+// Comme le score maximal est hors d'atteinte, et qu'une sexte n'y amène plus
+// mais rapporte ce que lui donne le barème (voir Regles::fait_gagner), la
+// façon de jouer un tour ne dépend du score que par le fait d'avoir ouvert ou
+// non. On n'a donc besoin que de deux distributions de la mise encaissée,
+// qu'on convole N fois.
+//
+// Les statistiques doivent avoir été préparées pour des règles en tours fixes.
+
+// Distribution de probabilité du total après N tours, sous forme de couples
+// (score, probabilité) triés par score croissant, en jouant chaque tour de
+// façon à maximiser l'espérance de gain
+pub fn distribution_finale(stats: &Stats, nb_tours: usize) -> Vec<(Valeur, Flottant)> {
+    let regles = stats.regles();
+    assert!(regles.nb_tours > 0, "Les règles ne se jouent pas en tours fixes");
+    let nb_des = regles.nb_des_tot;
+    let distribution_ferme = stats.distribution_tour(0, nb_des, 0);
+    let score_ouvert = regles.seuil_ouverture.max(1);
+    let distribution_ouvert = stats.distribution_tour(score_ouvert, nb_des, 0);
+
+    let mut probas_scores = BTreeMap::from([(0, 1.)]);
+    for _ in 0..nb_tours {
+        let mut suivants = BTreeMap::new();
+        for (&score, &proba) in probas_scores.iter() {
+            let distribution = if regles.ouvert(score) {
+                &distribution_ouvert
+            } else {
+                &distribution_ferme
+            };
+            for &(gain, proba_gain) in distribution.iter() {
+                *suivants.entry(score + gain).or_insert(0.) += proba * proba_gain;
+            }
+        }
+        probas_scores = suivants;
+    }
+    probas_scores.into_iter().collect()
+}
+
+// Total moyen après N tours, en jouant chaque tour de façon à maximiser
+// l'espérance de gain
+pub fn esperance_finale(stats: &Stats, nb_tours: usize) -> Flottant {
+    distribution_finale(stats, nb_tours).into_iter()
+                                        .map(|(score, proba)| score as Flottant * proba)
+                                        .sum()
+}
+
+// Probabilité de dépasser strictement un total adverse lors de son dernier
+// tour, en partant d'un certain score, selon qu'on joue pour le dépasser (voir
+// Stats::proba_depasser) ou qu'on continue à maximiser l'espérance de gain
+pub fn probas_dernier_tour(stats: &Stats,
+                           score: Valeur,
+                           total_adverse: Valeur) -> (Flottant, Flottant)
+{
+    let nb_des = stats.regles().nb_des_tot;
+    let chasse = stats.proba_depasser(score, nb_des, 0, total_adverse);
+    let esperance = stats.distribution_tour(score, nb_des, 0)
+                         .into_iter()
+                         .filter(|&(gain, _)| score + gain > total_adverse)
+                         .map(|(_, proba)| proba)
+                         .sum();
+    (chasse, esperance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        choix::{Enumeration, Histogramme},
+        combinaison::Combinaison,
+        etat::EtatTour,
+        regles::Regles,
+        simulation::{self, Action},
+    };
+
+    // En tours fixes, une sexte rapporte ce que lui donne le barème, au lieu
+    // de ce qui manque pour atteindre un score maximal hors d'atteinte
+    #[test]
+    fn sexte_bornee() {
+        let regles = Regles { carres_et_plus: true, ..Regles::default() }.en_tours_fixes(10);
+        let sexte = Combinaison::Sexte { idx_face: 2 };
+        let valeur = regles.bareme.valeur(&sexte);
+        assert_eq!(valeur, 2400);
+        assert!(!regles.fait_gagner(&sexte));
+
+        let etat = EtatTour { mise: 100, ..EtatTour::new(500, 6, 0) };
+        let histo = Histogramme::parse("3 3 3 3 3 3", &regles).unwrap();
+        let choix = histo.combinaisons_possibles(&regles, Enumeration::Elaguee);
+        let actions = simulation::actions_possibles(&regles, &etat, &choix);
+        assert!(actions.contains(&Action::Encaisser { comb: sexte, mise: 2500 }));
+        assert!(actions.contains(&Action::Relancer { comb: sexte, mise: 2500, nb_des: 6 }));
+        assert_eq!(etat.prendre(&regles, sexte), EtatTour { nb_des: 6, mise: 2500, ..etat });
+
+        // L'analyse statistique attribue la même valeur à la sexte (on réduit
+        // le score maximal pour que le calcul reste rapide, il ne joue pas tant
+        // qu'on reste loin de lui)
+        let stats = Stats::with_regles(Regles { score_max: 4000, ..regles });
+        let decisions = stats.decisions_possibles(500, 6, 100, &[sexte]);
+        assert_eq!(decisions.len(), 2);
+        assert!(decisions.iter().all(|decision| decision.mise() == 2500));
+    }
+}
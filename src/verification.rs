@@ -0,0 +1,124 @@
+use crate::{
+    Flottant,
+    Valeur,
+    simulation::{EtatTour, Jeu, Optimale},
+    stats::Stats,
+};
+
+use std::fmt::{self, Display};
+
+
+// Comparaison du gain moyen calculé analytiquement avec celui qu'on observe en
+// simulant des tours joués selon la stratégie optimale. Un écart de plus de
+// quelques erreurs standard trahit un bug dans l'énumération des combinaisons
+// ou dans le calcul récursif de l'espérance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Verification {
+    // Etat du tour à partir duquel on a simulé
+    pub score: Valeur,
+    pub nb_des: usize,
+    pub mise: Valeur,
+
+    // Nombre de tours simulés
+    pub nb_tours: usize,
+
+    // Gain moyen calculé analytiquement
+    pub gain_analytique: Flottant,
+
+    // Gain moyen observé lors de la simulation
+    pub gain_simule: f64,
+
+    // Erreur standard sur le gain moyen observé
+    pub erreur_standard: f64,
+}
+
+impl Verification {
+    // Ecart entre gain simulé et analytique, en nombre d'erreurs standard
+    // (la conversion est inutile quand on calcule en double précision)
+    #[allow(clippy::unnecessary_cast)]
+    pub fn nb_sigmas(&self) -> f64 {
+        (self.gain_simule - self.gain_analytique as f64) / self.erreur_standard
+    }
+}
+
+impl Display for Verification {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(formatter,
+               "Score {}, {} dés, mise {}: gain analytique {:+.2}, simulé {:+.2} ± {:.2} \
+                sur {} tours ({:+.2}σ)",
+               self.score, self.nb_des, self.mise, self.gain_analytique,
+               self.gain_simule, self.erreur_standard, self.nb_tours, self.nb_sigmas())
+    }
+}
+
+// Simuler des tours joués selon la stratégie optimale à partir d'un certain
+// état, et comparer le gain moyen observé au gain moyen analytique
+pub fn verifier(stats: &Stats,
+                score: Valeur,
+                nb_des: usize,
+                mise: Valeur,
+                nb_tours: usize,
+                graine: u64) -> Verification {
+    let mut jeu = Jeu::with_regles(stats.regles().clone(), graine);
+    let optimale = Optimale::new(stats);
+    let etat = EtatTour::new(score, nb_des, mise);
+
+    // On accumule la somme et la somme des carrés des gains observés
+    let mut somme = 0.;
+    let mut somme_carres = 0.;
+    for _ in 0..nb_tours {
+        let tour = jeu.jouer_tour(&optimale, &etat);
+        let gain = jeu.regles().gain_encaisse(score, tour.mise) as f64 - mise as f64;
+        somme += gain;
+        somme_carres += gain * gain;
+    }
+
+    // ...d'où l'on déduit la moyenne et son erreur standard
+    let n = nb_tours as f64;
+    let gain_simule = somme / n;
+    let variance = (somme_carres / n - gain_simule * gain_simule).max(0.) * n / (n - 1.);
+    Verification {
+        score,
+        nb_des,
+        mise,
+        nb_tours,
+        gain_analytique: stats.gain_moyen(score, nb_des, mise),
+        gain_simule,
+        erreur_standard: (variance / n).sqrt(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Le gain moyen simulé s'accorde avec le gain analytique à 3 erreurs
+    // standard près, pour quelques états du tour avec les règles par défaut.
+    // C'est long, donc à lancer avec "cargo test --release -- --ignored".
+    #[test]
+    #[ignore]
+    fn accord_simulation() {
+        let stats = Stats::new();
+        for (num_etat, &(score, nb_des, mise)) in [
+            (0, 6, 0), (0, 3, 300), (0, 1, 1000), (5000, 2, 200), (9000, 6, 0), (9800, 4, 50),
+        ].iter().enumerate() {
+            let verification = verifier(&stats, score, nb_des, mise, 100_000, num_etat as u64);
+            assert!(verification.nb_sigmas().abs() <= 3., "{}", verification);
+        }
+    }
+
+    // La variance analytique du gain est celle qu'on observe en simulant des
+    // tours joués selon la stratégie optimale, aux fluctuations près
+    // (la conversion est inutile en double précision)
+    #[test]
+    #[allow(clippy::unnecessary_cast)]
+    fn variance_simulee() {
+        let stats = Stats::with_score_max(1000);
+        let nb_tours = 20_000;
+        let verification = verifier(&stats, 0, 6, 0, nb_tours, 42);
+        let variance = verification.erreur_standard.powi(2) * nb_tours as f64;
+        let analytique = stats.variance_gain(0, 6, 0) as f64;
+        assert!((variance - analytique).abs() <= 0.05 * analytique,
+                "variance simulée {}, analytique {}", variance, analytique);
+    }
+}
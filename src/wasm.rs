@@ -0,0 +1,113 @@
+use crate::{
+    Flottant,
+    Valeur,
+    choix::{Enumeration, Histogramme},
+    regles::Regles,
+    stats::{Decision, Stats},
+};
+
+use serde::Serialize;
+
+use wasm_bindgen::prelude::*;
+
+
+// Nombre de résultats qu'on accepte de garder en cache entre deux appels. Les
+// calculs de fin de partie remplissent vite les caches, et un onglet de
+// navigateur n'a pas de mémoire à revendre.
+const TAILLE_CACHES_MAX : usize = 1_000_000;
+
+// Conseiller utilisable depuis JavaScript, qui garde ses statistiques (et donc
+// ses caches) d'un appel à l'autre
+#[wasm_bindgen]
+pub struct Conseiller {
+    stats: Stats,
+}
+
+// Une option qui s'offre au joueur face à un jet de dés, telle que transmise
+// à JavaScript
+#[derive(Serialize)]
+struct OptionConseil {
+    // Combinaison à prendre, en toutes lettres
+    combinaison: String,
+
+    // "encaisser" ou "relancer"
+    action: &'static str,
+
+    // Mise encaissée, ou avec laquelle on relance
+    mise: Valeur,
+
+    // Nombre de dés à relancer (0 si on encaisse)
+    nb_des: usize,
+
+    // Espérance de gain de cette option
+    esperance: Flottant,
+}
+
+impl Conseiller {
+    // Borner la mémoire utilisée en oubliant les caches quand ils deviennent
+    // trop gros
+    fn borner_caches(&mut self) {
+        if self.stats.taille_caches() > TAILLE_CACHES_MAX {
+            self.stats.vider_caches();
+        }
+    }
+}
+
+// Préparer un conseiller pour certaines règles, données sous forme d'objet
+// JavaScript dont les champs absents prennent leur valeur par défaut (passer
+// undefined pour les règles par défaut)
+#[wasm_bindgen]
+pub fn init(regles: JsValue) -> Result<Conseiller, JsValue> {
+    let regles = if regles.is_undefined() || regles.is_null() {
+        Regles::default()
+    } else {
+        serde_wasm_bindgen::from_value::<Regles>(regles)?
+    };
+    regles.verifier().map_err(|erreur| JsValue::from_str(&erreur))?;
+    Ok(Conseiller { stats: Stats::with_regles(regles) })
+}
+
+// Options qui s'offrent au joueur face à un jet de dés (faces numérotées à
+// partir de 1), de la meilleure à la moins bonne
+#[wasm_bindgen]
+pub fn conseil(conseiller: &mut Conseiller,
+               score: Valeur,
+               mise: Valeur,
+               faces: &[u8]) -> Result<JsValue, JsValue>
+{
+    let regles = conseiller.stats.regles();
+    let faces = faces.iter().map(|&face| face as usize).collect::<Vec<_>>();
+    let histo = Histogramme::depuis_faces(&faces, regles)
+                            .map_err(|erreur| JsValue::from_str(&erreur.to_string()))?;
+    let nb_des = histo.nb_des();
+    let combinaisons = histo.combinaisons_possibles(regles, Enumeration::Elaguee);
+    let mut decisions = conseiller.stats.decisions_possibles(score, nb_des, mise, &combinaisons);
+    decisions.sort_by(|a, b| b.esperance().total_cmp(&a.esperance()));
+    let options = decisions.into_iter().map(|decision| {
+        let (action, nb_des) = match decision {
+            Decision::Encaisser { .. } => ("encaisser", 0),
+            Decision::Relancer { nb_des, .. } => ("relancer", nb_des),
+        };
+        OptionConseil {
+            combinaison: decision.combinaison().to_string(),
+            action,
+            mise: decision.mise(),
+            nb_des,
+            esperance: decision.esperance(),
+        }
+    }).collect::<Vec<_>>();
+    conseiller.borner_caches();
+    Ok(serde_wasm_bindgen::to_value(&options)?)
+}
+
+// Gain moyen quand on risque "mise" points en lançant "nb_des" dés
+#[wasm_bindgen]
+pub fn gain_moyen(conseiller: &mut Conseiller,
+                  score: Valeur,
+                  nb_des: usize,
+                  mise: Valeur) -> Flottant
+{
+    let gain = conseiller.stats.gain_moyen(score, nb_des, mise);
+    conseiller.borner_caches();
+    gain
+}
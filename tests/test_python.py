@@ -0,0 +1,51 @@
+# Tests du module Python, à lancer après l'avoir compilé et installé avec
+# maturin ("maturin develop", voir pyproject.toml), par exemple avec
+# "python -m unittest tests/test_python.py"
+
+import unittest
+
+import mitraillette
+
+
+class TestStats(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.stats = mitraillette.Stats(score_max=1000)
+
+    # Les espérances à score nul avec 6 dés sont celles que calcule Rust
+    # (voir esperances_double_precision dans src/stats.rs)
+    def test_esperances_six_des(self):
+        for mise, esperance in [(0, 433.961090), (500, 737.961947)]:
+            self.assertAlmostEqual(self.stats.esperance(0, 6, mise), esperance, delta=1e-3)
+            self.assertAlmostEqual(self.stats.gain_moyen(0, 6, mise), esperance - mise,
+                                   delta=1e-3)
+
+    # A 50 points de l'arrivée, on finit en un lancer de 2 dés en tirant un 5
+    def test_proba_fin(self):
+        self.assertAlmostEqual(self.stats.proba_fin(950, 2, 0, 0), 11 / 36, delta=1e-6)
+
+    def test_histogramme(self):
+        self.assertEqual(self.stats.histogramme([1, 3, 3, 6, 6, 6]), [1, 0, 2, 0, 0, 3])
+        with self.assertRaises(ValueError):
+            self.stats.histogramme([7])
+
+    def test_action_optimale(self):
+        self.assertIsNone(self.stats.action_optimale(0, 0, [2, 3, 3, 4, 6, 6]))
+        decision = self.stats.action_optimale(950, 0, [2, 2, 3, 4, 5, 6])
+        self.assertEqual(decision["combinaison"], "un 5 (50 points, 1 dé)")
+        self.assertEqual(decision["action"], "encaisser")
+        self.assertEqual(decision["mise"], 50)
+
+    def test_nombre_de_des_invalide(self):
+        with self.assertRaises(ValueError):
+            self.stats.esperance(0, 7, 0)
+
+    # Des règles que le moteur ne sait pas jouer sont refusées à la création
+    def test_regles_invalides(self):
+        for regles in [dict(nb_des_tot=0), dict(nb_des_tot=7), dict(nb_faces=4)]:
+            with self.assertRaises(ValueError):
+                mitraillette.Stats(**regles)
+
+
+if __name__ == "__main__":
+    unittest.main()